@@ -0,0 +1,188 @@
+//! Measures the time spent in each stage of encoding and rendering.
+//!
+//! Run with `cargo run --release --example bench --features stats`. Pass
+//! `--smoke` to run with 1/100 of the corpus, e.g. in CI.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use qrqrpar::stats::{encode, encode_rmqr, render, EncodeStats, RenderStats};
+use qrqrpar::{EcLevel, Encoder, Family, PrefixEncoder, QrCode, QrStyle, RmqrStrategy, Version};
+
+/// Counts the allocations, to track the memory churn of the renderer.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// A small deterministic pseudo-random number generator, to keep the corpus
+/// identical between runs.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, n: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % n
+    }
+
+    fn string(&mut self, charset: &[u8], len: usize) -> String {
+        (0..len)
+            .map(|_| charset[self.next(charset.len() as u64) as usize] as char)
+            .collect()
+    }
+}
+
+fn print_encode_stats(name: &str, count: usize, stats: &EncodeStats) {
+    println!(
+        "{name:<24} {count:>6} codes  total {:>10.3?}  segmentation {:>10.3?}  ec {:>10.3?}  placement {:>10.3?}  masking {:>10.3?}",
+        stats.total(),
+        stats.segmentation,
+        stats.error_correction,
+        stats.placement,
+        stats.masking,
+    );
+}
+
+fn print_render_stats(name: &str, count: usize, stats: &RenderStats) {
+    println!(
+        "{name:<24} {count:>6} codes  total {:>10.3?}  path {:>10.3?}  rasterize {:>10.3?}",
+        stats.total(),
+        stats.path,
+        stats.rasterize,
+    );
+}
+
+fn main() {
+    let scale = if std::env::args().any(|arg| arg == "--smoke") {
+        100
+    } else {
+        1
+    };
+    let mut rng = Lcg(0x5eed);
+    let start = Instant::now();
+
+    let urls = (0..10_000 / scale)
+        .map(|_| {
+            let path = rng.string(b"abcdefghijklmnopqrstuvwxyz0123456789", 12);
+            format!("https://example.com/{path}")
+        })
+        .collect::<Vec<_>>();
+    let mut stats = EncodeStats::default();
+    for url in &urls {
+        stats += encode(url.as_bytes(), EcLevel::M).unwrap().1;
+    }
+    print_encode_stats("normal (urls)", urls.len(), &stats);
+
+    let mut encoder = Encoder::new();
+    for (name, reuse) in [("one-shot (urls)", false), ("encoder (urls)", true)] {
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let encode_start = Instant::now();
+        for url in &urls {
+            if reuse {
+                encoder.encode(url, EcLevel::M).unwrap();
+            } else {
+                QrCode::new(url).unwrap();
+            }
+        }
+        println!(
+            "{name:<24} {:>6} codes  total {:>10.3?}  allocations/code {:>6.1}",
+            urls.len(),
+            encode_start.elapsed(),
+            (ALLOCATIONS.load(Ordering::Relaxed) - allocations) as f64 / urls.len() as f64,
+        );
+    }
+
+    // Long URLs which only differ in their last 8 characters, comparing the
+    // segmentation from scratch with a shared prefix.
+    let prefix = format!(
+        "https://example.com/{}/",
+        rng.string(b"abcdefghijklmnopqrstuvwxyz0123456789/", 200)
+    );
+    let suffixes = (0..10_000 / scale)
+        .map(|_| rng.string(b"abcdefghijklmnopqrstuvwxyz0123456789", 8))
+        .collect::<Vec<_>>();
+    let segmentation_start = Instant::now();
+    for suffix in &suffixes {
+        qrqrpar::bits::encode_auto(format!("{prefix}{suffix}").as_bytes(), EcLevel::M).unwrap();
+    }
+    let whole = segmentation_start.elapsed();
+    let segmentation_start = Instant::now();
+    let encoder = PrefixEncoder::new(prefix.as_bytes(), EcLevel::M, Family::Normal);
+    for suffix in &suffixes {
+        encoder.encode_suffix_bits(suffix.as_bytes()).unwrap();
+    }
+    println!(
+        "{:<24} {:>6} codes  whole {:>10.3?}  prefix {:>10.3?}",
+        "segmentation (prefix)",
+        suffixes.len(),
+        whole,
+        segmentation_start.elapsed(),
+    );
+
+    let skus = (0..10_000 / scale)
+        .map(|_| {
+            let prefix = rng.string(b"ABCDEFGHIJKLMNOPQRSTUVWXYZ", 3);
+            let number = rng.string(b"0123456789", 8);
+            format!("{prefix}-{number}")
+        })
+        .collect::<Vec<_>>();
+    for (name, strategy) in [
+        ("rmqr width (skus)", RmqrStrategy::Width),
+        ("rmqr height (skus)", RmqrStrategy::Height),
+        ("rmqr area (skus)", RmqrStrategy::Area),
+    ] {
+        let mut stats = EncodeStats::default();
+        for sku in &skus {
+            stats += encode_rmqr(sku.as_bytes(), EcLevel::M, strategy).unwrap().1;
+        }
+        print_encode_stats(name, skus.len(), &stats);
+    }
+
+    let style = QrStyle {
+        width: 512,
+        ..Default::default()
+    };
+    let mut stats = RenderStats::default();
+    let count = 1_000 / scale;
+    for url in urls.iter().take(count) {
+        let (code, _) = encode(url.as_bytes(), EcLevel::M).unwrap();
+        stats += render(&code, &style).unwrap().1;
+    }
+    print_render_stats("png 512px (urls)", count, &stats);
+
+    let data = rng.string(b"abcdefghijklmnopqrstuvwxyz0123456789", 2_000);
+    let code = QrCode::with_version(data, Version::Normal(40), EcLevel::L).unwrap();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let path_start = Instant::now();
+    let svg = code.to_svg(&style);
+    println!(
+        "{:<24} {:>6} bytes  path {:>10.3?}  allocations {:>6}",
+        "svg path (normal 40)",
+        svg.len(),
+        path_start.elapsed(),
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+    );
+
+    println!("elapsed {:.3?}", start.elapsed());
+}
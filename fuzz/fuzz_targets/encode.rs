@@ -0,0 +1,79 @@
+//! Feeds arbitrary (data, version, error correction level) tuples to the
+//! public encoding functions, which must return an error rather than panic.
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use qrqrpar::bits::Bits;
+use qrqrpar::Segment;
+use qrqrpar::types::Mode;
+use qrqrpar::{fitting_ec_levels, EcLevel, QrCode, RmqrStrategy, Version};
+
+#[derive(Arbitrary, Debug)]
+enum FuzzVersion {
+    Normal(u8),
+    Micro(u8),
+    Rmqr(u8, u8),
+}
+
+#[derive(Arbitrary, Debug)]
+enum FuzzEcLevel {
+    L,
+    M,
+    Q,
+    H,
+}
+
+#[derive(Arbitrary, Debug)]
+enum FuzzMode {
+    Numeric,
+    Alphanumeric,
+    Byte,
+    Kanji,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    data: Vec<u8>,
+    version: FuzzVersion,
+    ec_level: FuzzEcLevel,
+    segments: Vec<(FuzzMode, u8, u8)>,
+}
+
+fuzz_target!(|input: Input| {
+    let data = &input.data[..];
+    let version = match input.version {
+        FuzzVersion::Normal(v) => Version::Normal(v),
+        FuzzVersion::Micro(v) => Version::Micro(v),
+        FuzzVersion::Rmqr(h, w) => Version::Rmqr(h, w),
+    };
+    let ec_level = match input.ec_level {
+        FuzzEcLevel::L => EcLevel::L,
+        FuzzEcLevel::M => EcLevel::M,
+        FuzzEcLevel::Q => EcLevel::Q,
+        FuzzEcLevel::H => EcLevel::H,
+    };
+
+    let _ = QrCode::with_version(data, version, ec_level);
+    let _ = QrCode::with_version_max_ec(data, version);
+    let _ = QrCode::with_version_truncating(data, version, ec_level, None);
+    let _ = QrCode::with_error_correction_level(data, ec_level);
+    let _ = QrCode::micro_with_error_correction_level(data, ec_level);
+    let _ = QrCode::rmqr_with_options(data, ec_level, RmqrStrategy::Area);
+    let _ = fitting_ec_levels(data, version);
+
+    let mut bits = Bits::new(version);
+    let segments = input.segments.iter().map(|(mode, begin, end)| Segment {
+        mode: match mode {
+            FuzzMode::Numeric => Mode::Numeric,
+            FuzzMode::Alphanumeric => Mode::Alphanumeric,
+            FuzzMode::Byte => Mode::Byte,
+            FuzzMode::Kanji => Mode::Kanji,
+        },
+        begin: usize::from(*begin),
+        end: usize::from(*end),
+    });
+    let _ = bits.push_segments(data, segments);
+    let _ = bits.push_terminator(ec_level);
+    let _ = QrCode::with_bits(bits, ec_level);
+});
@@ -0,0 +1,229 @@
+//! The `batch` module encodes and saves large numbers of symbols with a
+//! bounded pool of threads.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::types::{EcLevel, QrError};
+use crate::{QrCode, QrStyle, RenderError};
+
+/// An error saving one item of a batch, see `BatchFailure`.
+#[derive(Debug)]
+pub enum BatchError {
+    /// The payload cannot be encoded, e.g. when it is too long.
+    Encode(QrError),
+    /// The symbol cannot be rendered, or the file cannot be written.
+    Render(RenderError),
+}
+
+impl std::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatchError::Encode(e) => write!(f, "cannot encode: {e}"),
+            BatchError::Render(e) => write!(f, "cannot render: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BatchError::Encode(e) => Some(e),
+            BatchError::Render(e) => Some(e),
+        }
+    }
+}
+
+/// An item of a batch which was not saved.
+#[derive(Debug)]
+pub struct BatchFailure {
+    /// The position of the item in the batch.
+    pub index: usize,
+    /// The path the symbol was to be saved to.
+    pub path: PathBuf,
+    /// Why the item was not saved.
+    pub error: BatchError,
+}
+
+/// The outcome of `save_png_batch`.
+#[derive(Debug)]
+pub struct BatchReport {
+    /// The number of items in the batch.
+    pub total: usize,
+    /// The number of files saved.
+    pub saved: usize,
+    /// The items which were not saved, by position in the batch.
+    pub failures: Vec<BatchFailure>,
+    /// The total size of the saved files in bytes.
+    pub bytes_written: u64,
+    /// The time taken by the whole batch.
+    pub elapsed: Duration,
+}
+
+impl BatchReport {
+    /// Returns true if every item was saved.
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Encodes each payload at the error correction level `ec_level`, and saves
+/// it as a PNG file to its path, using `parallelism` threads.
+///
+///     use qrqrpar::{save_png_batch, EcLevel, QrStyle};
+///
+///     let dir = std::env::temp_dir().join("qrqrpar-batch-doc");
+///     std::fs::create_dir_all(&dir).unwrap();
+///     let items = (0..4).map(|i| (dir.join(format!("{i}.png")), format!("item {i}").into_bytes()));
+///     let report = save_png_batch(items, EcLevel::M, &QrStyle::default(), 2);
+///     assert_eq!(report.saved, 4);
+///     assert!(report.is_success());
+///
+/// The items are pulled from the iterator as the threads become free, and
+/// each thread holds at most one symbol and its image, so the memory used
+/// does not grow with the size of the batch. A `parallelism` of 0 is treated
+/// as 1.
+///
+/// An item which cannot be encoded, rendered or written is recorded in
+/// `BatchReport::failures`, and the rest of the batch goes on.
+pub fn save_png_batch<I>(
+    items: I,
+    ec_level: EcLevel,
+    style: &QrStyle,
+    parallelism: usize,
+) -> BatchReport
+where
+    I: IntoIterator<Item = (PathBuf, Vec<u8>)>,
+    I::IntoIter: Send,
+{
+    let start = Instant::now();
+    let items = Mutex::new(items.into_iter().enumerate());
+    let report = Mutex::new(BatchReport {
+        total: 0,
+        saved: 0,
+        failures: vec![],
+        bytes_written: 0,
+        elapsed: Duration::ZERO,
+    });
+
+    let save = |data: &[u8], path: &PathBuf| -> Result<usize, BatchError> {
+        let code =
+            QrCode::with_error_correction_level(data, ec_level).map_err(BatchError::Encode)?;
+        let png = code.to_png(style).map_err(BatchError::Render)?;
+        std::fs::write(path, &png).map_err(|e| BatchError::Render(e.into()))?;
+        Ok(png.len())
+    };
+    let work = || loop {
+        // The lock is released before encoding, so the threads only contend
+        // to pull the next item.
+        let Some((index, (path, data))) = items.lock().unwrap().next() else {
+            break;
+        };
+        let result = save(&data, &path);
+        let mut report = report.lock().unwrap();
+        report.total += 1;
+        match result {
+            Ok(len) => {
+                report.saved += 1;
+                report.bytes_written += len as u64;
+            }
+            Err(error) => report.failures.push(BatchFailure { index, path, error }),
+        }
+    };
+    std::thread::scope(|scope| {
+        for _ in 1..parallelism.max(1) {
+            scope.spawn(work);
+        }
+        work();
+    });
+
+    let mut report = report.into_inner().unwrap();
+    report.failures.sort_by_key(|failure| failure.index);
+    report.elapsed = start.elapsed();
+    report
+}
+
+#[cfg(test)]
+mod batch_tests {
+    use std::path::PathBuf;
+
+    use tempdir::TempDir;
+
+    use crate::batch::{save_png_batch, BatchError};
+    use crate::types::{EcLevel, QrError};
+    use crate::{QrCode, QrStyle, RenderError};
+
+    #[test]
+    fn test_batch() {
+        let dir = TempDir::new("__batch__").unwrap();
+        let items = (0..10)
+            .map(|i| {
+                let data = if i == 6 {
+                    vec![b'a'; 3000]
+                } else {
+                    format!("item {i}").into_bytes()
+                };
+                (dir.path().join(format!("{i}.png")), data)
+            })
+            .collect::<Vec<_>>();
+        let style = QrStyle {
+            width: 100,
+            ..Default::default()
+        };
+        let report = save_png_batch(items, EcLevel::M, &style, 3);
+        assert_eq!(report.total, 10);
+        assert_eq!(report.saved, 9);
+        assert!(!report.is_success());
+        assert_eq!(report.failures.len(), 1);
+        let failure = &report.failures[0];
+        assert_eq!(failure.index, 6);
+        assert_eq!(failure.path, dir.path().join("6.png"));
+        assert!(matches!(
+            failure.error,
+            BatchError::Encode(QrError::DataTooLong)
+        ));
+
+        let mut bytes_written = 0;
+        for i in (0..10).filter(|i| *i != 6) {
+            let png = std::fs::read(dir.path().join(format!("{i}.png"))).unwrap();
+            let code =
+                QrCode::with_error_correction_level(format!("item {i}"), EcLevel::M).unwrap();
+            assert_eq!(png, code.to_png(&style).unwrap());
+            bytes_written += png.len() as u64;
+        }
+        assert!(!dir.path().join("6.png").exists());
+        assert_eq!(report.bytes_written, bytes_written);
+    }
+
+    #[test]
+    fn test_write_failure() {
+        let dir = TempDir::new("__batch__").unwrap();
+        let items = [
+            (dir.path().join("missing").join("0.png"), b"0".to_vec()),
+            (dir.path().join("1.png"), b"1".to_vec()),
+        ];
+        for parallelism in [0, 1, 4] {
+            let report =
+                save_png_batch(items.clone(), EcLevel::L, &QrStyle::default(), parallelism);
+            assert_eq!(report.saved, 1);
+            assert_eq!(report.failures[0].index, 0);
+            assert!(matches!(
+                report.failures[0].error,
+                BatchError::Render(RenderError::Io(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let report = save_png_batch(
+            Vec::<(PathBuf, Vec<u8>)>::new(),
+            EcLevel::L,
+            &QrStyle::default(),
+            8,
+        );
+        assert_eq!(report.total, 0);
+        assert!(report.is_success());
+    }
+}
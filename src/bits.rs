@@ -1,9 +1,10 @@
 //! The `bits` module encodes binary data into raw bits used in a QR code.
-use core::cmp::min;
+use core::cmp::{min, Ordering};
 
 use crate::{
-    coding::{total_encoded_len, Optimizer, Parser, Segment},
-    types::{EcLevel, Mode, QrError, QrResult, Version},
+    coding::{optimal_segments, total_encoded_len, Optimizer, Parser, Segment},
+    structured_append::PartInfo,
+    types::{alphanumeric_value, EcLevel, Mode, QrError, QrResult, Version, ALPHANUMERIC_CHARSET},
 };
 
 /// The `Bits` structure stores the encoded data for a QR code.
@@ -11,6 +12,11 @@ pub struct Bits {
     data: Vec<u8>,
     bit_offset: usize,
     version: Version,
+    headers: HeaderFlags,
+    /// The data bits pushed in each mode, indexed by `Mode as usize`.
+    mode_bits: [usize; 4],
+    /// The length of the bits before the terminator, once it is pushed.
+    payload_len: Option<usize>,
 }
 
 impl Bits {
@@ -20,6 +26,9 @@ impl Bits {
             data: Vec::new(),
             bit_offset: 0,
             version,
+            headers: HeaderFlags::default(),
+            mode_bits: [0; 4],
+            payload_len: None,
         }
     }
 
@@ -75,10 +84,70 @@ impl Bits {
         }
     }
 
+    /// Pushes the first `bit_len` bits of `bits`, most significant bit first,
+    /// e.g. to experiment with non-standard payloads.
+    ///
+    ///     use qrqrpar::bits::Bits;
+    ///     use qrqrpar::Version;
+    ///
+    ///     let mut bits = Bits::new(Version::Normal(1));
+    ///     bits.push_bits(&[0b1010_0000], 3).unwrap();
+    ///     bits.push_bits(&[0xff, 0b1100_0000], 10).unwrap();
+    ///     assert_eq!(bits.len(), 13);
+    ///     assert_eq!(bits.into_bytes(), vec![0b1011_1111, 0b1111_1000]);
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidBitLength)` if `bits` holds fewer than
+    /// `bit_len` bits, and `Err(QrError::DataTooLong)` if the terminator has
+    /// already been pushed, since the bits then fill the data capacity.
+    pub fn push_bits(&mut self, bits: &[u8], bit_len: usize) -> QrResult<()> {
+        if bit_len > bits.len() * 8 {
+            return Err(QrError::InvalidBitLength);
+        }
+        if bit_len == 0 {
+            return Ok(());
+        }
+        if self.payload_len.is_some() {
+            return Err(QrError::DataTooLong);
+        }
+        self.reserve(bit_len);
+        let (whole, rest) = (bit_len / 8, bit_len % 8);
+        if self.bit_offset == 0 {
+            self.data.extend_from_slice(&bits[..whole]);
+        } else {
+            for &byte in &bits[..whole] {
+                self.push_number(8, u16::from(byte));
+            }
+        }
+        if rest > 0 {
+            self.push_number(rest, u16::from(bits[whole] >> (8 - rest)));
+        }
+        Ok(())
+    }
+
     /// Reserves `n` extra bits of space for pushing.
     fn reserve(&mut self, n: usize) {
-        let extra_bytes = (n + (8 - self.bit_offset) % 8) / 8;
-        self.data.reserve(extra_bytes);
+        let free_bits = (8 - self.bit_offset) % 8;
+        let extra_bytes = n.saturating_sub(free_bits).div_ceil(8);
+        self.data.reserve_exact(extra_bytes);
+    }
+
+    /// Pushes the segments and the terminator with the padding `pad`,
+    /// reserving the whole data capacity of the version up front.
+    fn push_segments_and_terminator(
+        &mut self,
+        data: &[u8],
+        segments: Vec<Segment>,
+        ec_level: EcLevel,
+        pad: PaddingPattern,
+    ) -> QrResult<()> {
+        self.reserve(self.max_len(ec_level)?.saturating_sub(self.len()));
+        let capacity = self.data.capacity();
+        self.push_segments(data, segments.into_iter())?;
+        self.push_terminator_with_padding(ec_level, pad)?;
+        debug_assert_eq!(self.data.capacity(), capacity, "bits reallocated");
+        Ok(())
     }
 
     /// Convert the bits into a bytes vector.
@@ -109,6 +178,11 @@ impl Bits {
     /// `ec_level` for the given version (e.g. `Version::Micro(1)` with
     /// `EcLevel::H`).
     pub fn max_len(&self, ec_level: EcLevel) -> QrResult<usize> {
+        if self.version.is_valid() {
+            self.version
+                .check_ec_level(ec_level)
+                .map_err(QrError::EcLevelUnavailable)?;
+        }
         self.version.fetch(ec_level, &DATA_LENGTHS)
     }
 
@@ -117,13 +191,33 @@ impl Bits {
         self.version
     }
 
+    /// Checks that the version is valid and supports the mode.
+    fn check_mode(&self, mode: Mode) -> QrResult<()> {
+        if !self.version.is_valid() {
+            Err(QrError::InvalidVersion)
+        } else if !self.version.supported_modes().contains(&mode) {
+            Err(QrError::ModeUnavailable {
+                version: self.version,
+                mode,
+                smallest: mode.smallest_micro_version(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
     /// Push the mode indicator to the end of the bits.
     ///
     /// # Errors
     ///
     /// If the mode is not supported in the provided version, this method
-    /// returns `Err(QrError::UnsupportedCharacterSet)`.
+    /// returns `Err(QrError::ModeUnavailable)`, naming the smallest Micro QR
+    /// code version supporting it.
+    ///
+    /// Returns `Err(QrError::InvalidVersion)` if the version is invalid, see
+    /// `Version::is_valid`.
     pub fn push_mode_indicator(&mut self, mode: Mode) -> QrResult<()> {
+        self.check_mode(mode)?;
         let number = match (self.version, mode) {
             (Version::Micro(1), Mode::Numeric) => return Ok(()),
             (Version::Micro(_), Mode::Numeric) => 0,
@@ -175,13 +269,126 @@ fn test_push_number() {
     );
 }
 
+#[cfg(test)]
+mod reserve_tests {
+    use crate::bits::{encode_auto, encode_auto_rmqr, Bits, RmqrStrategy};
+    use crate::types::{EcLevel, Version};
+
+    #[test]
+    fn test_no_under_reservation() {
+        for bit_offset in 0..8 {
+            for n in 0..=24 {
+                let mut bits = Bits::new(Version::Normal(1));
+                // Start with a full byte, so that the offset 0 has a byte before.
+                bits.push_number(8, 0xa5);
+                if bit_offset > 0 {
+                    bits.push_number(bit_offset, 0);
+                }
+                bits.data.shrink_to_fit();
+                bits.reserve(n);
+                let capacity = bits.data.capacity();
+                let needed = (bits.len() + n).div_ceil(8);
+                assert!(capacity >= needed, "offset {bit_offset}, {n} bits");
+
+                let mut remaining = n;
+                while remaining > 0 {
+                    let chunk = remaining.min(16);
+                    bits.push_number(chunk, 0);
+                    remaining -= chunk;
+                }
+                assert_eq!(
+                    bits.data.capacity(),
+                    capacity,
+                    "offset {bit_offset}, {n} bits"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_auto_reserves_whole_capacity() {
+        for data in [
+            "",
+            "01234567",
+            "Hello, world!",
+            "https://example.com/テスト",
+        ] {
+            let bits = encode_auto(data.as_bytes(), EcLevel::M).unwrap();
+            let capacity = bits.max_len(EcLevel::M).unwrap().div_ceil(8);
+            assert_eq!(bits.data.len(), capacity);
+            assert_eq!(bits.data.capacity(), capacity);
+
+            let bits = encode_auto_rmqr(data.as_bytes(), EcLevel::H, RmqrStrategy::Area).unwrap();
+            let capacity = bits.max_len(EcLevel::H).unwrap().div_ceil(8);
+            assert_eq!(bits.data.capacity(), capacity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod push_bits_tests {
+    use crate::bits::Bits;
+    use crate::test_util::Rng;
+    use crate::types::{EcLevel, QrError, Version};
+
+    #[test]
+    fn test_same_bytes_as_push_number() {
+        let mut rng = Rng(0xb175);
+        let mut next = |n: usize| rng.below(n);
+        for _ in 0..500 {
+            let mut expected = Bits::new(Version::Normal(40));
+            let mut actual = Bits::new(Version::Normal(40));
+            for _ in 0..next(8) {
+                // A random prefix, to push the bits at any offset.
+                let n = next(17);
+                let number = next(1 << n) as u16;
+                expected.push_number(n, number);
+                actual.push_number(n, number);
+
+                let bytes: Vec<u8> = (0..next(6)).map(|_| next(256) as u8).collect();
+                let bit_len = next(bytes.len() * 8 + 1);
+                for i in 0..bit_len {
+                    let bit = bytes[i / 8] >> (7 - i % 8) & 1;
+                    expected.push_number(1, u16::from(bit));
+                }
+                actual.push_bits(&bytes, bit_len).unwrap();
+                assert_eq!(actual.len(), expected.len());
+            }
+            assert_eq!(actual.into_bytes(), expected.into_bytes());
+        }
+    }
+
+    #[test]
+    fn test_after_terminator() {
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_bits(&[0x12, 0x34], 12).unwrap();
+        bits.push_terminator(EcLevel::L).unwrap();
+        assert_eq!(bits.push_bits(&[0xff], 1), Err(QrError::DataTooLong));
+        assert_eq!(bits.push_bits(&[], 0), Ok(()));
+    }
+
+    #[test]
+    fn test_too_few_bits() {
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_bits(&[0x12], 4).unwrap();
+        assert_eq!(bits.push_bits(&[0xff], 9), Err(QrError::InvalidBitLength));
+        assert_eq!(bits.push_bits(&[], 1), Err(QrError::InvalidBitLength));
+        // Nothing was pushed.
+        assert_eq!(bits.len(), 4);
+        assert_eq!(bits.into_bytes(), vec![0x10]);
+    }
+}
+
 /// Mode::Numeric mode
 impl Bits {
     fn push_header(&mut self, mode: Mode, raw_data_len: usize) -> QrResult<()> {
+        self.check_mode(mode)?;
         let length_bits = mode.length_bits_count(self.version);
-        self.reserve(length_bits + 4 + mode.data_bits_count(raw_data_len));
+        let mode_bits = self.version.mode_bits_count();
+        self.reserve(mode_bits + length_bits + mode.data_bits_count(raw_data_len));
         self.push_mode_indicator(mode)?;
         self.push_number_checked(length_bits, raw_data_len)?;
+        self.mode_bits[mode as usize] += mode.data_bits_count(raw_data_len);
         Ok(())
     }
 
@@ -192,15 +399,22 @@ impl Bits {
     /// # Errors
     ///
     /// Returns `Err(QrError::DataTooLong)` on overflow.
+    ///
+    /// Returns `Err(QrError::InvalidCharacter)` if the data contains another
+    /// character.
     pub fn push_numeric_data(&mut self, data: &[u8]) -> QrResult<()> {
+        self.check_mode(Mode::Numeric)?;
+        if !data.iter().all(|b| Mode::Numeric.is_encodable(*b)) {
+            return Err(QrError::InvalidCharacter);
+        }
         self.push_header(Mode::Numeric, data.len())?;
         for chunk in data.chunks(3) {
             let number = chunk
                 .iter()
-                .map(|b| u16::from(*b - b'0'))
+                .map(|b| usize::from(*b - b'0'))
                 .fold(0, |a, b| a * 10 + b);
             let length = chunk.len() * 3 + 1;
-            self.push_number(length, number);
+            self.push_number_checked(length, number)?;
         }
         Ok(())
     }
@@ -277,30 +491,7 @@ mod numeric_tests {
     }
 }
 
-/// Mode::Alphanumeric mode
-
-/// In QR code `Mode::Alphanumeric` mode, a pair of alphanumeric characters will
-/// be encoded as a base-45 integer. `alphanumeric_digit` converts each
-/// character into its corresponding base-45 digit.
-///
-/// The conversion is specified in ISO/IEC 18004:2006, §8.4.3, Table 5.
-#[inline]
-fn alphanumeric_digit(character: u8) -> u16 {
-    match character {
-        b'0'..=b'9' => u16::from(character - b'0'),
-        b'A'..=b'Z' => u16::from(character - b'A') + 10,
-        b' ' => 36,
-        b'$' => 37,
-        b'%' => 38,
-        b'*' => 39,
-        b'+' => 40,
-        b'-' => 41,
-        b'.' => 42,
-        b'/' => 43,
-        b':' => 44,
-        _ => 0,
-    }
-}
+// Mode::Alphanumeric mode
 
 impl Bits {
     /// Encodes an alphanumeric string to the bits.
@@ -311,15 +502,24 @@ impl Bits {
     /// # Errors
     ///
     /// Returns `Err(QrError::DataTooLong)` on overflow.
+    ///
+    /// Returns `Err(QrError::InvalidCharacter)` if the data contains another
+    /// character.
     pub fn push_alphanumeric_data(&mut self, data: &[u8]) -> QrResult<()> {
+        self.check_mode(Mode::Alphanumeric)?;
+        if !data.iter().all(|b| Mode::Alphanumeric.is_encodable(*b)) {
+            return Err(QrError::InvalidCharacter);
+        }
         self.push_header(Mode::Alphanumeric, data.len())?;
         for chunk in data.chunks(2) {
             let number = chunk
                 .iter()
-                .map(|b| alphanumeric_digit(*b))
-                .fold(0, |a, b| a * 45 + b);
+                .try_fold(0, |a, b| {
+                    Some(a * 45 + usize::from(alphanumeric_value(*b)?))
+                })
+                .ok_or(QrError::InvalidCharacter)?;
             let length = chunk.len() * 5 + 1;
-            self.push_number(length, number);
+            self.push_number_checked(length, number)?;
         }
         Ok(())
     }
@@ -328,7 +528,7 @@ impl Bits {
 #[cfg(test)]
 mod alphanumeric_tests {
     use crate::bits::Bits;
-    use crate::types::{QrError, Version};
+    use crate::types::{alphanumeric_value, Mode, QrError, Version};
 
     #[test]
     fn test_iso_18004_2006_example() {
@@ -347,12 +547,35 @@ mod alphanumeric_tests {
         );
     }
 
+    #[test]
+    fn test_charset() {
+        for byte in 0..=255 {
+            let mut bits = Bits::new(Version::Normal(1));
+            let result = bits.push_alphanumeric_data(&[byte]);
+            match alphanumeric_value(byte) {
+                Some(value) => {
+                    assert_eq!(result, Ok(()));
+                    // The 6 bits of a single character follow the 4-bit mode
+                    // indicator and the 9-bit length.
+                    let bytes = bits.into_bytes();
+                    let word = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+                    assert_eq!((word >> 5) & 0x3f, u32::from(value));
+                }
+                None => assert_eq!(result, Err(QrError::InvalidCharacter)),
+            }
+        }
+    }
+
     #[test]
     fn test_micro_qr_unsupported() {
         let mut bits = Bits::new(Version::Micro(1));
         assert_eq!(
             bits.push_alphanumeric_data(b"A"),
-            Err(QrError::UnsupportedCharacterSet)
+            Err(QrError::ModeUnavailable {
+                version: Version::Micro(1),
+                mode: Mode::Alphanumeric,
+                smallest: Version::Micro(2),
+            })
         );
     }
 
@@ -366,7 +589,7 @@ mod alphanumeric_tests {
     }
 }
 
-/// Mode::Byte mode
+// Mode::Byte mode
 
 impl Bits {
     /// Encodes 8-bit byte data to the bits.
@@ -386,7 +609,7 @@ impl Bits {
 #[cfg(test)]
 mod byte_tests {
     use crate::bits::Bits;
-    use crate::types::{QrError, Version};
+    use crate::types::{Mode, QrError, Version};
 
     #[test]
     fn test() {
@@ -417,7 +640,11 @@ mod byte_tests {
         let mut bits = Bits::new(Version::Micro(2));
         assert_eq!(
             bits.push_byte_data(b"?"),
-            Err(QrError::UnsupportedCharacterSet)
+            Err(QrError::ModeUnavailable {
+                version: Version::Micro(2),
+                mode: Mode::Byte,
+                smallest: Version::Micro(3),
+            })
         );
     }
 
@@ -431,7 +658,24 @@ mod byte_tests {
     }
 }
 
-/// Mode::Kanji mode
+// Mode::Kanji mode
+
+/// Computes the 13-bit value of a Shift JIS double-byte character, or `None`
+/// if it is not in the ranges 0x8140–0x9ffc and 0xe040–0xebbf of the Kanji
+/// mode.
+pub(crate) fn kanji_number(kanji: &[u8]) -> Option<usize> {
+    let &[hi, lo] = kanji else {
+        return None;
+    };
+    let offset = match (hi, lo) {
+        (_, 0x7f) => return None,
+        (0x81..=0x9f, 0x40..=0xfc) => 0x8140,
+        (0xe0..=0xea, 0x40..=0xfc) | (0xeb, 0x40..=0xbf) => 0xc140,
+        _ => return None,
+    };
+    let bytes = (usize::from(hi) << 8 | usize::from(lo)) - offset;
+    Some((bytes >> 8) * 0xc0 + (bytes & 0xff))
+}
 
 impl Bits {
     /// Encodes Shift JIS double-byte data to the bits.
@@ -443,19 +687,14 @@ impl Bits {
     /// Returns `Err(QrError::InvalidCharacter)` if the data is not Shift JIS
     /// double-byte data (e.g. if the length of data is not an even number).
     pub fn push_kanji_data(&mut self, data: &[u8]) -> QrResult<()> {
+        self.check_mode(Mode::Kanji)?;
+        if data.chunks(2).any(|kanji| kanji_number(kanji).is_none()) {
+            return Err(QrError::InvalidCharacter);
+        }
         self.push_header(Mode::Kanji, data.len() / 2)?;
         for kanji in data.chunks(2) {
-            if kanji.len() != 2 {
-                return Err(QrError::InvalidCharacter);
-            }
-            let cp = u16::from(kanji[0]) * 256 + u16::from(kanji[1]);
-            let bytes = if cp < 0xe040 {
-                cp - 0x8140
-            } else {
-                cp - 0xc140
-            };
-            let number = (bytes >> 8) * 0xc0 + (bytes & 0xff);
-            self.push_number(13, number);
+            let number = kanji_number(kanji).ok_or(QrError::InvalidCharacter)?;
+            self.push_number_checked(13, number)?;
         }
         Ok(())
     }
@@ -464,7 +703,7 @@ impl Bits {
 #[cfg(test)]
 mod kanji_tests {
     use crate::bits::Bits;
-    use crate::types::{QrError, Version};
+    use crate::types::{Mode, QrError, Version};
 
     #[test]
     fn test_iso_18004_example() {
@@ -487,7 +726,11 @@ mod kanji_tests {
         let mut bits = Bits::new(Version::Micro(2));
         assert_eq!(
             bits.push_kanji_data(b"?"),
-            Err(QrError::UnsupportedCharacterSet)
+            Err(QrError::ModeUnavailable {
+                version: Version::Micro(2),
+                mode: Mode::Kanji,
+                smallest: Version::Micro(3),
+            })
         );
     }
 
@@ -501,8 +744,482 @@ mod kanji_tests {
     }
 }
 
+// Headers
+
+/// The position of the FNC1 indicator, which marks the data as following an
+/// industry or application specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fnc1Kind {
+    /// The data follows the GS1 General Specifications.
+    FirstPosition,
+    /// The data follows the specification identified by the application
+    /// indicator, e.g. a letter or a number assigned by AIM.
+    SecondPosition { application_indicator: u8 },
+}
+
+/// The headers pushed to some bits, see `Bits::headers` and
+/// `QrCode::headers`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct HeaderFlags {
+    /// The first ECI designator.
+    pub eci: Option<u32>,
+    /// The FNC1 indicator.
+    pub fnc1: Option<Fnc1Kind>,
+    /// The structured append header.
+    pub structured_append: Option<PartInfo>,
+}
+
+/// How the bits of a symbol are spent, see `QrCode::encode_stats`.
+///
+/// The data bits of each mode exclude the mode indicators and the character
+/// count indicators, which are counted as header bits together with the ECI,
+/// FNC1 and structured append headers. The fields add up to the capacity of
+/// the symbol, see `total_bits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BitStats {
+    /// The data bits of the numeric segments.
+    pub numeric_bits: usize,
+    /// The data bits of the alphanumeric segments.
+    pub alphanumeric_bits: usize,
+    /// The data bits of the byte segments.
+    pub byte_bits: usize,
+    /// The data bits of the Kanji segments.
+    pub kanji_bits: usize,
+    /// The bits of the mode indicators, the character count indicators and
+    /// the other headers.
+    pub header_bits: usize,
+    /// The bits of the terminator and of the padding filling the data
+    /// codewords.
+    pub padding_bits: usize,
+    /// The bits of the error correction codewords.
+    pub ec_bits: usize,
+}
+
+impl BitStats {
+    /// The data bits of every mode.
+    pub fn data_bits(&self) -> usize {
+        self.numeric_bits + self.alphanumeric_bits + self.byte_bits + self.kanji_bits
+    }
+
+    /// The bits of the data codewords, i.e. `Bits::max_len`.
+    pub fn data_capacity_bits(&self) -> usize {
+        self.data_bits() + self.header_bits + self.padding_bits
+    }
+
+    /// The bits of every codeword of the symbol. The last data codeword of
+    /// the Micro QR codes M1, M3-L and M3-M only counts its 4 bits.
+    pub fn total_bits(&self) -> usize {
+        self.data_capacity_bits() + self.ec_bits
+    }
+}
+
+/// Encodes an ECI designator as pushed after the ECI mode indicator, and
+/// returns the number of bits and their value, including the prefix
+/// announcing the width.
+///
+/// Designators from 0 to 127 take 8 bits starting with `0`, up to 16383 16
+/// bits starting with `10`, and up to 999999 24 bits starting with `110`.
+///
+///     use qrqrpar::bits::eci_encoding;
+///
+///     assert_eq!(eci_encoding(26), Ok((8, 26)));
+///     assert_eq!(eci_encoding(1000), Ok((16, 0b10 << 14 | 1000)));
+///     assert_eq!(eci_encoding(16384), Ok((24, 0b110 << 21 | 16384)));
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidEciDesignator)` if the designator is above
+/// 999999.
+pub fn eci_encoding(designator: u32) -> QrResult<(usize, u32)> {
+    match designator {
+        0..=127 => Ok((8, designator)),
+        128..=16383 => Ok((16, 0b10 << 14 | designator)),
+        16384..=999_999 => Ok((24, 0b110 << 21 | designator)),
+        _ => Err(QrError::InvalidEciDesignator),
+    }
+}
+
+impl Bits {
+    /// Gets the headers pushed with `push_eci_designator`,
+    /// `push_fnc1_first_position`, `push_fnc1_second_position` and
+    /// `push_structured_append_header`.
+    ///
+    /// Headers pushed as raw numbers, e.g. with `push_number_checked`, are not
+    /// recorded. `inspect` reads them back from the encoded bytes instead.
+    pub fn headers(&self) -> HeaderFlags {
+        self.headers
+    }
+
+    /// Returns the statistics of the data codewords, or `None` if the
+    /// terminator has not been pushed. The error correction bits are left to
+    /// the caller, which computes the codewords.
+    pub(crate) fn encode_stats(&self, ec_level: EcLevel) -> Option<BitStats> {
+        let payload_len = self.payload_len?;
+        let [numeric_bits, alphanumeric_bits, byte_bits, kanji_bits] = self.mode_bits;
+        let data_bits = self.mode_bits.iter().sum::<usize>();
+        Some(BitStats {
+            numeric_bits,
+            alphanumeric_bits,
+            byte_bits,
+            kanji_bits,
+            header_bits: payload_len - data_bits,
+            padding_bits: self.max_len(ec_level).ok()? - payload_len,
+            ec_bits: 0,
+        })
+    }
+
+    /// Pushes the indicator of a header which only QR and rMQR codes support,
+    /// or returns the `micro` error for Micro QR codes.
+    fn push_header_indicator(
+        &mut self,
+        normal: usize,
+        rmqr: usize,
+        micro: QrError,
+    ) -> QrResult<()> {
+        match self.version {
+            _ if !self.version.is_valid() => Err(QrError::InvalidVersion),
+            Version::Micro(_) => Err(micro),
+            Version::Normal(_) => self.push_number_checked(4, normal),
+            Version::Rmqr(..) => self.push_number_checked(3, rmqr),
+        }
+    }
+
+    /// Pushes an ECI (Extended Channel Interpretation) designator, selecting
+    /// the character set of the following data, e.g. 26 for UTF-8.
+    ///
+    ///     use qrqrpar::bits::Bits;
+    ///     use qrqrpar::Version;
+    ///
+    ///     let mut bits = Bits::new(Version::Normal(1));
+    ///     bits.push_eci_designator(26).unwrap();
+    ///     assert_eq!(bits.headers().eci, Some(26));
+    ///     assert_eq!(bits.into_bytes(), vec![0b0111_0001, 0b1010_0000]);
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidEciDesignator)` if the designator is above
+    /// 999999, and `Err(QrError::UnsupportedCharacterSet)` for Micro QR codes,
+    /// which do not support ECI.
+    pub fn push_eci_designator(&mut self, designator: u32) -> QrResult<()> {
+        let (bits_count, encoded) = eci_encoding(designator)?;
+        self.push_header_indicator(0b0111, 0b111, QrError::UnsupportedCharacterSet)?;
+        if bits_count > 16 {
+            self.push_number_checked(bits_count - 16, (encoded >> 16) as usize)?;
+        }
+        self.push_number_checked(bits_count.min(16), (encoded & 0xffff) as usize)?;
+        self.headers.eci.get_or_insert(designator);
+        Ok(())
+    }
+
+    /// Pushes the FNC1 first position indicator, marking the data as GS1 data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::UnsupportedHeader)` for Micro QR codes, which do
+    /// not support FNC1.
+    pub fn push_fnc1_first_position(&mut self) -> QrResult<()> {
+        self.push_header_indicator(0b0101, 0b101, QrError::UnsupportedHeader)?;
+        self.headers.fnc1 = Some(Fnc1Kind::FirstPosition);
+        Ok(())
+    }
+
+    /// Pushes the FNC1 second position indicator followed by the application
+    /// indicator.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::UnsupportedHeader)` for Micro QR codes, which do
+    /// not support FNC1.
+    pub fn push_fnc1_second_position(&mut self, application_indicator: u8) -> QrResult<()> {
+        self.push_header_indicator(0b1001, 0b110, QrError::UnsupportedHeader)?;
+        self.push_number_checked(8, usize::from(application_indicator))?;
+        self.headers.fnc1 = Some(Fnc1Kind::SecondPosition {
+            application_indicator,
+        });
+        Ok(())
+    }
+
+    /// Pushes a structured append header, see `QrCode::with_structured_append`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidStructuredAppend)` if the total is not
+    /// between 1 and 16 or if the index is not less than the total, and
+    /// `Err(QrError::UnsupportedHeader)` for Micro QR and rMQR codes, which do
+    /// not support structured append.
+    pub fn push_structured_append_header(&mut self, part: PartInfo) -> QrResult<()> {
+        if !self.version.is_valid() {
+            return Err(QrError::InvalidVersion);
+        }
+        if !matches!(self.version, Version::Normal(_)) {
+            return Err(QrError::UnsupportedHeader);
+        }
+        if !part.is_valid() {
+            return Err(QrError::InvalidStructuredAppend);
+        }
+        self.push_number_checked(4, 0b0011)?;
+        self.push_number_checked(4, usize::from(part.index))?;
+        self.push_number_checked(4, usize::from(part.total - 1))?;
+        self.push_number_checked(8, usize::from(part.parity))?;
+        self.headers.structured_append = Some(part);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod header_tests {
+    use crate::bits::{eci_encoding, inspect, Bits, Fnc1Kind, HeaderFlags, InspectedSegment};
+    use crate::structured_append::PartInfo;
+    use crate::types::{EcLevel, Mode, QrError, Version};
+
+    #[test]
+    fn test_eci_encoding_boundaries() {
+        assert_eq!(eci_encoding(0), Ok((8, 0)));
+        assert_eq!(eci_encoding(127), Ok((8, 0b0111_1111)));
+        assert_eq!(eci_encoding(128), Ok((16, 0b1000_0000_1000_0000)));
+        assert_eq!(eci_encoding(16383), Ok((16, 0b1011_1111_1111_1111)));
+        assert_eq!(
+            eci_encoding(16384),
+            Ok((24, 0b1100_0000_0100_0000_0000_0000))
+        );
+        assert_eq!(
+            eci_encoding(999_999),
+            Ok((24, 0b1100_1111_0100_0010_0011_1111))
+        );
+        assert_eq!(eci_encoding(1_000_000), Err(QrError::InvalidEciDesignator));
+        assert_eq!(eci_encoding(u32::MAX), Err(QrError::InvalidEciDesignator));
+    }
+
+    #[test]
+    fn test_eci_round_trip() {
+        for designator in [
+            0, 1, 127, 128, 255, 256, 16383, 16384, 65535, 65536, 999_999,
+        ] {
+            let (bits_count, encoded) = eci_encoding(designator).unwrap();
+            // The value fits its width, and the prefix tells the width.
+            assert!(encoded < 1 << bits_count);
+            let prefix_bits = (encoded >> (bits_count - 3) << 29).leading_ones();
+            assert_eq!(bits_count, [8, 16, 24][prefix_bits as usize]);
+
+            let mut bits = Bits::new(Version::Normal(1));
+            bits.push_eci_designator(designator).unwrap();
+            assert_eq!(bits.len(), 4 + bits_count);
+            let segments = inspect(&bits.into_bytes(), Version::Normal(1)).unwrap();
+            assert_eq!(segments[0], InspectedSegment::Eci(designator));
+        }
+    }
+
+    #[test]
+    fn test_eci_designators() {
+        for (designator, bytes) in [
+            (9, vec![0b0111_0000, 0b1001_0000]),
+            (1000, vec![0b0111_1000, 0b0011_1110, 0b1000_0000]),
+            (
+                999_999,
+                vec![0b0111_1100, 0b1111_0100, 0b0010_0011, 0b1111_0000],
+            ),
+        ] {
+            let mut bits = Bits::new(Version::Normal(1));
+            bits.push_eci_designator(designator).unwrap();
+            assert_eq!(bits.into_bytes(), bytes);
+        }
+        let mut bits = Bits::new(Version::Rmqr(7, 43));
+        bits.push_eci_designator(26).unwrap();
+        assert_eq!(bits.into_bytes(), vec![0b1110_0011, 0b0100_0000]);
+
+        let mut bits = Bits::new(Version::Normal(1));
+        assert_eq!(
+            bits.push_eci_designator(1_000_000),
+            Err(QrError::InvalidEciDesignator)
+        );
+        let mut bits = Bits::new(Version::Micro(4));
+        assert_eq!(
+            bits.push_eci_designator(26),
+            Err(QrError::UnsupportedCharacterSet)
+        );
+        assert_eq!(bits.headers(), HeaderFlags::default());
+    }
+
+    #[test]
+    fn test_iso_eci_example() {
+        // ISO/IEC 18004:2015 §7.4.2.3: "ΑΒΓΔΕ" in ISO-8859-7, i.e. ECI 000009
+        // followed by 5 bytes in byte mode.
+        let data = [0xa1, 0xa2, 0xa3, 0xa4, 0xa5];
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_eci_designator(9).unwrap();
+        bits.push_byte_data(&data).unwrap();
+        assert_eq!(
+            bits.into_bytes(),
+            vec![
+                0b0111_0000,
+                0b1001_0100,
+                0b0000_0101,
+                0xa1,
+                0xa2,
+                0xa3,
+                0xa4,
+                0xa5
+            ]
+        );
+
+        for version in [Version::Normal(1), Version::Rmqr(13, 43)] {
+            let mut bits = Bits::new(version);
+            bits.push_eci_designator(9).unwrap();
+            bits.push_byte_data(&data).unwrap();
+            bits.push_terminator(EcLevel::M).unwrap();
+            let segments = inspect(&bits.into_bytes(), version).unwrap();
+            assert_eq!(segments[0], InspectedSegment::Eci(9), "{version:?}");
+            assert_eq!(
+                segments[1],
+                InspectedSegment::Data {
+                    mode: Mode::Byte,
+                    chars_count: 5,
+                    data: data.to_vec(),
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_fnc1() {
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_fnc1_first_position().unwrap();
+        assert_eq!(bits.headers().fnc1, Some(Fnc1Kind::FirstPosition));
+        assert_eq!(bits.into_bytes(), vec![0b0101_0000]);
+
+        let mut bits = Bits::new(Version::Rmqr(7, 43));
+        bits.push_fnc1_second_position(b'A' + 100).unwrap();
+        assert_eq!(
+            bits.headers().fnc1,
+            Some(Fnc1Kind::SecondPosition {
+                application_indicator: 165
+            })
+        );
+        assert_eq!(bits.into_bytes(), vec![0b1101_0100, 0b1010_0000]);
+
+        let mut bits = Bits::new(Version::Micro(4));
+        assert_eq!(
+            bits.push_fnc1_first_position(),
+            Err(QrError::UnsupportedHeader)
+        );
+        assert_eq!(
+            QrError::UnsupportedHeader.to_string(),
+            "header not supported by the version"
+        );
+    }
+
+    #[test]
+    fn test_gs1_example() {
+        // The GS1 element strings (01)04912345123459 (15)970331 (30)128 and
+        // (10)ABC123, the variable length (30) being ended by FNC1, which
+        // reads as `%` in alphanumeric mode.
+        let mut bits = Bits::new(Version::Normal(2));
+        bits.push_fnc1_first_position().unwrap();
+        bits.push_numeric_data(b"01049123451234591597033130128")
+            .unwrap();
+        bits.push_alphanumeric_data(b"%10ABC123").unwrap();
+        let expected = concat!(
+            "0101",
+            "0001",
+            "0000011101",
+            "0000001010",
+            "0111101011",
+            "0011101010",
+            "1000000000",
+            "0101011001",
+            "1110010011",
+            "1111001010",
+            "0101001011",
+            "0100101101",
+            "0011100",
+            "0010",
+            "000001001",
+            "11010101111",
+            "00000001010",
+            "00111111011",
+            "00000101111",
+            "000011",
+        );
+        assert_eq!(bits.len(), expected.len());
+        let stream = bits
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:08b}"))
+            .collect::<String>();
+        assert_eq!(&stream[..expected.len()], expected);
+
+        // The 4 bits of the indicator count against the capacity: 41 digits
+        // exactly fit version 1-L without it.
+        let digits = [b'7'; 41];
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_numeric_data(&digits).unwrap();
+        assert_eq!(bits.len() + 1, 152);
+        assert_eq!(bits.push_terminator(EcLevel::L), Ok(()));
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_fnc1_first_position().unwrap();
+        bits.push_numeric_data(&digits).unwrap();
+        assert_eq!(bits.push_terminator(EcLevel::L), Err(QrError::DataTooLong));
+
+        // rMQR codes use the 3-bit indicator 0b101.
+        let mut bits = Bits::new(Version::Rmqr(11, 43));
+        bits.push_fnc1_first_position().unwrap();
+        bits.push_numeric_data(b"01").unwrap();
+        let segments = inspect(&bits.into_bytes(), Version::Rmqr(11, 43)).unwrap();
+        assert_eq!(segments[0], InspectedSegment::Fnc1(Fnc1Kind::FirstPosition));
+    }
+
+    #[test]
+    fn test_combined_headers() {
+        let part = PartInfo {
+            index: 1,
+            total: 3,
+            parity: 0x5a,
+        };
+        let mut bits = Bits::new(Version::Normal(2));
+        bits.push_structured_append_header(part).unwrap();
+        bits.push_eci_designator(26).unwrap();
+        bits.push_fnc1_first_position().unwrap();
+        bits.push_eci_designator(3).unwrap();
+        assert_eq!(
+            bits.headers(),
+            HeaderFlags {
+                eci: Some(26),
+                fnc1: Some(Fnc1Kind::FirstPosition),
+                structured_append: Some(part),
+            }
+        );
+
+        // Raw headers are not recorded.
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_number_checked(4, 0b0111).unwrap();
+        bits.push_number_checked(8, 26).unwrap();
+        assert_eq!(bits.headers(), HeaderFlags::default());
+
+        for version in [Version::Rmqr(7, 43), Version::Micro(4)] {
+            let mut bits = Bits::new(version);
+            assert_eq!(
+                bits.push_structured_append_header(part),
+                Err(QrError::UnsupportedHeader)
+            );
+            assert!(bits.is_empty());
+        }
+        let mut bits = Bits::new(Version::Normal(1));
+        for (index, total) in [(0, 0), (3, 3), (0, 17)] {
+            assert_eq!(
+                bits.push_structured_append_header(PartInfo {
+                    index,
+                    total,
+                    ..part
+                }),
+                Err(QrError::InvalidStructuredAppend)
+            );
+        }
+        assert!(bits.is_empty());
+    }
+}
+
 // This table is copied from ISO/IEC 18004:2006 §6.4.10, Table 7.
-static DATA_LENGTHS: [[usize; 4]; 76] = [
+pub(crate) const DATA_LENGTHS: [[usize; 4]; 76] = [
     // Normal versions
     [152, 128, 104, 72],
     [272, 224, 176, 128],
@@ -584,56 +1301,464 @@ static DATA_LENGTHS: [[usize; 4]; 76] = [
     [0, 1216, 0, 608],
 ];
 
-impl Bits {
-    /// Pushes the ending bits to indicate no more data.
-    ///
-    /// # Errors
-    ///
-    /// Returns `Err(QrError::DataTooLong)` on overflow.
-    ///
-    /// Returns `Err(QrError::InvalidVersion)` if it is not valid to use the
-    /// `ec_level` for the given version (e.g. `Version::Micro(1)` with
-    /// `EcLevel::H`).
-    pub fn push_terminator(&mut self, ec_level: EcLevel) -> QrResult<()> {
-        let terminator_size = match self.version {
-            Version::Micro(a) => a * 2 + 1,
-            Version::Rmqr(_, _) => 3,
-            _ => 4,
-        };
+// Compile-time capacity checks
 
-        let cur_length = self.len();
-        let data_length = self.max_len(ec_level)?;
-        if cur_length > data_length {
-            return Err(QrError::DataTooLong);
-        }
+/// The number of data bits of the version at `version_index` in the capacity
+/// table, at the error correction level `ec` (`EcLevel as usize`).
+///
+/// The table lists the QR code versions 1 to 40, then the Micro QR code
+/// versions 1 to 4, then the rMQR code versions in the order of
+/// `Version::rmqr_all`. Returns 0 if the version does not support the error
+/// correction level.
+///
+///     use qrqrpar::bits::capacity_bits;
+///     use qrqrpar::EcLevel;
+///
+///     const CAPACITY: usize = capacity_bits(2, EcLevel::M as usize);
+///     assert_eq!(CAPACITY, 352);
+///
+/// # Panics
+///
+/// Panics if an index is out of range, which fails the build in const context.
+pub const fn capacity_bits(version_index: usize, ec: usize) -> usize {
+    DATA_LENGTHS[version_index][ec]
+}
 
-        let terminator_size = min(terminator_size as usize, data_length - cur_length);
-        if terminator_size > 0 {
-            self.push_number(terminator_size, 0);
-        }
+/// Returns the index of the version in the table of `capacity_bits`.
+const fn capacity_index(version: Version) -> Option<usize> {
+    match version {
+        Version::Normal(v @ 1..=40) => Some(v as usize - 1),
+        Version::Micro(v @ 1..=4) => Some(v as usize + 39),
+        Version::Rmqr(_, _) => match version.rmqr_index() {
+            Ok(index) => Some(index + 44),
+            Err(_) => None,
+        },
+        _ => None,
+    }
+}
 
-        if self.len() < data_length {
-            const PADDING_BYTES: &[u8] = &[0b1110_1100, 0b0001_0001];
+/// Returns the number of data bits of the version at the error correction
+/// level, or 0 if the version does not support it.
+const fn capacity_of(version: Version, ec_level: EcLevel) -> usize {
+    match capacity_index(version) {
+        Some(index) => capacity_bits(index, ec_level as usize),
+        None => 0,
+    }
+}
 
-            self.bit_offset = 0;
+/// Computes the number of bits of `chars_count` characters encoded as a
+/// single segment, or `None` if the version does not support the mode or the
+/// length field overflows.
+const fn single_segment_bits(mode: Mode, version: Version, chars_count: usize) -> Option<usize> {
+    let supported = match (version, mode) {
+        (Version::Micro(1), Mode::Numeric) => true,
+        (Version::Micro(1), _) | (Version::Micro(2), Mode::Byte | Mode::Kanji) => false,
+        _ => capacity_index(version).is_some(),
+    };
+    let length_bits = mode.length_bits_count(version);
+    if !supported || chars_count >> length_bits != 0 {
+        return None;
+    }
+    Some(version.mode_bits_count() + length_bits + mode.data_bits_count(chars_count))
+}
+
+/// Computes the number of bits of `len` bytes encoded as a single byte mode
+/// segment, or `None` if the version does not support byte mode or the length
+/// field overflows.
+///
+///     use qrqrpar::bits::byte_mode_bits;
+///     use qrqrpar::Version;
+///
+///     const BITS: Option<usize> = byte_mode_bits(Version::Normal(1), 10);
+///     assert_eq!(BITS, Some(4 + 8 + 80));
+///
+pub const fn byte_mode_bits(version: Version, len: usize) -> Option<usize> {
+    single_segment_bits(Mode::Byte, version, len)
+}
+
+/// Computes the number of bits of `len` digits encoded as a single numeric
+/// mode segment, or `None` if the length field overflows.
+///
+///     use qrqrpar::bits::numeric_mode_bits;
+///     use qrqrpar::Version;
+///
+///     const BITS: Option<usize> = numeric_mode_bits(Version::Micro(1), 5);
+///     assert_eq!(BITS, Some(3 + 17));
+///
+pub const fn numeric_mode_bits(version: Version, len: usize) -> Option<usize> {
+    single_segment_bits(Mode::Numeric, version, len)
+}
+
+/// Checks whether `len` bytes encoded as a single byte mode segment fit the
+/// version at the error correction level.
+pub const fn byte_mode_fits(len: usize, version: Version, ec_level: EcLevel) -> bool {
+    let capacity = capacity_of(version, ec_level);
+    match byte_mode_bits(version, len) {
+        Some(bits) => capacity != 0 && bits <= capacity,
+        None => false,
+    }
+}
+
+/// The maximum number of bytes a single byte mode segment can hold in the
+/// version at the error correction level, or 0 if the version does not
+/// support byte mode or the error correction level.
+///
+///     use qrqrpar::bits::max_byte_mode_chars;
+///     use qrqrpar::{EcLevel, Version};
+///
+///     const MAX: usize = max_byte_mode_chars(Version::Normal(3), EcLevel::M);
+///     assert_eq!(MAX, 42);
+///
+pub const fn max_byte_mode_chars(version: Version, ec_level: EcLevel) -> usize {
+    let Some(header_bits) = byte_mode_bits(version, 0) else {
+        return 0;
+    };
+    let capacity = capacity_of(version, ec_level);
+    if capacity < header_bits {
+        return 0;
+    }
+    let chars_count = (capacity - header_bits) / 8;
+    let max_length = (1 << Mode::Byte.length_bits_count(version)) - 1;
+    if chars_count < max_length {
+        chars_count
+    } else {
+        max_length
+    }
+}
+
+/// Fails the build if the payload, encoded as a single byte mode segment,
+/// does not fit the version at the error correction level.
+///
+///     use qrqrpar::{static_assert_fits, EcLevel, Version};
+///
+///     static_assert_fits!(b"https://example.com/", Version::Normal(3), EcLevel::M);
+///
+/// ```compile_fail
+/// use qrqrpar::{static_assert_fits, EcLevel, Version};
+///
+/// static_assert_fits!(&[0u8; 43], Version::Normal(3), EcLevel::M);
+/// ```
+///
+/// Byte mode is the most expensive mode for most data, so a payload passing
+/// the check always fits, although the encoder may pick cheaper modes.
+#[macro_export]
+macro_rules! static_assert_fits {
+    ($data:expr, $version:expr, $ec_level:expr $(,)?) => {
+        const _: () = assert!(
+            $crate::bits::byte_mode_fits($data.len(), $version, $ec_level),
+            "the payload does not fit the version in byte mode",
+        );
+    };
+}
+
+#[cfg(test)]
+mod const_capacity_tests {
+    use crate::bits::{byte_mode_bits, max_byte_mode_chars, numeric_mode_bits, Bits, DATA_LENGTHS};
+    use crate::internals::coding::Segment;
+    use crate::types::{EcLevel, Mode, Version};
+
+    static_assert_fits!(b"https://example.com/", Version::Normal(3), EcLevel::M);
+    static_assert_fits!(b"12345", Version::Micro(3), EcLevel::L);
+    static_assert_fits!([0u8; 42], Version::Normal(3), EcLevel::M);
+
+    fn all_versions() -> Vec<Version> {
+        (1..=40)
+            .map(Version::Normal)
+            .chain((1..=4).map(Version::Micro))
+            .chain(Version::rmqr_all())
+            .collect()
+    }
+
+    #[test]
+    fn test_max_byte_mode_chars() {
+        for version in all_versions() {
+            for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+                let Ok(capacity) = version.fetch(ec_level, &DATA_LENGTHS) else {
+                    assert_eq!(max_byte_mode_chars(version, ec_level), 0);
+                    continue;
+                };
+                let max = max_byte_mode_chars(version, ec_level);
+                let fits = |len: usize| {
+                    let mut bits = Bits::new(version);
+                    bits.push_byte_data(&vec![b'a'; len]).is_ok() && bits.len() <= capacity
+                };
+                if version.supported_modes().contains(&Mode::Byte) {
+                    assert!(fits(max), "{version} {ec_level:?}");
+                }
+                assert!(!fits(max + 1), "{version} {ec_level:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_segment_bits() {
+        for version in all_versions() {
+            for len in [1, 7, 30] {
+                for (mode, bits) in [
+                    (Mode::Byte, byte_mode_bits(version, len)),
+                    (Mode::Numeric, numeric_mode_bits(version, len)),
+                ] {
+                    let segment = Segment {
+                        mode,
+                        begin: 0,
+                        end: len,
+                    };
+                    let supported = version.supported_modes().contains(&mode);
+                    if supported && len >> mode.length_bits_count(version) == 0 {
+                        assert_eq!(bits, Some(segment.encoded_len(version)));
+                    } else {
+                        assert_eq!(bits, None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The bytes filling the unused data capacity after the terminator.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum PaddingPattern {
+    /// The alternating `0xEC`, `0x11` bytes required by the specification.
+    #[default]
+    Standard,
+    /// Zero bytes.
+    ///
+    /// This produces a non-standard symbol.
+    Zero,
+    /// The given bytes, cycled as the standard pattern is. An empty pattern
+    /// pads with zero bytes.
+    ///
+    /// This produces a non-standard symbol, unless the bytes happen to be
+    /// `[0xEC, 0x11]`.
+    Custom(Vec<u8>),
+    /// The standard bytes, arranged as some legacy encoders do, as a
+    /// compatibility shim for verifiers expecting their output.
+    ///
+    /// The final 4-bit codeword of the Micro QR code symbols M1, M3-L and
+    /// M3-M is filled with the next pattern byte (`0xE` or `0x1`) instead of
+    /// being left zero, which is the only difference with `Standard`: the
+    /// capacity of every other symbol is a whole number of bytes, so a
+    /// terminator cut short by the capacity and a full-length one truncated
+    /// to it give the same bits.
+    ///
+    /// This produces a non-standard Micro QR code symbol.
+    Legacy,
+}
+
+impl PaddingPattern {
+    fn bytes(&self) -> &[u8] {
+        const STANDARD_BYTES: &[u8] = &[0b1110_1100, 0b0001_0001];
+        const ZERO_BYTES: &[u8] = &[0];
+        match self {
+            Self::Standard | Self::Legacy => STANDARD_BYTES,
+            Self::Zero => ZERO_BYTES,
+            Self::Custom(bytes) if bytes.is_empty() => ZERO_BYTES,
+            Self::Custom(bytes) => bytes,
+        }
+    }
+}
+
+impl Bits {
+    /// Pushes the ending bits to indicate no more data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::DataTooLong)` on overflow.
+    ///
+    /// Returns `Err(QrError::InvalidVersion)` if it is not valid to use the
+    /// `ec_level` for the given version (e.g. `Version::Micro(1)` with
+    /// `EcLevel::H`).
+    pub fn push_terminator(&mut self, ec_level: EcLevel) -> QrResult<()> {
+        self.push_terminator_with_padding(ec_level, PaddingPattern::Standard)
+    }
+
+    /// Pushes the ending bits to indicate no more data, filling the remaining
+    /// capacity with the given padding pattern.
+    ///
+    /// This is meant for testing decoders. Any pattern other than
+    /// `PaddingPattern::Standard` produces a symbol that does not conform to
+    /// the specification.
+    ///
+    ///     use qrqrpar::bits::{Bits, PaddingPattern};
+    ///     use qrqrpar::{EcLevel, Version};
+    ///
+    ///     let mut bits = Bits::new(Version::Normal(1));
+    ///     bits.push_terminator_with_padding(EcLevel::H, PaddingPattern::Custom(vec![0xA5]))
+    ///         .unwrap();
+    ///     assert_eq!(bits.into_bytes(), vec![0, 0xA5, 0xA5, 0xA5, 0xA5, 0xA5, 0xA5, 0xA5, 0xA5]);
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::DataTooLong)` on overflow.
+    ///
+    /// Returns `Err(QrError::InvalidVersion)` if it is not valid to use the
+    /// `ec_level` for the given version (e.g. `Version::Micro(1)` with
+    /// `EcLevel::H`).
+    pub fn push_terminator_with_padding(
+        &mut self,
+        ec_level: EcLevel,
+        pad: PaddingPattern,
+    ) -> QrResult<()> {
+        let cur_length = self.len();
+        let data_length = self.max_len(ec_level)?;
+        // The version is valid past `max_len`.
+        let terminator_size = match self.version {
+            Version::Micro(a) => a * 2 + 1,
+            Version::Rmqr(_, _) => 3,
+            _ => 4,
+        };
+        if cur_length > data_length {
+            return Err(QrError::DataTooLong);
+        }
+        self.payload_len = Some(cur_length);
+
+        let terminator_size = min(terminator_size as usize, data_length - cur_length);
+        if terminator_size > 0 {
+            self.push_number(terminator_size, 0);
+        }
+
+        let mut padding = pad.bytes().iter().copied().cycle();
+        if self.len() < data_length {
+            self.bit_offset = 0;
             let data_bytes_length = data_length / 8;
             let padding_bytes_count = data_bytes_length.saturating_sub(self.data.len());
-            let padding = PADDING_BYTES
-                .iter()
-                .cloned()
-                .cycle()
-                .take(padding_bytes_count);
-            self.data.extend(padding);
+            self.data.extend(padding.by_ref().take(padding_bytes_count));
         }
 
+        // Only M1, M3-L and M3-M end with a 4-bit codeword, which the
+        // specification fills with zero bits rather than with a pad codeword.
         if self.len() < data_length {
-            self.data.push(0);
+            let half_codeword = match pad {
+                PaddingPattern::Legacy => padding.next().unwrap_or(0) & 0xf0,
+                _ => 0,
+            };
+            self.data.push(half_codeword);
         }
 
         Ok(())
     }
 }
 
+#[cfg(test)]
+mod padding_tests {
+    use crate::bits::{Bits, PaddingPattern};
+    use crate::types::{EcLevel, Version};
+
+    fn padded(version: Version, ec_level: EcLevel, pad: PaddingPattern) -> Vec<u8> {
+        let mut bits = Bits::new(version);
+        bits.push_byte_data(b"Hi").unwrap();
+        bits.push_terminator_with_padding(ec_level, pad).unwrap();
+        bits.into_bytes()
+    }
+
+    #[test]
+    fn test_standard_matches_push_terminator() {
+        for (version, ec_level) in [
+            (Version::Normal(1), EcLevel::L),
+            (Version::Normal(5), EcLevel::Q),
+            (Version::Micro(3), EcLevel::M),
+            (Version::Rmqr(11, 43), EcLevel::H),
+        ] {
+            let mut bits = Bits::new(version);
+            bits.push_byte_data(b"Hi").unwrap();
+            bits.push_terminator(ec_level).unwrap();
+            assert_eq!(
+                padded(version, ec_level, PaddingPattern::Standard),
+                bits.into_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn test_padded_region() {
+        // Mode (4) + length (8) + 2 bytes + terminator (4) = 4 bytes, then 5
+        // bytes of padding for 1-H.
+        let version = Version::Normal(1);
+        let ec_level = EcLevel::H;
+        let standard = padded(version, ec_level, PaddingPattern::Standard);
+        assert_eq!(standard.len(), 9);
+        assert_eq!(&standard[4..], &[0xec, 0x11, 0xec, 0x11, 0xec]);
+
+        let zero = padded(version, ec_level, PaddingPattern::Zero);
+        assert_eq!(&zero[..4], &standard[..4]);
+        assert_eq!(&zero[4..], &[0, 0, 0, 0, 0]);
+
+        let custom = padded(version, ec_level, PaddingPattern::Custom(vec![1, 2, 3]));
+        assert_eq!(&custom[..4], &standard[..4]);
+        assert_eq!(&custom[4..], &[1, 2, 3, 1, 2]);
+
+        let empty = padded(version, ec_level, PaddingPattern::Custom(vec![]));
+        assert_eq!(empty, zero);
+    }
+
+    #[test]
+    fn test_micro_half_codeword() {
+        // The last 4-bit codeword of M3-M is never padded with a pattern byte.
+        let custom = padded(
+            Version::Micro(3),
+            EcLevel::M,
+            PaddingPattern::Custom(vec![0xff]),
+        );
+        let standard = padded(Version::Micro(3), EcLevel::M, PaddingPattern::Standard);
+        assert_eq!(custom.len(), standard.len());
+        assert_eq!(custom.last(), Some(&0));
+
+        // Mode (2) + length (4) + 2 bytes + terminator (7) = 4 bytes, then 4
+        // bytes of padding and the half codeword for M3-M.
+        let legacy = padded(Version::Micro(3), EcLevel::M, PaddingPattern::Legacy);
+        assert_eq!(&legacy[..8], &standard[..8]);
+        assert_eq!(&standard[4..], &[0xec, 0x11, 0xec, 0x11, 0]);
+        assert_eq!(&legacy[4..], &[0xec, 0x11, 0xec, 0x11, 0xe0]);
+    }
+
+    /// Pushes `len` bits of ones, then pads with `pad`.
+    fn padded_after(
+        version: Version,
+        ec_level: EcLevel,
+        len: usize,
+        pad: PaddingPattern,
+    ) -> Vec<u8> {
+        let mut bits = Bits::new(version);
+        while bits.len() < len {
+            let n = (len - bits.len()).min(16);
+            bits.push_number_checked(n, (1 << n) - 1).unwrap();
+        }
+        assert_eq!(bits.len(), len);
+        bits.push_terminator_with_padding(ec_level, pad).unwrap();
+        bits.into_bytes()
+    }
+
+    #[test]
+    fn test_legacy_with_small_slack() {
+        for (version, ec_level) in [
+            (Version::Normal(1), EcLevel::L),
+            (Version::Normal(2), EcLevel::H),
+            (Version::Rmqr(7, 43), EcLevel::M),
+            (Version::Micro(1), EcLevel::L),
+            (Version::Micro(2), EcLevel::L),
+            (Version::Micro(3), EcLevel::L),
+            (Version::Micro(3), EcLevel::M),
+        ] {
+            let capacity = Bits::new(version).max_len(ec_level).unwrap();
+            for slack in 1..=7 {
+                let len = capacity - slack;
+                let standard = padded_after(version, ec_level, len, PaddingPattern::Standard);
+                let legacy = padded_after(version, ec_level, len, PaddingPattern::Legacy);
+                assert_eq!(standard.len(), capacity.div_ceil(8));
+                assert_eq!(legacy.len(), standard.len());
+                // Only an empty half codeword of M1 is left with 7 bits of
+                // slack, since its terminator is 3 bits long.
+                if (version, slack) == (Version::Micro(1), 7) {
+                    assert_eq!(standard, [0xff, 0xf8, 0x00]);
+                    assert_eq!(legacy, [0xff, 0xf8, 0xe0]);
+                } else {
+                    assert_eq!(legacy, standard, "{version:?} {slack}");
+                }
+            }
+        }
+    }
+}
+
 impl Bits {
     /// Push a segmented data to the bits, and then terminate it.
     ///
@@ -641,14 +1766,17 @@ impl Bits {
     ///
     /// Returns `Err(QrError::DataTooLong)` on overflow.
     ///
-    /// Returns `Err(QrError::InvalidData)` if the segment refers to incorrectly
-    /// encoded byte sequence.
+    /// Returns `Err(QrError::InvalidSegment)` if a segment lies outside the
+    /// data, and `Err(QrError::InvalidCharacter)` if it refers to characters
+    /// its mode cannot encode.
     pub fn push_segments<I>(&mut self, data: &[u8], segments_iter: I) -> QrResult<()>
     where
         I: Iterator<Item = Segment>,
     {
         for segment in segments_iter {
-            let slice = &data[segment.begin..segment.end];
+            let slice = data
+                .get(segment.begin..segment.end)
+                .ok_or(QrError::InvalidSegment)?;
             match segment.mode {
                 Mode::Numeric => self.push_numeric_data(slice),
                 Mode::Alphanumeric => self.push_alphanumeric_data(slice),
@@ -661,46 +1789,306 @@ impl Bits {
 
     /// Pushes the data the bits, using the optimal encoding.
     ///
+    /// Empty data pushes no segment at all, not even an empty one, so that
+    /// the symbol only holds the terminator and the padding.
+    ///
     /// # Errors
     ///
     /// Returns `Err(QrError::DataTooLong)` on overflow.
     pub fn push_optimal_data(&mut self, data: &[u8]) -> QrResult<()> {
-        let segments = Parser::new(data).optimize(self.version);
-        self.push_segments(data, segments)
+        let segments = optimal_segments(data, self.version);
+        self.push_segments(data, segments.into_iter())
     }
 }
 
-/// Auto version minimization
+// Auto version minimization
+
+/// Fails fast if `len` bytes of data cannot fit any of the versions at the
+/// error correction level, not even as digits, the cheapest mode at 10 bits
+/// per 3 characters.
+///
+/// This bounds the time spent parsing and optimizing huge data which would be
+/// rejected anyway. Versions which do not support the error correction level
+/// are ignored, so that the caller reports them as usual.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::DataTooLong)` if the data surely does not fit.
+pub(crate) fn check_len_lower_bound(
+    len: usize,
+    versions: impl IntoIterator<Item = Version>,
+    ec_level: EcLevel,
+) -> QrResult<()> {
+    let max_capacity = versions
+        .into_iter()
+        .map(|version| capacity_of(version, ec_level))
+        .max()
+        .unwrap_or(0);
+    if max_capacity != 0 && len.saturating_mul(10) / 3 > max_capacity {
+        Err(QrError::DataTooLong)
+    } else {
+        Ok(())
+    }
+}
 
 /// Automatically determines the minimum version to store the data, and encode
 /// the result.
 ///
 /// This method will not consider any Micro QR code or rMQR versions.
 ///
+/// The segments are optimized for the length fields of the range of versions
+/// (1–9, 10–26 or 27–40) the chosen version belongs to, so they are the same
+/// as if they were optimized for the chosen version itself.
+///
 /// # Errors
 ///
 /// Returns `Err(QrError::DataTooLong)` if the data is too long to fit even the
 /// highest QR code version.
 pub fn encode_auto(data: &[u8], ec_level: EcLevel) -> QrResult<Bits> {
-    let segments = Parser::new(data).collect::<Vec<Segment>>();
-    for version in &[Version::Normal(9), Version::Normal(26), Version::Normal(40)] {
-        let opt_segments = Optimizer::new(segments.iter().copied(), *version).collect::<Vec<_>>();
-        let total_len = total_encoded_len(&opt_segments, *version);
-        let data_capacity = version
+    check_len_lower_bound(data.len(), AUTO_VERSIONS, ec_level)?;
+    encode_auto_with(data, ec_level, None, |version| {
+        Ok(optimal_segments(data, version))
+    })
+}
+
+/// Automatically determines the minimum version to store the text, and encode
+/// it after the ECI designator 26, so that readers decode it as UTF-8.
+///
+///     use qrqrpar::bits::{encode_auto_utf8, inspect, InspectedSegment};
+///     use qrqrpar::{EcLevel, Version};
+///
+///     let bits = encode_auto_utf8("日本語", EcLevel::M).unwrap();
+///     assert_eq!(bits.version(), Version::Normal(1));
+///     assert_eq!(bits.headers().eci, Some(26));
+///     let segments = inspect(&bits.into_bytes(), Version::Normal(1)).unwrap();
+///     assert_eq!(segments[0], InspectedSegment::Eci(26));
+///
+/// The ECI header takes 12 bits, which may need a larger version than
+/// `encode_auto` chooses for the same bytes. The Kanji mode is never used,
+/// since it encodes Shift JIS characters, but parts of the text can still be
+/// encoded in numeric or alphanumeric mode, which ASCII shares with UTF-8.
+///
+/// This method will not consider any Micro QR code or rMQR versions, see
+/// `encode_auto_rmqr_utf8`.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::TextTooLong)` if the text is too long to fit even the
+/// highest QR code version.
+pub fn encode_auto_utf8(text: &str, ec_level: EcLevel) -> QrResult<Bits> {
+    let data = text.as_bytes();
+    check_len_lower_bound(data.len(), AUTO_VERSIONS, ec_level).map_err(|e| e.for_text(text))?;
+    let segments = parse_utf8(data);
+    encode_auto_with(data, ec_level, Some(UTF8_ECI), |version| {
+        Ok(Optimizer::new(segments.iter().copied(), version).collect())
+    })
+    .map_err(|e| e.for_text(text))
+}
+
+/// The ECI designator of UTF-8.
+const UTF8_ECI: u32 = 26;
+
+/// Parses UTF-8 text into segments, turning the Kanji segments, which are
+/// UTF-8 bytes happening to be valid Shift JIS, into byte segments.
+fn parse_utf8(data: &[u8]) -> Vec<Segment> {
+    Parser::new(data)
+        .map(|segment| match segment.mode {
+            Mode::Kanji => Segment {
+                mode: Mode::Byte,
+                ..segment
+            },
+            _ => segment,
+        })
+        .collect()
+}
+
+/// The number of bits of the ECI header pushed before the data by the auto
+/// encoders, if any.
+fn eci_header_len(version: Version, eci: Option<u32>) -> QrResult<usize> {
+    let Some(designator) = eci else {
+        return Ok(0);
+    };
+    let indicator_len = if version.is_rmqr() { 3 } else { 4 };
+    Ok(indicator_len + eci_encoding(designator)?.0)
+}
+
+/// The versions whose optimized segments `encode_auto_with` asks for. Every
+/// QR code version of the same range encodes the segments equally.
+pub(crate) const AUTO_VERSIONS: [Version; 3] =
+    [Version::Normal(9), Version::Normal(26), Version::Normal(40)];
+
+/// Same as `encode_auto`, with the ECI designator `eci` pushed before the data
+/// if any, and `optimize` returning the optimized segments of the data for a
+/// given version, or an error to return as is.
+pub(crate) fn encode_auto_with(
+    data: &[u8],
+    ec_level: EcLevel,
+    eci: Option<u32>,
+    optimize: impl Fn(Version) -> QrResult<Vec<Segment>>,
+) -> QrResult<Bits> {
+    encode_auto_within(data, ec_level, 1, 40, eci, optimize)
+}
+
+/// Same as `encode_auto_with`, choosing among the QR code versions from `min`
+/// to `max` only.
+fn encode_auto_within(
+    data: &[u8],
+    ec_level: EcLevel,
+    min: u8,
+    max: u8,
+    eci: Option<u32>,
+    optimize: impl Fn(Version) -> QrResult<Vec<Segment>>,
+) -> QrResult<Bits> {
+    let (version, opt_segments) = select_auto_within(ec_level, min, max, eci, optimize)?;
+    let mut bits = Bits::new(version);
+    if let Some(designator) = eci {
+        bits.push_eci_designator(designator)?;
+    }
+    bits.push_segments_and_terminator(data, opt_segments, ec_level, PaddingPattern::Standard)?;
+    Ok(bits)
+}
+
+/// Finds the version `encode_auto_within` encodes the data in, with the
+/// segments to push.
+fn select_auto_within(
+    ec_level: EcLevel,
+    min: u8,
+    max: u8,
+    eci: Option<u32>,
+    optimize: impl Fn(Version) -> QrResult<Vec<Segment>>,
+) -> QrResult<(Version, Vec<Segment>)> {
+    let mut range_min = 1;
+    for version in &AUTO_VERSIONS {
+        // The versions of the range sharing the length fields of `version`.
+        let Version::Normal(range_max) = *version else {
+            unreachable!("AUTO_VERSIONS holds QR code versions");
+        };
+        let (lowest, highest) = (range_min.max(min), range_max.min(max));
+        range_min = range_max + 1;
+        if lowest > highest {
+            continue;
+        }
+        let opt_segments = optimize(*version)?;
+        let total_len = eci_header_len(*version, eci)? + total_encoded_len(&opt_segments, *version);
+        let data_capacity = Version::Normal(highest)
             .fetch(ec_level, &DATA_LENGTHS)
             .expect("invalid DATA_LENGTHS");
         if total_len <= data_capacity {
-            let min_version = find_min_version(total_len, ec_level);
-            let mut bits = Bits::new(min_version);
-            bits.reserve(total_len);
-            bits.push_segments(data, opt_segments.into_iter())?;
-            bits.push_terminator(ec_level)?;
-            return Ok(bits);
+            // The lower classes have narrower length fields, so their highest
+            // versions would hold these segments too if they held `total_len`
+            // bits. Having failed, they leave the smallest version holding
+            // the data in this class, where the segments are optimal.
+            let min_version = find_min_version(total_len, ec_level).max(Version::Normal(lowest));
+            return Ok((min_version, opt_segments));
         }
     }
     Err(QrError::DataTooLong)
 }
 
+/// Finds the QR code version `encode_auto` would encode the data in, without
+/// encoding it, e.g. to lay out the symbol before rendering it.
+///
+///     use qrqrpar::bits::minimal_version_for;
+///     use qrqrpar::{EcLevel, QrCode, Version};
+///
+///     let data = b"Some data to lay out";
+///     let version = minimal_version_for(data, EcLevel::M).unwrap();
+///     assert_eq!(version, Version::Normal(2));
+///     assert_eq!(QrCode::new(data).unwrap().version(), version);
+///
+/// # Errors
+///
+/// Returns `Err(QrError::DataTooLong)` if the data is too long to fit even the
+/// highest QR code version.
+pub fn minimal_version_for(data: &[u8], ec_level: EcLevel) -> QrResult<Version> {
+    check_len_lower_bound(data.len(), AUTO_VERSIONS, ec_level)?;
+    let (version, _) = select_auto_within(ec_level, 1, 40, None, |version| {
+        Ok(optimal_segments(data, version))
+    })?;
+    Ok(version)
+}
+
+/// Automatically determines the minimum version from `min` to `max` to store
+/// the data, and encode the result, e.g. when the printed symbols must be
+/// large enough but not too dense for the scanners.
+///
+///     use qrqrpar::bits::encode_auto_in_range;
+///     use qrqrpar::{EcLevel, Version};
+///
+///     let bits = encode_auto_in_range(b"Hello", EcLevel::M, 3, 10).unwrap();
+///     assert_eq!(bits.version(), Version::Normal(3));
+///
+/// The segments are optimized for the length fields of the chosen version,
+/// as `encode_auto` does.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidVersion)` if the range is empty or not within
+/// 1 to 40, and `Err(QrError::DataTooLong)` if the data is too long to fit
+/// version `max`.
+pub fn encode_auto_in_range(data: &[u8], ec_level: EcLevel, min: u8, max: u8) -> QrResult<Bits> {
+    if !(1 <= min && min <= max && max <= 40) {
+        return Err(QrError::InvalidVersion);
+    }
+    check_len_lower_bound(data.len(), [Version::Normal(max)], ec_level)?;
+    encode_auto_within(data, ec_level, min, max, None, |version| {
+        Ok(optimal_segments(data, version))
+    })
+}
+
+/// Checks that `mode` can encode the whole data, and returns it as a single
+/// segment, or no segment at all if the data is empty.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidCharacter)` if the data contains a character
+/// the mode cannot encode, or an odd number of bytes in Kanji mode.
+fn forced_segments(data: &[u8], mode: Mode) -> QrResult<Vec<Segment>> {
+    let encodable = match mode {
+        // A trailing odd byte is a chunk of one, which is no Kanji.
+        Mode::Kanji => data.chunks(2).all(|kanji| kanji_number(kanji).is_some()),
+        _ => data.iter().all(|&byte| mode.is_encodable(byte)),
+    };
+    if !encodable {
+        return Err(QrError::InvalidCharacter);
+    }
+    if data.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(vec![Segment {
+        mode,
+        begin: 0,
+        end: data.len(),
+    }])
+}
+
+/// Automatically determines the minimum version to store the data encoded in
+/// a single segment of the given mode, instead of the optimal segments, e.g.
+/// for readers which mishandle symbols mixing several modes.
+///
+///     use qrqrpar::bits::{encode_auto, encode_auto_forced};
+///     use qrqrpar::types::Mode;
+///     use qrqrpar::{EcLevel, Version};
+///
+///     let bits = encode_auto_forced(&[b'1'; 40], EcLevel::M, Mode::Byte).unwrap();
+///     assert_eq!(bits.version(), Version::Normal(3));
+///     let bits = encode_auto(&[b'1'; 40], EcLevel::M).unwrap();
+///     assert_eq!(bits.version(), Version::Normal(2));
+///
+/// This method will not consider any Micro QR code or rMQR versions, see
+/// `encode_auto_rmqr_forced`.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidCharacter)` if the mode cannot encode the
+/// data, and `Err(QrError::DataTooLong)` if the data is too long to fit even
+/// the highest QR code version.
+pub fn encode_auto_forced(data: &[u8], ec_level: EcLevel, mode: Mode) -> QrResult<Bits> {
+    let segments = forced_segments(data, mode)?;
+    check_len_lower_bound(data.len(), AUTO_VERSIONS, ec_level)?;
+    encode_auto_with(data, ec_level, None, |_| Ok(segments.clone()))
+}
+
 /// Finds the smallest version (QR code only) that can store N bits of data
 /// in the given error correction level.
 fn find_min_version(length: usize, ec_level: EcLevel) -> Version {
@@ -728,61 +2116,2324 @@ fn find_min_version(length: usize, ec_level: EcLevel) -> Version {
     Version::Normal((base + 1) as u8)
 }
 
-/// Auto rMQR's version minimization strategy
-#[derive(Debug, Clone, Copy)]
-pub enum RmqrStrategy {
-    /// minimize the width
-    Width,
-    /// minimize the height
-    Height,
-    /// minimize the area
-    Area,
+#[cfg(test)]
+mod in_range_tests {
+    use crate::bits::{encode_auto, encode_auto_in_range, Bits};
+    use crate::internals::coding::optimal_segments;
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::QrCode;
+
+    #[test]
+    fn test_full_range() {
+        for data in [&b"Hello"[..], b"0123456789", &[b'a'; 300], &[b'7'; 3000]] {
+            let expected = encode_auto(data, EcLevel::Q).unwrap();
+            let bits = encode_auto_in_range(data, EcLevel::Q, 1, 40).unwrap();
+            assert_eq!(bits.version(), expected.version());
+            assert_eq!(bits.into_bytes(), expected.into_bytes());
+        }
+    }
+
+    #[test]
+    fn test_window() {
+        let select = |data: &[u8], min, max| {
+            encode_auto_in_range(data, EcLevel::M, min, max).map(|bits| bits.version())
+        };
+        assert_eq!(select(b"Hello", 3, 10), Ok(Version::Normal(3)));
+        assert_eq!(select(&[b'a'; 180], 3, 10), Ok(Version::Normal(9)));
+        // 300 bytes need version 13 at level M.
+        assert_eq!(select(&[b'a'; 300], 3, 12), Err(QrError::DataTooLong));
+        assert_eq!(select(&[b'a'; 300], 3, 13), Ok(Version::Normal(13)));
+        assert_eq!(select(&[b'a'; 1 << 20], 3, 10), Err(QrError::DataTooLong));
+        for (min, max) in [(0, 10), (5, 4), (1, 41)] {
+            assert_eq!(select(b"Hello", min, max), Err(QrError::InvalidVersion));
+        }
+    }
+
+    #[test]
+    fn test_length_fields() {
+        // Version 10 has wider length fields than version 9, so the segments
+        // are optimized again for it.
+        let data = b"123456789012345678901234567890abcdefghijklmnopqrstuvwxyz";
+        let bits = encode_auto_in_range(data, EcLevel::L, 10, 40).unwrap();
+        assert_eq!(bits.version(), Version::Normal(10));
+        let mut expected = Bits::new(Version::Normal(10));
+        let segments = optimal_segments(data, Version::Normal(10));
+        expected.push_segments(data, segments.into_iter()).unwrap();
+        expected.push_terminator(EcLevel::L).unwrap();
+        assert_eq!(bits.into_bytes(), expected.into_bytes());
+
+        let code = QrCode::with_version_constraints(data, EcLevel::L, 10..=40).unwrap();
+        assert_eq!(code.version(), Version::Normal(10));
+        assert!(code.encode_stats().is_some());
+    }
 }
 
-/// Auto rMQR's version minimization
+#[cfg(test)]
+mod length_class_tests {
+    use crate::bits::{encode_auto, fits, Bits};
+    use crate::internals::coding::optimal_segments;
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::QrCode;
+
+    /// Checks that `encode_auto` chooses the smallest version holding the
+    /// data, with the segments optimized for the length fields of that very
+    /// version, and returns it.
+    fn check(data: &[u8], ec_level: EcLevel) -> Version {
+        let bits = encode_auto(data, ec_level).unwrap();
+        let version = bits.version();
+        let mut expected = Bits::new(version);
+        let segments = optimal_segments(data, version);
+        expected.push_segments(data, segments.into_iter()).unwrap();
+        expected.push_terminator(ec_level).unwrap();
+        assert_eq!(bits.into_bytes(), expected.into_bytes(), "{version:?}");
+        if let Version::Normal(n @ 2..) = version {
+            let smaller = QrCode::with_version(data, Version::Normal(n - 1), ec_level);
+            assert_eq!(smaller.err(), Some(QrError::DataTooLong), "{version:?}");
+        }
+        version
+    }
 
-/// Automatically determines the minimum version to store the data, and encode
-/// the result.
-///
-/// This method will not consider QR or Micro QR code versions.
-///
-/// # Errors
-///
-/// Returns `Err(QrError::DataTooLong)` if the data is too long to fit even the
-/// highest rMQR code version.
-pub fn encode_auto_rmqr(data: &[u8], ec_level: EcLevel, strategy: RmqrStrategy) -> QrResult<Bits> {
-    let segments = Parser::new(data).collect::<Vec<Segment>>();
-    let mut possible_versions = vec![];
-    for width in Version::rmqr_all_width() {
-        for height in Version::rmqr_all_height() {
-            let version = Version::Rmqr(height, width);
-            if !version.is_rmqr() {
-                continue;
-            }
-            let opt_segments =
-                Optimizer::new(segments.iter().copied(), version).collect::<Vec<_>>();
-            let total_len = total_encoded_len(&opt_segments, version);
-            let data_capacity = version.fetch(ec_level, &DATA_LENGTHS)?;
-            if total_len <= data_capacity {
-                possible_versions.push(version);
-                break;
+    #[test]
+    fn test_class_boundaries() {
+        // Mixed data is segmented differently in each length class.
+        let units: [&[u8]; 4] = [b"a", b"7", b"A", b"123456789abcdefXYZ"];
+        for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+            for unit in units {
+                let data = |len: usize| unit.iter().copied().cycle().take(len).collect::<Vec<_>>();
+                for boundary in [9, 26] {
+                    // The longest data of the unit fitting the last version of
+                    // the class.
+                    let version = Version::Normal(boundary);
+                    let lens = (0..4000).collect::<Vec<usize>>();
+                    let len = lens
+                        .partition_point(|len| fits(&data(*len), version, ec_level).unwrap())
+                        - 1;
+                    assert_eq!(check(&data(len), ec_level), version);
+                    let next = Version::Normal(boundary + 1);
+                    assert_eq!(check(&data(len + 1), ec_level), next);
+                }
             }
         }
     }
+}
 
-    let min_version = match strategy {
-        RmqrStrategy::Width => possible_versions.first(), // possible_versions is already sorted by width
-        RmqrStrategy::Height => possible_versions.iter().min_by_key(|v| v.height()),
-        RmqrStrategy::Area => possible_versions.iter().min_by_key(|v| v.area()),
+#[cfg(test)]
+mod char_optimization_tests {
+    use crate::bits::{
+        encode_auto, encode_auto_rmqr, encode_auto_rmqr_with, encode_auto_with, Bits, RmqrStrategy,
     };
+    use crate::internals::coding::{total_encoded_len, Optimizer, Parser};
+    use crate::types::{EcLevel, Version};
+    use crate::QrCode;
 
-    if let Some(version) = min_version {
-        let mut bits = Bits::new(*version);
-        let opt_segments = Optimizer::new(segments.iter().copied(), *version).collect::<Vec<_>>();
-        bits.reserve(total_encoded_len(&opt_segments, *version));
-        bits.push_segments(data, opt_segments.into_iter())?;
-        bits.push_terminator(ec_level)?;
-        return Ok(bits);
+    /// A stray Shift JIS lead byte after some digits, then `n` times "点".
+    fn misaligned(digits: usize, n: usize) -> Vec<u8> {
+        let mut data = vec![b'1'; digits];
+        data.push(0x93);
+        data.extend(b"\x93\x5f".repeat(n));
+        data
+    }
+
+    /// The version chosen when merging the parsed segments only.
+    fn merged_version(data: &[u8], ec_level: EcLevel) -> Version {
+        encode_auto_with(data, ec_level, None, |version| {
+            Ok(Optimizer::new(Parser::new(data), version).collect())
+        })
+        .unwrap()
+        .version()
+    }
+
+    #[test]
+    fn test_smaller_version() {
+        let data = misaligned(2, 8);
+        assert_eq!(merged_version(&data, EcLevel::L), Version::Normal(2));
+        let bits = encode_auto(&data, EcLevel::L).unwrap();
+        assert_eq!(bits.version(), Version::Normal(1));
+        let code = QrCode::with_error_correction_level(&data, EcLevel::L).unwrap();
+        assert_eq!(code.version(), Version::Normal(1));
+
+        let data = misaligned(0, 11);
+        assert_eq!(merged_version(&data, EcLevel::Q), Version::Normal(3));
+        assert_eq!(
+            encode_auto(&data, EcLevel::Q).unwrap().version(),
+            Version::Normal(2)
+        );
+    }
+
+    #[test]
+    fn test_smaller_rmqr_version() {
+        let data = misaligned(0, 5);
+        let strategy = RmqrStrategy::Area;
+        let merged = encode_auto_rmqr_with(&data, EcLevel::H, strategy, None, |version| {
+            Ok(Optimizer::new(Parser::new(&data), version).collect())
+        });
+        assert_eq!(merged.unwrap().version(), Version::Rmqr(13, 43));
+        let bits = encode_auto_rmqr(&data, EcLevel::H, strategy).unwrap();
+        assert_eq!(bits.version(), Version::Rmqr(11, 43));
+    }
+
+    #[test]
+    fn test_push_optimal_data() {
+        // Version 1-L holds 152 bits, exactly what the data needs, and 3 bits
+        // fewer than the merged parsed segments need.
+        let data = misaligned(2, 8);
+        let version = Version::Normal(1);
+        let merged = Optimizer::new(Parser::new(&data), version).collect::<Vec<_>>();
+        assert_eq!(total_encoded_len(&merged, version), 155);
+        let mut bits = Bits::new(version);
+        bits.push_optimal_data(&data).unwrap();
+        assert_eq!(bits.len(), 152);
+        bits.push_terminator(EcLevel::L).unwrap();
+        let stats = bits.encode_stats(EcLevel::L).unwrap();
+        assert_eq!(stats.kanji_bits, 8 * 13);
+        assert_eq!(stats.byte_bits, 3 * 8);
+        assert!(QrCode::with_version(&data, Version::Normal(1), EcLevel::L).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod kanji_estimate_tests {
+    use crate::bits::{
+        encode_auto, encode_auto_micro, encode_auto_rmqr, Bits, RmqrStrategy, DATA_LENGTHS,
+    };
+    use crate::internals::coding::{optimal_segments, total_encoded_len, Optimizer, Parser};
+    use crate::types::{EcLevel, Version};
+
+    /// Kanji-heavy Shift JIS payloads: "点茗" runs mixed with digits,
+    /// alphanumeric and byte characters, and a stray lead byte.
+    fn payloads() -> Vec<Vec<u8>> {
+        let kanji = b"\x93\x5f\xe4\xaa";
+        vec![
+            kanji.repeat(5),
+            [&b"0123"[..], &kanji.repeat(3), b"ABC", kanji, b"abc"].concat(),
+            [&kanji[..], b"12", kanji, b"3456789", kanji, b"XY"].concat(),
+            [&b"1"[..], &[0x93], &kanji.repeat(4), b"\x93"].concat(),
+            [
+                &kanji.repeat(20)[..],
+                b"0123456789".as_slice(),
+                &kanji.repeat(20),
+            ]
+            .concat(),
+        ]
+    }
+
+    /// The bits actually pushed for the segments, if they can be encoded.
+    fn pushed_len(
+        data: &[u8],
+        segments: &[crate::internals::coding::Segment],
+        version: Version,
+    ) -> Option<usize> {
+        let mut bits = Bits::new(version);
+        bits.push_segments(data, segments.iter().copied()).ok()?;
+        Some(bits.len())
+    }
+
+    fn versions() -> impl Iterator<Item = Version> {
+        (1..=40)
+            .map(Version::Normal)
+            .chain((3..=4).map(Version::Micro))
+            .chain(Version::rmqr_all())
+    }
+
+    #[test]
+    fn test_estimate_matches_pushed_bits() {
+        for data in payloads() {
+            for version in versions() {
+                let optimal = optimal_segments(&data, version);
+                let merged: Vec<_> = Optimizer::new(Parser::new(&data), version).collect();
+                for segments in [optimal, merged] {
+                    if let Some(len) = pushed_len(&data, &segments, version) {
+                        assert_eq!(len, total_encoded_len(&segments, version), "{version:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    /// The smallest version of the list holding the data, by actually pushing
+    /// it.
+    fn smallest_fitting(data: &[u8], versions: &[Version], ec_level: EcLevel) -> Option<Version> {
+        versions.iter().copied().find(|version| {
+            let Ok(capacity) = version.fetch(ec_level, &DATA_LENGTHS) else {
+                return false;
+            };
+            let segments = optimal_segments(data, *version);
+            pushed_len(data, &segments, *version).is_some_and(|len| len <= capacity)
+        })
+    }
+
+    #[test]
+    fn test_chosen_version_is_smallest() {
+        let normal: Vec<_> = (1..=40).map(Version::Normal).collect();
+        let micro: Vec<_> = (1..=4).map(Version::Micro).collect();
+        let mut rmqr = Version::rmqr_all().to_vec();
+        rmqr.sort_by_key(|v| v.width());
+        for data in payloads() {
+            for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+                assert_eq!(
+                    encode_auto(&data, ec_level).ok().map(|bits| bits.version()),
+                    smallest_fitting(&data, &normal, ec_level)
+                );
+                if ec_level != EcLevel::H {
+                    assert_eq!(
+                        encode_auto_micro(&data, ec_level)
+                            .ok()
+                            .map(|bits| bits.version()),
+                        smallest_fitting(&data, &micro, ec_level)
+                    );
+                }
+                if ec_level == EcLevel::M || ec_level == EcLevel::H {
+                    let result = encode_auto_rmqr(&data, ec_level, RmqrStrategy::Width);
+                    assert_eq!(
+                        result.ok().map(|bits| bits.version().width()),
+                        smallest_fitting(&data, &rmqr, ec_level).map(|v| v.width())
+                    );
+                }
+            }
+        }
+    }
+}
+
+// Auto Micro QR code version minimization
+
+/// Automatically determines the minimum Micro QR code version to store the
+/// data, and encode the result.
+///
+/// This method will not consider QR or rMQR code versions. The segments are
+/// optimized again for each version using only the modes it supports, so that
+/// e.g. `"123 456"` fits version 2 even though version 2 has no byte mode.
+/// Versions which cannot encode some character of the data, or do not support
+/// the error correction level (see `Version::available_ec_levels`), are
+/// skipped.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::EcLevelUnavailable)` if no Micro QR code version
+/// supports the error correction level, i.e. for `EcLevel::H`, or
+/// `Err(QrError::DataTooLong)` if the data is too long to fit even the
+/// highest Micro QR code version.
+pub fn encode_auto_micro(data: &[u8], ec_level: EcLevel) -> QrResult<Bits> {
+    encode_auto_micro_with(data, ec_level, PaddingPattern::Standard)
+}
+
+/// Same as `encode_auto_micro`, filling the remaining capacity with `pad`.
+pub(crate) fn encode_auto_micro_with(
+    data: &[u8],
+    ec_level: EcLevel,
+    pad: PaddingPattern,
+) -> QrResult<Bits> {
+    // M4 supports every level any Micro QR code version supports.
+    Version::Micro(4)
+        .check_ec_level(ec_level)
+        .map_err(QrError::EcLevelUnavailable)?;
+    let versions = (1..=4)
+        .map(Version::Micro)
+        .filter(|version| version.available_ec_levels().contains(&ec_level));
+    check_len_lower_bound(data.len(), versions.clone(), ec_level)?;
+    for version in versions {
+        // The segments only use the supported modes whenever the data allows
+        // it, so a version either fits or is skipped without encoding.
+        let opt_segments = optimal_segments(data, version);
+        if segments_fit(&opt_segments, version, ec_level)? {
+            let mut bits = Bits::new(version);
+            bits.push_segments_and_terminator(data, opt_segments, ec_level, pad)?;
+            return Ok(bits);
+        }
+    }
+    Err(QrError::DataTooLong)
+}
+
+#[cfg(test)]
+mod utf8_auto_tests {
+    use crate::bits::{
+        encode_auto, encode_auto_rmqr, encode_auto_rmqr_utf8, encode_auto_utf8, inspect, Bits,
+        InspectedSegment, RmqrStrategy,
+    };
+    use crate::types::{EcLevel, Mode, QrError, Version};
+
+    /// Reads back the data of the bits, keeping the segments before the
+    /// terminator.
+    fn segments(bits: Bits) -> Vec<InspectedSegment> {
+        let version = bits.version();
+        inspect(&bits.into_bytes(), version)
+            .unwrap()
+            .into_iter()
+            .take_while(|segment| !matches!(segment, InspectedSegment::Terminator { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_kanji() {
+        let text = "ありがとう";
+        let plain = segments(encode_auto(text.as_bytes(), EcLevel::M).unwrap());
+        assert!(plain.iter().any(|segment| matches!(
+            segment,
+            InspectedSegment::Data {
+                mode: Mode::Kanji,
+                ..
+            }
+        )));
+
+        let utf8 = segments(encode_auto_utf8(text, EcLevel::M).unwrap());
+        assert_eq!(
+            utf8,
+            vec![
+                InspectedSegment::Eci(26),
+                InspectedSegment::Data {
+                    mode: Mode::Byte,
+                    chars_count: 15,
+                    data: text.as_bytes().to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ascii_modes() {
+        let text = "Café 20240117";
+        let utf8 = segments(encode_auto_utf8(text, EcLevel::L).unwrap());
+        assert_eq!(utf8[0], InspectedSegment::Eci(26));
+        assert!(matches!(
+            utf8.last(),
+            Some(InspectedSegment::Data {
+                mode: Mode::Numeric,
+                chars_count: 8,
+                ..
+            })
+        ));
+        let data = utf8[1..]
+            .iter()
+            .flat_map(|segment| match segment {
+                InspectedSegment::Data { data, .. } => data.clone(),
+                _ => panic!("unexpected segment {segment}"),
+            })
+            .collect::<Vec<u8>>();
+        assert_eq!(String::from_utf8(data).unwrap(), text);
+    }
+
+    #[test]
+    fn test_version_boundary() {
+        let ec_level = EcLevel::M;
+        let (text, plain) = (1..)
+            .map(|n| "é".repeat(n))
+            .find_map(|text| {
+                let plain = encode_auto(text.as_bytes(), ec_level).unwrap();
+                let utf8 = encode_auto_utf8(&text, ec_level).unwrap();
+                (utf8.version() != plain.version()).then_some((text, plain.version()))
+            })
+            .unwrap();
+        let Version::Normal(n) = plain else {
+            panic!("unexpected version {plain:?}");
+        };
+        assert_eq!(
+            encode_auto_utf8(&text, ec_level).unwrap().version(),
+            Version::Normal(n + 1)
+        );
+
+        // The 12 bits of the ECI header are what overflows the smaller version.
+        let mut bits = Bits::new(plain);
+        bits.push_optimal_data(text.as_bytes()).unwrap();
+        let capacity = bits.max_len(ec_level).unwrap();
+        assert!(bits.len() <= capacity && bits.len() + 12 > capacity);
+        let mut bits = Bits::new(plain);
+        bits.push_eci_designator(26).unwrap();
+        bits.push_optimal_data(text.as_bytes()).unwrap();
+        assert_eq!(bits.push_terminator(ec_level), Err(QrError::DataTooLong));
+
+        // One character less fits the smaller version with the header.
+        let shorter = &text[..text.len() - "é".len()];
+        assert_eq!(
+            encode_auto_utf8(shorter, ec_level).unwrap().version(),
+            plain
+        );
+    }
+
+    #[test]
+    fn test_rmqr_version_boundary() {
+        let (ec_level, strategy) = (EcLevel::M, RmqrStrategy::Area);
+        let (text, plain, utf8) = (1..)
+            .map(|n| "é".repeat(n))
+            .find_map(|text| {
+                let plain = encode_auto_rmqr(text.as_bytes(), ec_level, strategy).unwrap();
+                let utf8 = encode_auto_rmqr_utf8(&text, ec_level, strategy).unwrap();
+                (utf8.version() != plain.version()).then_some((text, plain.version(), utf8))
+            })
+            .unwrap();
+        assert!(utf8.version().area() > plain.area());
+        assert_eq!(segments(utf8)[..1], [InspectedSegment::Eci(26)]);
+
+        // The ECI header of rMQR codes takes 3 + 8 bits.
+        let mut bits = Bits::new(plain);
+        bits.push_optimal_data(text.as_bytes()).unwrap();
+        let capacity = bits.max_len(ec_level).unwrap();
+        assert!(bits.len() <= capacity && bits.len() + 11 > capacity);
+    }
+
+    #[test]
+    fn test_too_long() {
+        let text = "é".repeat(1500);
+        let too_long = QrError::TextTooLong {
+            chars: 1500,
+            bytes: 3000,
+        };
+        assert_eq!(encode_auto_utf8(&text, EcLevel::L).err(), Some(too_long));
+        assert_eq!(
+            encode_auto_rmqr_utf8(&text, EcLevel::M, RmqrStrategy::Area).err(),
+            Some(too_long)
+        );
+        // Short enough for the length lower bound, too long once encoded.
+        let text = "かな".repeat(400);
+        assert_eq!(
+            encode_auto_rmqr_utf8(&text, EcLevel::H, RmqrStrategy::Area).err(),
+            Some(QrError::TextTooLong {
+                chars: 800,
+                bytes: 2400,
+            })
+        );
+    }
+}
+
+#[cfg(test)]
+mod micro_auto_tests {
+    use crate::bits::encode_auto_micro;
+    use crate::types::{EcLevel, EcLevelUnavailable, QrError, Version};
+
+    fn select(data: &[u8], ec_level: EcLevel) -> Result<Version, QrError> {
+        encode_auto_micro(data, ec_level).map(|bits| bits.version())
+    }
+
+    #[test]
+    fn test_smallest_version() {
+        assert_eq!(select(b"12345", EcLevel::L), Ok(Version::Micro(1)));
+        assert_eq!(select(b"123 456", EcLevel::L), Ok(Version::Micro(2)));
+        assert_eq!(select(b"123a", EcLevel::L), Ok(Version::Micro(3)));
+        assert_eq!(select(b"abcdefghijkl", EcLevel::L), Ok(Version::Micro(4)));
+    }
+
+    #[test]
+    fn test_ec_level() {
+        assert_eq!(select(b"12345", EcLevel::M), Ok(Version::Micro(2)));
+        assert_eq!(select(b"12345", EcLevel::Q), Ok(Version::Micro(4)));
+        let error = QrError::EcLevelUnavailable(EcLevelUnavailable {
+            version: Version::Micro(4),
+            ec_level: EcLevel::H,
+            smallest: None,
+        });
+        assert_eq!(select(b"12345", EcLevel::H), Err(error));
+        assert_eq!(
+            error.to_string(),
+            "M4 supports L, M and Q only; no Micro QR version supports H"
+        );
+        assert_eq!(select(&[b'1'; 100], EcLevel::H), Err(error));
+        assert_eq!(select(&[b'1'; 100], EcLevel::Q), Err(QrError::DataTooLong));
+    }
+
+    #[test]
+    fn test_too_long() {
+        assert_eq!(select(&[b'a'; 30], EcLevel::L), Err(QrError::DataTooLong));
+    }
+
+    #[test]
+    fn test_m4_capacity() {
+        // The byte and Kanji capacities of M4, which M3 cannot hold, and one
+        // character more.
+        let kanji = b"\x93\x5f";
+        for (ec_level, bytes, kanji_count) in
+            [(EcLevel::L, 15, 9), (EcLevel::M, 13, 8), (EcLevel::Q, 9, 5)]
+        {
+            assert_eq!(select(&vec![b'a'; bytes], ec_level), Ok(Version::Micro(4)));
+            assert_eq!(
+                select(&vec![b'a'; bytes + 1], ec_level),
+                Err(QrError::DataTooLong)
+            );
+            let data = kanji.repeat(kanji_count);
+            assert_eq!(select(&data, ec_level), Ok(Version::Micro(4)));
+            let data = kanji.repeat(kanji_count + 1);
+            assert_eq!(select(&data, ec_level), Err(QrError::DataTooLong));
+        }
+        // M3 holds 9 bytes at L, but has no Q level.
+        assert_eq!(select(&[b'a'; 9], EcLevel::L), Ok(Version::Micro(3)));
+        assert_eq!(select(b"1", EcLevel::Q), Ok(Version::Micro(4)));
+    }
+}
+
+#[cfg(test)]
+mod len_lower_bound_tests {
+    use crate::bits::{
+        check_len_lower_bound, encode_auto, encode_auto_micro, encode_auto_rmqr, RmqrStrategy,
+        AUTO_VERSIONS,
+    };
+    use crate::types::{EcLevel, QrError, Version};
+
+    #[test]
+    fn test_huge_data_fails_fast() {
+        let data = vec![b'a'; 1 << 20];
+        // The length alone rules out every family, so the data is rejected
+        // before it is parsed.
+        let micro = (1..=4).map(Version::Micro);
+        for (versions, ec_level) in [
+            (AUTO_VERSIONS.to_vec(), EcLevel::L),
+            (Version::rmqr_all().to_vec(), EcLevel::M),
+            (micro.collect(), EcLevel::L),
+        ] {
+            assert_eq!(
+                check_len_lower_bound(data.len(), versions, ec_level),
+                Err(QrError::DataTooLong)
+            );
+        }
+        assert_eq!(
+            encode_auto(&data, EcLevel::L).err(),
+            Some(QrError::DataTooLong)
+        );
+        let rmqr = encode_auto_rmqr(&data, EcLevel::M, RmqrStrategy::Area);
+        assert_eq!(rmqr.err(), Some(QrError::DataTooLong));
+        assert_eq!(
+            encode_auto_micro(&data, EcLevel::L).err(),
+            Some(QrError::DataTooLong)
+        );
+    }
+
+    #[test]
+    fn test_boundary() {
+        // 7089 digits is the capacity of version 40-L. The lower bound only
+        // rejects 7095 digits or more, so the lengths in between are still
+        // segmented.
+        let digits = vec![b'7'; 7095];
+        let bits = encode_auto(&digits[..7089], EcLevel::L).unwrap();
+        assert_eq!(bits.version(), Version::Normal(40));
+        for len in 7090..=7095 {
+            let result = encode_auto(&digits[..len], EcLevel::L);
+            assert_eq!(result.err(), Some(QrError::DataTooLong));
+        }
+
+        let bits = encode_auto_rmqr(&digits[..361], EcLevel::M, RmqrStrategy::Area).unwrap();
+        assert_eq!(bits.version(), Version::Rmqr(17, 139));
+        let bits = encode_auto_micro(&digits[..35], EcLevel::L).unwrap();
+        assert_eq!(bits.version(), Version::Micro(4));
+    }
+
+    #[test]
+    fn test_unsupported_ec_level() {
+        let data = vec![b'1'; 1 << 20];
+        let result = encode_auto_micro(&data, EcLevel::H);
+        assert!(matches!(result, Err(QrError::EcLevelUnavailable(_))));
+    }
+}
+
+/// Version minimization strategy, for the rMQR code versions of
+/// `encode_auto_rmqr` or every version of `encode_auto_smallest`.
+///
+/// Across families, the QR and Micro QR code versions are squares competing
+/// with the rMQR rectangles: `Width` and `Area` tend to pick a Micro QR code for
+/// short data since the narrowest rMQR version is already 27 modules wide,
+/// while `Height` picks a rMQR code whenever one of its 7 to 17 modules high
+/// versions can hold the data.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShapeStrategy {
+    /// Minimize the width.
+    Width,
+    /// Minimize the height.
+    Height,
+    /// Minimize the area.
+    Area,
+    /// Minimize the perimeter, i.e. width + height.
+    Perimeter,
+    /// Choose the width/height ratio nearest to the given target, preferring
+    /// the smaller area on ties.
+    ///
+    /// The target is rounded to thousandths, so that the selection does not
+    /// depend on floating point rounding.
+    AspectRatio(f64),
+    /// Only consider the versions of the given height, minimizing the width.
+    FixedHeight(u8),
+    /// Only consider the versions of the given width, minimizing the height.
+    FixedWidth(u8),
+}
+
+impl ShapeStrategy {
+    /// Whether the version has the dimension fixed by the strategy, if any.
+    fn admits(self, version: Version) -> bool {
+        match self {
+            ShapeStrategy::FixedHeight(height) => version.height() == i16::from(height),
+            ShapeStrategy::FixedWidth(width) => version.width() == i16::from(width),
+            _ => true,
+        }
+    }
+
+    /// Checks that some of the versions has the dimension fixed by the
+    /// strategy, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidVersion)` if none has.
+    fn check_admits_any(self, versions: impl IntoIterator<Item = Version>) -> QrResult<()> {
+        if versions.into_iter().any(|version| self.admits(version)) {
+            Ok(())
+        } else {
+            Err(QrError::InvalidVersion)
+        }
+    }
+}
+
+/// Checks a request to encode `len` bytes of data into an rMQR code, before
+/// any parsing: first that the strategy admits some rMQR version and that
+/// rMQR codes support the error correction level, then that the data may
+/// fit, see `check_len_lower_bound`. Every rMQR entry point calls this, so
+/// they all report an invalid request the same way however long the data is.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidVersion)` if no rMQR version has the fixed
+/// dimension, `Err(QrError::EcLevelUnavailable)` if the level is L or Q, and
+/// `Err(QrError::DataTooLong)` if the data surely does not fit.
+pub(crate) fn check_rmqr_request(
+    len: usize,
+    ec_level: EcLevel,
+    strategy: RmqrStrategy,
+) -> QrResult<()> {
+    strategy.check_admits_any(Version::rmqr_all())?;
+    // Every rMQR version supports the same levels.
+    Version::Rmqr(7, 43)
+        .check_ec_level(ec_level)
+        .map_err(QrError::EcLevelUnavailable)?;
+    check_len_lower_bound(len, Version::rmqr_all(), ec_level)
+}
+
+/// The former name of `ShapeStrategy`, from when it only applied to rMQR
+/// codes.
+pub type RmqrStrategy = ShapeStrategy;
+
+/// Compares how far the aspect ratios of `a` and `b` are from `target`, given
+/// in thousandths.
+///
+/// `|w/h - t|` is compared as `|1000w - th| / h` using an integer
+/// cross-multiplication.
+fn cmp_aspect_ratio_distance(a: Version, b: Version, target: u64) -> Ordering {
+    let distance = |v: Version| (1000 * v.width() as u64).abs_diff(target * v.height() as u64);
+    (distance(a) * b.height() as u64).cmp(&(distance(b) * a.height() as u64))
+}
+
+// Auto rMQR's version minimization
+
+/// Automatically determines the minimum version to store the data, and encode
+/// the result.
+///
+/// This method will not consider QR or Micro QR code versions.
+///
+/// `RmqrStrategy::FixedHeight` and `RmqrStrategy::FixedWidth` restrict the
+/// search to the versions of that height or width:
+///
+///     use qrqrpar::bits::{encode_auto_rmqr, RmqrStrategy};
+///     use qrqrpar::types::QrError;
+///     use qrqrpar::{EcLevel, Version};
+///
+///     let bits = encode_auto_rmqr(b"0123456789", EcLevel::M, RmqrStrategy::FixedHeight(13)).unwrap();
+///     assert_eq!(bits.version(), Version::Rmqr(13, 27));
+///
+///     let result = encode_auto_rmqr(b"0123456789", EcLevel::M, RmqrStrategy::FixedHeight(8));
+///     assert_eq!(result.err(), Some(QrError::InvalidVersion));
+///
+/// # Errors
+///
+/// Returns `Err(QrError::DataTooLong)` if the data is too long to fit even the
+/// highest rMQR code version, or the highest one of the fixed dimension, and
+/// `Err(QrError::InvalidVersion)` if the fixed dimension is not the height or
+/// width of any rMQR code version, even when the data is too long.
+pub fn encode_auto_rmqr(data: &[u8], ec_level: EcLevel, strategy: RmqrStrategy) -> QrResult<Bits> {
+    check_rmqr_request(data.len(), ec_level, strategy)?;
+    encode_auto_rmqr_with(data, ec_level, strategy, None, |version| {
+        Ok(optimal_segments(data, version))
+    })
+}
+
+/// Automatically determines the rMQR code version to store the text, and
+/// encode it after the ECI designator 26, like `encode_auto_utf8` does for QR
+/// codes.
+///
+///     use qrqrpar::bits::{encode_auto_rmqr_utf8, RmqrStrategy};
+///     use qrqrpar::{EcLevel, Version};
+///
+///     let bits = encode_auto_rmqr_utf8("日本語", EcLevel::M, RmqrStrategy::Area).unwrap();
+///     assert_eq!(bits.version(), Version::Rmqr(13, 27));
+///     assert_eq!(bits.headers().eci, Some(26));
+///
+/// # Errors
+///
+/// Returns `Err(QrError::TextTooLong)` if the text is too long to fit even the
+/// highest rMQR code version.
+pub fn encode_auto_rmqr_utf8(
+    text: &str,
+    ec_level: EcLevel,
+    strategy: RmqrStrategy,
+) -> QrResult<Bits> {
+    let data = text.as_bytes();
+    check_rmqr_request(data.len(), ec_level, strategy).map_err(|e| e.for_text(text))?;
+    let segments = parse_utf8(data);
+    encode_auto_rmqr_with(data, ec_level, strategy, Some(UTF8_ECI), |version| {
+        Ok(Optimizer::new(segments.iter().copied(), version).collect())
+    })
+    .map_err(|e| e.for_text(text))
+}
+
+/// Automatically determines the rMQR code version to store the data encoded
+/// in a single segment of the given mode, like `encode_auto_forced` does for
+/// QR codes.
+///
+///     use qrqrpar::bits::{encode_auto_rmqr_forced, RmqrStrategy};
+///     use qrqrpar::types::{Mode, QrError};
+///     use qrqrpar::EcLevel;
+///
+///     let strategy = RmqrStrategy::Area;
+///     let result = encode_auto_rmqr_forced(b"abc", EcLevel::M, Mode::Alphanumeric, strategy);
+///     assert_eq!(result.err(), Some(QrError::InvalidCharacter));
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidCharacter)` if the mode cannot encode the
+/// data, `Err(QrError::DataTooLong)` if the data is too long to fit even the
+/// highest rMQR code version, or the highest one of the fixed dimension, and
+/// `Err(QrError::InvalidVersion)` if the fixed dimension is not the height or
+/// width of any rMQR code version, even when the data is too long.
+pub fn encode_auto_rmqr_forced(
+    data: &[u8],
+    ec_level: EcLevel,
+    mode: Mode,
+    strategy: RmqrStrategy,
+) -> QrResult<Bits> {
+    check_rmqr_request(data.len(), ec_level, strategy)?;
+    let segments = forced_segments(data, mode)?;
+    encode_auto_rmqr_with(data, ec_level, strategy, None, |_| Ok(segments.clone()))
+}
+
+/// Same as `encode_auto_rmqr`, with the ECI designator `eci` pushed before the
+/// data if any, and `optimize` returning the optimized segments of the data
+/// for a given version, or an error to return as is.
+pub(crate) fn encode_auto_rmqr_with(
+    data: &[u8],
+    ec_level: EcLevel,
+    strategy: RmqrStrategy,
+    eci: Option<u32>,
+    optimize: impl Fn(Version) -> QrResult<Vec<Segment>>,
+) -> QrResult<Bits> {
+    let version = select_rmqr_version(ec_level, strategy, eci, &optimize)?;
+    let mut bits = Bits::new(version);
+    if let Some(designator) = eci {
+        bits.push_eci_designator(designator)?;
+    }
+    bits.push_segments_and_terminator(
+        data,
+        optimize(version)?,
+        ec_level,
+        PaddingPattern::Standard,
+    )?;
+    Ok(bits)
+}
+
+/// Finds the version `encode_auto_rmqr_with` encodes the data in.
+fn select_rmqr_version(
+    ec_level: EcLevel,
+    strategy: RmqrStrategy,
+    eci: Option<u32>,
+    optimize: impl Fn(Version) -> QrResult<Vec<Segment>>,
+) -> QrResult<Version> {
+    strategy.check_admits_any(Version::rmqr_all())?;
+    let mut possible_versions = vec![];
+    for width in Version::rmqr_all_width() {
+        for height in Version::rmqr_all_height() {
+            let version = Version::Rmqr(height, width);
+            if !version.is_rmqr() || !strategy.admits(version) {
+                continue;
+            }
+            let opt_segments = optimize(version)?;
+            let total_len =
+                eci_header_len(version, eci)? + total_encoded_len(&opt_segments, version);
+            let data_capacity = version.fetch(ec_level, &DATA_LENGTHS)?;
+            if total_len <= data_capacity {
+                possible_versions.push(version);
+                // Taller versions of the same width only matter when aiming
+                // at a ratio.
+                if !matches!(strategy, RmqrStrategy::AspectRatio(_)) {
+                    break;
+                }
+            }
+        }
+    }
+
+    let min_version = match strategy {
+        // possible_versions is already sorted by width
+        RmqrStrategy::Width | RmqrStrategy::FixedHeight(_) => possible_versions.first(),
+        RmqrStrategy::Height | RmqrStrategy::FixedWidth(_) => {
+            possible_versions.iter().min_by_key(|v| v.height())
+        }
+        RmqrStrategy::Area => possible_versions.iter().min_by_key(|v| v.area()),
+        RmqrStrategy::Perimeter => possible_versions
+            .iter()
+            .min_by_key(|v| v.width() + v.height()),
+        RmqrStrategy::AspectRatio(target) => {
+            let target = (target * 1000.0).round() as u64;
+            possible_versions.iter().min_by(|a, b| {
+                cmp_aspect_ratio_distance(**a, **b, target).then(a.area().cmp(&b.area()))
+            })
+        }
+    };
+
+    min_version.copied().ok_or(QrError::DataTooLong)
+}
+
+/// Finds the rMQR code version `encode_auto_rmqr` would encode the data in
+/// with the strategy, without encoding it.
+///
+///     use qrqrpar::bits::{minimal_rmqr_version_for, RmqrStrategy};
+///     use qrqrpar::{EcLevel, Version};
+///
+///     let version = minimal_rmqr_version_for(b"0123456789", EcLevel::M, RmqrStrategy::Height);
+///     assert_eq!(version, Ok(Version::Rmqr(7, 43)));
+///
+/// # Errors
+///
+/// Returns the errors of `encode_auto_rmqr`.
+pub fn minimal_rmqr_version_for(
+    data: &[u8],
+    ec_level: EcLevel,
+    strategy: RmqrStrategy,
+) -> QrResult<Version> {
+    check_rmqr_request(data.len(), ec_level, strategy)?;
+    select_rmqr_version(ec_level, strategy, None, |version| {
+        Ok(optimal_segments(data, version))
+    })
+}
+
+#[cfg(test)]
+mod rmqr_strategy_tests {
+    use crate::bits::{
+        encode_auto_rmqr, encode_auto_rmqr_forced, encode_auto_rmqr_utf8, minimal_rmqr_version_for,
+        RmqrStrategy,
+    };
+    use crate::encoder::Encoder;
+    use crate::prefix::{Family, PrefixEncoder};
+    use crate::types::{EcLevel, EcLevelUnavailable, Mode, QrError, Version};
+
+    fn select(data: &[u8], strategy: RmqrStrategy) -> Version {
+        encode_auto_rmqr(data, EcLevel::M, strategy)
+            .unwrap()
+            .version()
+    }
+
+    #[test]
+    fn test_perimeter() {
+        let data = [b'a'; 40];
+        assert_eq!(select(&data, RmqrStrategy::Area), Version::Rmqr(11, 77));
+        assert_eq!(
+            select(&data, RmqrStrategy::Perimeter),
+            Version::Rmqr(15, 59)
+        );
+        assert_eq!(
+            select(b"01234567", RmqrStrategy::Perimeter),
+            Version::Rmqr(11, 27)
+        );
+    }
+
+    #[test]
+    fn test_aspect_ratio() {
+        let data = [b'a'; 40];
+        assert_eq!(
+            select(&data, RmqrStrategy::AspectRatio(2.0)),
+            Version::Rmqr(17, 59)
+        );
+        assert_eq!(
+            select(&data, RmqrStrategy::AspectRatio(5.0)),
+            Version::Rmqr(15, 77)
+        );
+        assert_eq!(
+            select(&data, RmqrStrategy::AspectRatio(10.0)),
+            Version::Rmqr(13, 139)
+        );
+        assert_eq!(
+            select(&data, RmqrStrategy::AspectRatio(100.0)),
+            Version::Rmqr(7, 139)
+        );
+
+        // Taller versions of the same width are considered as well.
+        assert_eq!(
+            select(b"01234567", RmqrStrategy::AspectRatio(2.0)),
+            Version::Rmqr(13, 27)
+        );
+        assert_eq!(
+            select(b"01234567", RmqrStrategy::AspectRatio(2.0000001)),
+            Version::Rmqr(13, 27)
+        );
+    }
+
+    #[test]
+    fn test_fixed_height() {
+        let data = [b'a'; 40];
+        let versions: Vec<_> = Version::rmqr_all_height()
+            .into_iter()
+            .map(|height| select(&data, RmqrStrategy::FixedHeight(height)))
+            .collect();
+        assert_eq!(
+            versions,
+            [
+                Version::Rmqr(7, 139),
+                Version::Rmqr(9, 99),
+                Version::Rmqr(11, 77),
+                Version::Rmqr(13, 77),
+                Version::Rmqr(15, 59),
+                Version::Rmqr(17, 59),
+            ]
+        );
+        assert_eq!(
+            encode_auto_rmqr(&[b'a'; 200], EcLevel::M, RmqrStrategy::FixedHeight(7)).err(),
+            Some(QrError::DataTooLong)
+        );
+    }
+
+    #[test]
+    fn test_fixed_width() {
+        let data = [b'a'; 40];
+        let results: Vec<_> = Version::rmqr_all_width()
+            .into_iter()
+            .map(|width| {
+                encode_auto_rmqr(&data, EcLevel::M, RmqrStrategy::FixedWidth(width))
+                    .map(|bits| bits.version())
+            })
+            .collect();
+        assert_eq!(
+            results,
+            [
+                Err(QrError::DataTooLong),
+                Err(QrError::DataTooLong),
+                Ok(Version::Rmqr(15, 59)),
+                Ok(Version::Rmqr(11, 77)),
+                Ok(Version::Rmqr(9, 99)),
+                Ok(Version::Rmqr(7, 139)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_fixed_dimension() {
+        for strategy in [
+            RmqrStrategy::FixedHeight(8),
+            RmqrStrategy::FixedHeight(19),
+            RmqrStrategy::FixedWidth(0),
+            RmqrStrategy::FixedWidth(28),
+        ] {
+            // The dimension is checked before the length of the data.
+            for data in [&b"1"[..], &[b'1'; 3000]] {
+                assert_eq!(
+                    encode_auto_rmqr(data, EcLevel::M, strategy).err(),
+                    Some(QrError::InvalidVersion)
+                );
+                assert_eq!(
+                    minimal_rmqr_version_for(data, EcLevel::M, strategy),
+                    Err(QrError::InvalidVersion)
+                );
+                let text = std::str::from_utf8(data).unwrap();
+                assert_eq!(
+                    encode_auto_rmqr_utf8(text, EcLevel::M, strategy).err(),
+                    Some(QrError::InvalidVersion)
+                );
+                let mode = Mode::Numeric;
+                assert_eq!(
+                    encode_auto_rmqr_forced(data, EcLevel::M, mode, strategy).err(),
+                    Some(QrError::InvalidVersion)
+                );
+                assert_eq!(
+                    Encoder::new().encode_rmqr(data, EcLevel::M, strategy).err(),
+                    Some(QrError::InvalidVersion)
+                );
+                let prefix = PrefixEncoder::new(b"", EcLevel::M, Family::Rmqr(strategy));
+                assert_eq!(
+                    prefix.encode_suffix_bits(data).err(),
+                    Some(QrError::InvalidVersion)
+                );
+            }
+        }
+        assert_eq!(
+            encode_auto_rmqr(&[b'1'; 3000], EcLevel::M, RmqrStrategy::FixedHeight(7)).err(),
+            Some(QrError::DataTooLong)
+        );
+    }
+
+    #[test]
+    fn test_unsupported_ec_level() {
+        let error = QrError::EcLevelUnavailable(EcLevelUnavailable {
+            version: Version::Rmqr(7, 43),
+            ec_level: EcLevel::Q,
+            smallest: None,
+        });
+        for data in [&b"1"[..], &[b'1'; 3000]] {
+            let result = encode_auto_rmqr(data, EcLevel::Q, RmqrStrategy::Area);
+            assert_eq!(result.err(), Some(error));
+        }
+        assert_eq!(
+            error.to_string(),
+            "R7x43 supports M and H only; no rMQR version supports Q"
+        );
+    }
+}
+
+#[cfg(test)]
+mod minimal_version_tests {
+    use crate::bits::{minimal_rmqr_version_for, minimal_version_for, RmqrStrategy};
+    use crate::types::{EcLevel, Version};
+    use crate::QrCode;
+
+    /// Payloads of every length up to `max_len`, mixing digits, uppercase
+    /// letters, lowercase letters and Kanji.
+    fn corpus(max_len: usize) -> impl Iterator<Item = Vec<u8>> {
+        let pattern = b"0123456789HELLO WORLD \x93\x5f\xe4\xaa hello 31415926535 ";
+        (0..=max_len).map(|len| pattern.iter().copied().cycle().take(len).collect())
+    }
+
+    #[test]
+    fn test_agrees_with_new() {
+        for data in corpus(3000).step_by(53) {
+            let code = QrCode::new(&data).map(|code| code.version());
+            assert_eq!(
+                minimal_version_for(&data, EcLevel::M),
+                code,
+                "{}",
+                data.len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_rmqr_agrees_with_rmqr_with_options() {
+        let strategies = [
+            RmqrStrategy::Width,
+            RmqrStrategy::Height,
+            RmqrStrategy::Area,
+            RmqrStrategy::AspectRatio(4.0),
+            RmqrStrategy::FixedHeight(13),
+        ];
+        for data in corpus(400).step_by(17) {
+            for strategy in strategies {
+                let code = QrCode::rmqr_with_options(&data, EcLevel::M, strategy)
+                    .map(|code| code.version());
+                let version = minimal_rmqr_version_for(&data, EcLevel::M, strategy);
+                assert_eq!(version, code, "{} {strategy:?}", data.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_largest() {
+        assert_eq!(
+            minimal_version_for(&[b'1'; 7089], EcLevel::L),
+            Ok(Version::Normal(40))
+        );
+        assert_eq!(
+            minimal_rmqr_version_for(&[b'1'; 361], EcLevel::M, RmqrStrategy::Area),
+            Ok(Version::Rmqr(17, 139))
+        );
+    }
+}
+
+// Auto version minimization across families
+
+/// Gets the weakest error correction level supported by the version which is
+/// at least as strong as `ec_level`, e.g. M for a rMQR code asked for L.
+fn weakest_supported_level(version: Version, ec_level: EcLevel) -> Option<EcLevel> {
+    [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H]
+        .into_iter()
+        .filter(|level| *level >= ec_level)
+        .find(|level| version.fetch(*level, &DATA_LENGTHS).is_ok())
+}
+
+/// Automatically determines the version, among every QR, Micro QR and rMQR
+/// code version, which best satisfies the strategy, and encode the result.
+///
+///     use qrqrpar::bits::encode_auto_smallest;
+///     use qrqrpar::{EcLevel, ShapeStrategy, Version};
+///
+///     let (bits, ec_level) = encode_auto_smallest(b"0123456789", EcLevel::L, ShapeStrategy::Height).unwrap();
+///     assert_eq!((bits.version(), ec_level), (Version::Rmqr(7, 43), EcLevel::M));
+///
+/// Each version uses the weakest error correction level it supports which is
+/// at least as strong as `ec_level`, so the returned level may be stronger than
+/// the requested one, e.g. M for a rMQR code asked for L. Versions supporting
+/// no such level, like every Micro QR code version for H, are skipped.
+///
+/// Ties are broken by the smaller area, then by the family, Micro QR codes
+/// first, then rMQR codes ordered by width and then height, then QR codes.
+/// The selection is thus deterministic. With `ShapeStrategy::Area`, the only
+/// tie across families is between the QR code version 4 and R11x99, both of
+/// 1089 modules, where the rMQR code wins.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::DataTooLong)` if the data is too long to fit any
+/// version, and `Err(QrError::InvalidVersion)` if a fixed dimension is not
+/// the height or width of any version.
+pub fn encode_auto_smallest(
+    data: &[u8],
+    ec_level: EcLevel,
+    strategy: ShapeStrategy,
+) -> QrResult<(Bits, EcLevel)> {
+    let rmqr_versions = Version::rmqr_all_width().into_iter().flat_map(|width| {
+        Version::rmqr_all_height()
+            .into_iter()
+            .map(move |height| Version::Rmqr(height, width))
+            .filter(|version| version.is_rmqr())
+    });
+    let all_versions = (1..=4)
+        .map(Version::Micro)
+        .chain(rmqr_versions)
+        .chain((1..=40).map(Version::Normal));
+    strategy.check_admits_any(all_versions.clone())?;
+    check_len_lower_bound(data.len(), [Version::Normal(40)], ec_level)?;
+    let candidates = all_versions
+        .filter(|version| strategy.admits(*version))
+        .filter_map(|version| {
+            let level = weakest_supported_level(version, ec_level)?;
+            let len = optimal_encoded_len(data, version)?;
+            let data_capacity = version.fetch(level, &DATA_LENGTHS).ok()?;
+            (len <= data_capacity).then_some((version, level))
+        });
+
+    let key = |v: Version| -> u64 {
+        match strategy {
+            ShapeStrategy::Width | ShapeStrategy::FixedHeight(_) => v.width() as u64,
+            ShapeStrategy::Height | ShapeStrategy::FixedWidth(_) => v.height() as u64,
+            ShapeStrategy::Area => v.area() as u64,
+            ShapeStrategy::Perimeter => (v.width() + v.height()) as u64,
+            ShapeStrategy::AspectRatio(_) => 0,
+        }
+    };
+    let target = match strategy {
+        ShapeStrategy::AspectRatio(target) => (target * 1000.0).round() as u64,
+        _ => 0,
+    };
+    let best = candidates.min_by(|(a, _), (b, _)| {
+        let by_ratio = match strategy {
+            ShapeStrategy::AspectRatio(_) => cmp_aspect_ratio_distance(*a, *b, target),
+            _ => Ordering::Equal,
+        };
+        by_ratio
+            .then(key(*a).cmp(&key(*b)))
+            .then(a.area().cmp(&b.area()))
+    });
+
+    let (version, level) = best.ok_or(QrError::DataTooLong)?;
+    let opt_segments = optimal_segments(data, version);
+    let mut bits = Bits::new(version);
+    bits.push_segments_and_terminator(data, opt_segments, level, PaddingPattern::Standard)?;
+    Ok((bits, level))
+}
+
+#[cfg(test)]
+mod smallest_tests {
+    use crate::bits::{encode_auto_smallest, fits, weakest_supported_level, ShapeStrategy};
+    use crate::types::{EcLevel, QrError, Version};
+
+    fn select(data: &[u8], ec_level: EcLevel, strategy: ShapeStrategy) -> (Version, EcLevel) {
+        let (bits, ec_level) = encode_auto_smallest(data, ec_level, strategy).unwrap();
+        (bits.version(), ec_level)
+    }
+
+    #[test]
+    fn test_ten_digits() {
+        let data = b"0123456789";
+        assert_eq!(
+            select(data, EcLevel::L, ShapeStrategy::Height),
+            (Version::Rmqr(7, 43), EcLevel::M)
+        );
+        assert_eq!(
+            select(data, EcLevel::L, ShapeStrategy::Area),
+            (Version::Micro(2), EcLevel::L)
+        );
+        assert_eq!(
+            select(data, EcLevel::L, ShapeStrategy::Width),
+            (Version::Micro(2), EcLevel::L)
+        );
+        // Micro QR code version 2 only holds 8 digits at level M.
+        assert_eq!(
+            select(data, EcLevel::M, ShapeStrategy::Area),
+            (Version::Micro(3), EcLevel::M)
+        );
+        // No Micro QR code version supports level H, and R13x27 is smaller
+        // than the QR code version 1.
+        assert_eq!(
+            select(data, EcLevel::H, ShapeStrategy::Area),
+            (Version::Rmqr(13, 27), EcLevel::H)
+        );
+        assert_eq!(
+            select(data, EcLevel::L, ShapeStrategy::AspectRatio(1.0)),
+            (Version::Micro(2), EcLevel::L)
+        );
+        assert_eq!(
+            select(data, EcLevel::L, ShapeStrategy::AspectRatio(3.0)),
+            (Version::Rmqr(15, 43), EcLevel::M)
+        );
+    }
+
+    #[test]
+    fn test_area_across_families() {
+        let long = (0..200).map(|i| b'a' + i % 26).collect::<Vec<u8>>();
+        assert_eq!(
+            select(b"12345", EcLevel::L, ShapeStrategy::Area),
+            (Version::Micro(1), EcLevel::L)
+        );
+        let (version, _) = select(&long, EcLevel::M, ShapeStrategy::Area);
+        assert!(matches!(version, Version::Normal(_)), "{version:?}");
+
+        // 55 bytes fit both the QR code version 4 and R11x99, of equal areas.
+        let data = [b'a'; 55];
+        assert!(fits(&data, Version::Normal(4), EcLevel::M).unwrap());
+        assert_eq!(
+            select(&data, EcLevel::M, ShapeStrategy::Area),
+            (Version::Rmqr(11, 99), EcLevel::M)
+        );
+
+        // No fitting version of any family has a smaller area.
+        let rmqr = Version::rmqr_all_height().into_iter().flat_map(|height| {
+            Version::rmqr_all_width()
+                .into_iter()
+                .map(move |width| Version::Rmqr(height, width))
+        });
+        let versions = (1..=4)
+            .map(Version::Micro)
+            .chain((1..=40).map(Version::Normal))
+            .chain(rmqr.filter(|version| version.is_rmqr()))
+            .collect::<Vec<_>>();
+        for data in [
+            &b"12345"[..],
+            b"HELLO WORLD",
+            b"https://example.com/",
+            &long,
+        ] {
+            let (selected, _) = select(data, EcLevel::M, ShapeStrategy::Area);
+            for &version in &versions {
+                let holds = weakest_supported_level(version, EcLevel::M)
+                    .is_some_and(|level| fits(data, version, level).unwrap());
+                if holds {
+                    assert!(selected.area() <= version.area(), "{version:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ties() {
+        // Every square is exactly at the ratio, so the smallest one wins.
+        assert_eq!(
+            select(b"1", EcLevel::L, ShapeStrategy::AspectRatio(1.0)),
+            (Version::Micro(1), EcLevel::L)
+        );
+        assert_eq!(
+            select(b"1", EcLevel::H, ShapeStrategy::AspectRatio(1.0)),
+            (Version::Normal(1), EcLevel::H)
+        );
+        // R7x43 and R7x59 are both 7 modules high.
+        assert_eq!(
+            select(&[b'a'; 5], EcLevel::M, ShapeStrategy::Height),
+            (Version::Rmqr(7, 43), EcLevel::M)
+        );
+        assert_eq!(
+            select(&[b'a'; 20], EcLevel::M, ShapeStrategy::Height),
+            (Version::Rmqr(7, 99), EcLevel::M)
+        );
+    }
+
+    #[test]
+    fn test_large_data() {
+        assert_eq!(
+            select(&[b'a'; 500], EcLevel::M, ShapeStrategy::Height),
+            (Version::Normal(17), EcLevel::M)
+        );
+        assert_eq!(
+            encode_auto_smallest(&[b'a'; 3000], EcLevel::L, ShapeStrategy::Area).err(),
+            Some(QrError::DataTooLong)
+        );
+    }
+
+    #[test]
+    fn test_fixed_dimension() {
+        // The Micro QR code version 2 is 13 modules high as well.
+        assert_eq!(
+            select(b"01234", EcLevel::L, ShapeStrategy::FixedHeight(13)),
+            (Version::Micro(2), EcLevel::L)
+        );
+        assert_eq!(
+            select(&[b'a'; 10], EcLevel::L, ShapeStrategy::FixedHeight(13)),
+            (Version::Rmqr(13, 27), EcLevel::M)
+        );
+        assert_eq!(
+            select(&[b'a'; 10], EcLevel::L, ShapeStrategy::FixedWidth(21)),
+            (Version::Normal(1), EcLevel::L)
+        );
+        assert_eq!(
+            encode_auto_smallest(&[b'a'; 20], EcLevel::L, ShapeStrategy::FixedWidth(8)).err(),
+            Some(QrError::InvalidVersion)
+        );
+        assert_eq!(
+            encode_auto_smallest(&[b'a'; 3000], EcLevel::L, ShapeStrategy::FixedWidth(8)).err(),
+            Some(QrError::InvalidVersion)
+        );
+    }
+}
+
+// Error correction level selection
+
+/// Computes the number of bits needed to store the optimally segmented data in
+/// the given version.
+///
+/// Returns `None` if some segment cannot be encoded in the version at all, e.g.
+/// byte data in a Micro QR code version 2, or a segment too long for the
+/// length field.
+pub(crate) fn optimal_encoded_len(data: &[u8], version: Version) -> Option<usize> {
+    encodable_len(&optimal_segments(data, version), version)
+}
+
+/// Computes the number of bits needed to store the segments in the given
+/// version, or `None` if some segment cannot be encoded in it at all, see
+/// `optimal_encoded_len`.
+pub(crate) fn encodable_len(segments: &[Segment], version: Version) -> Option<usize> {
+    for segment in segments {
+        let supported = version.supported_modes().contains(&segment.mode);
+        let byte_size = segment.end - segment.begin;
+        let chars_count = if segment.mode == Mode::Kanji {
+            byte_size / 2
+        } else {
+            byte_size
+        };
+        let length_bits = segment.mode.length_bits_count(version) as u32;
+        if !supported || chars_count.checked_shr(length_bits).is_some_and(|n| n != 0) {
+            return None;
+        }
+    }
+    Some(total_encoded_len(segments, version))
+}
+
+/// Checks whether the data fits the given version and error correction level
+/// when optimally segmented, i.e. whether `QrCode::with_version` succeeds.
+///
+///     use qrqrpar::bits::fits;
+///     use qrqrpar::{EcLevel, Version};
+///
+///     assert_eq!(fits(b"01234567890123456789012345", Version::Normal(1), EcLevel::Q), Ok(true));
+///     assert_eq!(fits(b"01234567890123456789012345", Version::Normal(1), EcLevel::H), Ok(false));
+///     // The Micro QR code version 1 only has the numeric mode.
+///     assert_eq!(fits(b"A", Version::Micro(1), EcLevel::L), Ok(false));
+///
+/// Only the lengths of the segments are computed, without pushing the bits,
+/// drawing the symbol or computing the error correction, so this is cheap
+/// enough to validate input as it is typed.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidVersion)` if it is not valid to use the
+/// `ec_level` for the given version.
+pub fn fits(data: &[u8], version: Version, ec_level: EcLevel) -> QrResult<bool> {
+    segments_fit(&optimal_segments(data, version), version, ec_level)
+}
+
+/// Same as `fits`, for segments already optimized for the version.
+pub(crate) fn segments_fit(
+    segments: &[Segment],
+    version: Version,
+    ec_level: EcLevel,
+) -> QrResult<bool> {
+    let data_capacity = version.fetch(ec_level, &DATA_LENGTHS)?;
+    Ok(encodable_len(segments, version).is_some_and(|len| len <= data_capacity))
+}
+
+#[cfg(test)]
+mod fits_tests {
+    use crate::bits::fits;
+    use crate::test_util::{payloads, Content, Lengths, PayloadSpec};
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::QrCode;
+
+    #[test]
+    fn test_agrees_with_with_version() {
+        let versions = (1..=4)
+            .map(Version::Normal)
+            .chain((1..=4).map(Version::Micro))
+            .chain([
+                Version::Rmqr(7, 43),
+                Version::Rmqr(11, 27),
+                Version::Rmqr(13, 59),
+            ]);
+        let versions: Vec<_> = versions.collect();
+        let spec = PayloadSpec {
+            contents: vec![
+                Content::Numeric,
+                Content::Alphanumeric,
+                Content::Kanji,
+                Content::Utf8,
+                Content::Binary,
+            ],
+            max_run: 8,
+            lengths: Lengths::Between(0, 60),
+        };
+        for data in payloads(0xf175, spec).take(150) {
+            for &version in &versions {
+                for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+                    let encoded = QrCode::with_version(&data, version, ec_level);
+                    match fits(&data, version, ec_level) {
+                        Ok(holds) => assert_eq!(holds, encoded.is_ok(), "{data:?} {version}"),
+                        // with_version may fail on the data first, e.g. with
+                        // ModeUnavailable.
+                        Err(error) => {
+                            assert_eq!(error, QrError::InvalidVersion);
+                            assert!(encoded.is_err());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_ec_level() {
+        assert_eq!(
+            fits(b"1", Version::Micro(1), EcLevel::M),
+            Err(QrError::InvalidVersion)
+        );
+        assert_eq!(
+            fits(b"1", Version::Rmqr(7, 43), EcLevel::L),
+            Err(QrError::InvalidVersion)
+        );
+    }
+}
+
+/// Finds the versions of the same type as `version`, i.e. QR, Micro QR or
+/// rMQR, which are smaller than it and hold the data at the given error
+/// correction level when optimally segmented, from the smallest to the
+/// largest area.
+pub(crate) fn smaller_fitting_versions(
+    data: &[u8],
+    version: Version,
+    ec_level: EcLevel,
+) -> Vec<Version> {
+    let candidates = match version {
+        Version::Normal(_) => (1..=40).map(Version::Normal).collect::<Vec<_>>(),
+        Version::Micro(_) => (1..=4).map(Version::Micro).collect(),
+        Version::Rmqr(..) => Version::rmqr_all().to_vec(),
+    };
+    let mut versions = candidates
+        .into_iter()
+        .filter(|candidate| candidate.area() < version.area())
+        .filter(|candidate| {
+            let capacity = candidate.fetch(ec_level, &DATA_LENGTHS);
+            let len = optimal_encoded_len(data, *candidate);
+            matches!((capacity, len), (Ok(capacity), Some(len)) if len <= capacity)
+        })
+        .collect::<Vec<_>>();
+    versions.sort_by_key(|version| version.area());
+    versions
+}
+
+/// Finds all error correction levels at which the data fits the given version,
+/// ordered from the strongest to the weakest.
+///
+///     use qrqrpar::{fitting_ec_levels, EcLevel, Version};
+///
+///     let levels = fitting_ec_levels(b"01234567890123456789012345", Version::Normal(1)).unwrap();
+///     assert_eq!(levels, vec![EcLevel::Q, EcLevel::M, EcLevel::L]);
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidVersion)` if the version is not valid for any
+/// error correction level.
+pub fn fitting_ec_levels(data: &[u8], version: Version) -> QrResult<Vec<EcLevel>> {
+    let total_len = optimal_encoded_len(data, version);
+    let mut is_valid_version = false;
+    let mut levels = vec![];
+    for ec_level in [EcLevel::H, EcLevel::Q, EcLevel::M, EcLevel::L] {
+        if let Ok(data_capacity) = version.fetch(ec_level, &DATA_LENGTHS) {
+            is_valid_version = true;
+            if total_len.is_some_and(|len| len <= data_capacity) {
+                levels.push(ec_level);
+            }
+        }
+    }
+    if is_valid_version {
+        Ok(levels)
+    } else {
+        Err(QrError::InvalidVersion)
+    }
+}
+
+#[cfg(test)]
+mod fitting_ec_levels_tests {
+    use crate::bits::fitting_ec_levels;
+    use crate::types::{EcLevel, QrError, Version};
+
+    #[test]
+    fn test_normal() {
+        let version = Version::Normal(1);
+        assert_eq!(
+            fitting_ec_levels(b"01234567890123456", version),
+            Ok(vec![EcLevel::H, EcLevel::Q, EcLevel::M, EcLevel::L])
+        );
+        assert_eq!(
+            fitting_ec_levels(b"012345678901234567890123456789", version),
+            Ok(vec![EcLevel::M, EcLevel::L])
+        );
+        assert_eq!(fitting_ec_levels(&[b'a'; 30], version), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_micro_and_rmqr() {
+        assert_eq!(
+            fitting_ec_levels(b"12345", Version::Micro(1)),
+            Ok(vec![EcLevel::L])
+        );
+        assert_eq!(fitting_ec_levels(b"ABC", Version::Micro(1)), Ok(vec![]));
+        assert_eq!(
+            fitting_ec_levels(b"ABC", Version::Rmqr(7, 43)),
+            Ok(vec![EcLevel::H, EcLevel::M])
+        );
+    }
+
+    #[test]
+    fn test_invalid_version() {
+        assert_eq!(
+            fitting_ec_levels(b"1", Version::Rmqr(7, 27)),
+            Err(QrError::InvalidVersion)
+        );
+    }
+}
+
+// rMQR capacity report
+
+/// Whether the data fits a rMQR code version at an error correction level, see
+/// `rmqr_capacity_report`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RmqrFit {
+    /// The rMQR code version.
+    pub version: Version,
+    /// The error correction level, M or H.
+    pub ec_level: EcLevel,
+    /// The number of bits of the optimally segmented data, or `None` if some
+    /// segment is too long for the length field of the version.
+    pub required_bits: Option<usize>,
+    /// The number of data bits the version holds at the level.
+    pub available_bits: usize,
+    /// Whether the required bits are at most the available ones.
+    pub fits: bool,
+}
+
+/// Reports, for each of the 32 rMQR code versions and each of the levels M
+/// and H, whether the data fits, e.g. to let users pick a shape.
+///
+///     use qrqrpar::{rmqr_capacity_report, EcLevel, Version};
+///
+///     let report = rmqr_capacity_report(b"0123456789");
+///     assert_eq!(report.len(), 64);
+///     let fit = report
+///         .iter()
+///         .find(|fit| fit.version == Version::Rmqr(7, 43) && fit.ec_level == EcLevel::H)
+///         .unwrap();
+///     assert_eq!((fit.required_bits, fit.available_bits, fit.fits), (Some(41), 24, false));
+///
+/// The entries follow the order of `Version::rmqr_all`, M before H. The
+/// optimal segments only depend on the sizes of the length fields, so the
+/// data is segmented once for each distinct set of sizes rather than for each
+/// version.
+pub fn rmqr_capacity_report(data: &[u8]) -> Vec<RmqrFit> {
+    let modes = [Mode::Numeric, Mode::Alphanumeric, Mode::Byte, Mode::Kanji];
+    let mut required_by_length_bits: Vec<([usize; 4], Option<usize>)> = Vec::new();
+    let mut report = Vec::with_capacity(64);
+    for version in Version::rmqr_all() {
+        let length_bits = modes.map(|mode| mode.length_bits_count(version));
+        let required_bits = match required_by_length_bits
+            .iter()
+            .find(|(bits, _)| *bits == length_bits)
+        {
+            Some((_, required_bits)) => *required_bits,
+            None => {
+                let required_bits = optimal_encoded_len(data, version);
+                required_by_length_bits.push((length_bits, required_bits));
+                required_bits
+            }
+        };
+        for ec_level in [EcLevel::M, EcLevel::H] {
+            let Ok(available_bits) = version.fetch(ec_level, &DATA_LENGTHS) else {
+                continue;
+            };
+            report.push(RmqrFit {
+                version,
+                ec_level,
+                required_bits,
+                available_bits,
+                fits: required_bits.is_some_and(|len| len <= available_bits),
+            });
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod rmqr_capacity_report_tests {
+    use crate::bits::rmqr_capacity_report;
+    use crate::types::{EcLevel, Mode, Version};
+    use crate::QrCode;
+
+    #[test]
+    fn test_fits_matches_with_version() {
+        let payloads: [&[u8]; 4] = [
+            b"",
+            b"0123456789",
+            b"HELLO WORLD 0123456789 hello world",
+            &[b'a'; 120],
+        ];
+        for data in payloads {
+            let report = rmqr_capacity_report(data);
+            assert_eq!(report.len(), 64);
+            for fit in report {
+                let encoded = QrCode::with_version(data, fit.version, fit.ec_level);
+                assert_eq!(fit.fits, encoded.is_ok(), "{:?}", fit.version);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bits() {
+        let report = rmqr_capacity_report(&[b'a'; 20]);
+        let fit = |version: Version, ec_level: EcLevel| {
+            *report
+                .iter()
+                .find(|fit| fit.version == version && fit.ec_level == ec_level)
+                .unwrap()
+        };
+        // 3 bits of length field only count up to 7 bytes.
+        let small = fit(Version::Rmqr(7, 43), EcLevel::M);
+        assert_eq!(small.required_bits, None);
+        assert!(!small.fits);
+        let large = fit(Version::Rmqr(17, 139), EcLevel::H);
+        let length_bits = Mode::Byte.length_bits_count(Version::Rmqr(17, 139));
+        assert_eq!(large.required_bits, Some(3 + length_bits + 20 * 8));
+        assert_eq!(large.available_bits, 8 * 76);
+        assert!(large.fits);
+    }
+}
+
+// Character count estimation
+
+/// Estimates how many characters of text similar to `sample_text` fit the
+/// given version and error correction level, e.g. for a live character
+/// counter in a text field.
+///
+///     use qrqrpar::{estimate_chars_that_fit, EcLevel, Version};
+///
+///     assert_eq!(estimate_chars_that_fit(Version::Normal(1), EcLevel::M, "0123"), 34);
+///     assert_eq!(estimate_chars_that_fit(Version::Normal(1), EcLevel::M, "abc"), 14);
+///     assert_eq!(estimate_chars_that_fit(Version::Normal(1), EcLevel::M, "かな"), 5);
+///
+/// Characters are counted as Unicode scalar values, not as bytes, so
+/// multi-byte text yields fewer characters than its byte capacity. The
+/// estimate is the exact count for the sample repeated cyclically, and
+/// therefore reflects the mix of numeric, alphanumeric and other characters
+/// in the sample.
+///
+/// Returns 0 if the sample is empty, or if it is not valid to use the
+/// `ec_level` for the given version.
+pub fn estimate_chars_that_fit(version: Version, ec_level: EcLevel, sample_text: &str) -> usize {
+    let Ok(data_capacity) = version.fetch(ec_level, &DATA_LENGTHS) else {
+        return 0;
+    };
+    if sample_text.is_empty() {
+        return 0;
+    }
+    let similar_text = |chars_count: usize| {
+        sample_text
+            .chars()
+            .cycle()
+            .take(chars_count)
+            .collect::<String>()
+    };
+    // Every character needs more than 3 bits, even in numeric mode.
+    let candidates = (1..=data_capacity / 3).collect::<Vec<_>>();
+    candidates.partition_point(|&chars_count| {
+        fits(similar_text(chars_count).as_bytes(), version, ec_level).unwrap_or(false)
+    })
+}
+
+#[cfg(test)]
+mod estimate_chars_that_fit_tests {
+    use crate::bits::estimate_chars_that_fit;
+    use crate::types::{EcLevel, Version};
+    use crate::QrCode;
+
+    /// Checks the estimate against real encodes of a text with the same mix of
+    /// characters as the sample.
+    fn assert_estimate(version: Version, ec_level: EcLevel, sample: &str, text: &str) {
+        let estimate = estimate_chars_that_fit(version, ec_level, sample);
+        let fits = |count: usize| {
+            let prefix = text.chars().take(count).collect::<String>();
+            QrCode::with_version(prefix, version, ec_level).is_ok()
+        };
+        assert!(fits(estimate.saturating_sub(2)), "{sample:?} {estimate}");
+        assert!(!fits(estimate + 3), "{sample:?} {estimate}");
+    }
+
+    #[test]
+    fn test_ascii() {
+        let text = "Good day, Earth! ".repeat(100);
+        assert_estimate(Version::Normal(5), EcLevel::M, "Hello, World! ", &text);
+        let text = "https://example.com/".repeat(100);
+        assert_estimate(
+            Version::Normal(3),
+            EcLevel::L,
+            "https://example.com/",
+            &text,
+        );
+        let digits = "31415926535897932384626433832795".repeat(10);
+        assert_estimate(Version::Rmqr(11, 77), EcLevel::M, "271828", &digits);
+        assert_eq!(
+            estimate_chars_that_fit(Version::Micro(1), EcLevel::L, "123"),
+            5
+        );
+    }
+
+    #[test]
+    fn test_kana() {
+        let text = "かきくけこさしすせそ".repeat(20);
+        assert_estimate(Version::Normal(10), EcLevel::Q, "あいうえお", &text);
+        // 17 bytes would only hold 5 kana, but parts of their UTF-8 encoding
+        // happen to be valid Shift JIS and are packed in Kanji mode.
+        assert_eq!(
+            estimate_chars_that_fit(Version::Normal(1), EcLevel::L, "かな"),
+            6
+        );
+        assert_eq!(
+            estimate_chars_that_fit(Version::Micro(2), EcLevel::L, "かな"),
+            0
+        );
+    }
+
+    #[test]
+    fn test_emoji() {
+        let text = "🎉🚀✨🍣".repeat(100);
+        assert_estimate(Version::Normal(7), EcLevel::M, "😀", &text);
+        assert_eq!(
+            estimate_chars_that_fit(Version::Normal(1), EcLevel::L, "😀"),
+            17 / 4
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(
+            estimate_chars_that_fit(Version::Normal(1), EcLevel::M, ""),
+            0
+        );
+        assert_eq!(
+            estimate_chars_that_fit(Version::Micro(1), EcLevel::M, "1"),
+            0
+        );
+        assert_eq!(
+            estimate_chars_that_fit(Version::Rmqr(7, 43), EcLevel::L, "1"),
+            0
+        );
+    }
+}
+
+//------------------------------------------------------------------------------
+// Inspection
+
+/// A segment read back from an encoded bit stream by `inspect`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InspectedSegment {
+    /// Data in one of the four modes. `data` holds the decoded characters,
+    /// i.e. ASCII text in numeric and alphanumeric modes, and Shift JIS
+    /// double-byte characters in Kanji mode, so `chars_count` is half its
+    /// length there.
+    Data {
+        mode: Mode,
+        chars_count: usize,
+        data: Vec<u8>,
+    },
+    /// An ECI designator, selecting the character set of the following data.
+    Eci(u32),
+    /// A FNC1 indicator, see `Bits::push_fnc1_first_position`.
+    Fnc1(Fnc1Kind),
+    /// A structured append header, see `QrCode::with_structured_append`.
+    StructuredAppend(PartInfo),
+    /// The terminator, which may be shorter than usual if the data capacity
+    /// ends within it.
+    Terminator { bits_count: usize },
+    /// The bits after the terminator, i.e. the zero bits up to the codeword
+    /// boundary and the pad codewords. They are not read.
+    Padding { bits_count: usize },
+}
+
+impl core::fmt::Display for InspectedSegment {
+    /// Formats the segment on one line, with a preview of the data.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InspectedSegment::Data {
+                mode: Mode::Kanji,
+                chars_count,
+                data,
+            } => {
+                write!(f, "Kanji({chars_count}) ")?;
+                data.iter().try_for_each(|b| write!(f, "{b:02x}"))
+            }
+            InspectedSegment::Data {
+                mode,
+                chars_count,
+                data,
+            } => write!(f, "{mode:?}({chars_count}) \"{}\"", data.escape_ascii()),
+            InspectedSegment::Eci(designator) => write!(f, "ECI({designator:06})"),
+            InspectedSegment::Fnc1(Fnc1Kind::FirstPosition) => write!(f, "FNC1(first)"),
+            InspectedSegment::Fnc1(Fnc1Kind::SecondPosition {
+                application_indicator,
+            }) => write!(f, "FNC1(second, {application_indicator})"),
+            InspectedSegment::StructuredAppend(part) => write!(
+                f,
+                "StructuredAppend({}/{}, parity {:02x})",
+                part.index + 1,
+                part.total,
+                part.parity
+            ),
+            InspectedSegment::Terminator { bits_count } => write!(f, "Terminator({bits_count})"),
+            InspectedSegment::Padding { bits_count } => write!(f, "Padding({bits_count})"),
+        }
+    }
+}
+
+/// An error reading an encoded bit stream with `inspect`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InspectError {
+    /// The version is invalid, see `Version::is_valid`.
+    InvalidVersion,
+    /// The mode indicator at the given bit offset is unknown, or names a mode
+    /// the version does not support, e.g. `0b0110` in QR codes.
+    UnknownMode { offset: usize, indicator: u16 },
+    /// The stream ends within the segment starting at the given bit offset.
+    Truncated { offset: usize },
+    /// The segment starting at the given bit offset holds a value its mode
+    /// cannot encode, e.g. a group of digits above 999.
+    InvalidData { offset: usize },
+}
+
+impl core::fmt::Display for InspectError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InspectError::InvalidVersion => write!(f, "invalid version"),
+            InspectError::UnknownMode { offset, indicator } => {
+                write!(f, "unknown mode indicator {indicator:#b} at bit {offset}")
+            }
+            InspectError::Truncated { offset } => {
+                write!(f, "truncated segment at bit {offset}")
+            }
+            InspectError::InvalidData { offset } => {
+                write!(f, "invalid data in the segment at bit {offset}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InspectError {}
+
+/// Reads big-endian integers from a byte slice.
+struct BitReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl BitReader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.offset
+    }
+
+    /// Reads an N-bit integer, or returns `None` if fewer bits remain.
+    fn read(&mut self, n: usize) -> Option<u32> {
+        if n > self.remaining() {
+            return None;
+        }
+        let mut number = 0;
+        for i in self.offset..self.offset + n {
+            let bit = self.data[i / 8] >> (7 - i % 8) & 1;
+            number = number << 1 | u32::from(bit);
+        }
+        self.offset += n;
+        Some(number)
+    }
+}
+
+/// What a mode indicator announces.
+enum Indicator {
+    Data(Mode),
+    Eci,
+    Fnc1First,
+    Fnc1Second,
+    StructuredAppend,
+}
+
+/// Maps a mode indicator of the version to what it announces.
+fn parse_indicator(version: Version, indicator: u32) -> Option<Indicator> {
+    let mode = match (version, indicator) {
+        (Version::Micro(_), 0) => Mode::Numeric,
+        (Version::Micro(_), 1) => Mode::Alphanumeric,
+        (Version::Micro(_), 0b10) => Mode::Byte,
+        (Version::Micro(_), 0b11) => Mode::Kanji,
+        (Version::Rmqr(..), 0b001) => Mode::Numeric,
+        (Version::Rmqr(..), 0b010) => Mode::Alphanumeric,
+        (Version::Rmqr(..), 0b011) => Mode::Byte,
+        (Version::Rmqr(..), 0b100) => Mode::Kanji,
+        (Version::Rmqr(..), 0b101) => return Some(Indicator::Fnc1First),
+        (Version::Rmqr(..), 0b110) => return Some(Indicator::Fnc1Second),
+        (Version::Rmqr(..), 0b111) => return Some(Indicator::Eci),
+        (Version::Normal(_), 0b0001) => Mode::Numeric,
+        (Version::Normal(_), 0b0010) => Mode::Alphanumeric,
+        (Version::Normal(_), 0b0100) => Mode::Byte,
+        (Version::Normal(_), 0b1000) => Mode::Kanji,
+        (Version::Normal(_), 0b0111) => return Some(Indicator::Eci),
+        (Version::Normal(_), 0b0101) => return Some(Indicator::Fnc1First),
+        (Version::Normal(_), 0b1001) => return Some(Indicator::Fnc1Second),
+        (Version::Normal(_), 0b0011) => return Some(Indicator::StructuredAppend),
+        _ => return None,
+    };
+    version
+        .supported_modes()
+        .contains(&mode)
+        .then_some(Indicator::Data(mode))
+}
+
+/// Reads the characters of a data segment.
+fn read_data(reader: &mut BitReader, mode: Mode, chars_count: usize) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(chars_count * 2);
+    match mode {
+        Mode::Numeric => {
+            for digits in (0..chars_count).step_by(3) {
+                let digits = min(3, chars_count - digits);
+                let number = reader.read(digits * 3 + 1)?;
+                if number >= 10_u32.pow(digits as u32) {
+                    return None;
+                }
+                data.extend(format!("{number:0digits$}").bytes());
+            }
+        }
+        Mode::Alphanumeric => {
+            for chars in (0..chars_count).step_by(2) {
+                let (number, chars) = match chars_count - chars {
+                    1 => (reader.read(6)? as usize, 1),
+                    _ => (reader.read(11)? as usize, 2),
+                };
+                let first = if chars == 2 { number / 45 } else { number };
+                data.push(*ALPHANUMERIC_CHARSET.get(first)?);
+                if chars == 2 {
+                    data.push(ALPHANUMERIC_CHARSET[number % 45]);
+                }
+            }
+        }
+        Mode::Byte => {
+            for _ in 0..chars_count {
+                data.push(reader.read(8)? as u8);
+            }
+        }
+        Mode::Kanji => {
+            for _ in 0..chars_count {
+                let number = reader.read(13)? as usize;
+                let bytes = ((number / 0xc0) << 8) | (number % 0xc0);
+                let offset = if bytes + 0x8140 <= 0x9ffc {
+                    0x8140
+                } else {
+                    0xc140
+                };
+                let kanji = ((bytes + offset) as u16).to_be_bytes();
+                if kanji_number(&kanji) != Some(number) {
+                    return None;
+                }
+                data.extend_from_slice(&kanji);
+            }
+        }
+    }
+    Some(data)
+}
+
+/// Reads an ECI designator, encoded in 1, 2 or 3 bytes.
+fn read_eci_designator(reader: &mut BitReader) -> Option<u32> {
+    match reader.read(1)? {
+        0 => reader.read(7),
+        _ => match reader.read(1)? {
+            0 => reader.read(14),
+            _ => match reader.read(1)? {
+                0 => reader.read(21).filter(|designator| *designator <= 999_999),
+                _ => None,
+            },
+        },
+    }
+}
+
+/// Reads an encoded bit stream back into its segments, e.g. to debug a
+/// stream built by hand with `Bits`.
+///
+///     use qrqrpar::bits::{inspect, Bits, InspectedSegment};
+///     use qrqrpar::types::Mode;
+///     use qrqrpar::{EcLevel, Version};
+///
+///     let mut bits = Bits::new(Version::Normal(1));
+///     bits.push_numeric_data(b"01234567").unwrap();
+///     bits.push_terminator(EcLevel::H).unwrap();
+///     let segments = inspect(&bits.into_bytes(), Version::Normal(1)).unwrap();
+///     assert_eq!(
+///         segments[0],
+///         InspectedSegment::Data { mode: Mode::Numeric, chars_count: 8, data: b"01234567".to_vec() }
+///     );
+///     assert_eq!(segments[1], InspectedSegment::Terminator { bits_count: 4 });
+///     assert_eq!(segments[2], InspectedSegment::Padding { bits_count: 27 });
+///     assert_eq!(segments[0].to_string(), r#"Numeric(8) "01234567""#);
+///
+/// The mode indicators, length fields and terminators are read with the
+/// widths of the version, so the stream must be read with the version it was
+/// encoded for. Reading stops at the terminator: the rest of the stream is
+/// reported as padding without being checked. Since the stream is given as
+/// bytes, the zero bits completing the last byte of a full stream are read as
+/// a terminator too.
+///
+/// # Errors
+///
+/// Returns an `InspectError` with the bit offset of the segment which cannot
+/// be read.
+pub fn inspect(data: &[u8], version: Version) -> Result<Vec<InspectedSegment>, InspectError> {
+    if !version.is_valid() {
+        return Err(InspectError::InvalidVersion);
+    }
+    let terminator_size = match version {
+        Version::Micro(a) => usize::from(a) * 2 + 1,
+        Version::Rmqr(_, _) => 3,
+        Version::Normal(_) => 4,
+    };
+    let mut reader = BitReader { data, offset: 0 };
+    let mut segments = vec![];
+    while reader.remaining() > 0 {
+        let offset = reader.offset;
+
+        // In Micro QR codes, the terminator reads as an empty numeric segment,
+        // which the encoder never produces.
+        let bits_count = min(terminator_size, reader.remaining());
+        let mut peek = BitReader { data, offset };
+        if peek.read(bits_count) == Some(0) {
+            segments.push(InspectedSegment::Terminator { bits_count });
+            let bits_count = reader.remaining() - bits_count;
+            if bits_count > 0 {
+                segments.push(InspectedSegment::Padding { bits_count });
+            }
+            break;
+        }
+
+        let truncated = InspectError::Truncated { offset };
+        let indicator = reader.read(version.mode_bits_count()).ok_or(truncated)?;
+        let segment = match parse_indicator(version, indicator) {
+            Some(Indicator::Data(mode)) => {
+                let chars_count = reader
+                    .read(mode.length_bits_count(version))
+                    .ok_or(truncated)? as usize;
+                if reader.remaining() < mode.data_bits_count(chars_count) {
+                    return Err(truncated);
+                }
+                let data = read_data(&mut reader, mode, chars_count)
+                    .ok_or(InspectError::InvalidData { offset })?;
+                InspectedSegment::Data {
+                    mode,
+                    chars_count,
+                    data,
+                }
+            }
+            Some(Indicator::Eci) => {
+                let designator = read_eci_designator(&mut reader);
+                InspectedSegment::Eci(designator.ok_or(InspectError::InvalidData { offset })?)
+            }
+            Some(Indicator::Fnc1First) => InspectedSegment::Fnc1(Fnc1Kind::FirstPosition),
+            Some(Indicator::Fnc1Second) => {
+                let application_indicator = reader.read(8).ok_or(truncated)? as u8;
+                InspectedSegment::Fnc1(Fnc1Kind::SecondPosition {
+                    application_indicator,
+                })
+            }
+            Some(Indicator::StructuredAppend) => {
+                let header = reader.read(16).ok_or(truncated)?;
+                let part = PartInfo {
+                    index: (header >> 12) as u8,
+                    total: (header >> 8 & 0xf) as u8 + 1,
+                    parity: header as u8,
+                };
+                InspectedSegment::StructuredAppend(part)
+            }
+            None => {
+                return Err(InspectError::UnknownMode {
+                    offset,
+                    indicator: indicator as u16,
+                })
+            }
+        };
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod inspect_tests {
+    use crate::bits::{inspect, Bits, Fnc1Kind, InspectError, InspectedSegment};
+    use crate::internals::coding::{Parser, Segment};
+    use crate::structured_append::{parity, PartInfo};
+    use crate::types::{EcLevel, Mode, Version};
+
+    /// Encodes the data optimally and checks that it inspects back to the
+    /// pushed segments.
+    fn assert_round_trip(data: &[u8], version: Version, ec_level: EcLevel) {
+        let segments = Parser::new(data)
+            .optimize(version)
+            .collect::<Vec<Segment>>();
+        let mut bits = Bits::new(version);
+        bits.push_segments(data, segments.iter().copied()).unwrap();
+        bits.push_terminator(ec_level).unwrap();
+        let inspected = inspect(&bits.into_bytes(), version).unwrap();
+
+        let expected = segments
+            .iter()
+            .map(|segment| (segment.mode, data[segment.begin..segment.end].to_vec()))
+            .collect::<Vec<_>>();
+        let actual = inspected
+            .iter()
+            .filter_map(|segment| match segment {
+                InspectedSegment::Data { mode, data, .. } => Some((*mode, data.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected, "{version:?}");
+        assert!(matches!(
+            inspected[segments.len()],
+            InspectedSegment::Terminator { .. }
+        ));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let kanji = b"\x93\x5f\x82\xcc\x89\xa4\xe4\xaa";
+        let mixed = [&b"Hello, 12345678901234 QR CODE "[..], kanji, b"!"].concat();
+        let cases: [(&[u8], Version, EcLevel); 9] = [
+            (b"01234567", Version::Normal(1), EcLevel::H),
+            (&mixed, Version::Normal(3), EcLevel::M),
+            (&mixed, Version::Normal(10), EcLevel::L),
+            (&mixed, Version::Normal(27), EcLevel::H),
+            (b"12345", Version::Micro(1), EcLevel::L),
+            (b"AC-42", Version::Micro(2), EcLevel::L),
+            (kanji, Version::Micro(3), EcLevel::M),
+            (b"ab12345678QR", Version::Micro(4), EcLevel::L),
+            (&mixed, Version::Rmqr(13, 77), EcLevel::M),
+        ];
+        for (data, version, ec_level) in cases {
+            assert_round_trip(data, version, ec_level);
+        }
+        for version in Version::rmqr_all() {
+            assert_round_trip(b"ABC-123", version, EcLevel::M);
+        }
+        for len in 0..=20 {
+            let data = (0..len)
+                .map(|i| b"09AZ $%*+-./:az\x00\xff"[i % 17])
+                .collect::<Vec<u8>>();
+            assert_round_trip(&data, Version::Normal(2), EcLevel::L);
+        }
+    }
+
+    #[test]
+    fn test_structured_append_and_eci() {
+        let part = PartInfo {
+            index: 2,
+            total: 5,
+            parity: parity(b"message"),
+        };
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_number_checked(4, 0b0011).unwrap();
+        bits.push_number_checked(4, 2).unwrap();
+        bits.push_number_checked(4, 4).unwrap();
+        bits.push_number_checked(8, usize::from(part.parity))
+            .unwrap();
+        bits.push_number_checked(4, 0b0111).unwrap();
+        bits.push_number_checked(8, 0b1000_0000).unwrap();
+        bits.push_number_checked(8, 26).unwrap();
+        bits.push_byte_data("é".as_bytes()).unwrap();
+        bits.push_terminator(EcLevel::L).unwrap();
+        let segments = inspect(&bits.into_bytes(), Version::Normal(1)).unwrap();
+        assert_eq!(segments[0], InspectedSegment::StructuredAppend(part));
+        assert_eq!(segments[1], InspectedSegment::Eci(26));
+        assert_eq!(segments[2].to_string(), r#"Byte(2) "\xc3\xa9""#);
+        assert_eq!(segments[0].to_string(), "StructuredAppend(3/5, parity 6b)");
+        assert_eq!(segments[1].to_string(), "ECI(000026)");
+    }
+
+    #[test]
+    fn test_fnc1() {
+        let mut bits = Bits::new(Version::Rmqr(7, 43));
+        bits.push_fnc1_second_position(b'A').unwrap();
+        bits.push_alphanumeric_data(b"AB").unwrap();
+        bits.push_terminator(EcLevel::M).unwrap();
+        let segments = inspect(&bits.into_bytes(), Version::Rmqr(7, 43)).unwrap();
+        assert_eq!(
+            segments[0],
+            InspectedSegment::Fnc1(Fnc1Kind::SecondPosition {
+                application_indicator: b'A'
+            })
+        );
+        assert_eq!(segments[0].to_string(), "FNC1(second, 65)");
+
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_fnc1_first_position().unwrap();
+        bits.push_numeric_data(b"01").unwrap();
+        bits.push_terminator(EcLevel::M).unwrap();
+        let segments = inspect(&bits.into_bytes(), Version::Normal(1)).unwrap();
+        assert_eq!(segments[0], InspectedSegment::Fnc1(Fnc1Kind::FirstPosition));
+        assert_eq!(segments[0].to_string(), "FNC1(first)");
+    }
+
+    #[test]
+    fn test_micro_terminator() {
+        // M1 holds 20 bits, which the data fills, so there is no terminator.
+        // The 4 zero bits completing the last byte read as a 3-bit terminator.
+        let mut bits = Bits::new(Version::Micro(1));
+        bits.push_numeric_data(b"12345").unwrap();
+        bits.push_terminator(EcLevel::L).unwrap();
+        let segments = inspect(&bits.into_bytes(), Version::Micro(1)).unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                InspectedSegment::Data {
+                    mode: Mode::Numeric,
+                    chars_count: 5,
+                    data: b"12345".to_vec(),
+                },
+                InspectedSegment::Terminator { bits_count: 3 },
+                InspectedSegment::Padding { bits_count: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            inspect(&[0], Version::Micro(5)),
+            Err(InspectError::InvalidVersion)
+        );
+        assert_eq!(
+            inspect(&[0b0110_0000], Version::Normal(1)),
+            Err(InspectError::UnknownMode {
+                offset: 0,
+                indicator: 0b0110
+            })
+        );
+        assert_eq!(
+            inspect(&[0b1001_0000], Version::Normal(1)),
+            Err(InspectError::Truncated { offset: 0 })
+        );
+        assert_eq!(
+            inspect(&[0b0100_0000, 0b0101_0000], Version::Normal(1)),
+            Err(InspectError::Truncated { offset: 0 })
+        );
+        // 1023 is not a group of 3 digits.
+        assert_eq!(
+            inspect(&[0b0001_0000, 0b0000_1111, 0b1111_1111], Version::Normal(1)),
+            Err(InspectError::InvalidData { offset: 0 })
+        );
     }
-    Err(QrError::DataTooLong)
 }
@@ -4,6 +4,7 @@ use core::panic;
 
 use crate::{types::Color, EcLevel, Version};
 
+#[doc(hidden)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Module {
     Empty,
@@ -28,7 +29,7 @@ impl Module {
 
     /// Apply a mask to the unmasked modules.
     ///
-    ///     use qrqrpar::canvas::Module;
+    ///     use qrqrpar::internals::canvas::Module;
     ///     use qrqrpar::types::Color;
     ///
     ///     assert_eq!(Module::Unmasked(Color::Light).mask(true), Module::Masked(Color::Dark));
@@ -47,7 +48,8 @@ impl Module {
     }
 }
 
-#[derive(Debug, Clone)]
+#[doc(hidden)]
+#[derive(Debug)]
 pub struct Canvas {
     modules: Vec<Module>,
     version: Version,
@@ -56,6 +58,27 @@ pub struct Canvas {
     height: i16,
 }
 
+impl Clone for Canvas {
+    fn clone(&self) -> Self {
+        Self {
+            modules: self.modules.clone(),
+            version: self.version,
+            ec_level: self.ec_level,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Copies the source canvas, reusing the allocated modules.
+    fn clone_from(&mut self, source: &Self) {
+        self.modules.clone_from(&source.modules);
+        self.version = source.version;
+        self.ec_level = source.ec_level;
+        self.width = source.width;
+        self.height = source.height;
+    }
+}
+
 impl Canvas {
     pub fn new(version: Version, ec_level: EcLevel) -> Self {
         let modules = vec![Module::Empty; (version.width() * version.height()) as usize];
@@ -68,6 +91,18 @@ impl Canvas {
         }
     }
 
+    /// Clears the canvas for the given version, reusing the allocated
+    /// modules. The result is the same as `Canvas::new`.
+    pub fn reset(&mut self, version: Version, ec_level: EcLevel) {
+        self.modules.clear();
+        self.modules
+            .resize((version.width() * version.height()) as usize, Module::Empty);
+        self.version = version;
+        self.ec_level = ec_level;
+        self.width = version.width();
+        self.height = version.height();
+    }
+
     /// Converts the canvas into a human-readable string.
     #[cfg(test)]
     fn to_debug_str(&self) -> String {
@@ -132,6 +167,12 @@ impl Canvas {
     pub fn put(&mut self, x: i16, y: i16, color: Color) {
         *self.get_mut(x, y) = Module::Masked(color);
     }
+
+    /// Checks whether the module at the given coordinates has been drawn. Before
+    /// `.draw_data()`, this means the module belongs to a functional pattern.
+    pub fn is_drawn(&self, x: i16, y: i16) -> bool {
+        self.get(x, y) != Module::Empty
+    }
 }
 
 impl Canvas {
@@ -257,7 +298,7 @@ impl Canvas {
 
 #[cfg(test)]
 mod alignment_pattern_tests {
-    use crate::canvas::Canvas;
+    use crate::internals::canvas::Canvas;
     use crate::types::{EcLevel, Version};
 
     #[test]
@@ -567,7 +608,7 @@ impl Canvas {
 
 #[cfg(test)]
 mod timing_pattern_tests {
-    use crate::canvas::Canvas;
+    use crate::internals::canvas::Canvas;
     use crate::types::{EcLevel, Version};
 
     #[test]
@@ -696,8 +737,7 @@ impl Canvas {
     }
 
     /// Draws the format info patterns for an encoded number.
-    fn draw_format_info_patterns_with_number(&mut self, format_info: u16) {
-        let format_info = u32::from(format_info);
+    fn draw_format_info_patterns_with_number(&mut self, format_info: u32) {
         match self.version {
             Version::Micro(_) => {
                 self.draw_number(format_info, 15, &FORMAT_INFO_COORDS_MICRO_QR);
@@ -720,16 +760,14 @@ impl Canvas {
     fn draw_version_info_patterns(&mut self) {
         match self.version {
             Version::Micro(_) | Version::Normal(1..=6) => {}
-            Version::Normal(a) => {
-                let version_info = VERSION_INFOS[(a - 7) as usize];
+            Version::Normal(_) => {
+                let version_info = version_info(self.version).unwrap();
                 self.draw_number(version_info, 18, &VERSION_INFO_COORDS_BL);
                 self.draw_number(version_info, 18, &VERSION_INFO_COORDS_TR);
             }
             Version::Rmqr(_, _) => {
-                let index = self.version.rmqr_index().unwrap();
-                let ec_level = if self.ec_level == EcLevel::M { 0 } else { 1 };
-                let version_info_left = RMQR_VERSION_INFOS_L[index][ec_level];
-                let version_info_right = RMQR_VERSION_INFOS_R[index][ec_level];
+                let (version_info_left, version_info_right) =
+                    rmqr_format_info(self.version, self.ec_level).unwrap();
                 self.draw_number(version_info_left, 18, &RMQR_VERSION_INFO_COORDS_L);
                 self.draw_number(version_info_right, 18, &RMQR_VERSION_INFO_COORDS_R);
             }
@@ -739,7 +777,7 @@ impl Canvas {
 
 #[cfg(test)]
 mod draw_version_info_tests {
-    use crate::canvas::Canvas;
+    use crate::internals::canvas::Canvas;
     use crate::types::{EcLevel, Version};
 
     #[test]
@@ -1248,11 +1286,19 @@ impl Canvas {
         self.draw_codewords(data, is_half_codeword_at_end, &mut coords);
         self.draw_codewords(ec, false, &mut coords);
     }
+
+    /// Returns the modules left empty by the functional patterns, in the
+    /// order the codeword bits are placed into them, the remainder bits last.
+    pub fn data_module_order(&self) -> Vec<(i16, i16)> {
+        DataModuleIter::new(self.version)
+            .filter(|&(x, y)| !self.is_drawn(x, y))
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod draw_codewords_test {
-    use crate::canvas::Canvas;
+    use crate::internals::canvas::Canvas;
     use crate::types::{EcLevel, Version};
 
     #[test]
@@ -1432,43 +1478,15 @@ impl Canvas {
             return;
         }
 
-        let format_number = match self.version {
-            Version::Normal(_) => {
-                let simple_format_number = ((self.ec_level as usize) ^ 1) << 3 | (pattern as usize);
-                FORMAT_INFOS_QR[simple_format_number]
-            }
-            Version::Micro(a) => {
-                let micro_pattern_number = match pattern {
-                    MaskPattern::HorizontalLines => 0b00,
-                    MaskPattern::LargeCheckerboard => 0b01,
-                    MaskPattern::Diamonds => 0b10,
-                    MaskPattern::Meadow => 0b11,
-                    _ => panic!("Unsupported mask pattern in Micro QR code"),
-                };
-                let symbol_number = match (a, self.ec_level) {
-                    (1, EcLevel::L) => 0b000,
-                    (2, EcLevel::L) => 0b001,
-                    (2, EcLevel::M) => 0b010,
-                    (3, EcLevel::L) => 0b011,
-                    (3, EcLevel::M) => 0b100,
-                    (4, EcLevel::L) => 0b101,
-                    (4, EcLevel::M) => 0b110,
-                    (4, EcLevel::Q) => 0b111,
-                    _ => panic!("Unsupported version/ec_level combination in Micro QR code"),
-                };
-                let simple_format_number = symbol_number << 2 | micro_pattern_number;
-                FORMAT_INFOS_MICRO_QR[simple_format_number]
-            }
-
-            Version::Rmqr(_, _) => return,
-        };
+        let format_number = format_info(self.version, self.ec_level, pattern)
+            .expect("Unsupported mask pattern or ec_level in this version");
         self.draw_format_info_patterns_with_number(format_number);
     }
 }
 
 #[cfg(test)]
 mod mask_tests {
-    use crate::canvas::{Canvas, MaskPattern};
+    use crate::internals::canvas::{Canvas, MaskPattern};
     use crate::types::{EcLevel, Version};
 
     #[test]
@@ -1559,6 +1577,100 @@ mod mask_tests {
     }
 }
 
+// Format and version information
+
+/// Computes the 15-bit format information of a QR or Micro QR code, encoding
+/// the error correction level and the mask pattern, including the BCH error
+/// correction bits and the XOR mask.
+///
+///     use qrqrpar::MaskPattern;
+///     use qrqrpar::{format_info, EcLevel, Version};
+///
+///     let info = format_info(Version::Normal(1), EcLevel::M, MaskPattern::LargeCheckerboard);
+///     assert_eq!(info, Some(0x45f9));
+///
+/// Only the family matters for QR codes, while Micro QR codes also encode the
+/// version number in their format information.
+///
+/// Returns `None` for rMQR codes, which have no mask pattern in their format
+/// information (see `rmqr_format_info`), and for combinations which cannot be
+/// encoded, e.g. a Micro QR code with `MaskPattern::Checkerboard`.
+pub fn format_info(version: Version, ec_level: EcLevel, pattern: MaskPattern) -> Option<u32> {
+    match version {
+        Version::Normal(_) => {
+            let simple_format_number = ((ec_level as usize) ^ 1) << 3 | (pattern as usize);
+            Some(u32::from(FORMAT_INFOS_QR[simple_format_number]))
+        }
+        Version::Micro(a) => {
+            let micro_pattern_number = match pattern {
+                MaskPattern::HorizontalLines => 0b00,
+                MaskPattern::LargeCheckerboard => 0b01,
+                MaskPattern::Diamonds => 0b10,
+                MaskPattern::Meadow => 0b11,
+                _ => return None,
+            };
+            let symbol_number = match (a, ec_level) {
+                (1, EcLevel::L) => 0b000,
+                (2, EcLevel::L) => 0b001,
+                (2, EcLevel::M) => 0b010,
+                (3, EcLevel::L) => 0b011,
+                (3, EcLevel::M) => 0b100,
+                (4, EcLevel::L) => 0b101,
+                (4, EcLevel::M) => 0b110,
+                (4, EcLevel::Q) => 0b111,
+                _ => return None,
+            };
+            let simple_format_number = symbol_number << 2 | micro_pattern_number;
+            Some(u32::from(FORMAT_INFOS_MICRO_QR[simple_format_number]))
+        }
+        Version::Rmqr(_, _) => None,
+    }
+}
+
+/// Computes the 18-bit version information of a QR code, including the BCH
+/// error correction bits.
+///
+///     use qrqrpar::{version_info, Version};
+///
+///     assert_eq!(version_info(Version::Normal(7)), Some(0x07c94));
+///     assert_eq!(version_info(Version::Normal(6)), None);
+///
+/// Returns `None` for QR code versions 1 to 6 and Micro QR codes, which have no
+/// version information, and for rMQR codes, which encode their version in the
+/// format information (see `rmqr_format_info`).
+pub fn version_info(version: Version) -> Option<u32> {
+    match version {
+        Version::Normal(a @ 7..=40) => Some(VERSION_INFOS[(a - 7) as usize]),
+        _ => None,
+    }
+}
+
+/// Computes the two 18-bit format information blocks of a rMQR code, encoding
+/// the error correction level and the version, including the BCH error
+/// correction bits and the XOR masks. The first block is placed next to the
+/// finder pattern and the second one next to the finder sub pattern.
+///
+///     use qrqrpar::{rmqr_format_info, EcLevel, Version};
+///
+///     let info = rmqr_format_info(Version::Rmqr(7, 43), EcLevel::M);
+///     assert_eq!(info, Some((0x1fab2, 0x20a7b)));
+///
+/// rMQR codes always use `MaskPattern::LargeCheckerboard`, so the mask pattern
+/// is not encoded. Returns `None` if the version is not a valid rMQR version or
+/// if the error correction level is neither M nor H.
+pub fn rmqr_format_info(version: Version, ec_level: EcLevel) -> Option<(u32, u32)> {
+    let index = version.rmqr_index().ok()?;
+    let ec_level = match ec_level {
+        EcLevel::M => 0,
+        EcLevel::H => 1,
+        _ => return None,
+    };
+    Some((
+        RMQR_VERSION_INFOS_L[index][ec_level],
+        RMQR_VERSION_INFOS_R[index][ec_level],
+    ))
+}
+
 static FORMAT_INFOS_QR: [u16; 32] = [
     0x5412, 0x5125, 0x5e7c, 0x5b4b, 0x45f9, 0x40ce, 0x4f97, 0x4aa0, 0x77c4, 0x72f3, 0x7daa, 0x789d,
     0x662f, 0x6318, 0x6c41, 0x6976, 0x1689, 0x13be, 0x1ce7, 0x19d0, 0x0762, 0x0255, 0x0d0c, 0x083b,
@@ -1571,6 +1683,133 @@ static FORMAT_INFOS_MICRO_QR: [u16; 32] = [
     0x2508, 0x203f, 0x2f66, 0x2a51, 0x34e3, 0x31d4, 0x3e8d, 0x3bba,
 ];
 
+#[cfg(test)]
+mod format_info_tests {
+    use crate::internals::canvas::{format_info, rmqr_format_info, version_info, MaskPattern};
+    use crate::types::{EcLevel, Version};
+
+    static ALL_PATTERNS: [MaskPattern; 8] = [
+        MaskPattern::Checkerboard,
+        MaskPattern::HorizontalLines,
+        MaskPattern::VerticalLines,
+        MaskPattern::DiagonalLines,
+        MaskPattern::LargeCheckerboard,
+        MaskPattern::Fields,
+        MaskPattern::Diamonds,
+        MaskPattern::Meadow,
+    ];
+
+    /// Appends the BCH error correction bits of `data` using the generator
+    /// polynomial of the given degree.
+    fn bch(data: u32, generator: u32, degree: u32) -> u32 {
+        let mut remainder = data << degree;
+        for i in (degree..32).rev() {
+            if remainder & (1 << i) != 0 {
+                remainder ^= generator << (i - degree);
+            }
+        }
+        data << degree | remainder
+    }
+
+    #[test]
+    fn test_qr() {
+        // ISO/IEC 18004:2015 Annex C.
+        for (ec_level, ec_bits) in [
+            (EcLevel::L, 0b01),
+            (EcLevel::M, 0b00),
+            (EcLevel::Q, 0b11),
+            (EcLevel::H, 0b10),
+        ] {
+            for pattern in ALL_PATTERNS {
+                let expected = bch(ec_bits << 3 | pattern as u32, 0b101_0011_0111, 10) ^ 0x5412;
+                for version in [Version::Normal(1), Version::Normal(40)] {
+                    assert_eq!(format_info(version, ec_level, pattern), Some(expected));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_micro_qr() {
+        // ISO/IEC 18004:2015 Table 13 and Annex C.
+        let symbols = [
+            (1, EcLevel::L),
+            (2, EcLevel::L),
+            (2, EcLevel::M),
+            (3, EcLevel::L),
+            (3, EcLevel::M),
+            (4, EcLevel::L),
+            (4, EcLevel::M),
+            (4, EcLevel::Q),
+        ];
+        let patterns = [
+            MaskPattern::HorizontalLines,
+            MaskPattern::LargeCheckerboard,
+            MaskPattern::Diamonds,
+            MaskPattern::Meadow,
+        ];
+        for (symbol_number, (version, ec_level)) in symbols.into_iter().enumerate() {
+            for (pattern_number, pattern) in patterns.into_iter().enumerate() {
+                let data = (symbol_number << 2 | pattern_number) as u32;
+                let expected = bch(data, 0b101_0011_0111, 10) ^ 0x4445;
+                assert_eq!(
+                    format_info(Version::Micro(version), ec_level, pattern),
+                    Some(expected)
+                );
+            }
+            assert_eq!(
+                format_info(Version::Micro(version), ec_level, MaskPattern::Checkerboard),
+                None
+            );
+        }
+        assert_eq!(
+            format_info(Version::Micro(1), EcLevel::M, MaskPattern::Meadow),
+            None
+        );
+    }
+
+    #[test]
+    fn test_version_info() {
+        // ISO/IEC 18004:2015 Annex D.
+        for version in 7..=40 {
+            let expected = bch(u32::from(version), 0b1_1111_0010_0101, 12);
+            assert_eq!(version_info(Version::Normal(version)), Some(expected));
+        }
+        assert_eq!(version_info(Version::Normal(6)), None);
+        assert_eq!(version_info(Version::Micro(4)), None);
+        assert_eq!(version_info(Version::Rmqr(7, 43)), None);
+    }
+
+    #[test]
+    fn test_rmqr() {
+        // ISO/IEC 23941:2022 §7.9.
+        let mut index = 0;
+        for height in Version::rmqr_all_height() {
+            for width in Version::rmqr_all_width() {
+                let version = Version::Rmqr(height, width);
+                if !version.is_rmqr() {
+                    continue;
+                }
+                for (ec_level, ec_bit) in [(EcLevel::M, 0), (EcLevel::H, 1)] {
+                    let info = bch(ec_bit << 5 | index, 0b1_1111_0010_0101, 12);
+                    assert_eq!(
+                        rmqr_format_info(version, ec_level),
+                        Some((info ^ 0x1fab2, info ^ 0x20a7b)),
+                        "{version:?} {ec_level:?}"
+                    );
+                }
+                assert_eq!(rmqr_format_info(version, EcLevel::L), None);
+                assert_eq!(
+                    format_info(version, EcLevel::M, MaskPattern::LargeCheckerboard),
+                    None
+                );
+                index += 1;
+            }
+        }
+        assert_eq!(index, 32);
+    }
+}
+
 impl Canvas {
     /// Compute the penalty score for having too many adjacent modules with the
     /// same color.
@@ -1589,9 +1828,7 @@ impl Canvas {
                 }
             };
 
-            let colors = (0..self.width)
-                .map(map_fn)
-                .chain(Some(Module::Empty).into_iter());
+            let colors = (0..self.width).map(map_fn).chain(Some(Module::Empty));
             let mut last_color = Module::Empty;
             let mut consecutive_len = 1_u16;
 
@@ -1733,7 +1970,7 @@ impl Canvas {
 
 #[cfg(test)]
 mod penalty_tests {
-    use crate::canvas::{Canvas, MaskPattern};
+    use crate::internals::canvas::{Canvas, MaskPattern};
     use crate::types::{Color, EcLevel, Version};
 
     fn create_test_canvas() -> Canvas {
@@ -1876,26 +2113,112 @@ static ALL_PATTERNS_MICRO_QR: [MaskPattern; 4] = [
 
 static ALL_PATTERNS_RMQR: [MaskPattern; 1] = [MaskPattern::LargeCheckerboard];
 
+/// The mask patterns available to the symbol family of the version, in
+/// ascending order of their pattern number.
+pub fn mask_patterns(version: Version) -> &'static [MaskPattern] {
+    match version {
+        Version::Normal(_) => &ALL_PATTERNS_QR,
+        Version::Micro(_) => &ALL_PATTERNS_MICRO_QR,
+        Version::Rmqr(_, _) => &ALL_PATTERNS_RMQR,
+    }
+}
+
 impl Canvas {
     /// Construct a new canvas and apply the best masking that gives the lowest
     /// penalty score.
+    ///
+    /// Among the patterns sharing the lowest score, the one with the lowest
+    /// pattern number of the symbol family is chosen.
     pub fn apply_best_mask(&self) -> Self {
-        match self.version {
-            Version::Normal(_) => ALL_PATTERNS_QR.iter(),
-            Version::Micro(_) => ALL_PATTERNS_MICRO_QR.iter(),
-            Version::Rmqr(_, _) => ALL_PATTERNS_RMQR.iter(),
+        let mut scratch = self.clone();
+        let mut best = self.clone();
+        self.apply_best_mask_into(&mut scratch, &mut best);
+        best
+    }
+
+    /// Same as `apply_best_mask`, but stores the result into `best`, using
+    /// `scratch` as working space, so that their allocations can be reused.
+    pub fn apply_best_mask_into(&self, scratch: &mut Canvas, best: &mut Canvas) {
+        let mut best_score = None;
+        for ptn in mask_patterns(self.version) {
+            scratch.clone_from(self);
+            scratch.apply_mask(*ptn);
+            let score = scratch.compute_total_penalty_scores();
+            // Keep the first pattern among those with the lowest score.
+            if best_score.is_none_or(|best_score| score < best_score) {
+                best_score = Some(score);
+                std::mem::swap(scratch, best);
+            }
         }
-        .map(|ptn| {
-            let mut c = self.clone();
-            c.apply_mask(*ptn);
-            c
-        })
-        .min_by_key(Self::compute_total_penalty_scores)
-        .expect("at least one pattern")
     }
 
     /// Convert the modules into a vector of colors.
     pub fn into_colors(self) -> Vec<Color> {
-        self.modules.into_iter().map(Color::from).collect()
+        self.to_colors()
+    }
+
+    /// Copies the modules into a vector of colors.
+    pub fn to_colors(&self) -> Vec<Color> {
+        self.modules.iter().copied().map(Color::from).collect()
+    }
+}
+
+#[cfg(test)]
+mod best_mask_tests {
+    use crate::internals::canvas::{Canvas, ALL_PATTERNS_MICRO_QR, ALL_PATTERNS_QR};
+    use crate::types::{EcLevel, Version};
+
+    fn create_test_canvas(version: Version, ec_level: EcLevel, data: &[u8]) -> Canvas {
+        let mut c = Canvas::new(version, ec_level);
+        c.draw_all_functional_patterns();
+        c.draw_data(data, &[]);
+        c
+    }
+
+    #[test]
+    fn test_matches_first_minimum() {
+        for (version, patterns) in [
+            (Version::Normal(2), &ALL_PATTERNS_QR[..]),
+            (Version::Micro(3), &ALL_PATTERNS_MICRO_QR[..]),
+        ] {
+            for seed in 0..16u8 {
+                let data = (0..40)
+                    .map(|i: u8| i.wrapping_mul(31) ^ seed.wrapping_mul(97))
+                    .collect::<Vec<_>>();
+                let c = create_test_canvas(version, EcLevel::L, &data);
+                let expected = patterns
+                    .iter()
+                    .map(|ptn| {
+                        let mut masked = c.clone();
+                        masked.apply_mask(*ptn);
+                        masked
+                    })
+                    .min_by_key(Canvas::compute_total_penalty_scores)
+                    .unwrap();
+                assert_eq!(c.apply_best_mask().modules, expected.modules);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reused_buffers() {
+        let mut scratch = Canvas::new(Version::Normal(40), EcLevel::L);
+        let mut best = Canvas::new(Version::Micro(1), EcLevel::L);
+        for version in [Version::Normal(3), Version::Rmqr(7, 43), Version::Micro(2)] {
+            let c = create_test_canvas(version, EcLevel::M, &[0x5a; 20]);
+            c.apply_best_mask_into(&mut scratch, &mut best);
+            assert_eq!(best.modules, c.apply_best_mask().modules);
+            assert_eq!(best.to_debug_str(), c.apply_best_mask().to_debug_str());
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut c = create_test_canvas(Version::Normal(5), EcLevel::L, &[0xff; 30]);
+        c.reset(Version::Micro(2), EcLevel::M);
+        assert_eq!(
+            c.to_debug_str(),
+            Canvas::new(Version::Micro(2), EcLevel::M).to_debug_str()
+        );
     }
 }
@@ -1,4 +1,5 @@
 //! Find the optimal data mode sequence to encode a piece of data.
+use crate::bits::kanji_number;
 use crate::types::{Mode, Version};
 use core::slice::Iter;
 
@@ -21,6 +22,9 @@ pub struct Segment {
 impl Segment {
     /// Compute the number of bits (including the size of the mode indicator and
     /// length bits) when this segment is encoded.
+    ///
+    /// A Kanji segment holds one character per 2 bytes, and its length field
+    /// counts the characters, as `Bits::push_kanji_data` pushes them.
     pub fn encoded_len(&self, version: Version) -> usize {
         let byte_size = self.end - self.begin;
         let chars_count = if self.mode == Mode::Kanji {
@@ -80,6 +84,20 @@ impl<'a, I: Iterator<Item = &'a u8>> Iterator for EcsIter<I> {
 }
 
 /// QR code data parser to classify the input into distinct segments.
+///
+/// The parser yields the segments in order. Each segment starts where the
+/// previous one ends, the first one at 0 and the last one at the end of the
+/// data, so empty data yields no segment at all.
+///
+/// Each segment has the narrowest mode encoding all of its characters, and
+/// adjacent segments have different modes, except for the bytes which are not
+/// part of a Shift JIS character: each of them is a separate byte segment.
+/// Kanji characters are paired from the left, so a Kanji segment never ends
+/// in the middle of a character, and a lone leading byte at its end is
+/// yielded as a byte segment.
+///
+/// The segments are not optimized for any version, see `Optimizer` and
+/// `optimal_segments`.
 pub struct Parser<'a> {
     ecs_iter: EcsIter<Iter<'a, u8>>,
     state: State,
@@ -91,7 +109,7 @@ impl<'a> Parser<'a> {
     /// Creates a new iterator which parse the data into segments that only
     /// contains their exclusive subsets. No optimization is done at this point.
     ///
-    ///     use qrqrpar::coding::{Parser, Segment};
+    ///     use qrqrpar::{Parser, Segment};
     ///     use qrqrpar::types::Mode::{Alphanumeric, Numeric, Byte};
     ///
     ///     let parse_res = Parser::new(b"ABC123abcd").collect::<Vec<Segment>>();
@@ -99,7 +117,7 @@ impl<'a> Parser<'a> {
     ///                                Segment { mode: Numeric, begin: 3, end: 6 },
     ///                                Segment { mode: Byte, begin: 6, end: 10 }]);
     ///
-    pub fn new(data: &[u8]) -> Parser {
+    pub fn new(data: &[u8]) -> Parser<'_> {
         Parser {
             ecs_iter: EcsIter {
                 base: data.iter(),
@@ -128,10 +146,7 @@ impl<'a> Iterator for Parser<'a> {
         }
 
         loop {
-            let (i, ecs) = match self.ecs_iter.next() {
-                None => return None,
-                Some(a) => a,
-            };
+            let (i, ecs) = self.ecs_iter.next()?;
             let (next_state, action) = STATE_TRANSITION[self.state as usize + ecs as usize];
             self.state = next_state;
 
@@ -170,13 +185,27 @@ impl<'a> Iterator for Parser<'a> {
 
 #[cfg(test)]
 mod parse_tests {
-    use crate::coding::{Parser, Segment};
-    use crate::types::Mode;
+    use crate::internals::coding::{Parser, Segment};
+    use crate::types::{Mode, ALPHANUMERIC_CHARSET, NUMERIC_CHARSET};
 
     fn parse(data: &[u8]) -> Vec<Segment> {
         Parser::new(data).collect()
     }
 
+    #[test]
+    fn test_single_bytes_follow_charsets() {
+        for byte in 0..=255 {
+            let mode = if NUMERIC_CHARSET.contains(&byte) {
+                Mode::Numeric
+            } else if ALPHANUMERIC_CHARSET.contains(&byte) {
+                Mode::Alphanumeric
+            } else {
+                Mode::Byte
+            };
+            assert_eq!(parse(&[byte])[0].mode, mode, "{byte:#04x}");
+        }
+    }
+
     #[test]
     fn test_parse_1() {
         let segs = parse(b"01049123451234591597033130128%10ABC123");
@@ -370,46 +399,300 @@ mod parse_tests {
 //------------------------------------------------------------------------------
 //{{{ Optimizer
 
+/// Why the optimizer produced a segment.
+///
+/// Every optimized segment is a run of consecutive parsed segments, so the
+/// reasons only tell how many were merged. There is no reason for a split,
+/// since the optimizer never splits a parsed segment, nor a direction of
+/// merging, since a run absorbs its neighbours on both sides at once rather
+/// than merging into the previous segment one by one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MergeReason {
+    /// The segment is exactly as parsed, because merging it with a neighbour
+    /// would need more bits.
+    Kept,
+
+    /// The segment merges several parsed segments into the mode of the
+    /// widest one.
+    Merged {
+        /// The number of parsed segments merged.
+        parsed_segments: usize,
+
+        /// The number of bits saved compared to encoding the parsed segments
+        /// separately. Zero when both are equally long, in which case the
+        /// optimizer prefers fewer segments.
+        saved_bits: usize,
+    },
+}
+
+/// The states of the last segment of a grouping: its mode, and the number of
+/// characters modulo the number encoded together by the mode (3 digits in
+/// numeric mode, 2 characters in alphanumeric mode), which is all that is
+/// needed to compute the bits of extending it.
+const STATES: [(Mode, usize); 7] = [
+    (Mode::Numeric, 0),
+    (Mode::Numeric, 1),
+    (Mode::Numeric, 2),
+    (Mode::Alphanumeric, 0),
+    (Mode::Alphanumeric, 1),
+    (Mode::Byte, 0),
+    (Mode::Kanji, 0),
+];
+
+/// Returns the index in `STATES` of a segment of `chars_count` characters.
+fn state_index(mode: Mode, chars_count: usize) -> usize {
+    match mode {
+        Mode::Numeric => chars_count % 3,
+        Mode::Alphanumeric => 3 + chars_count % 2,
+        Mode::Byte => 5,
+        Mode::Kanji => 6,
+    }
+}
+
+/// The cheapest way to reach a state, after some parsed segments.
+#[derive(Debug, Copy, Clone)]
+struct Step {
+    /// The number of bits of all segments.
+    cost: usize,
+    /// The number of segments, to prefer fewer of them among equal costs.
+    segments_count: usize,
+    /// The state before the last parsed segment, `None` at the beginning.
+    from: Option<usize>,
+    /// Whether the last parsed segment starts a new segment.
+    starts_segment: bool,
+}
+
+impl Step {
+    fn is_better_than(&self, other: &Option<Step>) -> bool {
+        other.is_none_or(|o| (self.cost, self.segments_count) < (o.cost, o.segments_count))
+    }
+}
+
+/// The cheapest grouping of the parsed segments pushed so far.
+///
+/// Every way to merge runs of adjacent parsed segments is considered, by
+/// dynamic programming over the states of the last segment. Pushing more
+/// segments never changes the steps already computed.
+#[derive(Debug, Clone)]
+pub(crate) struct Segmentation {
+    version: Version,
+    segments: Vec<Segment>,
+    /// `steps[j][state]` is the cheapest way to encode the first `j + 1`
+    /// parsed segments ending in `state`.
+    steps: Vec<[Option<Step>; 7]>,
+}
+
+impl Segmentation {
+    pub(crate) fn new(version: Version) -> Self {
+        Self {
+            version,
+            segments: Vec::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Pushes the next parsed segment.
+    pub(crate) fn push(&mut self, segment: Segment) {
+        let previous = self.steps.last();
+        let best_previous = match previous {
+            None => Some((0, 0, None)),
+            Some(steps) => steps
+                .iter()
+                .enumerate()
+                .filter_map(|(i, step)| step.map(|s| (s.cost, s.segments_count, Some(i))))
+                .min(),
+        };
+
+        let byte_size = segment.end - segment.begin;
+        let chars_count = |mode: Mode| {
+            if mode == Mode::Kanji {
+                byte_size / 2
+            } else {
+                byte_size
+            }
+        };
+        let mut steps: [Option<Step>; 7] = [None; 7];
+        let mut update = |state: usize, next: Step| {
+            if next.is_better_than(&steps[state]) {
+                steps[state] = Some(next);
+            }
+        };
+
+        // Extends the last segment.
+        for (i, step) in previous.into_iter().flatten().enumerate() {
+            let (mode, residue) = STATES[i];
+            let Some(step) = step else {
+                continue;
+            };
+            if segment.mode <= mode {
+                let chars_count = residue + chars_count(mode);
+                let next = Step {
+                    cost: step.cost + mode.data_bits_count(chars_count)
+                        - mode.data_bits_count(residue),
+                    segments_count: step.segments_count,
+                    from: Some(i),
+                    starts_segment: false,
+                };
+                update(state_index(mode, chars_count), next);
+            }
+        }
+
+        // Starts a new segment, in one of the modes the version supports.
+        if let Some((cost, segments_count, from)) = best_previous {
+            for &mode in self.version.supported_modes() {
+                if segment.mode <= mode {
+                    let next = Step {
+                        cost: cost + Segment { mode, ..segment }.encoded_len(self.version),
+                        segments_count: segments_count + 1,
+                        from,
+                        starts_segment: true,
+                    };
+                    update(state_index(mode, chars_count(mode)), next);
+                }
+            }
+        }
+
+        self.segments.push(segment);
+        self.steps.push(steps);
+    }
+
+    /// Returns the optimized segments, with the reason of each.
+    pub(crate) fn explain(&self) -> Vec<(Segment, MergeReason)> {
+        let Some(last) = self.steps.last() else {
+            return Vec::new();
+        };
+        let mut state = (0..STATES.len())
+            .filter(|i| last[*i].is_some())
+            .min_by_key(|i| last[*i].map(|s| (s.cost, s.segments_count)));
+        if state.is_none() {
+            // Some character needs a mode the version does not support, so
+            // the segments are kept as parsed and fail to be encoded.
+            return self
+                .segments
+                .iter()
+                .map(|segment| (*segment, MergeReason::Kept))
+                .collect();
+        }
+
+        let mut res = Vec::new();
+        let mut end = self.segments.len();
+        for j in (0..self.segments.len()).rev() {
+            let step = state
+                .and_then(|i| self.steps[j][i])
+                .expect("every parsed segment has a step");
+            if step.starts_segment {
+                let parsed = &self.segments[j..end];
+                let segment = Segment {
+                    mode: STATES[state.unwrap_or(0)].0,
+                    begin: parsed[0].begin,
+                    end: parsed[parsed.len() - 1].end,
+                };
+                let reason = if parsed.len() == 1 && parsed[0].mode == segment.mode {
+                    MergeReason::Kept
+                } else {
+                    MergeReason::Merged {
+                        parsed_segments: parsed.len(),
+                        saved_bits: total_encoded_len(parsed, self.version)
+                            .saturating_sub(segment.encoded_len(self.version)),
+                    }
+                };
+                res.push((segment, reason));
+                end = j;
+            }
+            state = step.from;
+        }
+        res.reverse();
+        res
+    }
+}
+
+/// Merges adjacent parsed segments to minimize the number of bits needed to
+/// encode them.
+///
+/// Among all the ways to merge runs of adjacent segments, the optimizer picks
+/// one needing the fewest bits, so the result is never longer than the parsed
+/// segments, nor than encoding the whole data in a single mode. Among equally
+/// long results, it prefers fewer segments.
+///
+/// Only the modes supported by the version are used, e.g. no byte segment is
+/// merged for Micro QR code version 2. If some character cannot be encoded in
+/// any supported mode, the segments are yielded as parsed.
+///
+/// The segments are only cut where the parsed segments are, which can cost a
+/// few bits around Shift JIS data, see `optimal_segments`, which the encoders
+/// use instead. The optimizer is still useful to explain the merges, see
+/// `with_explanations`.
+///
+///     use qrqrpar::{total_encoded_len, Optimizer, Parser, Segment};
+///     use qrqrpar::types::{Mode, Version};
+///
+///     let data = b"https://example.com/items/20240117";
+///     let version = Version::Normal(2);
+///     let segments = Parser::new(data).optimize(version).collect::<Vec<_>>();
+///     assert_eq!(
+///         segments,
+///         vec![
+///             Segment { mode: Mode::Byte, begin: 0, end: 26 },
+///             Segment { mode: Mode::Numeric, begin: 26, end: 34 },
+///         ]
+///     );
+///
+///     let single = [Segment { mode: Mode::Byte, begin: 0, end: data.len() }];
+///     assert!(total_encoded_len(&segments, version) < total_encoded_len(&single, version));
+///
+/// The segments are only yielded once the input has been consumed entirely.
 pub struct Optimizer<I> {
-    parser: I,
-    last_segment: Segment,
-    last_segment_size: usize,
+    parser: Option<I>,
     version: Version,
-    ended: bool,
+    optimized: std::vec::IntoIter<(Segment, MergeReason)>,
 }
 
 impl<I: Iterator<Item = Segment>> Optimizer<I> {
-    /// Optimize the segments by combining adjacent segments when possible.
+    /// Optimize the segments for the given version.
     ///
-    /// Currently this method uses a greedy algorithm by combining segments from
-    /// left to right until the new segment is longer than before. This method
-    /// does *not* use Annex J from the ISO standard.
+    /// The segments must be adjacent, as produced by a `Parser`. The version
+    /// decides the lengths of the mode indicators and the length fields.
+    pub fn new(segments: I, version: Version) -> Self {
+        Self {
+            parser: Some(segments),
+            version,
+            optimized: Vec::new().into_iter(),
+        }
+    }
+
+    /// Returns the optimized segments together with the reason why each was
+    /// kept as parsed or merged.
     ///
-    pub fn new(mut segments: I, version: Version) -> Self {
-        match segments.next() {
-            None => Self {
-                parser: segments,
-                last_segment: Segment {
-                    mode: Mode::Numeric,
-                    begin: 0,
-                    end: 0,
-                },
-                last_segment_size: 0,
-                version,
-                ended: true,
-            },
-            Some(segment) => Self {
-                parser: segments,
-                last_segment: segment,
-                last_segment_size: segment.encoded_len(version),
-                version,
-                ended: false,
-            },
+    ///     use qrqrpar::{MergeReason, Parser};
+    ///     use qrqrpar::types::Version;
+    ///
+    ///     let segments = Parser::new(b"ABC123d").optimize(Version::Normal(1));
+    ///     let explained = segments.with_explanations().collect::<Vec<_>>();
+    ///     assert_eq!(
+    ///         explained[0].1,
+    ///         MergeReason::Merged { parsed_segments: 2, saved_bits: 8 }
+    ///     );
+    ///     assert_eq!(explained[1].1, MergeReason::Kept);
+    ///
+    pub fn with_explanations(mut self) -> std::vec::IntoIter<(Segment, MergeReason)> {
+        self.optimize();
+        self.optimized
+    }
+
+    fn optimize(&mut self) {
+        if let Some(parser) = self.parser.take() {
+            let mut segmentation = Segmentation::new(self.version);
+            for segment in parser {
+                segmentation.push(segment);
+            }
+            self.optimized = segmentation.explain().into_iter();
         }
     }
 }
 
 impl<'a> Parser<'a> {
+    /// Optimizes the parsed segments for the given version, the same as
+    /// `Optimizer::new(self, version)`.
     pub fn optimize(self, version: Version) -> Optimizer<Parser<'a>> {
         Optimizer::new(self, version)
     }
@@ -419,49 +702,228 @@ impl<I: Iterator<Item = Segment>> Iterator for Optimizer<I> {
     type Item = Segment;
 
     fn next(&mut self) -> Option<Segment> {
-        if self.ended {
-            return None;
+        self.optimize();
+        self.optimized.next().map(|(segment, _)| segment)
+    }
+}
+
+/// Computes the total encoded length of all segments.
+pub fn total_encoded_len(segments: &[Segment], version: Version) -> usize {
+    segments.iter().map(|seg| seg.encoded_len(version)).sum()
+}
+
+/// The cheapest segmentation of the bytes pushed so far, choosing the mode of
+/// every character.
+///
+/// This is the same dynamic programming as `Segmentation`, over the bytes
+/// instead of the parsed segments. Pushing more bytes never changes the steps
+/// already computed, so the segmentation of a prefix can be cloned and
+/// continued.
+#[derive(Debug, Clone)]
+pub(crate) struct CharSegmentation {
+    version: Version,
+    data: Vec<u8>,
+    /// `steps[i][state]` is the cheapest way to encode the first `i` bytes
+    /// ending in `state`. The step of a Kanji state covers 2 bytes, the
+    /// others 1.
+    steps: Vec<[Option<Step>; 7]>,
+}
+
+impl CharSegmentation {
+    pub(crate) fn new(version: Version) -> Self {
+        Self {
+            version,
+            data: Vec::new(),
+            steps: vec![[None; 7]],
         }
+    }
 
-        loop {
-            match self.parser.next() {
-                None => {
-                    self.ended = true;
-                    return Some(self.last_segment);
+    pub(crate) fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Pushes the next bytes.
+    pub(crate) fn extend(&mut self, data: &[u8]) {
+        self.data.reserve(data.len());
+        self.steps.reserve(data.len());
+        for &byte in data {
+            self.push(byte);
+        }
+    }
+
+    /// Pushes the next byte.
+    fn push(&mut self, byte: u8) {
+        self.data.push(byte);
+        let len = self.data.len();
+        let mut steps: [Option<Step>; 7] = [None; 7];
+        for &mode in self.version.supported_modes() {
+            let width = if mode == Mode::Kanji { 2 } else { 1 };
+            let encodable = match mode {
+                Mode::Kanji => len >= 2 && kanji_number(&self.data[len - 2..]).is_some(),
+                _ => mode.is_encodable(byte),
+            };
+            if !encodable {
+                continue;
+            }
+            let from = len - width;
+            let previous = &self.steps[from];
+            let mut update = |state: usize, next: Step| {
+                if next.is_better_than(&steps[state]) {
+                    steps[state] = Some(next);
                 }
-                Some(segment) => {
-                    let seg_size = segment.encoded_len(self.version);
+            };
 
-                    let new_segment = Segment {
-                        mode: self.last_segment.mode.max(segment.mode),
-                        begin: self.last_segment.begin,
-                        end: segment.end,
+            // Extends the last segment.
+            for (i, step) in previous.iter().enumerate() {
+                let (last_mode, residue) = STATES[i];
+                let Some(step) = step else {
+                    continue;
+                };
+                if last_mode == mode {
+                    let next = Step {
+                        cost: step.cost + mode.data_bits_count(residue + 1)
+                            - mode.data_bits_count(residue),
+                        segments_count: step.segments_count,
+                        from: Some(i),
+                        starts_segment: false,
                     };
-                    let new_size = new_segment.encoded_len(self.version);
-
-                    if self.last_segment_size + seg_size >= new_size {
-                        self.last_segment = new_segment;
-                        self.last_segment_size = new_size;
-                    } else {
-                        let old_segment = self.last_segment;
-                        self.last_segment = segment;
-                        self.last_segment_size = seg_size;
-                        return Some(old_segment);
-                    }
+                    update(state_index(mode, residue + 1), next);
                 }
             }
+
+            // Starts a new segment.
+            let best_previous = if from == 0 {
+                Some((0, 0, None))
+            } else {
+                previous
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, step)| step.map(|s| (s.cost, s.segments_count, Some(i))))
+                    .min()
+            };
+            if let Some((cost, segments_count, from)) = best_previous {
+                let next = Step {
+                    cost: cost
+                        + self.version.mode_bits_count()
+                        + mode.length_bits_count(self.version)
+                        + mode.data_bits_count(1),
+                    segments_count: segments_count + 1,
+                    from,
+                    starts_segment: true,
+                };
+                update(state_index(mode, 1), next);
+            }
+        }
+        self.steps.push(steps);
+    }
+
+    /// The segmentation of the first `len` bytes pushed so far, e.g. to
+    /// continue it with other bytes.
+    pub(crate) fn prefix(&self, len: usize) -> Self {
+        Self {
+            version: self.version,
+            data: self.data[..len].to_vec(),
+            steps: self.steps[..=len].to_vec(),
         }
     }
+
+    /// Returns the optimized segments of the bytes pushed so far.
+    pub(crate) fn segments(&self) -> Vec<Segment> {
+        let last = &self.steps[self.data.len()];
+        let state = (0..STATES.len())
+            .filter(|i| last[*i].is_some())
+            .min_by_key(|i| last[*i].map(|s| (s.cost, s.segments_count)));
+        let Some(mut state) = state else {
+            // Some character needs a mode the version does not support, so
+            // the segments are kept as parsed and fail to be encoded. Empty
+            // data has no segment either way.
+            return Parser::new(&self.data).collect();
+        };
+
+        let mut segments = Vec::new();
+        let mut end = self.data.len();
+        let mut i = self.data.len();
+        while i > 0 {
+            let step = self.steps[i][state].expect("every reached state has a step");
+            let mode = STATES[state].0;
+            i -= if mode == Mode::Kanji { 2 } else { 1 };
+            if step.starts_segment {
+                segments.push(Segment {
+                    mode,
+                    begin: i,
+                    end,
+                });
+                end = i;
+            }
+            state = step.from.unwrap_or(0);
+        }
+        segments.reverse();
+        segments
+    }
 }
 
-/// Computes the total encoded length of all segments.
-pub fn total_encoded_len(segments: &[Segment], version: Version) -> usize {
-    segments.iter().map(|seg| seg.encoded_len(version)).sum()
+/// Finds the segments needing the fewest bits to encode the data in the given
+/// version, as the constructors of `QrCode` do.
+///
+/// The example of the optimization in ISO/IEC 18004 is encoded in a numeric
+/// segment followed by an alphanumeric one, rather than switching modes for
+/// each of the short runs of digits.
+///
+///     use qrqrpar::{optimal_segments, total_encoded_len, Parser, Segment};
+///     use qrqrpar::types::{Mode, Version};
+///
+///     let data = b"01049123451234591597033130128%10ABC123";
+///     let version = Version::Normal(9);
+///     let segments = optimal_segments(data, version);
+///     assert_eq!(
+///         segments,
+///         vec![
+///             Segment { mode: Mode::Numeric, begin: 0, end: 29 },
+///             Segment { mode: Mode::Alphanumeric, begin: 29, end: 38 },
+///         ]
+///     );
+///
+///     let parsed = Parser::new(data).collect::<Vec<_>>();
+///     assert_eq!(parsed.len(), 5);
+///     assert!(total_encoded_len(&segments, version) < total_encoded_len(&parsed, version));
+///
+/// The mode of every character is chosen by dynamic programming over the
+/// states of the last segment, so a segment may start at any character. This
+/// matters for Kanji characters, which `Parser` pairs from the left: a lone
+/// lead byte before a run of Kanji characters misaligns every pair after it,
+/// while this function can leave the first byte alone in a byte segment.
+///
+///     use qrqrpar::{optimal_segments, Parser, Segment};
+///     use qrqrpar::types::{Mode, Version};
+///
+///     // A stray lead byte, then "点点点点点".
+///     let data = [&b"\x93"[..], &b"\x93\x5f".repeat(5)].concat();
+///     assert_eq!(Parser::new(&data).next().unwrap().end, 2);
+///     assert_eq!(
+///         optimal_segments(&data, Version::Normal(1)),
+///         vec![
+///             Segment { mode: Mode::Byte, begin: 0, end: 1 },
+///             Segment { mode: Mode::Kanji, begin: 1, end: 11 },
+///         ]
+///     );
+///
+/// Among equally long results, fewer segments are preferred. Only the modes
+/// supported by the version are used; if some character cannot be encoded in
+/// any of them, the segments are returned as parsed and fail to be encoded.
+///
+/// Only the length fields of the version matter, so every QR code version
+/// from 1 to 9 gives the same segments, and so do the versions from 10 to 26
+/// and from 27 to 40.
+pub fn optimal_segments(data: &[u8], version: Version) -> Vec<Segment> {
+    let mut segmentation = CharSegmentation::new(version);
+    segmentation.extend(data);
+    segmentation.segments()
 }
 
 #[cfg(test)]
 mod optimize_tests {
-    use crate::coding::{total_encoded_len, Optimizer, Segment};
+    use crate::internals::coding::{total_encoded_len, MergeReason, Optimizer, Parser, Segment};
+    use crate::test_util::{self, Content, Lengths, PayloadSpec};
     use crate::types::{Mode, Version};
 
     fn test_optimization_result(given: Vec<Segment>, expected: Vec<Segment>, version: Version) {
@@ -736,6 +1198,427 @@ mod optimize_tests {
             Version::Micro(3),
         );
     }
+
+    /// Returns the encoded length of the parsed segments merged into one.
+    fn single_mode_len(parsed: &[Segment], version: Version) -> usize {
+        let segment = Segment {
+            mode: parsed.iter().fold(parsed[0].mode, |m, s| m.max(s.mode)),
+            begin: parsed[0].begin,
+            end: parsed[parsed.len() - 1].end,
+        };
+        segment.encoded_len(version)
+    }
+
+    #[test]
+    fn test_digits_in_url_path() {
+        let data = b"https://example.com/p/12345678";
+        let version = Version::Normal(2);
+        let parsed = Parser::new(data).collect::<Vec<_>>();
+        let optimized = Optimizer::new(parsed.iter().copied(), version).collect::<Vec<_>>();
+        assert_eq!(
+            optimized,
+            vec![
+                Segment {
+                    mode: Mode::Byte,
+                    begin: 0,
+                    end: 22,
+                },
+                Segment {
+                    mode: Mode::Numeric,
+                    begin: 22,
+                    end: 30,
+                },
+            ]
+        );
+        assert!(total_encoded_len(&optimized, version) < single_mode_len(&parsed, version));
+    }
+
+    #[test]
+    fn test_never_worse_than_single_mode() {
+        let versions = [
+            Version::Normal(2),
+            Version::Normal(10),
+            Version::Normal(27),
+            Version::Micro(4),
+            Version::Rmqr(7, 43),
+            Version::Rmqr(17, 139),
+        ];
+        let spec = PayloadSpec {
+            contents: vec![
+                Content::Numeric,
+                Content::Alphanumeric,
+                Content::Kanji,
+                Content::Binary,
+            ],
+            max_run: 6,
+            lengths: Lengths::Between(1, 40),
+        };
+        // Greedily merging from left to right needed more bits for these.
+        let mut payloads = vec![b"a2509z159".to_vec(), b"azx:-73178b-Ba7".to_vec()];
+        payloads.extend(test_util::payloads(0x0b7, spec).take(300));
+
+        for data in &payloads {
+            let parsed = Parser::new(data).collect::<Vec<_>>();
+            for version in versions {
+                let explained = Optimizer::new(parsed.iter().copied(), version)
+                    .with_explanations()
+                    .collect::<Vec<_>>();
+                let optimized = explained.iter().map(|(s, _)| *s).collect::<Vec<_>>();
+                let len = total_encoded_len(&optimized, version);
+                assert!(
+                    len <= single_mode_len(&parsed, version),
+                    "{data:?} {version}"
+                );
+                assert!(
+                    len <= total_encoded_len(&parsed, version),
+                    "{data:?} {version}"
+                );
+
+                assert_eq!(optimized[0].begin, 0);
+                assert_eq!(optimized[optimized.len() - 1].end, data.len());
+                assert!(optimized.windows(2).all(|w| w[0].end == w[1].begin));
+
+                let saved = explained
+                    .iter()
+                    .map(|(_, reason)| match reason {
+                        MergeReason::Kept => 0,
+                        MergeReason::Merged { saved_bits, .. } => *saved_bits,
+                    })
+                    .sum::<usize>();
+                assert_eq!(total_encoded_len(&parsed, version) - len, saved);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod boundary_tests {
+    use crate::bits::kanji_number;
+    use crate::internals::coding::{optimal_segments, Parser, Segment};
+    use crate::types::{Mode, Version};
+
+    fn segments(segments: &[(Mode, usize, usize)]) -> Vec<Segment> {
+        segments
+            .iter()
+            .map(|&(mode, begin, end)| Segment { mode, begin, end })
+            .collect()
+    }
+
+    /// The data between `prefix` and `suffix` with `n` digits in between.
+    fn digits_between(prefix: &[u8], n: usize, suffix: &[u8]) -> Vec<u8> {
+        let mut data = prefix.to_vec();
+        data.extend(std::iter::repeat_n(b'1', n));
+        data.extend(suffix);
+        data
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(Parser::new(b"").next(), None);
+        assert_eq!(optimal_segments(b"", Version::Normal(1)), vec![]);
+    }
+
+    #[test]
+    fn test_digits_after_alphanumeric() {
+        use Mode::{Alphanumeric, Numeric};
+        let version = Version::Normal(1);
+        assert_eq!(
+            optimal_segments(&digits_between(b"ABC", 7, b""), version),
+            segments(&[(Alphanumeric, 0, 10)])
+        );
+        assert_eq!(
+            optimal_segments(&digits_between(b"ABC", 8, b""), version),
+            segments(&[(Alphanumeric, 0, 3), (Numeric, 3, 11)])
+        );
+        assert_eq!(
+            optimal_segments(&digits_between(b"", 7, b"ABC"), version),
+            segments(&[(Alphanumeric, 0, 10)])
+        );
+        assert_eq!(
+            optimal_segments(&digits_between(b"", 8, b"ABC"), version),
+            segments(&[(Numeric, 0, 8), (Alphanumeric, 8, 11)])
+        );
+        // Digits between two alphanumeric runs need a third segment to pay
+        // off.
+        assert_eq!(
+            optimal_segments(&digits_between(b"ABC", 13, b"DEF"), version),
+            segments(&[(Alphanumeric, 0, 19)])
+        );
+        assert_eq!(
+            optimal_segments(&digits_between(b"ABC", 14, b"DEF"), version),
+            segments(&[
+                (Alphanumeric, 0, 3),
+                (Numeric, 3, 17),
+                (Alphanumeric, 17, 20)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_digits_after_byte() {
+        use Mode::{Byte, Numeric};
+        let version = Version::Normal(1);
+        assert_eq!(
+            optimal_segments(&digits_between(b"a", 3, b""), version),
+            segments(&[(Byte, 0, 4)])
+        );
+        assert_eq!(
+            optimal_segments(&digits_between(b"a", 4, b""), version),
+            segments(&[(Byte, 0, 1), (Numeric, 1, 5)])
+        );
+        assert_eq!(
+            optimal_segments(&digits_between(b"", 4, b"a"), version),
+            segments(&[(Numeric, 0, 4), (Byte, 4, 5)])
+        );
+        assert_eq!(
+            optimal_segments(&digits_between(b"a", 5, b"b"), version),
+            segments(&[(Byte, 0, 7)])
+        );
+        assert_eq!(
+            optimal_segments(&digits_between(b"a", 6, b"b"), version),
+            segments(&[(Byte, 0, 1), (Numeric, 1, 7), (Byte, 7, 8)])
+        );
+    }
+
+    #[test]
+    fn test_digits_after_kanji() {
+        use Mode::{Byte, Kanji, Numeric};
+        let version = Version::Normal(1);
+        assert_eq!(
+            optimal_segments(&digits_between(b"\x93\x5f", 2, b""), version),
+            segments(&[(Byte, 0, 4)])
+        );
+        assert_eq!(
+            optimal_segments(&digits_between(b"\x93\x5f", 3, b""), version),
+            segments(&[(Kanji, 0, 2), (Numeric, 2, 5)])
+        );
+    }
+
+    #[test]
+    fn test_kanji_pairs() {
+        use Mode::{Byte, Kanji, Numeric};
+        // The pairs are formed from the left, so the second byte of "点" is
+        // paired with the lead byte before it, and the lone byte left at the
+        // end is a byte segment.
+        assert_eq!(
+            Parser::new(b"\x93\x93\x5f").collect::<Vec<_>>(),
+            segments(&[(Kanji, 0, 2), (Byte, 2, 3)])
+        );
+        assert_eq!(
+            Parser::new(b"\x93\x5f\x93\x5f\x93").collect::<Vec<_>>(),
+            segments(&[(Kanji, 0, 4), (Byte, 4, 5)])
+        );
+        // A byte which cannot start a pair splits the Kanji runs.
+        assert_eq!(
+            Parser::new(b"\x93\x5f1\x93\x5f").collect::<Vec<_>>(),
+            segments(&[(Kanji, 0, 2), (Numeric, 2, 3), (Kanji, 3, 5)])
+        );
+    }
+
+    #[test]
+    fn test_kanji_segments_hold_pairs() {
+        for data in [
+            &b"a\x93\x5f\x93\x5f\x93\x5f\x93\x5f\x93"[..],
+            b"\x93\x93\x5f\x5f12345678",
+            b"ABC\x81\x40\x81\x40\x81\x401234567890abc",
+            b"1\x93\x5f\x935f",
+        ] {
+            for version in [
+                Version::Normal(1),
+                Version::Micro(4),
+                Version::Rmqr(17, 139),
+            ] {
+                let segments = optimal_segments(data, version);
+                assert_eq!(segments[0].begin, 0);
+                assert_eq!(segments[segments.len() - 1].end, data.len());
+                assert!(segments.windows(2).all(|w| w[0].end == w[1].begin));
+                for segment in segments.iter().filter(|s| s.mode == Mode::Kanji) {
+                    let kanji = &data[segment.begin..segment.end];
+                    assert!(kanji.chunks(2).all(|c| kanji_number(c).is_some()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod char_optimize_tests {
+    use crate::internals::coding::{
+        optimal_segments, total_encoded_len, CharSegmentation, Optimizer, Parser, Segment,
+    };
+    use crate::test_util::{payloads, Content, Lengths, PayloadSpec};
+    use crate::types::{Mode, Version};
+
+    /// The segments of the parsed segments merged by `Optimizer`.
+    fn merged(data: &[u8], version: Version) -> Vec<Segment> {
+        Optimizer::new(Parser::new(data), version).collect()
+    }
+
+    const VERSIONS: [Version; 6] = [
+        Version::Normal(1),
+        Version::Normal(10),
+        Version::Normal(27),
+        Version::Micro(3),
+        Version::Rmqr(7, 43),
+        Version::Rmqr(17, 139),
+    ];
+
+    #[test]
+    fn test_never_longer_than_merged() {
+        // Binary runs bring stray Shift JIS lead and trail bytes.
+        let spec = PayloadSpec {
+            contents: vec![
+                Content::Numeric,
+                Content::Alphanumeric,
+                Content::Kanji,
+                Content::Binary,
+            ],
+            max_run: 4,
+            lengths: Lengths::Between(1, 40),
+        };
+        let mut shorter = 0;
+        for data in payloads(0xd1ce, spec).take(500) {
+            for version in VERSIONS {
+                let optimal = total_encoded_len(&optimal_segments(&data, version), version);
+                let merged = total_encoded_len(&merged(&data, version), version);
+                assert!(optimal <= merged, "{data:?} {version}");
+                if optimal < merged {
+                    shorter += 1;
+                }
+            }
+        }
+        // Stray lead bytes are common enough in this data to shorten some.
+        assert_ne!(shorter, 0);
+    }
+
+    #[test]
+    fn test_same_length_without_kanji() {
+        // Without Shift JIS lead bytes, the parsed segments are already cut
+        // where an optimal segmentation may be.
+        let spec = PayloadSpec {
+            contents: vec![Content::Numeric, Content::Alphanumeric, Content::Binary],
+            max_run: 4,
+            lengths: Lengths::Between(1, 40),
+        };
+        let is_lead_byte = |b: u8| matches!(b, 0x81..=0x9f | 0xe0..=0xea);
+        for mut data in payloads(0xd1ce, spec).take(500) {
+            for b in data.iter_mut().filter(|b| is_lead_byte(**b)) {
+                *b &= 0x7f;
+            }
+            for version in VERSIONS {
+                assert_eq!(
+                    total_encoded_len(&optimal_segments(&data, version), version),
+                    total_encoded_len(&merged(&data, version), version),
+                    "{data:?} {version}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_reinterpreted_trail_byte() {
+        // 0x5a is the trail byte of a Kanji character, but also 'Z'.
+        let data = b"-\x81\x40\x93\x5a1/91A\x93\x5f";
+        let version = Version::Micro(3);
+        assert_eq!(
+            merged(data, version),
+            vec![
+                Segment {
+                    mode: Mode::Alphanumeric,
+                    begin: 0,
+                    end: 1
+                },
+                Segment {
+                    mode: Mode::Kanji,
+                    begin: 1,
+                    end: 5
+                },
+                Segment {
+                    mode: Mode::Alphanumeric,
+                    begin: 5,
+                    end: 10
+                },
+                Segment {
+                    mode: Mode::Kanji,
+                    begin: 10,
+                    end: 12
+                },
+            ]
+        );
+        // As long, with fewer segments.
+        let optimal = optimal_segments(data, version);
+        assert_eq!(
+            optimal,
+            vec![
+                Segment {
+                    mode: Mode::Byte,
+                    begin: 0,
+                    end: 4
+                },
+                Segment {
+                    mode: Mode::Alphanumeric,
+                    begin: 4,
+                    end: 10
+                },
+                Segment {
+                    mode: Mode::Kanji,
+                    begin: 10,
+                    end: 12
+                },
+            ]
+        );
+        assert_eq!(
+            total_encoded_len(&optimal, version),
+            total_encoded_len(&merged(data, version), version)
+        );
+    }
+
+    #[test]
+    fn test_unsupported_mode() {
+        // Micro QR code version 1 only has the numeric mode.
+        assert_eq!(
+            optimal_segments(b"12A", Version::Micro(1)),
+            Parser::new(b"12A").collect::<Vec<_>>()
+        );
+        assert_eq!(
+            optimal_segments(b"123", Version::Micro(1)),
+            vec![Segment {
+                mode: Mode::Numeric,
+                begin: 0,
+                end: 3
+            }]
+        );
+    }
+
+    #[test]
+    fn test_continued() {
+        let data = b"https://example.com/\x93\x93\x5f\x93\x5f?id=0123456789";
+        for version in VERSIONS {
+            for cut in 0..=data.len() {
+                let mut segmentation = CharSegmentation::new(version);
+                segmentation.extend(&data[..cut]);
+                let mut continued = segmentation.clone();
+                continued.extend(&data[cut..]);
+                assert_eq!(continued.segments(), optimal_segments(data, version));
+            }
+        }
+    }
+
+    #[test]
+    fn test_prefix() {
+        let data = b"https://example.com/\x93\x93\x5f\x93\x5f?id=0123456789";
+        let marker = "…".as_bytes();
+        for version in VERSIONS {
+            let mut whole = CharSegmentation::new(version);
+            whole.extend(data);
+            for kept in 0..=data.len() {
+                let mut truncated = whole.prefix(kept);
+                truncated.extend(marker);
+                let expected = [&data[..kept], marker].concat();
+                assert_eq!(truncated.segments(), optimal_segments(&expected, version));
+            }
+        }
+    }
 }
 
 #[cfg(feature = "bench")]
@@ -817,9 +1700,9 @@ impl ExclCharSet {
     /// Determines which character set a byte is in.
     fn from_u8(c: u8) -> Self {
         match c {
-            0x20 | 0x24 | 0x25 | 0x2a | 0x2b | 0x2d..=0x2f | 0x3a => ExclCharSet::Symbol,
-            0x30..=0x39 => ExclCharSet::Numeric,
+            _ if Mode::Numeric.is_encodable(c) => ExclCharSet::Numeric,
             0x41..=0x5a => ExclCharSet::Alpha,
+            _ if Mode::Alphanumeric.is_encodable(c) => ExclCharSet::Symbol,
             0x81..=0x9f => ExclCharSet::KanjiHi1,
             0xe0..=0xea => ExclCharSet::KanjiHi2,
             0xeb => ExclCharSet::KanjiHi3,
@@ -831,7 +1714,7 @@ impl ExclCharSet {
 }
 
 /// The current parsing state.
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone)]
 enum State {
     /// Just initialized.
     Init = 0,
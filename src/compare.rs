@@ -0,0 +1,286 @@
+//! The `compare` module encodes the same data with two profiles side by side,
+//! e.g. to evaluate a change of defaults or to attach to a bug report.
+
+use crate::bits::optimal_encoded_len;
+use crate::types::{Color, EcLevel, Version};
+use crate::{QrProfile, QrProfileError};
+
+/// The symbol a profile produced for the data, see `compare`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeSummary {
+    /// The chosen version.
+    pub version: Version,
+    /// The error correction level of the symbol.
+    pub ec_level: EcLevel,
+    /// The number of bits of the optimally segmented data, before the
+    /// terminator and the padding.
+    pub total_bits: usize,
+    /// The number of dark modules of the symbol.
+    pub dark_modules: usize,
+    /// The width of the rendered image in pixels, see `QrCode::image_sizes`.
+    pub image_width: u32,
+    /// The height of the rendered image in pixels, see `QrCode::image_sizes`.
+    pub image_height: u32,
+}
+
+impl EncodeSummary {
+    /// The number of modules of the symbol, excluding the quiet zone.
+    pub fn modules(&self) -> usize {
+        self.version.width() as usize * self.version.height() as usize
+    }
+
+    /// The area of the rendered image in pixels.
+    pub fn rendered_area(&self) -> u64 {
+        u64::from(self.image_width) * u64::from(self.image_height)
+    }
+}
+
+/// The differences between the two symbols of a `Comparison`, counted from
+/// the first to the second, so that a negative value means the second symbol
+/// is smaller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComparisonDelta {
+    /// Whether the versions differ.
+    pub version_changed: bool,
+    /// The difference of the error correction levels, in levels, e.g. 2 from
+    /// L to Q.
+    pub ec_level: i8,
+    /// The difference of the number of modules of the symbols.
+    pub modules: i64,
+    /// The difference of the number of data bits.
+    pub total_bits: i64,
+    /// The difference of the number of dark modules.
+    pub dark_modules: i64,
+    /// The difference of the rendered areas in pixels.
+    pub rendered_area: i64,
+}
+
+/// The outcome of `compare`.
+///
+/// `Display` formats it as a small table, one row per metric, which can be
+/// pasted into an issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comparison {
+    /// The symbol of the first profile.
+    pub a: EncodeSummary,
+    /// The symbol of the second profile.
+    pub b: EncodeSummary,
+    /// The differences from `a` to `b`.
+    pub delta: ComparisonDelta,
+}
+
+impl std::fmt::Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (a, b, delta) = (&self.a, &self.b, &self.delta);
+        let signed = |n: i64| format!("{n:+}");
+        let rows = [
+            (
+                "version",
+                a.version.to_string(),
+                b.version.to_string(),
+                String::from(if delta.version_changed { "changed" } else { "" }),
+            ),
+            (
+                "ec level",
+                format!("{:?}", a.ec_level),
+                format!("{:?}", b.ec_level),
+                signed(delta.ec_level.into()),
+            ),
+            (
+                "modules",
+                a.modules().to_string(),
+                b.modules().to_string(),
+                signed(delta.modules),
+            ),
+            (
+                "total bits",
+                a.total_bits.to_string(),
+                b.total_bits.to_string(),
+                signed(delta.total_bits),
+            ),
+            (
+                "dark modules",
+                a.dark_modules.to_string(),
+                b.dark_modules.to_string(),
+                signed(delta.dark_modules),
+            ),
+            (
+                "image",
+                format!("{}x{}", a.image_width, a.image_height),
+                format!("{}x{}", b.image_width, b.image_height),
+                signed(delta.rendered_area),
+            ),
+        ];
+        let width = |column: fn(&(&str, String, String, String)) -> usize| {
+            rows.iter().map(column).max().unwrap_or(0)
+        };
+        let widths = [
+            width(|row| row.0.len()),
+            width(|row| row.1.len()).max(1),
+            width(|row| row.2.len()).max(1),
+        ];
+        writeln!(
+            f,
+            "{:w0$}  {:>w1$}  {:>w2$}  delta",
+            "",
+            "a",
+            "b",
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2]
+        )?;
+        for (name, a, b, delta) in &rows {
+            let line = format!(
+                "{name:w0$}  {a:>w1$}  {b:>w2$}  {delta}",
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2]
+            );
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Encodes the data with both profiles, and renders each symbol with the
+/// style of its profile, to compare the symbols side by side.
+///
+///     use qrqrpar::{compare, EcLevel, Family, QrProfile, QrStyle, Version};
+///
+///     let a = QrProfile::new(EcLevel::L, Family::Normal, QrStyle::default()).unwrap();
+///     let b = QrProfile::new(EcLevel::H, Family::Normal, QrStyle::default()).unwrap();
+///     let comparison = compare(b"https://example.com/", &a, &b).unwrap();
+///     assert_eq!(comparison.a.version, Version::Normal(2));
+///     assert_eq!(comparison.b.version, Version::Normal(3));
+///     assert_eq!(comparison.delta.ec_level, 3);
+///     assert_eq!(comparison.delta.modules, 29 * 29 - 25 * 25);
+///     println!("{comparison}");
+///
+/// # Errors
+///
+/// Returns `QrProfileError::Encode` if either profile cannot encode the data,
+/// e.g. when it is too long.
+pub fn compare<D: AsRef<[u8]>>(
+    data: D,
+    a: &QrProfile,
+    b: &QrProfile,
+) -> Result<Comparison, QrProfileError> {
+    let data = data.as_ref();
+    let summarize = |profile: &QrProfile| -> Result<EncodeSummary, QrProfileError> {
+        let code = profile.encode(data)?;
+        let version = code.version();
+        let (_, _, image_width, image_height) = code.image_sizes(profile.style());
+        Ok(EncodeSummary {
+            version,
+            ec_level: code.error_correction_level(),
+            total_bits: optimal_encoded_len(data, version).unwrap_or(0),
+            dark_modules: code
+                .to_colors()
+                .iter()
+                .filter(|color| **color == Color::Dark)
+                .count(),
+            image_width,
+            image_height,
+        })
+    };
+    let (a, b) = (summarize(a)?, summarize(b)?);
+    let diff = |a: u64, b: u64| b as i64 - a as i64;
+    let delta = ComparisonDelta {
+        version_changed: a.version != b.version,
+        ec_level: b.ec_level as i8 - a.ec_level as i8,
+        modules: diff(a.modules() as u64, b.modules() as u64),
+        total_bits: diff(a.total_bits as u64, b.total_bits as u64),
+        dark_modules: diff(a.dark_modules as u64, b.dark_modules as u64),
+        rendered_area: diff(a.rendered_area(), b.rendered_area()),
+    };
+    Ok(Comparison { a, b, delta })
+}
+
+#[cfg(test)]
+mod compare_tests {
+    use crate::compare::compare;
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::{Family, QrCode, QrProfile, QrProfileError, QrStyle, RmqrStrategy};
+
+    #[test]
+    fn test_families() {
+        let data = b"SKU-0042-ABCDEFGH";
+        let style = || QrStyle {
+            width: 400,
+            ..Default::default()
+        };
+        let a = QrProfile::new(EcLevel::M, Family::Normal, style()).unwrap();
+        let b = QrProfile::new(EcLevel::M, Family::Rmqr(RmqrStrategy::Area), style()).unwrap();
+        let comparison = compare(data, &a, &b).unwrap();
+
+        let qr = QrCode::with_error_correction_level(data, EcLevel::M).unwrap();
+        let rmqr = QrCode::rmqr_with_options(data, EcLevel::M, RmqrStrategy::Area).unwrap();
+        assert_eq!(comparison.a.version, qr.version());
+        assert_eq!(comparison.b.version, rmqr.version());
+        assert!(comparison.b.version.is_rmqr());
+
+        let dark = |code: &QrCode| {
+            (code.dark_ratio() * (code.width() * code.height()) as f64).round() as i64
+        };
+        let (_, _, width, height) = rmqr.image_sizes(&style());
+        let delta = comparison.delta;
+        assert!(delta.version_changed);
+        assert_eq!(delta.ec_level, 0);
+        assert_eq!(
+            delta.modules,
+            (rmqr.width() * rmqr.height()) as i64 - (qr.width() * qr.height()) as i64
+        );
+        assert_eq!(delta.dark_modules, dark(&rmqr) - dark(&qr));
+        assert_eq!(comparison.a.dark_modules as i64, dark(&qr));
+        assert_eq!(delta.rendered_area, i64::from(width * height) - 400 * 400);
+        assert!(delta.rendered_area < 0);
+        let bits = |version| {
+            let mut bits = crate::bits::Bits::new(version);
+            bits.push_optimal_data(data).unwrap();
+            bits.len()
+        };
+        assert_eq!(comparison.a.total_bits, bits(qr.version()));
+        assert_eq!(comparison.b.total_bits, bits(rmqr.version()));
+        assert_eq!(
+            delta.total_bits,
+            bits(rmqr.version()) as i64 - bits(qr.version()) as i64
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let a = QrProfile::new(EcLevel::L, Family::Normal, QrStyle::default()).unwrap();
+        let b = QrProfile::new(EcLevel::H, Family::Normal, QrStyle::default()).unwrap();
+        let comparison = compare(b"https://example.com/", &a, &b).unwrap();
+        assert_eq!(comparison.a.version, Version::Normal(2));
+        assert_eq!(comparison.b.version, Version::Normal(3));
+        assert_eq!(comparison.a.total_bits, comparison.b.total_bits);
+        assert_eq!(
+            comparison.to_string(),
+            concat!(
+                "                    a        b  delta\n",
+                "version             2        3  changed\n",
+                "ec level            L        H  +3\n",
+                "modules           625      841  +216\n",
+                "total bits        172      172  +0\n",
+                "dark modules      334      415  +81\n",
+                "image         720x720  720x720  +0\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let a = QrProfile::new(EcLevel::L, Family::Normal, QrStyle::default()).unwrap();
+        let b = QrProfile::new(
+            EcLevel::H,
+            Family::Rmqr(RmqrStrategy::Area),
+            QrStyle::default(),
+        )
+        .unwrap();
+        assert!(matches!(
+            compare([b'a'; 500], &a, &b),
+            Err(QrProfileError::Encode(QrError::DataTooLong))
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+//! Conversions from and to the types of the `qrcode` crate, to ease the
+//! migration from it.
+//!
+//! This module is only available with the `qrcode-compat` feature.
+
+use crate::internals::canvas::{self, Canvas};
+use crate::types::{Color, EcLevel, QrError, QrResult, Version};
+use crate::{bits, QrCode};
+
+impl From<qrcode::Color> for Color {
+    fn from(color: qrcode::Color) -> Self {
+        match color {
+            qrcode::Color::Light => Color::Light,
+            qrcode::Color::Dark => Color::Dark,
+        }
+    }
+}
+
+impl From<Color> for qrcode::Color {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Light => qrcode::Color::Light,
+            Color::Dark => qrcode::Color::Dark,
+        }
+    }
+}
+
+impl From<qrcode::EcLevel> for EcLevel {
+    fn from(ec_level: qrcode::EcLevel) -> Self {
+        match ec_level {
+            qrcode::EcLevel::L => EcLevel::L,
+            qrcode::EcLevel::M => EcLevel::M,
+            qrcode::EcLevel::Q => EcLevel::Q,
+            qrcode::EcLevel::H => EcLevel::H,
+        }
+    }
+}
+
+impl From<EcLevel> for qrcode::EcLevel {
+    fn from(ec_level: EcLevel) -> Self {
+        match ec_level {
+            EcLevel::L => qrcode::EcLevel::L,
+            EcLevel::M => qrcode::EcLevel::M,
+            EcLevel::Q => qrcode::EcLevel::Q,
+            EcLevel::H => qrcode::EcLevel::H,
+        }
+    }
+}
+
+/// Only the QR code versions 1 to 40 are converted. Micro QR code versions are
+/// rejected with `QrError::InvalidVersion` like out of range versions.
+impl TryFrom<qrcode::Version> for Version {
+    type Error = QrError;
+
+    fn try_from(version: qrcode::Version) -> QrResult<Self> {
+        match version {
+            qrcode::Version::Normal(v @ 1..=40) => Ok(Version::Normal(v as u8)),
+            _ => Err(QrError::InvalidVersion),
+        }
+    }
+}
+
+/// Only the QR code versions 1 to 40 are converted. Micro QR and rMQR code
+/// versions are rejected with `QrError::InvalidVersion` like out of range
+/// versions.
+impl TryFrom<Version> for qrcode::Version {
+    type Error = QrError;
+
+    fn try_from(version: Version) -> QrResult<Self> {
+        match version {
+            Version::Normal(v @ 1..=40) => Ok(qrcode::Version::Normal(i16::from(v))),
+            _ => Err(QrError::InvalidVersion),
+        }
+    }
+}
+
+impl QrCode {
+    /// Constructs the same QR code as one of the `qrcode` crate, e.g. to render
+    /// it with `to_svg`.
+    ///
+    /// The modules are copied, and the codewords are read back from them so
+    /// that e.g. `final_codewords` and `remask_preferring` work as for the
+    /// codes encoded by this crate.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidVersion)` if the code is a Micro QR code,
+    /// or if its modules are not a valid symbol of its version.
+    pub fn from_qrcode(code: &qrcode::QrCode) -> QrResult<Self> {
+        let version = Version::try_from(code.version())?;
+        let ec_level = EcLevel::from(code.error_correction_level());
+        let colors = code
+            .to_colors()
+            .into_iter()
+            .map(Color::from)
+            .collect::<Vec<_>>();
+        let width = version.width() as usize;
+
+        let mut functional = Canvas::new(version, ec_level);
+        functional.draw_all_functional_patterns();
+        let order = functional.data_module_order();
+        let data_len = bits::Bits::new(version).max_len(ec_level)?.div_ceil(8);
+        let mut codewords = vec![0; order.len() / 8];
+
+        for pattern in canvas::mask_patterns(version) {
+            // The modules of a symbol whose codewords are all zero are dark
+            // exactly where the mask inverts them.
+            let mut mask = Canvas::new(version, ec_level);
+            QrCode::draw_canvas(&mut mask, version, ec_level, &[], &[]);
+            mask.apply_mask(*pattern);
+            let mask = mask.to_colors();
+
+            codewords.fill(0);
+            for (i, &(x, y)) in order.iter().take(codewords.len() * 8).enumerate() {
+                let index = y as usize * width + x as usize;
+                if colors[index] != mask[index] {
+                    codewords[i / 8] |= 0x80 >> (i % 8);
+                }
+            }
+            let (encoded_data, ec_data) = codewords.split_at(data_len);
+
+            let mut masked = Canvas::new(version, ec_level);
+            QrCode::draw_canvas(&mut masked, version, ec_level, encoded_data, ec_data);
+            masked.apply_mask(*pattern);
+            if masked.to_colors() == colors {
+                return Ok(QrCode::from_masked_canvas(
+                    &masked,
+                    version,
+                    ec_level,
+                    encoded_data.to_vec(),
+                    ec_data.to_vec(),
+                ));
+            }
+        }
+        Err(QrError::InvalidVersion)
+    }
+}
+
+#[cfg(test)]
+mod compat_tests {
+    use crate::types::{Color, EcLevel, QrError, Version};
+    use crate::QrCode;
+
+    #[test]
+    fn test_version() {
+        assert_eq!(
+            Version::try_from(qrcode::Version::Normal(5)),
+            Ok(Version::Normal(5))
+        );
+        assert_eq!(
+            qrcode::Version::try_from(Version::Normal(40)),
+            Ok(qrcode::Version::Normal(40))
+        );
+        for version in [
+            qrcode::Version::Normal(0),
+            qrcode::Version::Normal(41),
+            qrcode::Version::Normal(-1),
+            qrcode::Version::Micro(2),
+        ] {
+            assert_eq!(Version::try_from(version), Err(QrError::InvalidVersion));
+        }
+        for version in [
+            Version::Normal(0),
+            Version::Normal(41),
+            Version::Micro(2),
+            Version::Rmqr(7, 43),
+        ] {
+            assert!(qrcode::Version::try_from(version).is_err());
+        }
+    }
+
+    #[test]
+    fn test_ec_level_and_color() {
+        for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+            assert_eq!(EcLevel::from(qrcode::EcLevel::from(ec_level)), ec_level);
+        }
+        for color in [Color::Light, Color::Dark] {
+            assert_eq!(Color::from(qrcode::Color::from(color)), color);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_version_5() {
+        let data = b"https://github.com/Nakanishi123/qrqrpar";
+        for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+            let theirs = qrcode::QrCode::with_version(
+                data,
+                qrcode::Version::Normal(5),
+                qrcode::EcLevel::from(ec_level),
+            )
+            .unwrap();
+            let converted = QrCode::from_qrcode(&theirs).unwrap();
+            assert_eq!(converted.version(), Version::Normal(5));
+            assert_eq!(converted.error_correction_level(), ec_level);
+            let their_colors = theirs.to_colors().into_iter().map(Color::from);
+            assert_eq!(converted.to_colors(), their_colors.collect::<Vec<_>>());
+
+            let ours = QrCode::with_version(data, Version::Normal(5), ec_level).unwrap();
+            assert_eq!(converted.to_colors(), ours.to_colors());
+            assert_eq!(converted.final_codewords(), ours.final_codewords());
+        }
+    }
+
+    #[test]
+    fn test_micro_is_rejected() {
+        let theirs = qrcode::QrCode::with_version(
+            b"01234567",
+            qrcode::Version::Micro(2),
+            qrcode::EcLevel::L,
+        )
+        .unwrap();
+        assert_eq!(
+            QrCode::from_qrcode(&theirs).err(),
+            Some(QrError::InvalidVersion)
+        );
+    }
+}
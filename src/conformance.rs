@@ -0,0 +1,301 @@
+//! The `conformance` module bundles fixtures of encoded rMQR symbols, and
+//! re-encodes them to catch any change in the symbols the crate produces.
+//!
+//! Each fixture records the final codewords and the modules of a payload
+//! encoded with `QrCode::with_version` at a given version and error
+//! correction level. The fixtures cover 8 rMQR versions at both levels M and
+//! H, with payloads in every mode.
+//!
+//! The rMQR fixtures are snapshots of the output of this crate, not of the
+//! ISO/IEC 23941 examples nor of an external verifier, so they detect
+//! regressions rather than prove conformance. The `reference_fixtures` come
+//! from outside the crate: the QR code and Micro QR code examples of
+//! ISO/IEC 18004 Annex I, and symbols encoded by the `qrcode` crate. No rMQR
+//! reference is available yet.
+//!
+//!     use qrqrpar::conformance;
+//!
+//!     assert_eq!(conformance::check_all(), vec![]);
+//!
+//! This module requires the `test-util` feature.
+
+use core::fmt::{Display, Formatter};
+
+use crate::types::{Color, EcLevel, QrError, Version};
+use crate::QrCode;
+
+mod reference;
+mod rmqr;
+
+/// A known encoding of a payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fixture {
+    /// A unique name, e.g. to report failures.
+    pub name: &'static str,
+    /// The payload, encoded with the optimal segmentation.
+    pub data: &'static [u8],
+    /// The version of the symbol.
+    pub version: Version,
+    /// The error correction level of the symbol.
+    pub ec_level: EcLevel,
+    /// The final codewords, i.e. the interleaved data and error correction
+    /// codewords, as lowercase hexadecimal.
+    pub codewords: &'static str,
+    /// The modules, one line per row, with `#` for dark modules and `.` for
+    /// light ones.
+    pub modules: &'static str,
+}
+
+/// How a re-encoded fixture differs from the recorded one, see `Failure`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The fixture is malformed, e.g. its codewords are not hexadecimal.
+    InvalidFixture,
+    /// The payload cannot be encoded any more.
+    Encode(QrError),
+    /// The number of final codewords differs.
+    CodewordCount { expected: usize, actual: usize },
+    /// The first final codeword which differs.
+    Codeword {
+        index: usize,
+        expected: u8,
+        actual: u8,
+    },
+    /// The first module which differs, in reading order.
+    Module { x: usize, y: usize, expected: Color },
+}
+
+/// A fixture which does not re-encode to the recorded symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Failure {
+    /// The name of the fixture.
+    pub name: &'static str,
+    /// The first difference found.
+    pub kind: FailureKind,
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: ", self.name)?;
+        match self.kind {
+            FailureKind::InvalidFixture => write!(f, "invalid fixture"),
+            FailureKind::Encode(e) => write!(f, "cannot encode: {e}"),
+            FailureKind::CodewordCount { expected, actual } => {
+                write!(f, "expected {expected} codewords, got {actual}")
+            }
+            FailureKind::Codeword {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "expected codeword {index} to be {expected:02x}, got {actual:02x}"
+            ),
+            FailureKind::Module { x, y, expected } => {
+                write!(f, "expected module ({x}, {y}) to be {expected:?}")
+            }
+        }
+    }
+}
+
+/// Gets the rMQR fixtures recorded from this crate.
+pub fn fixtures() -> &'static [Fixture] {
+    rmqr::RMQR_FIXTURES
+}
+
+/// Gets the fixtures taken from outside the crate, see the module
+/// documentation.
+pub fn reference_fixtures() -> &'static [Fixture] {
+    reference::REFERENCE_FIXTURES
+}
+
+/// Parses the lowercase hexadecimal codewords of a fixture.
+fn parse_codewords(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Parses the modules of a fixture, row by row.
+fn parse_modules(modules: &str) -> Option<Vec<Color>> {
+    modules
+        .lines()
+        .flat_map(str::chars)
+        .map(|c| match c {
+            '#' => Some(Color::Dark),
+            '.' => Some(Color::Light),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Re-encodes a fixture and compares it with the recorded symbol.
+///
+/// # Errors
+///
+/// Returns a `Failure` with the first difference found.
+pub fn check(fixture: &Fixture) -> Result<(), Failure> {
+    let fail = |kind| Failure {
+        name: fixture.name,
+        kind,
+    };
+    let code = QrCode::with_version(fixture.data, fixture.version, fixture.ec_level)
+        .map_err(|e| fail(FailureKind::Encode(e)))?;
+
+    let expected = parse_codewords(fixture.codewords).ok_or(fail(FailureKind::InvalidFixture))?;
+    let actual = code.final_codewords();
+    if expected.len() != actual.len() {
+        return Err(fail(FailureKind::CodewordCount {
+            expected: expected.len(),
+            actual: actual.len(),
+        }));
+    }
+    if let Some(index) = (0..expected.len()).find(|&i| expected[i] != actual[i]) {
+        return Err(fail(FailureKind::Codeword {
+            index,
+            expected: expected[index],
+            actual: actual[index],
+        }));
+    }
+
+    let expected = parse_modules(fixture.modules).ok_or(fail(FailureKind::InvalidFixture))?;
+    let actual = code.to_colors();
+    if expected.len() != actual.len() {
+        return Err(fail(FailureKind::InvalidFixture));
+    }
+    if let Some(index) = (0..expected.len()).find(|&i| expected[i] != actual[i]) {
+        return Err(fail(FailureKind::Module {
+            x: index % code.width(),
+            y: index / code.width(),
+            expected: expected[index],
+        }));
+    }
+    Ok(())
+}
+
+/// Re-encodes every fixture, including the reference fixtures, and returns
+/// the ones which differ from the recorded symbols.
+pub fn check_all() -> Vec<Failure> {
+    fixtures()
+        .iter()
+        .chain(reference_fixtures())
+        .filter_map(|fixture| check(fixture).err())
+        .collect()
+}
+
+#[cfg(test)]
+mod conformance_tests {
+    use crate::conformance::{
+        check, check_all, fixtures, reference_fixtures, Failure, FailureKind, Fixture,
+    };
+    use crate::types::{Color, EcLevel, Version};
+
+    #[test]
+    fn test_check_all() {
+        assert_eq!(check_all(), vec![]);
+    }
+
+    #[test]
+    fn test_reference_fixtures() {
+        for fixture in reference_fixtures() {
+            assert_eq!(check(fixture), Ok(()));
+        }
+        let annex_i = reference_fixtures()
+            .iter()
+            .filter(|fixture| fixture.name.contains("Annex I"))
+            .map(|fixture| (fixture.data, fixture.version, fixture.ec_level))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            annex_i,
+            [
+                (&b"01234567"[..], Version::Normal(1), EcLevel::M),
+                (&b"01234567"[..], Version::Micro(2), EcLevel::L),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_coverage() {
+        let mut versions = fixtures()
+            .iter()
+            .map(|fixture| fixture.version)
+            .collect::<Vec<_>>();
+        versions.dedup();
+        assert!(versions.len() >= 8);
+        for version in versions {
+            for ec_level in [EcLevel::M, EcLevel::H] {
+                assert!(fixtures()
+                    .iter()
+                    .any(|fixture| fixture.version == version && fixture.ec_level == ec_level));
+            }
+        }
+        for fixture in fixtures() {
+            assert!(fixture.version.is_rmqr(), "{}", fixture.name);
+        }
+    }
+
+    #[test]
+    fn test_failures() {
+        let fixture = fixtures()[0];
+        let name = fixture.name;
+
+        let mut codewords = fixture.codewords.to_owned();
+        codewords.replace_range(2..4, "00");
+        let tampered = Fixture {
+            codewords: Box::leak(codewords.into_boxed_str()),
+            ..fixture
+        };
+        let failure = check(&tampered).unwrap_err();
+        assert!(matches!(
+            failure.kind,
+            FailureKind::Codeword {
+                index: 1,
+                expected: 0,
+                ..
+            }
+        ));
+
+        let tampered = Fixture {
+            codewords: &fixture.codewords[2..],
+            ..fixture
+        };
+        assert!(matches!(
+            check(&tampered).unwrap_err().kind,
+            FailureKind::CodewordCount { .. }
+        ));
+
+        let modules = fixture.modules.replacen('#', ".", 1);
+        let tampered = Fixture {
+            modules: Box::leak(modules.into_boxed_str()),
+            ..fixture
+        };
+        assert_eq!(
+            check(&tampered),
+            Err(Failure {
+                name,
+                kind: FailureKind::Module {
+                    x: 0,
+                    y: 0,
+                    expected: Color::Light
+                }
+            })
+        );
+        assert_eq!(
+            check(&tampered).unwrap_err().to_string(),
+            format!("{name}: expected module (0, 0) to be Light")
+        );
+
+        let tampered = Fixture {
+            codewords: "xyz",
+            ..fixture
+        };
+        assert_eq!(
+            check(&tampered).unwrap_err().kind,
+            FailureKind::InvalidFixture
+        );
+    }
+}
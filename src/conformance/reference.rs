@@ -0,0 +1,306 @@
+//! The reference fixtures of `conformance::reference_fixtures`.
+//!
+//! Unlike the rMQR fixtures, they were not recorded from this crate:
+//!
+//! * The two "Annex I" fixtures are the example symbols of ISO/IEC 18004
+//!   Annex I, "01234567" at 1-M and M2-L, as transcribed in the Annex I
+//!   tests of the `qrcode` crate.
+//! * The "qrcode 0.14.1" fixtures were encoded by that crate with its
+//!   `QrCode::with_version`. This crate was forked from it, so they catch
+//!   changes made since the fork rather than mistakes shared by both.
+//!
+//! No rMQR reference is available yet: the ISO/IEC 23941 examples and an
+//! independent rMQR encoder would be needed to add some.
+
+use crate::conformance::Fixture;
+use crate::types::{EcLevel, Version};
+
+pub(super) const REFERENCE_FIXTURES: &[Fixture] = &[
+    Fixture {
+        name: "1-M numeric (ISO/IEC 18004 Annex I)",
+        data: b"01234567",
+        version: Version::Normal(1),
+        ec_level: EcLevel::M,
+        codewords: "10200c566180ec11ec11ec11ec11ec11a524d4c1ed36c7872c55",
+        modules: "\
+            #######..#.##.#######\n\
+            #.....#..####.#.....#\n\
+            #.###.#.#.....#.###.#\n\
+            #.###.#.##....#.###.#\n\
+            #.###.#.#.###.#.###.#\n\
+            #.....#.#...#.#.....#\n\
+            #######.#.#.#.#######\n\
+            ........#..##........\n\
+            #.#####..#..#.#####..\n\
+            ...#.#.##.#.#..#.##..\n\
+            ..#...##.#.#.#..#####\n\
+            ....#....#.....####..\n\
+            ...######..#.#..#....\n\
+            ........#.#####..##..\n\
+            #######..##.#.##.....\n\
+            #.....#.#.#####...#.#\n\
+            #.###.#.#...#..#.##..\n\
+            #.###.#.##..#..#.....\n\
+            #.###.#.#.##.#..#.#..\n\
+            #.....#........##.##.\n\
+            #######.####.#..#.#..",
+    },
+    Fixture {
+        name: "M2-L numeric (ISO/IEC 18004 Annex I)",
+        data: b"01234567",
+        version: Version::Micro(2),
+        ec_level: EcLevel::L,
+        codewords: "4018acc300860d22ae30",
+        modules: "\
+            #######.#.#.#\n\
+            #.....#.###.#\n\
+            #.###.#..##.#\n\
+            #.###.#..####\n\
+            #.###.#.###..\n\
+            #.....#.#...#\n\
+            #######..####\n\
+            .........##..\n\
+            ##.#....#...#\n\
+            .##.#.#.#.#.#\n\
+            ###..#######.\n\
+            ...#.#....##.\n\
+            ###.#..##.###",
+    },
+    Fixture {
+        name: "1-Q alphanumeric (qrcode 0.14.1)",
+        data: b"HELLO WORLD",
+        version: Version::Normal(1),
+        ec_level: EcLevel::Q,
+        codewords: "205b0b78d172dc4d4340ec11eca8481652d9369c002e0fb47a10",
+        modules: "\
+            #######.##....#######\n\
+            #.....#.#..#..#.....#\n\
+            #.###.#.#..##.#.###.#\n\
+            #.###.#.#.....#.###.#\n\
+            #.###.#.#.#...#.###.#\n\
+            #.....#...#...#.....#\n\
+            #######.#.#.#.#######\n\
+            ........#............\n\
+            .##.#.##....#.#.#####\n\
+            .#......####....#...#\n\
+            ..##.###.##...#.##...\n\
+            .##.##.#..##.#.#.###.\n\
+            #...#.#.#.###.###.#.#\n\
+            ........##.#..#...#.#\n\
+            #######.#.#....#.##..\n\
+            #.....#..#.##.##.#...\n\
+            #.###.#.#.#...#######\n\
+            #.###.#..#.#.#.#...#.\n\
+            #.###.#.#..#.###.#..#\n\
+            #.....#.#.####...#.##\n\
+            #######....#.###....#",
+    },
+    Fixture {
+        name: "2-M byte (qrcode 0.14.1)",
+        data: b"https://example.com/",
+        version: Version::Normal(2),
+        ec_level: EcLevel::M,
+        codewords: "41468747470733a2f2f6578616d706c652e636f6d2f0ec11ec11ec1117f7ba7032c9ce371eaab32a51726728",
+        modules: "\
+            #######.###.#.#...#######\n\
+            #.....#..######.#.#.....#\n\
+            #.###.#.##..##..#.#.###.#\n\
+            #.###.#....####...#.###.#\n\
+            #.###.#..##...#...#.###.#\n\
+            #.....#.##..#.#...#.....#\n\
+            #######.#.#.#.#.#.#######\n\
+            ..........###.###........\n\
+            #.#...##..#...#.#..#..#.#\n\
+            .###.#.#.#.###.#.###.#.##\n\
+            .###.###.#.#...#.#..###.#\n\
+            #.###..#.#....#.#..#.#...\n\
+            ####.##.####.####.##....#\n\
+            ..#....#.##..#.##.##...##\n\
+            ###.#.#.##.#...####..##.#\n\
+            ..##.#.#.#...#..##.###...\n\
+            ###.###.#..###..#####..#.\n\
+            ........#...#...#...#...#\n\
+            #######.#.#.###.#.#.#...#\n\
+            #.....#...###.###...#....\n\
+            #.###.#......########...#\n\
+            #.###.#...#..##..#..#.##.\n\
+            #.###.#.####...###.###.##\n\
+            #.....#..##..#.######....\n\
+            #######.#..###..#.#..#..#",
+    },
+    Fixture {
+        name: "1-H Kanji (qrcode 0.14.1)",
+        data: b"\x93_\x8e\x9a\x93_",
+        version: Version::Normal(1),
+        ec_level: EcLevel::H,
+        codewords: "8036cfa869b3e0ec11477545d920b692bd3938859ccf01a1d0a6",
+        modules: "\
+            #######..##.#.#######\n\
+            #.....#..###..#.....#\n\
+            #.###.#.##.#..#.###.#\n\
+            #.###.#.###...#.###.#\n\
+            #.###.#..#.#..#.###.#\n\
+            #.....#..#.##.#.....#\n\
+            #######.#.#.#.#######\n\
+            ..........###........\n\
+            ...##.##.##......##..\n\
+            ###.##.##....####..#.\n\
+            ####..#.##..#.##.#.#.\n\
+            ..#.##...##..#.###...\n\
+            #....####.##...####..\n\
+            ........#.#.#.#..###.\n\
+            #######.#.#.#.#......\n\
+            #.....#..#...##....#.\n\
+            #.###.#.##.####.#.#.#\n\
+            #.###.#.##..##..##...\n\
+            #.###.#..###.####..##\n\
+            #.....#..####.#.#.###\n\
+            #######....#.....#.##",
+    },
+    Fixture {
+        name: "5-L numeric (qrcode 0.14.1)",
+        data: b"12345678901234567890",
+        version: Version::Normal(5),
+        ec_level: EcLevel::L,
+        codewords: "10507b7231503159a9ad00ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec0c6ab54001f4d910a627a378774c24f5187881514b8e635ae71c",
+        modules: "\
+            #######.####.###.###.###.###..#######\n\
+            #.....#.#..#...#...#...#...#..#.....#\n\
+            #.###.#..#...#...#...#...#....#.###.#\n\
+            #.###.#....##..##..##..##..##.#.###.#\n\
+            #.###.#.#..##..##..##..##..##.#.###.#\n\
+            #.....#.###.###.###.###.###.#.#.....#\n\
+            #######.#.#.#.#.#.#.#.#.#.#.#.#######\n\
+            ........##...#...#...#...#...........\n\
+            ###..##.##.###.###.###.###.######..##\n\
+            ..##........#...#...#...#...#..###.#.\n\
+            ...#..#..##.###.###.###.###.####..###\n\
+            .....#.###.##.###.###.###.####.##.###\n\
+            .#....##.#...##..##..##..##..##.#.###\n\
+            .##..#.#.....##..##..##..##..#....#..\n\
+            #..##.###..#...#...#...#...#....##.##\n\
+            ##.##..#.#...#...#...#...#....#...##.\n\
+            .##.#.#...####.###.###.###.##..##.#.#\n\
+            #......###..#...#...#...#...#..##.##.\n\
+            ..#..####.#.###.###.###.###.####.#.##\n\
+            ...#...#..###.###.###.###.####.######\n\
+            ..#.#.####...##..##..##..##..##.#.###\n\
+            .#..#..##.#..##..##..##..##..#......#\n\
+            ####..####.#...#...#...#...#....#####\n\
+            ##...#.#.....#...#...#...#....#....##\n\
+            #..##.#.######.###.###.###.##..####.#\n\
+            .....#......#...#...#...#...#..######\n\
+            ##..####.##.###.###.###.###.####.#.#.\n\
+            ..####.....##.###.###.###.####.######\n\
+            ########.##..##..##..##..##.#######.#\n\
+            ........###..##..##..##..####...#....\n\
+            #######....#...#...#...#...##.#.#.###\n\
+            #.....#.#.#..#...#...#...#..#...#..#.\n\
+            #.###.#..#.###.###.###.###.######.#.#\n\
+            #.###.#..#..#...#...#...#..#...#.##..\n\
+            #.###.#.##..###.###.###.#####.####.##\n\
+            #.....#.#####.###.###.###.#..###.###.\n\
+            #######.###..##..##..##..##..#.######",
+    },
+    Fixture {
+        name: "M3-M alphanumeric (qrcode 0.14.1)",
+        data: b"ABC123",
+        version: Version::Micro(3),
+        ec_level: EcLevel::M,
+        codewords: "58e6a1d0ba00ec1100f6cae2c79de24ba5",
+        modules: "\
+            #######.#.#.#.#\n\
+            #.....#.#.#.#.#\n\
+            #.###.#..#.#.##\n\
+            #.###.#..#####.\n\
+            #.###.#.####.##\n\
+            #.....#...#..#.\n\
+            #######.#.###.#\n\
+            ........#..#..#\n\
+            #.....#####.#.#\n\
+            ..#####.....##.\n\
+            ##.#..#.####.##\n\
+            ..###.....#.#..\n\
+            ##..#..#.....#.\n\
+            ...#.#...#.#..#\n\
+            #.###....###.#.",
+    },
+    Fixture {
+        name: "M4-Q byte (qrcode 0.14.1)",
+        data: b"qrcode",
+        version: Version::Micro(4),
+        ec_level: EcLevel::Q,
+        codewords: "467172636f64650000ec7d4cfbacce4ef175f377fbfa3774",
+        modules: "\
+            #######.#.#.#.#.#\n\
+            #.....#.###..#.##\n\
+            #.###.#.#....#.##\n\
+            #.###.#...#..##.#\n\
+            #.###.#.....#...#\n\
+            #.....#...##.#..#\n\
+            #######.##...#.##\n\
+            ........######.##\n\
+            #.##.#..###.###.#\n\
+            ....####.##..#.#.\n\
+            #..#....###..#.##\n\
+            .#.##..####.##.##\n\
+            #.......##....#.#\n\
+            .#.###.#.#......#\n\
+            #..#...########.#\n\
+            .#.###.####......\n\
+            ###......#.####.#",
+    },
+    Fixture {
+        name: "7-H mixed (qrcode 0.14.1)",
+        data: b"Order 0012345678 ABCDEF",
+        version: Version::Normal(7),
+        ec_level: EcLevel::H,
+        codewords: "408211ec116403ec11ecf7e511ec1126e3ec11ec46f611ec115795ec11ec22cf11ec110100ec11ec02ec11ec118011ec11ec13ec11ec11aa11ec11ec37ec11ec11ecea22b5a2e9c95e6c6c37af4dca86e8df7af7120469887d411234da7b1aef0312717f5e646894d90c4c4b9c7a9151fc80f70262ece62a3e19d25f636ef3ae65377bf9bf4016d338dece1f4c532db4cf61a8f3944fc2da6ade138e24a121e7536eae5a01f1d9a84b559970ac91b98a2b15e8ec6580c0b51ec92772d85874b8fa828203",
+        modules: "\
+            #######..#.......##...##.#....#.....#.#######\n\
+            #.....#.#.#.##.#.##.#.###.##.##....#..#.....#\n\
+            #.###.#.##..######....#.#......##..#..#.###.#\n\
+            #.###.#.#######.##..#..#.####.####.##.#.###.#\n\
+            #.###.#.##.####..#.######.#.#..#.####.#.###.#\n\
+            #.....#.##.#.#......#...#####..##.....#.....#\n\
+            #######.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#######\n\
+            .........#.#.#.##.#.#...#......#...##........\n\
+            ..#..#####...###.#..#####.##..#####.##.#####.\n\
+            ...#.#..#.##.####.##.#.##...#..#.......#.###.\n\
+            .#.####..###.##.####.#...#....####..#.####.##\n\
+            ###.##.#...##.#####...###...###..##..#...###.\n\
+            .####.#.###.#..##..####.###.##...#.##.#..####\n\
+            ...###.#.#...#..#.#...##.#...##.######..###..\n\
+            #..######..#.#...##...######.##..###.........\n\
+            #####..####..##..#.#.#.###..#..#...##.##.#..#\n\
+            #.#.#.##.##.#.#..#...##.##.#..###.###.###....\n\
+            ....#...##.......#.####.#.#....#.....#.#.####\n\
+            .....####..#....#..##.###.#...###.###.####.##\n\
+            ###..#..##..#..###....##..##.##.###..##.##...\n\
+            #...######.##..#.##########..#.#.#..#####.###\n\
+            .##.#...##.#.###..#.#...#.#..##.###.#...#....\n\
+            #.#.#.#.##.#..#.#.###.#.###.####.####.#.#.#.#\n\
+            ....#...#.#...#..#.##...##.#####....#...#...#\n\
+            ##.#######.#.####..######....#.##.#######.#.#\n\
+            .#.#....#..###...##.##...##.#.##......##.###.\n\
+            #.#######.####.##.#..##...##.#.##.##.....#.##\n\
+            ..##....#...#.##..###....#.##.....#....#####.\n\
+            ###...#.##...#...##....####.##...###.###.####\n\
+            .###....###....####........##...#....#.#.....\n\
+            ##.####...#.#.....##..#...####...########.#..\n\
+            ........##...##.#.#.#..##.....##....##.#....#\n\
+            ##.##.#.#.##.##.#..#...#..#....##.#.#..##.#..\n\
+            ###.##...#.####.#.#....##...##.#...##.###..#.\n\
+            ....#.#.##..##.....#..##.#..#.###.#....#.#.##\n\
+            .####...##.###.####.....#.....#..##....###.#.\n\
+            #..##.####.#..#..##.#####....#...#.#######.##\n\
+            ........##..#...###.#...##..#...#####...###.#\n\
+            #######.#.##..#..#..#.#.###.##...####.#.##.##\n\
+            #.....#.####...#..#.#...###.#####...#...#....\n\
+            #.###.#..###........#####.#..#.##.#######..#.\n\
+            #.###.#.....####..###.##..##..##...##..#.##..\n\
+            #.###.#.##..#.....#.#..###..#.###.#..#.###.##\n\
+            #.....#...###.....#...####.#.##..##....#.##..\n\
+            #######....#.##.#.#...##...#.##..#...#.####.#",
+    },
+];
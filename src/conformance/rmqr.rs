@@ -0,0 +1,319 @@
+//! The rMQR fixtures of `conformance::fixtures`.
+//!
+//! They were recorded from the output of `QrCode::with_version`. A fixture
+//! must only be updated together with a change to the symbols the crate
+//! produces, once the new symbols have been checked with a reader.
+
+use crate::conformance::Fixture;
+use crate::types::{EcLevel, Version};
+
+pub(super) const RMQR_FIXTURES: &[Fixture] = &[
+    Fixture {
+        name: "R7x43-M numeric",
+        data: b"1234",
+        version: Version::Rmqr(7, 43),
+        ec_level: EcLevel::M,
+        codewords: "283da0ec11ec17e8897821a328",
+        modules: "\
+            #######.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###\n\
+            #.....#..#.##.##..###.##..##....##.##...#.#\n\
+            #.###.#.#.##.#.###.####..#.####.#.#########\n\
+            #.###.#..##...###.........#.#...##....#...#\n\
+            #.###.#...#...#####.###.##.##.###..#..#.#.#\n\
+            #.....#.###.#####..##.###...##.##.###.#...#\n\
+            #######.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R7x43-H numeric",
+        data: b"1234",
+        version: Version::Rmqr(7, 43),
+        ec_level: EcLevel::H,
+        codewords: "283da09e120285e8a8dc3a544c",
+        modules: "\
+            #######.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###\n\
+            #.....#.##.#...#..###.###.##....##..#.....#\n\
+            #.###.#.##.#....##.####...#####.#.##..#####\n\
+            #.###.#.#.###......#.......#....##.##.#...#\n\
+            #.###.#..##.##..#.#.###..##.#####..##.#.#.#\n\
+            #.....#..##..#..#####.##.##....##.#.#.#...#\n\
+            #######.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R7x59-M alphanumeric",
+        data: b"AB-12",
+        version: Version::Rmqr(7, 59),
+        ec_level: EcLevel::M,
+        codewords: "4539bcd820ec11ec11ec11ec715b6edf173732d474",
+        modules: "\
+            #######.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.###\n\
+            #.....#.#.####...##.#.#........##.....#.#.##..#####..#..#.#\n\
+            #.###.#.#..#.###.#####...###.....###.#####...#.######.#####\n\
+            #.###.#.##...#..#.#.#...####.##.#..####.###..#..#..#.##...#\n\
+            #.###.#..##...########..#.##....##...####....#.##.###.#.#.#\n\
+            #.....#.#.#.####.##.#.#..#.####..##.###.##.####.#.##..#...#\n\
+            #######.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R7x59-H alphanumeric",
+        data: b"AB-12",
+        version: Version::Rmqr(7, 59),
+        ec_level: EcLevel::H,
+        codewords: "4539bcd820ec119d96d0a10cedeaf5df70df6ec699",
+        modules: "\
+            #######.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.###\n\
+            #.....#...###..##.#.###.....###.###...#.#.##..######.#....#\n\
+            #.###.#.####..##..###...#.#.#....##.#.####...#.#####.######\n\
+            #.###.#....###..#....#..#.....#..#.#.##.###..#..#...###...#\n\
+            #.###.#...#.###.#####.#...##..##..#..####....#.##.##..#.#.#\n\
+            #.....#...#..#.####.#.##..######.#.####.##.####.#.#...#...#\n\
+            #######.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R9x77-M byte",
+        data: b"rMQR9",
+        version: Version::Rmqr(9, 77),
+        ec_level: EcLevel::M,
+        codewords: "6172423f899000ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec116ea89abcabc85ec5a1edabc9423535444b0c",
+        modules: "\
+            #######.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.###\n\
+            #.....#..####.##...#..###.#..##.#.#.....###.#...###.####..#######.###.#####.#\n\
+            #.###.#.#..#....#..#.##.####...#####.#.#######..#.###..#......######...##.#.#\n\
+            #.###.#.#....#..#.##.###......#.###########..##.###......###.##...##.###..##.\n\
+            #.###.#....###.....#.#.#.##...#.#######.#.##..###.###.###..###.......##.#####\n\
+            #.....#..#..#.###..#.##.#..#.###.#.####....#.##...#..##.##....#..#.######...#\n\
+            #######...###...###.##..###.#..#....#.#..#....##..#####..#####.###...#.##.#.#\n\
+            .........##.#....###....#.#.#..#...#........#..#..#.#####.#.#..####...###...#\n\
+            ###.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R9x77-H byte",
+        data: b"rMQR9",
+        version: Version::Rmqr(9, 77),
+        ec_level: EcLevel::H,
+        codewords: "611172ec42113fec891190ec0011ecec116ac60414b0d2e0b9929ab722ea94908761daabb56baa1752e90ef7ae6ad138e5",
+        modules: "\
+            #######.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.###\n\
+            #.....#.#####...##.##...#.####.#..#.#.##..#.#...###.#.#...##.###..#.#.#.###.#\n\
+            #.###.#.####.###.#.....#####.#...##.##..######.##.###.#....#..##.#..#####.#.#\n\
+            #.###.#..#.#..##...##..#..###..#..#.#.###..######.#...##.####.#.##.#..##...#.\n\
+            #.###.#..#..#..#.######..#.#.#.#...#..#######...#.###...#..###.#...#.#.######\n\
+            #.....#.##...##.##.#####..##.#..#..#####.#.###.##.#..####...#..#.#.....##...#\n\
+            #######.....###...##...####.#.#..#...#.#.####..#.#####.#.#....#.###.#####.#.#\n\
+            ...........#..##.##.##.##.#.#.....#...###....######.##...###.####.#..####...#\n\
+            ###.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R11x27-M short numeric",
+        data: b"1234",
+        version: Version::Rmqr(11, 27),
+        ec_level: EcLevel::M,
+        codewords: "283da0ec11ec1133aed657a59243dd",
+        modules: "\
+            #######.#.#.#.#.#.#.#.#.###\n\
+            #.....#.###..###....#...#.#\n\
+            #.###.#..########..#.#....#\n\
+            #.###.#...#.##..####....#..\n\
+            #.###.#.....#.....#####..##\n\
+            #.....#..##.#..#.##.###.##.\n\
+            #######..##...##.#...######\n\
+            ............####....#.#...#\n\
+            ######.##...#..###.#.##.#.#\n\
+            #.##.#.....##.....#.#.#...#\n\
+            ###.#.#.#.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R11x27-H short numeric",
+        data: b"1234",
+        version: Version::Rmqr(11, 27),
+        ec_level: EcLevel::H,
+        codewords: "283da0ec11d4cd1a4dbfcb6cea6296",
+        modules: "\
+            #######.#.#.#.#.#.#.#.#.###\n\
+            #.....#..##...##.#..#...#.#\n\
+            #.###.#....#.#.#.#.#.#....#\n\
+            #.###.#.#####.#.#.##....#..\n\
+            #.###.#..#...#..#.#####..##\n\
+            #.....#.#####.#..######..#.\n\
+            #######..##.##..#...#.#####\n\
+            ........#..######..#..#...#\n\
+            ##.#.#.##.##..####.####.#.#\n\
+            #.#....#..#..#.#..###.#...#\n\
+            ###.#.#.#.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R11x99-M URL",
+        data: b"https://ex.am/",
+        version: Version::Rmqr(11, 99),
+        ec_level: EcLevel::M,
+        codewords: "67ec34113aec3a1138ec39119dec171197ecb211bcec171130ecb61197ec8011ecec1111ecec1111ecec1111ecec1111ecec1111ecec1111ecc2f9f0cece1f490aaa516ddabe0d2db4a670f86e3449a572fc4b9f15b4373eb0",
+        modules: "\
+            #######.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.###\n\
+            #.....#.###.##.########.###....#...#.####....#..#.######.#.#.......##.##.##.#...#..####.#.###.##..#\n\
+            #.###.#.#.###..#..###.###.##....##.#####..#..#..########....#.#..#....###.####....##.#.#..#.###.###\n\
+            #.###.#.###.#####.#.....##.....###..#......#......#....##.##....###.##.#..#.#..#.#.......#..#......\n\
+            #.###.#.#...#.#...##..#######.#.....#.#....##.#..##..#....##.#.#..#..#..##.#....##....#....##.....#\n\
+            #.....#.#....#####.##.#..#..#.##.....#...#..##.##.#.#####..####....####...#......###.###.##.###.##.\n\
+            #######.....#.#..#....###.##.#.#.#....#####..#.#.###.#..##..#.#.###..###.###.###..#...##.#.########\n\
+            ...........##....#.##.#..#....##.....##.#..#.......####..###....#.###...###.#.#..#...##...##..#...#\n\
+            ###......#####..#..#.#####.#.#.##..#...#..###.#.###.#.##.....#.#....##..#######.#.##.##.#....##.#.#\n\
+            #.#...##.#.....#.##.#.#.#...###....#..#..####..##.##...##.#.###.###..#....#.#..####.....#..#.##...#\n\
+            ###.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R11x99-H URL",
+        data: b"https://ex.am/",
+        version: Version::Rmqr(11, 99),
+        ec_level: EcLevel::H,
+        codewords: "679734803aec3a1138ec39119dec171197ecb211bcec171130ecb611ec5a0a873e162d901dd3af6d600c2b428782e9c4d80e9de4b26f128c2440a87b107aa7ca99c219aa2ca3c7eea4a4011a79d919b92ea9095cf73ebb8f6e",
+        modules: "\
+            #######.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.###\n\
+            #.....#..##.#.##.#....#.######.##.######...#...##.#..####.#.##.##..##.##.##.#...#..####.#.#.#..#..#\n\
+            #.###.#.##.######.#...###..#.#.........#.#.#...#####...........###....###.####....##.#.#....##.####\n\
+            #.###.#...####.##..##.#..##.##..#..#.#..###..##....#.#..##.#..#.#.#.##.#..#.#..#.#.......#..#..#...\n\
+            #.###.#.##.####.##.#...###.##...#.#.###..###.#######......##.#.#.#...#..##.#....##....#...###.##..#\n\
+            #.....#.......##.#####...#.##.##.##.#.#.#....#..#..#.###.##.#.##.##.###...#......###.###.######..#.\n\
+            #######.##..#.#####...###.###.##.####.#####...####..#.....###..#####.###.###.###..#...##.#.#..#####\n\
+            ........#.#.####.#.####..##..##.##..####.####.###...##.###..######..#...###.#.#..#...##...#.#.#...#\n\
+            ###...#..###.....#.##.####..#..#..##....#.#.#.#.###...#..#.##....#.###..#######.#.##.##.#...###.#.#\n\
+            #.#..#..#..###.####...#.##.....#...####..###..###.###....#.#####.#.#.#....#.#..####.....#....##...#\n\
+            ###.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R13x43-M Kanji",
+        data: b"\x93_\xe4\xaa",
+        version: Version::Rmqr(13, 43),
+        ec_level: EcLevel::M,
+        codewords: "826cfeaa80ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11947e65e4edbebb289ac8fabbbcc3",
+        modules: "\
+            #######.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###\n\
+            #.....#.#####....#..#.####.#...#.#..##.#..#\n\
+            #.###.#.###.........####..#.#..##.##.#.#..#\n\
+            #.###.#.##..####......##.#####...#...##.##.\n\
+            #.###.#.#.###...#..#.###.###..####.##..##.#\n\
+            #.....#..##....#..##..##..#..##.#.###..#...\n\
+            #######...##.#...##.##.###.#.#...###.#..###\n\
+            ........#.#..###.####.#.......##..#.##.##..\n\
+            ###.###..##...#..##.##..#.#.##.....#.######\n\
+            .#.####.#..#...##...#...##.#...#.#.#.##...#\n\
+            #..##.##..#..#..##..###...#.#..##.....#.#.#\n\
+            #..#...#.#..#.#..#..#.##.#####...##.#.#...#\n\
+            ###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R13x43-H Kanji",
+        data: b"\x93_\xe4\xaa",
+        version: Version::Rmqr(13, 43),
+        ec_level: EcLevel::H,
+        codewords: "826cfeaa80ec11ec11ec11ec11ec297aa944533f8196d5242f8aa87b288eaa1d2806ce90d7d889b4c7",
+        modules: "\
+            #######.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###\n\
+            #.....#..#######...##.#.####...#.#..##.#..#\n\
+            #.###.#.#...##.##...#######..#.##.##.#.#..#\n\
+            #.###.#....#.##.#.....#####..#...#...##.##.\n\
+            #.###.#.####.###..#..###......####.##..##.#\n\
+            #.....#.##########..#....#...##.#.###..#...\n\
+            #######...#.....#.##.##..###.#...###.#..###\n\
+            ........###........##...#.##..##..####.#...\n\
+            ###.#.##.##.#.###.#####.#.#.##.....##.#####\n\
+            .#.####..#..#.#...#....##.##...#.#..###...#\n\
+            #..##...####..###..####.....#..##...#.#.#.#\n\
+            #.##.#.###..#.#.#..##.##.#.###...####.#...#\n\
+            ###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R15x59-M mixed",
+        data: b"Mix 0123456789",
+        version: Version::Rmqr(15, 59),
+        ec_level: EcLevel::M,
+        codewords: "6226b4bc10114062b35348ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ecf5832f7937bad4e3efe21ac1c0f34fc122eebd728fff3e1054d3",
+        modules: "\
+            #######.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.###\n\
+            #.....#...##.#..###.####..#.###..##.###.#.#.#...#...#.#.#.#\n\
+            #.###.#.###.#.#######..#...##.####..#.####...####..######.#\n\
+            #.###.#.#.......##.....#####.....##...#.####..#.##.#.##..#.\n\
+            #.###.#.##.#.##.#..###.##.#.#.##..######.#.......##...##..#\n\
+            #.....#.#..##.#...#..#.........##.....#.#..#.#####....#.#..\n\
+            #######.#..#..#...##..###.#..#...###.#.#...##...#...#######\n\
+            ........#...........#.......###.#..####......#.#.#.#.###.#.\n\
+            ####.#...##.#.#.####.....#.#....##...#.#..####.##.##..##.##\n\
+            .###......#...#.#....#.####.###..##.##...##.#....#.#.#.##..\n\
+            #.####.#.#..####..##.#.....##.####..#.####...###.#.#.######\n\
+            ....#.#.###.#.#...#.##..####.....##.....####..#..######...#\n\
+            ####.......##.###.###.#.#.#.#.##..#######.......#...###.#.#\n\
+            #.......#####.....#.####.......##.....#.##.#.###.###.##...#\n\
+            ###.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R15x59-H mixed",
+        data: b"Mix 0123456789",
+        version: Version::Rmqr(15, 59),
+        ec_level: EcLevel::H,
+        codewords: "62ec2611b4ecbc1110ec111140ec6211b3ec531148ecec1111ec80e28d340607974d251bc3d7b57655faf3bcb4d3a44b3c458e567d88c438fb1eaff5f324d8abf8777079625d9ea04320",
+        modules: "\
+            #######.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.###\n\
+            #.....#.#.####.##.#.########.#.##.#...#.#.#.##.#####..###.#\n\
+            #.###.#.#.....#..####...###....#####..#####..##..##....#..#\n\
+            #.###.#..#.####..#...###.##...####...##.#..#..##..#.#.#.##.\n\
+            #.###.#.#..#..#########.###.#...###.#.##..##....#.#.###..##\n\
+            #.....#....#..#####.#....#.##.###...###..##..##.###...##...\n\
+            #######...#.#.#.####...#.....#..####.#.###..#....###.##.###\n\
+            .........#..#.#.#...##.##.#..####.#####.##.###.#...#.....#.\n\
+            ###..#.....#.####.##.#.##..#.#..#.#.##.#.##.##.###...#.#.##\n\
+            .#....#.#.##.#..###..#..#.#.#..#...###..#..#....#.#..#.#...\n\
+            #...#####.#........###..##....#.#.##..##....#...#..##.#####\n\
+            ..####....######.##...#...#..#######.#...##..#####...##...#\n\
+            ###.#...#...#.#...#####..#..#.#...#.#####...###..#...##.#.#\n\
+            #.#.#....###.######.#.#...#.#...####..#.##.##...###..##...#\n\
+            ###.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R17x43-M alphanumeric",
+        data: b"ABCDEFGHIJ",
+        version: Version::Rmqr(17, 43),
+        ec_level: EcLevel::M,
+        codewords: "451cd452a1570b3d00ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec11ec118a9d00d9b920b5ab7bb60f9f2c193ea24325a81180",
+        modules: "\
+            #######.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###\n\
+            #.....#..#..#.#...###.#...#..##.#.##....#.#\n\
+            #.###.#...#.#..####.###.##.#.#...#.#...##.#\n\
+            #.###.#.#...###..#....#.......##..######.#.\n\
+            #.###.#.#...#...##.###..#.#.##......#....##\n\
+            #.....#.###...###.......##.#...#.#..###.##.\n\
+            #######...##.####..####...#.#..##.##....###\n\
+            ............##..#.##..##.#####...#..##..#..\n\
+            ##....#..###.#.#.###.###.###..####.#.###.##\n\
+            .##...##.#.#..##..##..##..#..##.#.##.##....\n\
+            #.###..#.#.#.####.#.##.###.#.#...####.###.#\n\
+            ....#.#.##...#.#.#..#.#.......##..###.####.\n\
+            ###..########.##...###..#.#.##......#######\n\
+            .##.#.##.....##.#...#...##.#...#.#.####...#\n\
+            #..#.###.##.#..###.####...#.#..##....##.#.#\n\
+            #..##....#..##..#####.##.#####...####.#...#\n\
+            ###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#####",
+    },
+    Fixture {
+        name: "R17x43-H alphanumeric",
+        data: b"ABCDEFGHIJ",
+        version: Version::Rmqr(17, 43),
+        ec_level: EcLevel::H,
+        codewords: "45111cecd41152eca11157ec0b113dec0011ecec117a37a9bf81700db8062b5b6c215261af897955fab1d4ad2436fce90584b9fa81cd50b91989552db8",
+        modules: "\
+            #######.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###\n\
+            #.....#.##...##.##.##.#.###.#.....###..##.#\n\
+            #.###.#..#..#.###.#.####..##...#..####.####\n\
+            #.###.#..#.#..#.#...#.........##.##..##.#..\n\
+            #.###.#.##.###...##.##.....#.#..##.#####..#\n\
+            #.....#..##..#..##.##.#..#.#...##....#.#.#.\n\
+            #######.####.#.#...#.#.######..#...##..####\n\
+            ........##.#.#...#.....#.#####..#####.#.#..\n\
+            ##..#..#.#....###..#.#.#.##...##..#.##...##\n\
+            .#..#.###..###.##.#.#..###.#.####....#.....\n\
+            #.###...#...####.#.#.#....##.#...#.#...##.#\n\
+            ...###.####.##.#.###...#..#.......#.#.##.#.\n\
+            ####.#..#.#...##.#..##.##..##.##..#...#####\n\
+            .#.##.#.#.###...###...##...#.##..#...##...#\n\
+            #.###.#.....#..#.#..####.#.#.#..#...###.#.#\n\
+            #....#....#.##.#.#.##.#....#..###.#.#.#...#\n\
+            ###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#####",
+    },
+];
@@ -1,5 +1,5 @@
 //! The `ec` module applies the Reed-Solomon error correction codes.
-use crate::types::{EcLevel, QrResult, Version};
+use crate::types::{EcLevel, QrError, QrResult, Version};
 use core::ops::Deref;
 
 //------------------------------------------------------------------------------
@@ -39,7 +39,7 @@ pub fn create_error_correction_code(data: &[u8], ec_code_size: usize) -> Vec<u8>
 
 #[cfg(test)]
 mod ec_tests {
-    use crate::ec::create_error_correction_code;
+    use crate::internals::ec::create_error_correction_code;
 
     #[test]
     fn test_poly_mod_1() {
@@ -105,6 +105,9 @@ fn test_interleave() {
 /// Returns `Err(QrError::InvalidVersion)` if it is not valid to use the
 ///  `ec_level` for the given version (e.g. `Version::Micro(1)` with
 /// `EcLevel::H`).
+///
+/// Returns `Err(QrError::DataTooLong)` or `Err(QrError::DataTooShort)` if
+/// `rawbits` is not exactly the data capacity of the version.
 pub fn construct_codewords(
     rawbits: &[u8],
     version: Version,
@@ -117,7 +120,11 @@ pub fn construct_codewords(
     let block_1_end = block_1_size * block_1_count;
     let total_size = block_1_end + block_2_size * block_2_count;
 
-    debug_assert_eq!(rawbits.len(), total_size);
+    if rawbits.len() > total_size {
+        return Err(QrError::DataTooLong);
+    } else if rawbits.len() < total_size {
+        return Err(QrError::DataTooShort);
+    }
 
     // Divide the data into blocks.
     let mut blocks = Vec::with_capacity(blocks_count);
@@ -141,7 +148,7 @@ pub fn construct_codewords(
 
 #[cfg(test)]
 mod construct_codewords_test {
-    use crate::ec::construct_codewords;
+    use crate::internals::ec::construct_codewords;
     use crate::types::{EcLevel, Version};
 
     #[test]
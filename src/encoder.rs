@@ -0,0 +1,442 @@
+//! The `encoder` module provides an encoder which reuses its buffers across
+//! codes.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::bits::{self, Bits, PaddingPattern, RmqrStrategy};
+use crate::internals::canvas::Canvas;
+use crate::internals::coding::{CharSegmentation, Segment};
+use crate::internals::ec;
+use crate::types::{EcLevel, QrError, QrResult, Version};
+use crate::QrCode;
+
+/// The number of bytes optimized between two checks of the cancellation flag
+/// and the deadline.
+const CHECK_INTERVAL: usize = 1024;
+
+/// An encoder which keeps its canvases between calls, to avoid allocating
+/// them again for every code when encoding many codes in a row.
+///
+///     use qrqrpar::{EcLevel, Encoder, QrCode};
+///
+///     let mut encoder = Encoder::new();
+///     for data in ["first", "second", "third"] {
+///         let code = encoder.encode(data, EcLevel::M).unwrap();
+///         assert_eq!(code.to_colors(), QrCode::new(data).unwrap().to_colors());
+///     }
+///
+/// The codes are identical to those of the one-shot constructors of `QrCode`,
+/// which create a temporary encoder.
+///
+/// The encoding of huge or pathological data can be aborted with a
+/// cancellation flag or a deadline, e.g. to respect the latency budget of a
+/// request handler:
+///
+///     use std::time::Duration;
+///     use qrqrpar::{EcLevel, Encoder};
+///
+///     let mut encoder = Encoder::new().with_deadline(Duration::from_millis(50));
+///     let code = encoder.encode("Some data", EcLevel::M).unwrap();
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    canvas: Canvas,
+    scratch: Canvas,
+    masked: Canvas,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    deadline: Option<Duration>,
+    legacy_padding: bool,
+}
+
+/// The cancellation state of one encoding.
+struct Cancellation<'a> {
+    flag: Option<&'a AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl Cancellation<'_> {
+    /// Returns `Err(QrError::Cancelled)` if the flag is set or the deadline is
+    /// passed.
+    fn check(&self) -> QrResult<()> {
+        let cancelled = self.flag.is_some_and(|flag| flag.load(Ordering::Relaxed))
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline);
+        if cancelled {
+            Err(QrError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Optimizes the segments of the data for the version, checking for
+    /// cancellation every `CHECK_INTERVAL` bytes.
+    fn optimal_segments(&self, data: &[u8], version: Version) -> QrResult<Vec<Segment>> {
+        let mut segmentation = CharSegmentation::new(version);
+        for chunk in data.chunks(CHECK_INTERVAL) {
+            self.check()?;
+            segmentation.extend(chunk);
+        }
+        Ok(segmentation.segments())
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Encoder {
+    /// Constructs a new encoder.
+    pub fn new() -> Self {
+        let canvas = Canvas::new(Version::Normal(1), EcLevel::L);
+        Self {
+            scratch: canvas.clone(),
+            masked: canvas.clone(),
+            canvas,
+            cancel_flag: None,
+            deadline: None,
+            legacy_padding: false,
+        }
+    }
+
+    /// Aborts the encodings with `QrError::Cancelled` once the flag is set,
+    /// e.g. from another thread.
+    ///
+    /// The flag is checked before parsing the data, and then regularly while
+    /// parsing and optimizing the segments and between the candidate
+    /// versions. The error correction and the masking are never interrupted.
+    pub fn with_cancel_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel_flag = Some(flag);
+        self
+    }
+
+    /// Aborts the encodings with `QrError::Cancelled` once they took longer
+    /// than `deadline`, which is counted from the start of each encoding.
+    ///
+    /// The deadline is checked at the same points as the cancellation flag,
+    /// see `with_cancel_flag`, so an encoding may run a little longer.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Pads the data capacity as some legacy encoders do, see
+    /// `PaddingPattern::Legacy`, as a compatibility shim for verifiers
+    /// expecting their output.
+    ///
+    /// Only the Micro QR code symbols M1, M3-L and M3-M of `encode_micro` are
+    /// affected, which then do not conform to the specification. The other
+    /// symbols are padded identically either way, and `encode_bits` keeps the
+    /// padding of its bits.
+    ///
+    ///     use qrqrpar::{EcLevel, Encoder, QrCode, Version};
+    ///
+    ///     let mut encoder = Encoder::new().with_legacy_padding(true);
+    ///     let code = encoder.encode_micro("123", EcLevel::L).unwrap();
+    ///     assert_eq!(code.version(), Version::Micro(1));
+    ///     assert_ne!(code.to_colors(), QrCode::micro("123").unwrap().to_colors());
+    pub fn with_legacy_padding(mut self, legacy_padding: bool) -> Self {
+        self.legacy_padding = legacy_padding;
+        self
+    }
+
+    /// Starts the cancellation state of an encoding.
+    fn cancellation(&self) -> Cancellation<'_> {
+        Cancellation {
+            flag: self.cancel_flag.as_deref(),
+            deadline: self.deadline.map(|deadline| Instant::now() + deadline),
+        }
+    }
+
+    /// Encodes the data into the smallest QR code at the given error correction
+    /// level, like `QrCode::with_error_correction_level`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, e.g. when the data
+    /// is too long.
+    ///
+    /// Returns `Err(QrError::Cancelled)` if the encoding was cancelled, see
+    /// `with_cancel_flag` and `with_deadline`.
+    pub fn encode<D: AsRef<[u8]>>(&mut self, data: D, ec_level: EcLevel) -> QrResult<QrCode> {
+        let data = data.as_ref();
+        let cancellation = self.cancellation();
+        cancellation.check()?;
+        bits::check_len_lower_bound(data.len(), bits::AUTO_VERSIONS, ec_level)?;
+        let bits = bits::encode_auto_with(data, ec_level, None, |version| {
+            cancellation.optimal_segments(data, version)
+        })?;
+        self.encode_auto_bits(bits, ec_level)
+    }
+
+    /// Encodes the data into the rMQR code chosen by the strategy at the given
+    /// error correction level, like `QrCode::rmqr_with_options`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the rMQR code cannot be constructed, e.g. when the data
+    /// is too long, or `Err(QrError::Cancelled)` if the encoding was
+    /// cancelled, see `with_cancel_flag` and `with_deadline`.
+    pub fn encode_rmqr<D: AsRef<[u8]>>(
+        &mut self,
+        data: D,
+        ec_level: EcLevel,
+        strategy: RmqrStrategy,
+    ) -> QrResult<QrCode> {
+        let data = data.as_ref();
+        let cancellation = self.cancellation();
+        cancellation.check()?;
+        bits::check_rmqr_request(data.len(), ec_level, strategy)?;
+        let bits = bits::encode_auto_rmqr_with(data, ec_level, strategy, None, |version| {
+            cancellation.optimal_segments(data, version)
+        })?;
+        self.encode_auto_bits(bits, ec_level)
+    }
+
+    /// Encodes the data into the smallest Micro QR code at the given error
+    /// correction level, like `QrCode::micro_with_error_correction_level`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the Micro QR code cannot be constructed, e.g. when the
+    /// data is too long, or `Err(QrError::Cancelled)` if the encoding was
+    /// cancelled, see `with_cancel_flag` and `with_deadline`.
+    pub fn encode_micro<D: AsRef<[u8]>>(&mut self, data: D, ec_level: EcLevel) -> QrResult<QrCode> {
+        self.cancellation().check()?;
+        let pad = if self.legacy_padding {
+            PaddingPattern::Legacy
+        } else {
+            PaddingPattern::Standard
+        };
+        let bits = bits::encode_auto_micro_with(data.as_ref(), ec_level, pad)?;
+        self.encode_auto_bits(bits, ec_level)
+    }
+
+    /// Constructs a QR code with encoded bits, like `QrCode::with_bits`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, e.g. when the bits
+    /// are too long, or when the version and error correction level are
+    /// incompatible.
+    pub fn encode_bits(&mut self, bits: Bits, ec_level: EcLevel) -> QrResult<QrCode> {
+        self.encode_bits_with(bits, ec_level, false)
+    }
+
+    /// Same as `encode_bits`, but also records the `QrCode::encode_stats` of
+    /// bits pushed by the high-level constructors.
+    pub(crate) fn encode_auto_bits(&mut self, bits: Bits, ec_level: EcLevel) -> QrResult<QrCode> {
+        self.encode_bits_with(bits, ec_level, true)
+    }
+
+    fn encode_bits_with(
+        &mut self,
+        bits: Bits,
+        ec_level: EcLevel,
+        record_stats: bool,
+    ) -> QrResult<QrCode> {
+        let version = bits.version();
+        let headers = bits.headers();
+        let stats = if record_stats {
+            bits.encode_stats(ec_level)
+        } else {
+            None
+        };
+        let data = bits.into_bytes();
+        let (encoded_data, ec_data) = ec::construct_codewords(&data, version, ec_level)?;
+        let ec_bits = ec_data.len() * 8;
+        QrCode::draw_canvas(&mut self.canvas, version, ec_level, &encoded_data, &ec_data);
+        self.canvas
+            .apply_best_mask_into(&mut self.scratch, &mut self.masked);
+        let mut code =
+            QrCode::from_masked_canvas(&self.masked, version, ec_level, encoded_data, ec_data);
+        code.headers = headers;
+        code.encode_stats = stats.map(|stats| bits::BitStats { ec_bits, ..stats });
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod encoder_tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use crate::bits::{self, PaddingPattern};
+    use crate::encoder::{Cancellation, Encoder};
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::{QrCode, RmqrStrategy};
+
+    #[test]
+    fn test_reused_encoder_matches_one_shot() {
+        let mut encoder = Encoder::new();
+        // Alternate between sizes, so that the buffers are both grown and
+        // shrunk between codes.
+        let corpus = (0..40).map(|i| {
+            let len = if i % 2 == 0 { i * 37 } else { i };
+            (0..len)
+                .map(|j| b'0' + ((i * 7 + j) % 43) as u8)
+                .collect::<Vec<_>>()
+        });
+        for (i, data) in corpus.enumerate() {
+            let ec_level = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H][i % 4];
+            let reused = encoder.encode(&data, ec_level).unwrap();
+            let one_shot = QrCode::with_error_correction_level(&data, ec_level).unwrap();
+            assert_eq!(reused.version(), one_shot.version());
+            assert_eq!(reused.to_colors(), one_shot.to_colors(), "{i}");
+            assert_eq!(reused.final_codewords(), one_shot.final_codewords());
+        }
+    }
+
+    #[test]
+    fn test_other_families() {
+        let mut encoder = Encoder::new();
+        for i in 0..8 {
+            let data = "1".repeat(i * 3);
+            let code = QrCode::with_version(&data, Version::Micro(4), EcLevel::L).unwrap();
+            let bits =
+                crate::bits::encode_auto_rmqr(data.as_bytes(), EcLevel::M, RmqrStrategy::Area)
+                    .unwrap();
+            let rmqr = encoder.encode_bits(bits, EcLevel::M).unwrap();
+            assert_eq!(rmqr.to_colors(), QrCode::rmqr(&data).unwrap().to_colors());
+
+            let mut bits = crate::bits::Bits::new(Version::Micro(4));
+            bits.push_optimal_data(data.as_bytes()).unwrap();
+            bits.push_terminator(EcLevel::L).unwrap();
+            let micro = encoder.encode_bits(bits, EcLevel::L).unwrap();
+            assert_eq!(micro.to_colors(), code.to_colors());
+        }
+    }
+
+    #[test]
+    fn test_legacy_padding() {
+        let mut standard = Encoder::new();
+        let mut legacy = Encoder::new().with_legacy_padding(true);
+        // M1, M3-L and M3-M end with a 4-bit codeword, which only the legacy
+        // padding fills when the data leaves it free.
+        for (data, ec_level, version, free) in [
+            ("123", EcLevel::L, Version::Micro(1), true),
+            ("1234", EcLevel::L, Version::Micro(1), false),
+            ("12345", EcLevel::L, Version::Micro(1), false),
+            ("1234567890123", EcLevel::L, Version::Micro(3), true),
+            (
+                "123456789012345678901",
+                EcLevel::L,
+                Version::Micro(3),
+                false,
+            ),
+            ("1234567890123", EcLevel::M, Version::Micro(3), true),
+        ] {
+            let code = standard.encode_micro(data, ec_level).unwrap();
+            let expected = QrCode::micro_with_error_correction_level(data, ec_level).unwrap();
+            assert_eq!(code.version(), version, "{data}");
+            assert_eq!(code.to_colors(), expected.to_colors());
+            let code = legacy.encode_micro(data, ec_level).unwrap();
+            assert_eq!(code.version(), version, "{data}");
+            let bits =
+                bits::encode_auto_micro_with(data.as_bytes(), ec_level, PaddingPattern::Legacy)
+                    .unwrap();
+            assert_eq!(
+                code.to_colors(),
+                QrCode::with_bits(bits, ec_level).unwrap().to_colors()
+            );
+            assert_eq!(code.to_colors() != expected.to_colors(), free, "{data}");
+        }
+
+        // The other symbols are unaffected.
+        for data in ["1234567", "Some data"] {
+            let code = legacy.encode_micro(data, EcLevel::L).unwrap();
+            assert_eq!(code.to_colors(), QrCode::micro(data).unwrap().to_colors());
+            let code = legacy.encode(data, EcLevel::M).unwrap();
+            assert_eq!(code.to_colors(), QrCode::new(data).unwrap().to_colors());
+        }
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let flag = Arc::new(AtomicBool::new(true));
+        let mut encoder = Encoder::new().with_cancel_flag(Arc::clone(&flag));
+        let data = "0123456789".repeat(700);
+        assert_eq!(
+            encoder.encode(&data, EcLevel::L).err(),
+            Some(QrError::Cancelled)
+        );
+        assert_eq!(
+            encoder
+                .encode_rmqr("1234", EcLevel::M, RmqrStrategy::Area)
+                .err(),
+            Some(QrError::Cancelled)
+        );
+
+        flag.store(false, Ordering::Relaxed);
+        let code = encoder.encode(&data, EcLevel::L).unwrap();
+        assert_eq!(code.version(), Version::Normal(40));
+
+        let mut encoder = Encoder::new().with_deadline(Duration::ZERO);
+        assert_eq!(
+            encoder.encode("1234", EcLevel::L).err(),
+            Some(QrError::Cancelled)
+        );
+    }
+
+    /// Returns megabytes of mixed data, far more than fits any symbol, so that
+    /// optimizing it takes long enough to be cancelled halfway.
+    fn long_data() -> Vec<u8> {
+        b"0123ABCDabcd\x93\x5f".repeat(1 << 19)
+    }
+
+    #[test]
+    fn test_flag_set_during_optimization() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let cancellation = Cancellation {
+            flag: Some(&flag),
+            deadline: None,
+        };
+        let data = long_data();
+        let result = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(20));
+                flag.store(true, Ordering::Relaxed);
+            });
+            cancellation.optimal_segments(&data, Version::Normal(40))
+        });
+        assert_eq!(result.err(), Some(QrError::Cancelled));
+    }
+
+    #[test]
+    fn test_deadline_during_optimization() {
+        let cancellation = Cancellation {
+            flag: None,
+            deadline: Some(Instant::now() + Duration::from_millis(20)),
+        };
+        let result = cancellation.optimal_segments(&long_data(), Version::Rmqr(17, 139));
+        assert_eq!(result.err(), Some(QrError::Cancelled));
+    }
+
+    #[test]
+    fn test_generous_deadline() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let mut encoder = Encoder::new()
+            .with_deadline(Duration::from_secs(3600))
+            .with_cancel_flag(flag);
+        for i in 0..20 {
+            let data = "a1".repeat(i * 50);
+            let code = encoder.encode(&data, EcLevel::M).unwrap();
+            let expected = QrCode::with_error_correction_level(&data, EcLevel::M).unwrap();
+            assert_eq!(code.to_colors(), expected.to_colors());
+
+            let data = &data[..data.len().min(40)];
+            for strategy in [
+                RmqrStrategy::Width,
+                RmqrStrategy::Height,
+                RmqrStrategy::Area,
+            ] {
+                let code = encoder.encode_rmqr(data, EcLevel::H, strategy).unwrap();
+                let expected = QrCode::rmqr_with_options(data, EcLevel::H, strategy).unwrap();
+                assert_eq!(code.to_colors(), expected.to_colors());
+            }
+        }
+    }
+}
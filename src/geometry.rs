@@ -0,0 +1,224 @@
+//! The `geometry` module provides types to describe regions of a QR code.
+//!
+//! All coordinates are measured in modules, with the origin at the top-left
+//! corner of the symbol (excluding the quiet zone), except for `PixelRect`.
+
+/// The position of a single module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ModulePoint {
+    /// The x-coordinate, increasing to the right.
+    pub x: u32,
+    /// The y-coordinate, increasing downwards.
+    pub y: u32,
+}
+
+impl ModulePoint {
+    /// Constructs a new point.
+    pub fn new(x: u32, y: u32) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<(u32, u32)> for ModulePoint {
+    fn from((x, y): (u32, u32)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<ModulePoint> for (u32, u32) {
+    fn from(point: ModulePoint) -> Self {
+        (point.x, point.y)
+    }
+}
+
+/// A rectangle of modules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModuleRect {
+    /// The x-coordinate of the left edge.
+    pub x: u32,
+    /// The y-coordinate of the top edge.
+    pub y: u32,
+    /// The width.
+    pub w: u32,
+    /// The height.
+    pub h: u32,
+}
+
+impl ModuleRect {
+    /// Constructs a new rectangle.
+    ///
+    ///     use qrqrpar::geometry::ModuleRect;
+    ///
+    ///     assert!(ModuleRect::new(2, 3, 4, 5).is_some());
+    ///     assert!(ModuleRect::new(2, 3, 0, 5).is_none());
+    ///
+    /// Returns `None` if the rectangle is empty, or if its right or bottom edge
+    /// overflows `u32`.
+    pub fn new(x: u32, y: u32, w: u32, h: u32) -> Option<Self> {
+        if w == 0 || h == 0 {
+            return None;
+        }
+        x.checked_add(w)?;
+        y.checked_add(h)?;
+        Some(Self { x, y, w, h })
+    }
+
+    /// The number of modules covered by the rectangle, as a `u64` since the
+    /// product of two `u32` sides may not fit a `u32`.
+    ///
+    ///     use qrqrpar::geometry::ModuleRect;
+    ///
+    ///     let rect = ModuleRect::new(0, 0, 1 << 16, 1 << 16).unwrap();
+    ///     assert_eq!(rect.area(), 1 << 32);
+    ///
+    pub fn area(&self) -> u64 {
+        u64::from(self.w) * u64::from(self.h)
+    }
+
+    /// The x-coordinate just past the right edge.
+    pub fn right(&self) -> u32 {
+        self.x + self.w
+    }
+
+    /// The y-coordinate just past the bottom edge.
+    pub fn bottom(&self) -> u32 {
+        self.y + self.h
+    }
+
+    /// Checks whether the module at the given point lies inside the rectangle.
+    pub fn contains(&self, point: impl Into<ModulePoint>) -> bool {
+        let ModulePoint { x, y } = point.into();
+        (self.x..self.right()).contains(&x) && (self.y..self.bottom()).contains(&y)
+    }
+
+    /// Computes the modules covered by both rectangles.
+    ///
+    ///     use qrqrpar::geometry::ModuleRect;
+    ///
+    ///     let a = ModuleRect::new(0, 0, 4, 4).unwrap();
+    ///     let b = ModuleRect::new(2, 3, 4, 4).unwrap();
+    ///     assert_eq!(a.intersect(&b), ModuleRect::new(2, 3, 2, 1));
+    ///
+    /// Returns `None` if the rectangles do not overlap, including when they
+    /// only share an edge.
+    pub fn intersect(&self, other: &ModuleRect) -> Option<ModuleRect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        ModuleRect::new(x, y, right.checked_sub(x)?, bottom.checked_sub(y)?)
+    }
+
+    /// Iterates over the points of all modules in the rectangle, row by row.
+    pub fn iter_points(&self) -> impl Iterator<Item = ModulePoint> {
+        let (xs, ys) = (self.x..self.right(), self.y..self.bottom());
+        ys.flat_map(move |y| xs.clone().map(move |x| ModulePoint { x, y }))
+    }
+}
+
+/// The modules whose rendering does not match the module matrix, see
+/// `QrCode::verify_render_geometry`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeometryError {
+    /// The mismatching modules, in row-major order.
+    pub mismatches: Vec<ModulePoint>,
+}
+
+impl std::fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} modules rendered incorrectly", self.mismatches.len())?;
+        if let Some(first) = self.mismatches.first() {
+            write!(f, ", first at ({}, {})", first.x, first.y)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for GeometryError {}
+
+/// A rectangle in the pixel coordinates of a rendered image, with the origin
+/// at the top-left corner of the image (including the quiet zone).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelRect {
+    /// The x-coordinate of the left edge.
+    pub x: f64,
+    /// The y-coordinate of the top edge.
+    pub y: f64,
+    /// The width.
+    pub w: f64,
+    /// The height.
+    pub h: f64,
+}
+
+#[cfg(test)]
+mod geometry_tests {
+    use crate::geometry::{ModulePoint, ModuleRect};
+
+    #[test]
+    fn test_new() {
+        assert_eq!(
+            ModuleRect::new(1, 2, 3, 4),
+            Some(ModuleRect {
+                x: 1,
+                y: 2,
+                w: 3,
+                h: 4
+            })
+        );
+        assert_eq!(ModuleRect::new(1, 2, 0, 4), None);
+        assert_eq!(ModuleRect::new(1, 2, 3, 0), None);
+        assert_eq!(ModuleRect::new(u32::MAX, 0, 1, 1), None);
+        assert!(ModuleRect::new(u32::MAX - 1, 0, 1, 1).is_some());
+    }
+
+    #[test]
+    fn test_area() {
+        assert_eq!(ModuleRect::new(2, 3, 4, 5).unwrap().area(), 20);
+        let rect = ModuleRect::new(0, 0, u32::MAX, u32::MAX).unwrap();
+        assert_eq!(rect.area(), u64::from(u32::MAX) * u64::from(u32::MAX));
+    }
+
+    #[test]
+    fn test_contains() {
+        let rect = ModuleRect::new(2, 3, 4, 5).unwrap();
+        assert!(rect.contains((2, 3)));
+        assert!(rect.contains(ModulePoint::new(5, 7)));
+        assert!(!rect.contains((6, 3)));
+        assert!(!rect.contains((2, 8)));
+        assert!(!rect.contains((1, 3)));
+        assert!(!rect.contains((2, 2)));
+    }
+
+    #[test]
+    fn test_intersect() {
+        let rect = ModuleRect::new(2, 3, 4, 5).unwrap();
+        assert_eq!(rect.intersect(&rect), Some(rect));
+        assert_eq!(
+            rect.intersect(&ModuleRect::new(0, 0, 3, 4).unwrap()),
+            ModuleRect::new(2, 3, 1, 1)
+        );
+        assert_eq!(
+            rect.intersect(&ModuleRect::new(3, 4, 1, 1).unwrap()),
+            ModuleRect::new(3, 4, 1, 1)
+        );
+        // Sharing the right or bottom edge is not an intersection.
+        assert_eq!(rect.intersect(&ModuleRect::new(6, 3, 2, 2).unwrap()), None);
+        assert_eq!(rect.intersect(&ModuleRect::new(2, 8, 2, 2).unwrap()), None);
+        assert_eq!(
+            rect.intersect(&ModuleRect::new(10, 10, 2, 2).unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_iter_points() {
+        let rect = ModuleRect::new(2, 3, 3, 2).unwrap();
+        let points = rect
+            .iter_points()
+            .map(<(u32, u32)>::from)
+            .collect::<Vec<_>>();
+        assert_eq!(points, [(2, 3), (3, 3), (4, 3), (2, 4), (3, 4), (4, 4)]);
+        assert_eq!(rect.iter_points().count(), rect.area() as usize);
+        assert!(rect.iter_points().all(|point| rect.contains(point)));
+    }
+}
@@ -0,0 +1,14 @@
+//! The `internals` module gathers the implementation details of the crate,
+//! which are only public for its benchmarks, fuzzers and tests.
+//!
+//! Nothing here is covered by the stable API snapshot, and any item may
+//! change or disappear in any release, patch releases included.
+
+pub use self::canvas::{Canvas, Module};
+
+#[path = "canvas.rs"]
+pub mod canvas;
+#[path = "coding.rs"]
+pub mod coding;
+#[path = "ec.rs"]
+pub mod ec;
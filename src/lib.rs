@@ -3,6 +3,14 @@
 //! This crate provides a QR code, Micro QR and rMQR code encoder for binary data.
 //!
 //! ```
+//! // Encode a text and render it to PNG data with the default settings.
+//! let png = qrqrpar::quick::png("https://example.com").unwrap();
+//! # assert!(png.starts_with(b"\x89PNG"));
+//! ```
+//!
+//! `QrCode` and `QrStyle` give control over the symbol and its rendering.
+//!
+//! ```
 //! use qrqrpar::{QrCode, QrStyle};
 //!
 //! // Encode some data into bits.
@@ -12,51 +20,874 @@
 //! let style = QrStyle::default();
 //!
 //! // Render the bits into an image and save it.
-//! code.save_png("rmqr.png", &style).unwrap();
+//! let dir = tempdir::TempDir::new("qrqrpar").unwrap();
+//! code.save_png(dir.path().join("rmqr.png"), &style).unwrap();
+//! ```
+//!
+//! # Empty data
+//!
+//! Empty data is valid. Every constructor encodes it as a symbol holding no
+//! segment, only the terminator and the padding, in the smallest version
+//! allowed: version 1 for `QrCode::new`, and the version picked by the
+//! `RmqrStrategy` for `QrCode::rmqr_with_options`.
+//!
 //! ```
+//! use qrqrpar::{QrCode, Version};
+//!
+//! let code = QrCode::new(b"").unwrap();
+//! assert_eq!(code.version(), Version::Normal(1));
+//! ```
+//!
+//! # Other encoders
+//!
+//! The symbols follow the standards, but are not guaranteed to match the
+//! output of another encoder such as zxing bit for bit: where the standards
+//! allow several segmentations, versions or masks, the choice may differ.
+//! There is no mode emulating another encoder. The tie-breaks are
+//! documented where they are made, e.g. `bits::encode_auto`.
+//!
+//! # Stability
+//!
+//! The public items fall into three tiers:
+//!
+//! * The `prelude`, the crate root re-exports and the `quick` module are the
+//!   supported surface. Their paths stay put when modules are reorganized.
+//! * The remaining public modules (`bits`, `geometry`, `payload`, `stats`
+//!   behind the `stats` feature, `structured_append` and `types`) give access
+//!   to the lower level building blocks and the encoding statistics. Their
+//!   paths may move between minor versions. So may the modules behind the
+//!   `test-util` feature (`conformance` and `test_util`), which are tools for
+//!   testing rather than part of the encoder.
+//! * The items hidden from the documentation are implementation details
+//!   which are only public for internal use and may change at any time, even
+//!   in patch releases. They are gathered in the hidden `internals` module:
+//!   the error correction, the drawing canvas and the segment coding, whose
+//!   user facing items are re-exported at the root. The former `ec`,
+//!   `canvas` and `coding` paths are kept as deprecated aliases.
+//!
+//! The supported surface, with the signatures of its functions, the fields
+//! and variants of its types and their methods, is recorded in
+//! `stable-api.txt` at the root of the repository. A test fails when it
+//! changes without the snapshot being updated, by running the tests with
+//! `QRQRPAR_UPDATE_API_SNAPSHOT=1`.
 
+mod batch;
 pub mod bits;
-pub mod canvas;
-pub mod coding;
-pub mod ec;
+/// Moved to `internals::canvas`.
+#[doc(hidden)]
+#[deprecated(note = "the drawing canvas is an implementation detail, see `internals::canvas`")]
+pub mod canvas {
+    pub use crate::internals::canvas::*;
+}
+/// Moved to `internals::coding`.
+#[doc(hidden)]
+#[deprecated(note = "the segment coding moved, see `internals::coding` and the crate root")]
+pub mod coding {
+    pub use crate::internals::coding::*;
+}
+mod compare;
+#[cfg(feature = "qrcode-compat")]
+mod compat;
+#[cfg(feature = "test-util")]
+pub mod conformance;
+/// Moved to `internals::ec`.
+#[doc(hidden)]
+#[deprecated(note = "the error correction is an implementation detail, see `internals::ec`")]
+pub mod ec {
+    pub use crate::internals::ec::*;
+}
+mod encoder;
+pub mod geometry;
+#[doc(hidden)]
+pub mod internals;
+mod partial;
+pub mod payload;
+mod prefix;
+pub mod prelude;
+mod print;
+mod profile;
+pub mod quick;
+mod recommend;
 mod render;
+mod sprite;
+#[cfg(feature = "stats")]
+pub mod stats;
+pub mod structured_append;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 pub mod types;
 
-pub use crate::bits::RmqrStrategy;
-pub use crate::types::{Color, EcLevel, QrResult, Version};
+pub use crate::batch::{save_png_batch, BatchError, BatchFailure, BatchReport};
+pub use crate::bits::{
+    estimate_chars_that_fit, fitting_ec_levels, rmqr_capacity_report, BitStats, Fnc1Kind,
+    HeaderFlags, RmqrFit, RmqrStrategy, ShapeStrategy,
+};
+pub use crate::compare::{compare, Comparison, ComparisonDelta, EncodeSummary};
+pub use crate::encoder::Encoder;
+pub use crate::internals::canvas::{format_info, rmqr_format_info, version_info, MaskPattern};
+pub use crate::internals::coding::{
+    optimal_segments, total_encoded_len, MergeReason, Optimizer, Parser, Segment,
+};
+pub use crate::partial::PartialQr;
+pub use crate::prefix::{Family, PrefixEncoder};
+pub use crate::print::{
+    PrintSize, RobustConstraints, ScannerClass, INDUSTRIAL_MODULE_FACTOR, PHONE_MODULE_FACTOR,
+};
+pub use crate::profile::{QrProfile, QrProfileError};
+pub use crate::recommend::{
+    best_micro_symbol, recommend, Constraints, Recommendation, SymbolOption,
+};
+pub use crate::sprite::{render_sprite, sprite_use, SpriteError, SpriteUse};
+pub use crate::types::{
+    alphanumeric_value, Color, EcLevel, EcLevelUnavailable, QrResult, Version,
+    ALPHANUMERIC_CHARSET, NUMERIC_CHARSET,
+};
+
+use crate::render::Number;
+use std::sync::Arc;
+
+/// The criterion `QrCode::remask_preferring` chooses the mask by, instead of
+/// the penalty score of the specification.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MaskPreference {
+    /// Keep the ratio of dark modules closest to 50%, see `QrCode::dark_ratio`.
+    BalancedDarkRatio,
+    /// Keep the longest run of dark modules shortest, see
+    /// `QrCode::largest_dark_run`.
+    ShortestDarkRun,
+}
+
+/// How `QrCode::with_version_checked` treats a pinned version which is larger
+/// than the data needs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SizePolicy {
+    /// Use the version without checking, like `QrCode::with_version`.
+    Exact,
+    /// Report an `OversizedVersion` if more than the given number of smaller
+    /// versions would hold the data.
+    WarnIfOversized(usize),
+    /// Fail with `QrError::VersionOversized` if more than the given number of
+    /// smaller versions would hold the data.
+    ErrorIfOversized(usize),
+}
+
+/// A pinned version which is larger than the data needs, reported by
+/// `QrCode::with_version_checked`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OversizedVersion {
+    /// The pinned version.
+    pub version: Version,
+    /// The smallest version of the same type holding the data.
+    pub minimum: Version,
+    /// The number of versions of the same type which are smaller than the
+    /// pinned one and hold the data, e.g. 17 for version 20 when version 3
+    /// would do.
+    pub gap: usize,
+}
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum QrShape {
     Square,
     Round,
 }
 
+/// How the corners lying on the border of the symbol are drawn with
+/// `QrShape::Round`.
+///
+/// The rounded corners always stay inside their module, so neither option
+/// changes the size of the image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BorderCorners {
+    /// Round the corners on the border like every other corner.
+    #[default]
+    Round,
+    /// Keep the corners on the border square, so the outline of the symbol
+    /// stays a clean rectangle even without a quiet zone.
+    Square,
+}
+
+/// Horizontal alignment of a `Caption` relative to the symbol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaptionAlign {
+    /// Align to the left edge of the symbol.
+    Start,
+    /// Center under the symbol.
+    #[default]
+    Middle,
+    /// Align to the right edge of the symbol.
+    End,
+}
+
+/// A line of text printed under the symbol, e.g. the human-readable form of
+/// the payload on a label.
+///
+/// The image grows by 1.5 times the font size below the bottom quiet zone to
+/// make room for the caption.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Caption {
+    /// The text. It is truncated with an ellipsis if it is estimated to be
+    /// wider than the symbol.
+    pub text: String,
+    /// The CSS font family.
+    pub font_family: String,
+    /// The font size, in modules.
+    pub size_modules: f64,
+    /// The horizontal alignment.
+    pub align: CaptionAlign,
+    /// Draw a box outline for every character instead of the text itself, for
+    /// systems where no font can be resolved, e.g. headless servers.
+    pub path_fallback: bool,
+}
+
+impl Caption {
+    /// Constructs a centered caption in a 2-module sans-serif font.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            font_family: String::from("sans-serif"),
+            size_modules: 2.0,
+            align: CaptionAlign::default(),
+            path_fallback: false,
+        }
+    }
+
+    /// The height of the line holding the caption, in modules.
+    fn line_height(&self) -> f64 {
+        self.size_modules * 1.5
+    }
+}
+
+/// The bleed and the crop marks added around the image for print prepress.
+///
+/// The image drawn without them becomes the trim box: the bleed extends the
+/// image beyond it on every side, filled with the background color, and the
+/// crop marks show where to cut. The symbol keeps its position within the
+/// trim box, and its modules keep their size in pixels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Prepress {
+    /// The width of the bleed on every side, in modules.
+    pub bleed_modules: f64,
+    /// Draw a horizontal and a vertical crop mark at every corner of the trim
+    /// box. They run along its edges from half the bleed to the full bleed
+    /// away from it, so they are cut off with the bleed. Without bleed, they
+    /// would have no length and are not drawn.
+    pub crop_marks: bool,
+    /// Draw the crop marks in pure black, the closest to the registration
+    /// color printing on every separation, instead of the module color.
+    pub registration_color: bool,
+}
+
+impl Prepress {
+    /// Whether the crop marks are drawn, i.e. asked for and of some length.
+    fn draws_crop_marks(self) -> bool {
+        self.crop_marks && self.bleed_modules > 0.0
+    }
+}
+
+/// A CMYK color for print artwork, used in a `QrStyle` through its string
+/// form.
+///
+///     use qrqrpar::{Cmyk, QrCode, QrStyle};
+///
+///     let cyan = Cmyk::new(1.0, 0.0, 0.0, 0.0);
+///     assert_eq!(cyan.to_string(), "#00ffff device-cmyk(1 0 0 0)");
+///
+///     let style = QrStyle { color: cyan.to_string(), ..Default::default() };
+///     let svg = QrCode::new(b"Some data").unwrap().to_svg(&style);
+///     assert!(svg.contains(r##"<path fill="#00ffff device-cmyk(1 0 0 0)""##));
+///
+/// The string is a dual value: SVG 1.1 cannot express CMYK colors, so the
+/// `device-cmyk()` value follows an RGB fallback, which viewers without
+/// CMYK support use instead. `to_pixmap` and the other rasterizing methods
+/// always draw the fallback.
+///
+/// The fallback is the naive conversion without color profile, so it only
+/// approximates the printed color.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Cmyk {
+    /// The cyan component, between 0 and 1.
+    pub c: f64,
+    /// The magenta component, between 0 and 1.
+    pub m: f64,
+    /// The yellow component, between 0 and 1.
+    pub y: f64,
+    /// The black component, between 0 and 1.
+    pub k: f64,
+}
+
+impl Cmyk {
+    /// Constructs a new color, clamping the components between 0 and 1. NaN
+    /// components become 0.
+    pub fn new(c: f64, m: f64, y: f64, k: f64) -> Self {
+        let clamp = |v: f64| if v.is_nan() { 0.0 } else { v.clamp(0.0, 1.0) };
+        Self {
+            c: clamp(c),
+            m: clamp(m),
+            y: clamp(y),
+            k: clamp(k),
+        }
+    }
+
+    /// Gets the CMYK value of a style color, i.e. the components of its
+    /// `device-cmyk()` value, clamped between 0 and 1. Returns `None` for
+    /// plain RGB colors, or if the value is malformed.
+    ///
+    ///     use qrqrpar::Cmyk;
+    ///
+    ///     let color = Cmyk::parse("#000 device-cmyk(0.2 0.4 0.6 1.5)").unwrap();
+    ///     assert_eq!(color, Cmyk::new(0.2, 0.4, 0.6, 1.0));
+    ///     assert_eq!(Cmyk::parse("#000000"), None);
+    pub fn parse(color: &str) -> Option<Self> {
+        let (_, value) = color.split_once(" device-cmyk(")?;
+        let components = value.strip_suffix(')')?;
+        let components = components
+            .split(|c: char| c == ',' || c.is_ascii_whitespace())
+            .filter(|component| !component.is_empty())
+            .map(|component| component.parse::<f64>().ok().filter(|v| v.is_finite()))
+            .collect::<Option<Vec<_>>>()?;
+        match components[..] {
+            [c, m, y, k] => Some(Self::new(c, m, y, k)),
+            _ => None,
+        }
+    }
+
+    /// Converts the color to RGB without color profile, as used for the
+    /// fallback.
+    pub fn to_rgb(&self) -> [u8; 3] {
+        let channel = |v: f64| (255.0 * (1.0 - v) * (1.0 - self.k)).round() as u8;
+        [channel(self.c), channel(self.m), channel(self.y)]
+    }
+}
+
+impl std::fmt::Display for Cmyk {
+    /// Formats the color as its RGB fallback followed by its `device-cmyk()`
+    /// value, e.g. `#00ffff device-cmyk(1 0 0 0)`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [r, g, b] = self.to_rgb();
+        write!(
+            f,
+            "#{r:02x}{g:02x}{b:02x} device-cmyk({} {} {} {})",
+            self.c, self.m, self.y, self.k
+        )
+    }
+}
+
+/// Removes the `device-cmyk()` values of the colors, which resvg does not
+/// support, so that their RGB fallbacks are drawn instead of black.
+///
+/// Only the `fill` and `stroke` attributes written by the crate are
+/// rewritten. Their values are XML-escaped, so they hold no quote, and a
+/// caption mentioning `device-cmyk(` is left alone.
+fn strip_cmyk(svg_string: &str) -> std::borrow::Cow<'_, str> {
+    const CMYK: &str = " device-cmyk(";
+    if !svg_string.contains(CMYK) {
+        return std::borrow::Cow::Borrowed(svg_string);
+    }
+    let mut stripped = String::with_capacity(svg_string.len());
+    let mut rest = svg_string;
+    while let Some(start) = [r#" fill=""#, r#" stroke=""#]
+        .into_iter()
+        .filter_map(|attribute| rest.find(attribute).map(|i| i + attribute.len()))
+        .min()
+    {
+        let Some(len) = rest[start..].find('"') else {
+            break;
+        };
+        let value = &rest[start..start + len];
+        stripped.push_str(&rest[..start]);
+        stripped.push_str(
+            value
+                .split_once(CMYK)
+                .map_or(value, |(fallback, _)| fallback),
+        );
+        rest = &rest[start + len..];
+    }
+    stripped.push_str(rest);
+    std::borrow::Cow::Owned(stripped)
+}
+
+/// Size of the quiet zone around the symbol.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QuietZone {
+    /// The quiet zone recommended for the symbol family, see
+    /// `Version::recommended_quiet_zone`.
+    #[default]
+    Auto,
+    /// The same quiet zone on every side, measured in terms of a single dot
+    /// size.
+    Uniform(f64),
+    /// A separate quiet zone on every side, measured in terms of a single dot
+    /// size.
+    Sides {
+        top: f64,
+        right: f64,
+        bottom: f64,
+        left: f64,
+    },
+}
+
+impl QuietZone {
+    /// Returns the size of the quiet zone in modules for the given version, as
+    /// `[top, right, bottom, left]`.
+    pub fn resolve(self, version: Version) -> [f64; 4] {
+        match self {
+            QuietZone::Auto => [f64::from(version.recommended_quiet_zone()); 4],
+            QuietZone::Uniform(modules) => [modules; 4],
+            QuietZone::Sides {
+                top,
+                right,
+                bottom,
+                left,
+            } => [top, right, bottom, left],
+        }
+    }
+}
+
+impl From<f64> for QuietZone {
+    fn from(modules: f64) -> Self {
+        QuietZone::Uniform(modules)
+    }
+}
+
+/// An error in the glyphs of `QrCode::to_text`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphError {
+    /// A glyph takes no terminal column, e.g. an empty string.
+    ZeroWidth,
+    /// The dark and light glyphs take different numbers of terminal columns,
+    /// so the rows would not line up.
+    WidthMismatch { dark: usize, light: usize },
+}
+
+impl std::fmt::Display for GlyphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GlyphError::ZeroWidth => write!(f, "zero-width glyph"),
+            GlyphError::WidthMismatch { dark, light } => write!(
+                f,
+                "dark glyph is {dark} columns wide but light glyph is {light}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GlyphError {}
+
+/// The number of terminal columns taken by a character: 2 for the wide and
+/// fullwidth East Asian characters and the emoji, 0 for the control, combining
+/// and zero-width characters, and 1 otherwise.
+///
+/// Only the ranges which matter for drawing modules are listed, rather than
+/// the full Unicode tables.
+fn char_width(c: char) -> usize {
+    match u32::from(c) {
+        0x00..=0x1f
+        | 0x7f..=0x9f
+        | 0x0300..=0x036f
+        | 0x200b..=0x200f
+        | 0x2060..=0x2064
+        | 0xfe00..=0xfe0f
+        | 0xfeff => 0,
+        0x1100..=0x115f
+        | 0x2b1b..=0x2b1c
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f64f
+        | 0x1f900..=0x1f9ff
+        | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+/// The number of terminal columns taken by a string, see `char_width`.
+fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// An error in a `QrStyle` which prevents rendering.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StyleError {
+    /// The output image width is zero.
+    ZeroWidth,
+    /// The color is not a valid SVG color.
+    InvalidColor(String),
+    /// A side of the quiet zone is NaN or infinite.
+    NonFiniteQuietZone(f64),
+    /// A side of the quiet zone is negative.
+    NegativeQuietZone(f64),
+    /// The caption font size is not positive or not finite.
+    InvalidCaptionSize(f64),
+    /// The minimum module size is negative or not finite.
+    InvalidMinimumModuleSize(f64),
+    /// The prepress bleed is negative or not finite.
+    InvalidBleed(f64),
+    /// Both `quiet_zone_sides` and a `quiet_zone` other than the default are
+    /// set, holding the `quiet_zone`.
+    ConflictingQuietZone(f64),
+}
+
+impl std::fmt::Display for StyleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StyleError::ZeroWidth => write!(f, "zero image width"),
+            StyleError::InvalidColor(color) => write!(f, "invalid color {color:?}"),
+            StyleError::NonFiniteQuietZone(modules) => {
+                write!(f, "non-finite quiet zone of {modules} modules")
+            }
+            StyleError::NegativeQuietZone(modules) => {
+                write!(f, "negative quiet zone of {modules} modules")
+            }
+            StyleError::InvalidCaptionSize(modules) => {
+                write!(f, "invalid caption size of {modules} modules")
+            }
+            StyleError::InvalidMinimumModuleSize(px) => {
+                write!(f, "invalid minimum module size of {px} px")
+            }
+            StyleError::InvalidBleed(modules) => {
+                write!(f, "invalid bleed of {modules} modules")
+            }
+            StyleError::ConflictingQuietZone(modules) => {
+                write!(
+                    f,
+                    "quiet zone of {modules} modules conflicts with quiet_zone_sides"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for StyleError {}
+
+/// A questionable but renderable setting in a `QrStyle`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StyleWarning {
+    /// A side of the quiet zone is narrower than the specification requires,
+    /// which may make the symbol harder to scan.
+    QuietZoneBelowMinimum { modules: f64, minimum: u8 },
+    /// The modules are smaller than `QrStyle::minimum_module_px`, so the
+    /// rasterized symbol is blurred. Only the SVG output is unaffected.
+    ModuleTooSmall { module_px: f64, minimum: f64 },
+    /// The printed modules are smaller than `QrCode::recommended_print_size`
+    /// recommends for the scanning distance, see `QrCode::validate_print`.
+    PrintedModuleTooSmall { module_mm: f64, recommended_mm: f64 },
+}
+
+/// An error which prevents rasterizing a symbol, or saving the image.
+///
+/// Like every error type of the crate, it is `Send + Sync + 'static`, so it
+/// can cross threads and tasks, e.g. out of `tokio::task::spawn_blocking`:
+///
+///     use qrqrpar::{QrCode, QrStyle};
+///
+///     type BoxError = Box<dyn std::error::Error + Send + Sync>;
+///
+///     async fn label_png(data: String) -> Result<Vec<u8>, BoxError> {
+///         let png = tokio::task::spawn_blocking(move || -> Result<_, BoxError> {
+///             let code = QrCode::new(data)?;
+///             Ok(code.to_png(&QrStyle::default())?)
+///         })
+///         .await??;
+///         Ok(png)
+///     }
+///
+///     let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+///     let png = runtime.block_on(label_png(String::from("SKU-0042"))).unwrap();
+///     assert!(png.starts_with(b"\x89PNG"));
+#[derive(Debug)]
+pub enum RenderError {
+    /// The style is invalid, see `QrStyle::validate`. It is displayed as the
+    /// `StyleError` itself.
+    Style(StyleError),
+    /// A module would be `module_px` pixels wide, less than the `minimum` of
+    /// `QrStyle::minimum_module_px`. Set `QrStyle::allow_tiny` to render it
+    /// anyway.
+    ModuleTooSmall { module_px: f64, minimum: f64 },
+    /// The generated SVG document cannot be parsed by the rasterizer.
+    Svg(String),
+    /// The image would be empty or too large to allocate.
+    ImageSize { width: u32, height: u32 },
+    /// The pixmap cannot be encoded as PNG.
+    Png(String),
+    /// The image cannot be written.
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Style(e) => e.fmt(f),
+            RenderError::ModuleTooSmall { module_px, minimum } => {
+                write!(f, "module of {module_px} px smaller than {minimum} px")
+            }
+            RenderError::Svg(e) => write!(f, "cannot parse SVG: {e}"),
+            RenderError::ImageSize { width, height } => {
+                write!(f, "cannot allocate a {width}x{height} px image")
+            }
+            RenderError::Png(e) => write!(f, "cannot encode PNG: {e}"),
+            RenderError::Io(e) => write!(f, "cannot write image: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<StyleError> for RenderError {
+    fn from(e: StyleError) -> Self {
+        RenderError::Style(e)
+    }
+}
+
+impl From<std::io::Error> for RenderError {
+    fn from(e: std::io::Error) -> Self {
+        RenderError::Io(e)
+    }
+}
+
+/// The default `QrStyle::quiet_zone`.
+const DEFAULT_QUIET_ZONE: f64 = 2.0;
+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct QrStyle {
     pub color: String,
     pub background_color: String,
     pub shape: QrShape,
     /// output image width. The height is automatically calculated.
     pub width: u32,
-    /// Size of the quiet zone around the QR code, measured in terms of a single dot size.
+    /// Size of the quiet zone around the QR code, measured in terms of a
+    /// single dot size, 2.0 by default. Must be left at the default if
+    /// `quiet_zone_sides` is set.
     pub quiet_zone: f64,
+    /// Overrides `quiet_zone`, e.g. with a separate size on every side or the
+    /// size recommended for the symbol family, none by default. Rendering
+    /// fails with `StyleError::ConflictingQuietZone` if `quiet_zone` is
+    /// changed as well; `with_quiet_zone` keeps the two consistent.
+    pub quiet_zone_sides: Option<QuietZone>,
+    /// How the corners on the border of the symbol are drawn with `QrShape::Round`.
+    pub border_corners: BorderCorners,
+    /// Text printed under the symbol.
+    pub caption: Option<Caption>,
+    /// The smallest width of a module in pixels that `to_pixmap` renders,
+    /// 1.0 by default.
+    pub minimum_module_px: f64,
+    /// Rasterizes modules smaller than `minimum_module_px` instead of failing.
+    pub allow_tiny: bool,
+    /// The bleed and crop marks for print prepress, none by default.
+    pub prepress: Option<Prepress>,
 }
 
 impl QrStyle {
+    /// Constructs a new style without validating it. Invalid styles fail when
+    /// rendered to a pixmap, see `try_new`.
     pub fn new(
         color: impl Into<String>,
         background_color: impl Into<String>,
         shape: QrShape,
         width: u32,
-        quiet_zone: f64,
+        quiet_zone: impl Into<QuietZone>,
     ) -> Self {
         Self {
             color: color.into(),
             background_color: background_color.into(),
             shape,
             width,
-            quiet_zone,
+            ..Default::default()
         }
+        .with_quiet_zone(quiet_zone)
+    }
+
+    /// Sets the quiet zone, keeping `quiet_zone` in sync for a uniform one
+    /// and resetting it to the default otherwise.
+    ///
+    ///     use qrqrpar::{QrStyle, QuietZone};
+    ///
+    ///     let style = QrStyle::default().with_quiet_zone(4.0);
+    ///     assert_eq!((style.quiet_zone, style.quiet_zone_sides), (4.0, None));
+    ///
+    ///     let style = style.with_quiet_zone(QuietZone::Auto);
+    ///     assert_eq!((style.quiet_zone, style.quiet_zone_sides), (2.0, Some(QuietZone::Auto)));
+    ///
+    pub fn with_quiet_zone(mut self, quiet_zone: impl Into<QuietZone>) -> Self {
+        match quiet_zone.into() {
+            QuietZone::Uniform(modules) => {
+                self.quiet_zone = modules;
+                self.quiet_zone_sides = None;
+            }
+            sides => {
+                self.quiet_zone = DEFAULT_QUIET_ZONE;
+                self.quiet_zone_sides = Some(sides);
+            }
+        }
+        self
+    }
+
+    /// Returns the size of the quiet zone in modules for the given version, as
+    /// `[top, right, bottom, left]`.
+    pub fn resolve_quiet_zone(&self, version: Version) -> [f64; 4] {
+        self.quiet_zone_sides
+            .unwrap_or(QuietZone::Uniform(self.quiet_zone))
+            .resolve(version)
+    }
+
+    /// Constructs a new style, checking that it can be rendered.
+    ///
+    ///     use qrqrpar::{QrShape, QrStyle, StyleError};
+    ///
+    ///     assert!(QrStyle::try_new("#000000", "white", QrShape::Square, 720, 2.0).is_ok());
+    ///     assert_eq!(
+    ///         QrStyle::try_new("#000000", "white", QrShape::Square, 0, 2.0).unwrap_err(),
+    ///         StyleError::ZeroWidth
+    ///     );
+    ///
+    /// # Errors
+    ///
+    /// Returns a `StyleError` if the width is zero, if a color is not a valid
+    /// SVG color, if a side of the quiet zone is negative or not finite, if
+    /// both `quiet_zone_sides` and a non-default `quiet_zone` are set, if
+    /// the caption size is not positive, or if the minimum module size is
+    /// negative or not finite, or if the prepress bleed is negative or not
+    /// finite.
+    pub fn try_new(
+        color: impl Into<String>,
+        background_color: impl Into<String>,
+        shape: QrShape,
+        width: u32,
+        quiet_zone: impl Into<QuietZone>,
+    ) -> Result<Self, StyleError> {
+        let style = Self::new(color, background_color, shape, width, quiet_zone);
+        style.check()?;
+        Ok(style)
+    }
+
+    /// Checks everything which does not depend on the rendered symbol.
+    fn check(&self) -> Result<(), StyleError> {
+        if self.width == 0 {
+            return Err(StyleError::ZeroWidth);
+        }
+        for color in [&self.color, &self.background_color] {
+            let fallback = match color.split_once(" device-cmyk(") {
+                Some((fallback, _)) if Cmyk::parse(color).is_some() => fallback,
+                Some(_) => return Err(StyleError::InvalidColor(color.clone())),
+                None => color,
+            };
+            if fallback != "none" && fallback.parse::<svgtypes::Color>().is_err() {
+                return Err(StyleError::InvalidColor(color.clone()));
+            }
+        }
+        if self.quiet_zone_sides.is_some() && self.quiet_zone != DEFAULT_QUIET_ZONE {
+            return Err(StyleError::ConflictingQuietZone(self.quiet_zone));
+        }
+        let sides = match self
+            .quiet_zone_sides
+            .unwrap_or(QuietZone::Uniform(self.quiet_zone))
+        {
+            QuietZone::Auto => [0.0; 4],
+            QuietZone::Uniform(modules) => [modules; 4],
+            QuietZone::Sides {
+                top,
+                right,
+                bottom,
+                left,
+            } => [top, right, bottom, left],
+        };
+        for modules in sides {
+            if !modules.is_finite() {
+                return Err(StyleError::NonFiniteQuietZone(modules));
+            }
+            if modules < 0.0 {
+                return Err(StyleError::NegativeQuietZone(modules));
+            }
+        }
+        if let Some(caption) = &self.caption {
+            let size = caption.size_modules;
+            if !size.is_finite() || size <= 0.0 {
+                return Err(StyleError::InvalidCaptionSize(size));
+            }
+        }
+        let minimum = self.minimum_module_px;
+        if !minimum.is_finite() || minimum < 0.0 {
+            return Err(StyleError::InvalidMinimumModuleSize(minimum));
+        }
+        let bleed = self.bleed();
+        if !bleed.is_finite() || bleed < 0.0 {
+            return Err(StyleError::InvalidBleed(bleed));
+        }
+        Ok(())
+    }
+
+    /// The width of the prepress bleed in modules, or 0 without prepress.
+    fn bleed(&self) -> f64 {
+        self.prepress.map_or(0.0, |prepress| prepress.bleed_modules)
+    }
+
+    /// Returns the width of a module in pixels when rendering a symbol of the
+    /// given version.
+    ///
+    ///     use qrqrpar::{QrStyle, Version};
+    ///
+    ///     let style = QrStyle { width: 250, ..Default::default() };
+    ///     assert_eq!(style.module_px(Version::Normal(1)), 10.0);
+    ///
+    pub fn module_px(&self, version: Version) -> f64 {
+        let [_, right, _, left] = self.resolve_quiet_zone(version);
+        f64::from(self.width) / (f64::from(version.width()) + left + right)
+    }
+
+    /// Checks the style for rendering a symbol of the given version.
+    ///
+    ///     use qrqrpar::{QrStyle, QuietZone, StyleWarning, Version};
+    ///
+    ///     let style = QrStyle { quiet_zone: 1.0, ..Default::default() };
+    ///     assert_eq!(
+    ///         style.validate(Version::Normal(1)),
+    ///         Ok(vec![StyleWarning::QuietZoneBelowMinimum { modules: 1.0, minimum: 4 }])
+    ///     );
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `try_new`.
+    pub fn validate(&self, version: Version) -> Result<Vec<StyleWarning>, StyleError> {
+        self.check()?;
+        let sides = self.resolve_quiet_zone(version);
+        let minimum = version.recommended_quiet_zone();
+        let narrowest = sides.into_iter().fold(f64::INFINITY, f64::min);
+        let mut warnings = vec![];
+        if narrowest < f64::from(minimum) {
+            warnings.push(StyleWarning::QuietZoneBelowMinimum {
+                modules: narrowest,
+                minimum,
+            });
+        }
+        let module_px = self.module_px(version);
+        if module_px < self.minimum_module_px {
+            warnings.push(StyleWarning::ModuleTooSmall {
+                module_px,
+                minimum: self.minimum_module_px,
+            });
+        }
+        Ok(warnings)
     }
 }
 
@@ -67,18 +898,27 @@ impl Default for QrStyle {
             background_color: String::from("#ffffff"),
             shape: QrShape::Square,
             width: 720,
-            quiet_zone: 2.0,
+            quiet_zone: DEFAULT_QUIET_ZONE,
+            quiet_zone_sides: None,
+            border_corners: BorderCorners::default(),
+            caption: None,
+            minimum_module_px: 1.0,
+            allow_tiny: false,
+            prepress: None,
         }
     }
 }
 
 #[derive(Clone)]
 pub struct QrCode {
-    content: Vec<Color>,
+    content: Arc<Vec<Color>>,
     version: Version,
     ec_level: EcLevel,
     width: usize,
     height: usize,
+    codewords: Vec<u8>,
+    headers: bits::HeaderFlags,
+    encode_stats: Option<bits::BitStats>,
 }
 
 impl QrCode {
@@ -117,276 +957,4804 @@ impl QrCode {
         ec_level: EcLevel,
     ) -> QrResult<Self> {
         let bits = bits::encode_auto(data.as_ref(), ec_level)?;
-        Self::with_bits(bits, ec_level)
+        Self::with_auto_bits(bits, ec_level)
     }
-    /// Constructs a new QR code for the given version and error correction
-    /// level.
+
+    /// Constructs a new QR code which automatically encodes the given data at a
+    /// specific error correction level, in the smallest QR code version of the
+    /// range.
     ///
-    ///     use qrqrpar::{QrCode, Version, EcLevel};
+    ///     use qrqrpar::{QrCode, EcLevel, Version};
     ///
-    ///     let code = QrCode::with_version(b"Some data", Version::Normal(5), EcLevel::M).unwrap();
+    ///     let code = QrCode::with_version_constraints(b"Some data", EcLevel::M, 3..=10).unwrap();
+    ///     assert_eq!(code.version(), Version::Normal(3));
     ///
-    /// This method can also be used to generate Micro QR code.
+    /// # Errors
     ///
-    ///     use qrqrpar::{QrCode, Version, EcLevel};
+    /// Returns `Err(QrError::InvalidVersion)` if the range is empty or not
+    /// within 1 to 40, and `Err(QrError::DataTooLong)` if the data is too long
+    /// for the highest version of the range, see `bits::encode_auto_in_range`.
+    pub fn with_version_constraints<D: AsRef<[u8]>>(
+        data: D,
+        ec_level: EcLevel,
+        versions: std::ops::RangeInclusive<u8>,
+    ) -> QrResult<Self> {
+        let (min, max) = versions.into_inner();
+        let bits = bits::encode_auto_in_range(data.as_ref(), ec_level, min, max)?;
+        Self::with_auto_bits(bits, ec_level)
+    }
+
+    /// Constructs a new QR code which automatically encodes the given data in
+    /// the smallest QR code version at the given error correction level, and
+    /// then raises the level as far as the data still fits that version.
     ///
-    ///     let micro_code = QrCode::with_version(b"123", Version::Micro(1), EcLevel::L).unwrap();
+    /// The spare capacity of the version makes the symbol more robust for
+    /// free. Use `error_correction_level()` to tell the level used.
+    ///
+    ///     use qrqrpar::{EcLevel, QrCode, Version};
+    ///
+    ///     let code = QrCode::with_error_correction_level_boosted(b"HELLO WORLD", EcLevel::M).unwrap();
+    ///     assert_eq!(code.version(), Version::Normal(1));
+    ///     assert_eq!(code.error_correction_level(), EcLevel::Q);
     ///
     /// # Errors
     ///
     /// Returns error if the QR code cannot be constructed, e.g. when the data
-    /// is too long, or when the version and error correction level are
-    /// incompatible.
-    pub fn with_version<D: AsRef<[u8]>>(
+    /// is too long at the given error correction level.
+    pub fn with_error_correction_level_boosted<D: AsRef<[u8]>>(
         data: D,
-        version: Version,
         ec_level: EcLevel,
     ) -> QrResult<Self> {
-        let mut bits = bits::Bits::new(version);
-        bits.push_optimal_data(data.as_ref())?;
-        bits.push_terminator(ec_level)?;
-        Self::with_bits(bits, ec_level)
+        let data = data.as_ref();
+        let version = bits::encode_auto(data, ec_level)?.version();
+        Self::with_version_max_ec(data, version)
     }
 
-    /// Constructs a new QR code with encoded bits.
+    /// Constructs a new QR code which automatically encodes the given text as
+    /// UTF-8 at a specific error correction level.
     ///
-    /// Use this method only if there are very special need to manipulate the
-    /// raw bits before encoding.
+    /// The data starts with the ECI designator 26, telling readers that the
+    /// text is UTF-8 instead of letting them guess the character set of the
+    /// bytes. This method automatically chooses the smallest QR code holding
+    /// the ECI header as well.
     ///
-    /// * Encode data using specific character set with ECI
-    /// * Use the FNC1 modes
-    /// * Avoid the optimal segmentation algorithm
+    ///     use qrqrpar::{EcLevel, QrCode, Version};
+    ///
+    ///     let code = QrCode::new_utf8("日本語", EcLevel::M).unwrap();
+    ///     assert_eq!(code.version(), Version::Normal(1));
+    ///     assert_eq!(code.headers().eci, Some(26));
     ///
     /// # Errors
     ///
-    /// Returns error if the QR code cannot be constructed, e.g. when the bits
-    /// are too long, or when the version and error correction level are
-    /// incompatible.
-    pub fn with_bits(bits: bits::Bits, ec_level: EcLevel) -> QrResult<Self> {
-        let version = bits.version();
-        let data = bits.into_bytes();
-        let (encoded_data, ec_data) = ec::construct_codewords(&data, version, ec_level)?;
-        let mut canvas = canvas::Canvas::new(version, ec_level);
-        canvas.draw_all_functional_patterns();
-        canvas.draw_data(&encoded_data, &ec_data);
-        let canvas = canvas.apply_best_mask();
-        Ok(Self {
-            content: canvas.into_colors(),
-            version,
-            ec_level,
-            width: version.width() as usize,
-            height: version.height() as usize,
-        })
+    /// Returns `Err(QrError::TextTooLong)` with the character and byte counts
+    /// of the text if it is too long, see `bits::encode_auto_utf8`.
+    pub fn new_utf8(text: &str, ec_level: EcLevel) -> QrResult<Self> {
+        let bits = bits::encode_auto_utf8(text, ec_level)?;
+        Self::with_auto_bits(bits, ec_level)
     }
 
-    /// Gets the version of this QR code.
-    pub fn version(&self) -> Version {
-        self.version
+    /// Constructs a new QR code which encodes the whole data in the given mode
+    /// at a specific error correction level, instead of mixing the modes best
+    /// suited to each part of the data.
+    ///
+    /// Some readers mishandle symbols with several segments. This method
+    /// automatically chooses the smallest QR code holding the single segment.
+    ///
+    ///     use qrqrpar::types::Mode;
+    ///     use qrqrpar::{EcLevel, QrCode};
+    ///
+    ///     let code = QrCode::with_forced_mode(b"0123456789", Mode::Byte, EcLevel::M).unwrap();
+    ///     assert_eq!(code.encode_stats().unwrap().byte_bits, 80);
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidCharacter)` if the mode cannot encode the
+    /// data, and `Err(QrError::DataTooLong)` if the data is too long, see
+    /// `bits::encode_auto_forced`.
+    pub fn with_forced_mode<D: AsRef<[u8]>>(
+        data: D,
+        mode: types::Mode,
+        ec_level: EcLevel,
+    ) -> QrResult<Self> {
+        let bits = bits::encode_auto_forced(data.as_ref(), ec_level, mode)?;
+        Self::with_auto_bits(bits, ec_level)
     }
 
-    /// Gets the error correction level of this QR code.
-    pub fn error_correction_level(&self) -> EcLevel {
-        self.ec_level
+    /// Constructs a new Micro QR code which automatically encodes the given
+    /// data at a specific error correction level.
+    ///
+    /// This method automatically chooses the smallest Micro QR code supporting
+    /// the error correction level.
+    ///
+    ///     use qrqrpar::{QrCode, EcLevel, Version};
+    ///
+    ///     let code = QrCode::micro_with_error_correction_level(b"123 456", EcLevel::L).unwrap();
+    ///     assert_eq!(code.version(), Version::Micro(2));
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::EcLevelUnavailable)` if no Micro QR code version
+    /// supports the error correction level, i.e. for `EcLevel::H`, and
+    /// `Err(QrError::DataTooLong)` if the data does not fit at the level
+    /// otherwise.
+    pub fn micro_with_error_correction_level<D: AsRef<[u8]>>(
+        data: D,
+        ec_level: EcLevel,
+    ) -> QrResult<Self> {
+        let bits = bits::encode_auto_micro(data.as_ref(), ec_level)?;
+        Self::with_auto_bits(bits, ec_level)
     }
 
-    /// Gets the number of modules per side, i.e. the width of this QR code.
+    /// Constructs a new Micro QR code which automatically encodes the given
+    /// data.
     ///
-    /// The width here does not contain the quiet zone paddings.
-    pub fn width(&self) -> usize {
-        self.width
+    /// This method uses the "low" error correction level, the only one of
+    /// version M1, and automatically chooses the smallest Micro QR code.
+    ///
+    ///     use qrqrpar::{QrCode, Version};
+    ///
+    ///     let code = QrCode::micro(b"01234").unwrap();
+    ///     assert_eq!(code.version(), Version::Micro(1));
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::DataTooLong)` if the data is too long to fit even
+    /// the highest Micro QR code version.
+    pub fn micro<D: AsRef<[u8]>>(data: D) -> QrResult<Self> {
+        Self::micro_with_error_correction_level(data, EcLevel::L)
     }
 
-    /// Gets the number of modules per side, i.e. the height of this QR code.
+    /// Constructs a new Micro QR code which automatically encodes the given
+    /// data at a specific error correction level.
     ///
-    /// The height here does not contain the quiet zone paddings.
-    pub fn height(&self) -> usize {
+    /// This is an alias of `micro_with_error_correction_level`, named like
+    /// `rmqr_with_options`.
+    ///
+    ///     use qrqrpar::{QrCode, EcLevel, Version};
+    ///     use qrqrpar::types::QrError;
+    ///
+    ///     let code = QrCode::micro_with_options(b"HELLO", EcLevel::M).unwrap();
+    ///     assert_eq!(code.version(), Version::Micro(2));
+    ///
+    ///     // 30 digits fit M4-L, and M4 supports Q, but M4-Q only holds 21.
+    ///     let result = QrCode::micro_with_options([b'7'; 30], EcLevel::Q);
+    ///     assert_eq!(result.err(), Some(QrError::DataTooLong));
+    ///     let result = QrCode::micro_with_options(b"1", EcLevel::H);
+    ///     assert!(matches!(result, Err(QrError::EcLevelUnavailable(_))));
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `micro_with_error_correction_level`.
+    pub fn micro_with_options<D: AsRef<[u8]>>(data: D, ec_level: EcLevel) -> QrResult<Self> {
+        Self::micro_with_error_correction_level(data, ec_level)
+    }
+
+    /// Constructs a new QR code holding binary data converted with the given
+    /// encoding, e.g. base45 for readers expecting it.
+    ///
+    /// This method automatically chooses the smallest QR code, as
+    /// `with_error_correction_level` does for the converted data. Base45 and
+    /// hexadecimal text are encoded in alphanumeric mode, except for runs of
+    /// digits which the numeric mode stores more compactly.
+    ///
+    ///     use qrqrpar::payload::binary::BinaryEncoding;
+    ///     use qrqrpar::{EcLevel, QrCode, Version};
+    ///
+    ///     let hash = [0xa5; 32];
+    ///     let code = QrCode::binary(&hash, BinaryEncoding::Base45, EcLevel::M).unwrap();
+    ///     assert_eq!(code.version(), Version::Normal(3));
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, e.g. when the
+    /// converted data is too long.
+    pub fn binary(
+        data: &[u8],
+        encoding: payload::binary::BinaryEncoding,
+        ec_level: EcLevel,
+    ) -> QrResult<Self> {
+        Self::with_error_correction_level(encoding.apply(data), ec_level)
+    }
+
+    /// Constructs a new QR code holding GS1 data, i.e. application identifiers
+    /// (AIs) and their values, in FNC1 first position mode.
+    ///
+    /// The values of the AIs without a predefined length are ended by group
+    /// separators, encoded as `%` in alphanumeric mode and as 0x1d in byte
+    /// mode, see `payload::gs1`. This method automatically chooses the
+    /// smallest QR code.
+    ///
+    ///     use qrqrpar::{EcLevel, QrCode, Version};
+    ///
+    ///     let code = QrCode::gs1(&[("01", "09501101530003"), ("10", "AB-123")], EcLevel::M).unwrap();
+    ///     assert_eq!(code.version(), Version::Normal(1));
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidCharacter)` if an AI is not supported or
+    /// a value does not match the format of its AI, and
+    /// `Err(QrError::TextTooLong)` with the length of the element string if
+    /// the elements are too long.
+    pub fn gs1(elements: &[(&str, &str)], ec_level: EcLevel) -> QrResult<Self> {
+        let bits = payload::gs1::encode(elements, ec_level)?;
+        Self::with_auto_bits(bits, ec_level)
+    }
+
+    /// Constructs a new QR code for the given version and error correction
+    /// level.
+    ///
+    ///     use qrqrpar::{QrCode, Version, EcLevel};
+    ///
+    ///     let code = QrCode::with_version(b"Some data", Version::Normal(5), EcLevel::M).unwrap();
+    ///
+    /// This method can also be used to generate Micro QR code.
+    ///
+    ///     use qrqrpar::{QrCode, Version, EcLevel};
+    ///
+    ///     let micro_code = QrCode::with_version(b"123", Version::Micro(1), EcLevel::L).unwrap();
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, e.g. when the data
+    /// is too long, or when the version and error correction level are
+    /// incompatible.
+    pub fn with_version<D: AsRef<[u8]>>(
+        data: D,
+        version: Version,
+        ec_level: EcLevel,
+    ) -> QrResult<Self> {
+        let mut bits = bits::Bits::new(version);
+        bits.push_optimal_data(data.as_ref())?;
+        bits.push_terminator(ec_level)?;
+        Self::with_auto_bits(bits, ec_level)
+    }
+
+    /// Constructs a new QR code for the given version and error correction
+    /// level, or for the next larger version of the same family which holds
+    /// the data.
+    ///
+    ///     use qrqrpar::{QrCode, Version, EcLevel};
+    ///
+    ///     let code = QrCode::with_version_or_larger([b'a'; 20], Version::Normal(1), EcLevel::M).unwrap();
+    ///     assert_eq!(code.version(), Version::Normal(2));
+    ///
+    /// QR and Micro QR code versions grow one by one. rMQR code versions grow
+    /// by area, ties going to the lower version, even though a larger rMQR
+    /// code does not always hold more data, e.g. R7x59 holds less than
+    /// R11x27.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::DataTooLong)` if no version from the given one
+    /// holds the data, `Err(QrError::InvalidVersion)` if the given version
+    /// does not exist, and `Err(QrError::EcLevelUnavailable)` if it does not
+    /// support the error correction level.
+    pub fn with_version_or_larger<D: AsRef<[u8]>>(
+        data: D,
+        version: Version,
+        ec_level: EcLevel,
+    ) -> QrResult<Self> {
+        if !version.is_valid() {
+            return Err(types::QrError::InvalidVersion);
+        }
+        version
+            .check_ec_level(ec_level)
+            .map_err(types::QrError::EcLevelUnavailable)?;
+        let data = data.as_ref();
+        let versions = match version {
+            Version::Normal(n) => (n..=40).map(Version::Normal).collect(),
+            Version::Micro(m) => (m..=4).map(Version::Micro).collect(),
+            Version::Rmqr(..) => {
+                let mut versions = Version::rmqr_all();
+                versions.sort_by_key(|v| (v.area(), v.height()));
+                versions
+                    .into_iter()
+                    .skip_while(|v| *v != version)
+                    .collect::<Vec<_>>()
+            }
+        };
+        for version in versions {
+            match Self::with_version(data, version, ec_level) {
+                Err(types::QrError::DataTooLong | types::QrError::ModeUnavailable { .. }) => {}
+                result => return result,
+            }
+        }
+        Err(types::QrError::DataTooLong)
+    }
+
+    /// Constructs a new QR code for the given version and error correction
+    /// level, encoding the data in the given segments instead of the ones
+    /// chosen by the optimizer.
+    ///
+    ///     use qrqrpar::Segment;
+    ///     use qrqrpar::types::Mode;
+    ///     use qrqrpar::{EcLevel, QrCode, Version};
+    ///
+    ///     let segments = [
+    ///         Segment { mode: Mode::Byte, begin: 0, end: 4 },
+    ///         Segment { mode: Mode::Numeric, begin: 4, end: 10 },
+    ///     ];
+    ///     let code =
+    ///         QrCode::with_segments(b"ID: 123456", &segments, Version::Normal(1), EcLevel::M)
+    ///             .unwrap();
+    ///     assert_eq!(code.encode_stats().unwrap().numeric_bits, 20);
+    ///
+    /// The segments must cover the data in order, each one starting where the
+    /// previous one ends.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidSegment)` if the segments do not cover the
+    /// data in order, and `Err(QrError::InvalidCharacter)` if a segment refers
+    /// to characters its mode cannot encode. Returns other errors if the QR
+    /// code cannot be constructed, e.g. when the data is too long, or when the
+    /// version does not support a mode.
+    pub fn with_segments(
+        data: &[u8],
+        segments: &[internals::coding::Segment],
+        version: Version,
+        ec_level: EcLevel,
+    ) -> QrResult<Self> {
+        let mut end = 0;
+        for segment in segments {
+            if segment.begin != end || segment.end < segment.begin {
+                return Err(types::QrError::InvalidSegment);
+            }
+            end = segment.end;
+        }
+        if end != data.len() {
+            return Err(types::QrError::InvalidSegment);
+        }
+        let mut bits = bits::Bits::new(version);
+        bits.push_segments(data, segments.iter().copied())?;
+        bits.push_terminator(ec_level)?;
+        Self::with_auto_bits(bits, ec_level)
+    }
+
+    /// Constructs a new QR code for the given version and error correction
+    /// level, checking that the version is not much larger than the data
+    /// needs.
+    ///
+    /// Returns the code, and an `OversizedVersion` if the policy is
+    /// `SizePolicy::WarnIfOversized` and the version is oversized, so that
+    /// pinned versions which no longer match the payloads can be noticed
+    /// before they make the labels needlessly dense.
+    ///
+    ///     use qrqrpar::{EcLevel, QrCode, SizePolicy, Version};
+    ///
+    ///     let policy = SizePolicy::WarnIfOversized(2);
+    ///     let (code, warning) =
+    ///         QrCode::with_version_checked(b"Some data", Version::Normal(5), EcLevel::M, policy)
+    ///             .unwrap();
+    ///     assert_eq!(code.version(), Version::Normal(5));
+    ///     let warning = warning.unwrap();
+    ///     assert_eq!(warning.minimum, Version::Normal(1));
+    ///     assert_eq!(warning.gap, 4);
+    ///
+    /// The gap is the number of smaller versions of the same type, i.e. QR,
+    /// Micro QR or rMQR, holding the data. For QR and Micro QR codes, it is
+    /// the difference between the version numbers.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, as `with_version`
+    /// does, or `Err(QrError::VersionOversized)` with the smallest version
+    /// holding the data if the policy is `SizePolicy::ErrorIfOversized` and
+    /// the version is oversized.
+    pub fn with_version_checked<D: AsRef<[u8]>>(
+        data: D,
+        version: Version,
+        ec_level: EcLevel,
+        policy: SizePolicy,
+    ) -> QrResult<(Self, Option<OversizedVersion>)> {
+        let data = data.as_ref();
+        let code = Self::with_version(data, version, ec_level)?;
+        let threshold = match policy {
+            SizePolicy::Exact => return Ok((code, None)),
+            SizePolicy::WarnIfOversized(threshold) | SizePolicy::ErrorIfOversized(threshold) => {
+                threshold
+            }
+        };
+        let smaller = bits::smaller_fitting_versions(data, version, ec_level);
+        if smaller.len() <= threshold {
+            return Ok((code, None));
+        }
+        if let SizePolicy::ErrorIfOversized(_) = policy {
+            return Err(types::QrError::VersionOversized {
+                requested: version,
+                minimal: smaller[0],
+            });
+        }
+        let warning = OversizedVersion {
+            version,
+            minimum: smaller[0],
+            gap: smaller.len(),
+        };
+        Ok((code, Some(warning)))
+    }
+
+    /// Constructs a new QR code for the given version, using the strongest
+    /// error correction level at which the data still fits.
+    ///
+    ///     use qrqrpar::{QrCode, Version, EcLevel};
+    ///
+    ///     let code = QrCode::with_version_max_ec(b"12345", Version::Normal(1)).unwrap();
+    ///     assert_eq!(code.error_correction_level(), EcLevel::H);
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, e.g. when the data
+    /// is too long even at the weakest error correction level.
+    pub fn with_version_max_ec<D: AsRef<[u8]>>(data: D, version: Version) -> QrResult<Self> {
+        let ec_level = *fitting_ec_levels(data.as_ref(), version)?
+            .first()
+            .ok_or(types::QrError::DataTooLong)?;
+        Self::with_version(data, version, ec_level)
+    }
+
+    /// Constructs a new QR code for the given version and error correction
+    /// level, truncating the data if it is too long.
+    ///
+    /// The longest prefix of the data that fits is kept, followed by `marker`
+    /// (e.g. `"…"`) if the data has been truncated. If the data is valid UTF-8,
+    /// it is only cut at character boundaries. Returns the code and the number
+    /// of bytes of `data` which have been kept.
+    ///
+    ///     use qrqrpar::{QrCode, Version, EcLevel};
+    ///
+    ///     let data = "https://example.com/ A long note which does not fit";
+    ///     let (code, kept) = QrCode::with_version_truncating(
+    ///         data,
+    ///         Version::Normal(1),
+    ///         EcLevel::L,
+    ///         Some("…".as_bytes()),
+    ///     )
+    ///     .unwrap();
+    ///     assert_eq!(&data[..kept], "https://exampl");
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, e.g. when even the
+    /// marker alone does not fit, or when the version and error correction
+    /// level are incompatible.
+    pub fn with_version_truncating<D: AsRef<[u8]>>(
+        data: D,
+        version: Version,
+        ec_level: EcLevel,
+        marker: Option<&[u8]>,
+    ) -> QrResult<(Self, usize)> {
+        let data = data.as_ref();
+        // The data is segmented once, and each truncation continues the
+        // segmentation of its prefix with the marker.
+        let mut whole = internals::coding::CharSegmentation::new(version);
+        whole.extend(data);
+        if bits::segments_fit(&whole.segments(), version, ec_level)? {
+            return Ok((Self::with_version(data, version, ec_level)?, data.len()));
+        }
+
+        let marker = marker.unwrap_or_default();
+        let cut_points = match core::str::from_utf8(data) {
+            Ok(s) => s.char_indices().map(|(i, _)| i).collect::<Vec<_>>(),
+            Err(_) => (0..data.len()).collect(),
+        };
+        let truncated = |kept: usize| [&data[..kept], marker].concat();
+
+        // The encoded length grows with the prefix, so binary search for the
+        // last cut point which still fits.
+        let fitting_count = cut_points.partition_point(|&kept| {
+            let mut segmentation = whole.prefix(kept);
+            segmentation.extend(marker);
+            bits::segments_fit(&segmentation.segments(), version, ec_level).unwrap_or(false)
+        });
+        let kept = *cut_points[..fitting_count]
+            .last()
+            .ok_or(types::QrError::DataTooLong)?;
+        let code = Self::with_version(truncated(kept), version, ec_level)?;
+        Ok((code, kept))
+    }
+
+    /// Constructs a new QR code storing a part of a structured append sequence,
+    /// in the smallest QR code version.
+    ///
+    /// The parity in `part` must be computed over the whole message with
+    /// `structured_append::parity`.
+    ///
+    ///     use qrqrpar::{QrCode, EcLevel};
+    ///     use qrqrpar::structured_append::{parity, validate_set, PartInfo};
+    ///
+    ///     let message = b"Hello, structured append!";
+    ///     let parity = parity(message);
+    ///     let codes = message
+    ///         .chunks(13)
+    ///         .enumerate()
+    ///         .map(|(index, chunk)| {
+    ///             let part = PartInfo { index: index as u8, total: 2, parity };
+    ///             QrCode::with_structured_append(chunk, part, EcLevel::M).unwrap()
+    ///         })
+    ///         .collect::<Vec<_>>();
+    ///     assert!(validate_set(&codes).is_ok());
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidStructuredAppend)` if the total is not
+    /// between 1 and 16 or if the index is not less than the total, and
+    /// `Err(QrError::DataTooLong)` if the data is too long.
+    pub fn with_structured_append<D: AsRef<[u8]>>(
+        data: D,
+        part: structured_append::PartInfo,
+        ec_level: EcLevel,
+    ) -> QrResult<Self> {
+        let bits = structured_append::encode_part(data.as_ref(), part, ec_level)?;
+        Self::with_auto_bits(bits, ec_level)
+    }
+
+    /// Constructs the QR codes of a structured append sequence holding the
+    /// data, for data too long for a single QR code.
+    ///
+    ///     use qrqrpar::{EcLevel, QrCode, Version};
+    ///     use qrqrpar::structured_append::validate_set;
+    ///
+    ///     let data = vec![b'x'; 4000];
+    ///     let codes = QrCode::new_multi(&data, EcLevel::L, 4).unwrap();
+    ///     assert!(validate_set(&codes).is_ok());
+    ///     // The smallest version holding the data in 4 symbols is chosen.
+    ///     assert_eq!(codes.len(), 4);
+    ///     assert!(codes[0].version() < Version::Normal(40));
+    ///     assert_eq!(QrCode::new_multi(&data, EcLevel::L, 2).unwrap().len(), 2);
+    ///
+    ///     let codes = QrCode::new_multi(b"Some data", EcLevel::L, 4).unwrap();
+    ///     assert_eq!(codes[0].part_info(), None);
+    ///
+    /// Data which fits a single QR code is encoded like
+    /// `with_error_correction_level`, without structured append header.
+    /// Otherwise the data is split into as few parts as the smallest version
+    /// holding it in at most `max_symbols` symbols allows, never within a
+    /// UTF-8 or Kanji character, and every part is encoded with
+    /// `with_structured_append` and the parity of the whole data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::DataTooLong)` if the data needs more than
+    /// `max_symbols` symbols, or more than 16 which is the maximum of a
+    /// sequence.
+    pub fn new_multi<D: AsRef<[u8]>>(
+        data: D,
+        ec_level: EcLevel,
+        max_symbols: usize,
+    ) -> QrResult<Vec<Self>> {
+        let data = data.as_ref();
+        match Self::with_error_correction_level(data, ec_level) {
+            Err(types::QrError::DataTooLong) => {}
+            code => return code.map(|code| vec![code]),
+        }
+        let parts = structured_append::split(data, ec_level, max_symbols)?;
+        let (total, parity) = (parts.len() as u8, structured_append::parity(data));
+        parts
+            .iter()
+            .enumerate()
+            .map(|(index, part)| {
+                let index = index as u8;
+                let part_info = structured_append::PartInfo {
+                    index,
+                    total,
+                    parity,
+                };
+                Self::with_structured_append(part, part_info, ec_level)
+            })
+            .collect()
+    }
+
+    /// Constructs a new QR code with encoded bits.
+    ///
+    /// Use this method only if there are very special need to manipulate the
+    /// raw bits before encoding.
+    ///
+    /// * Encode data using specific character set with ECI, see
+    ///   `Bits::push_eci_designator` (`new_utf8` covers UTF-8)
+    /// * Use the FNC1 modes, e.g. for GS1 data, see
+    ///   `Bits::push_fnc1_first_position`
+    /// * Avoid the optimal segmentation algorithm
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, e.g. when the bits
+    /// are too long, or when the version and error correction level are
+    /// incompatible.
+    pub fn with_bits(bits: bits::Bits, ec_level: EcLevel) -> QrResult<Self> {
+        Encoder::new().encode_bits(bits, ec_level)
+    }
+
+    /// Same as `with_bits` for the bits of the high-level constructors, which
+    /// also records `encode_stats`.
+    fn with_auto_bits(bits: bits::Bits, ec_level: EcLevel) -> QrResult<Self> {
+        Encoder::new().encode_auto_bits(bits, ec_level)
+    }
+
+    /// Draws the functional patterns and the codewords on the canvas, which is
+    /// cleared first.
+    fn draw_canvas(
+        canvas: &mut internals::canvas::Canvas,
+        version: Version,
+        ec_level: EcLevel,
+        encoded_data: &[u8],
+        ec_data: &[u8],
+    ) {
+        canvas.reset(version, ec_level);
+        canvas.draw_all_functional_patterns();
+        canvas.draw_data(encoded_data, ec_data);
+    }
+
+    /// Returns a canvas containing only the functional patterns of this code.
+    fn functional_canvas(&self) -> internals::canvas::Canvas {
+        let mut canvas = internals::canvas::Canvas::new(self.version, self.ec_level);
+        canvas.draw_all_functional_patterns();
+        canvas
+    }
+
+    /// Constructs the QR code from a canvas which has been masked.
+    fn from_masked_canvas(
+        canvas: &internals::canvas::Canvas,
+        version: Version,
+        ec_level: EcLevel,
+        encoded_data: Vec<u8>,
+        ec_data: Vec<u8>,
+    ) -> Self {
+        Self {
+            content: Arc::new(canvas.to_colors()),
+            version,
+            ec_level,
+            width: version.width() as usize,
+            height: version.height() as usize,
+            codewords: [encoded_data, ec_data].concat(),
+            headers: bits::HeaderFlags::default(),
+            encode_stats: None,
+        }
+    }
+
+    /// Gets the structured append position of this QR code, if it has been
+    /// constructed with `with_structured_append`.
+    pub fn part_info(&self) -> Option<structured_append::PartInfo> {
+        self.headers.structured_append
+    }
+
+    /// Gets the headers of the bits this QR code was constructed from, e.g. to
+    /// label GS1 symbols.
+    ///
+    ///     use qrqrpar::bits::{Bits, Fnc1Kind};
+    ///     use qrqrpar::{EcLevel, QrCode, Version};
+    ///
+    ///     let mut bits = Bits::new(Version::Normal(2));
+    ///     bits.push_fnc1_first_position().unwrap();
+    ///     bits.push_numeric_data(b"0104912345123459").unwrap();
+    ///     bits.push_terminator(EcLevel::M).unwrap();
+    ///     let code = QrCode::with_bits(bits, EcLevel::M).unwrap();
+    ///     assert_eq!(code.headers().fnc1, Some(Fnc1Kind::FirstPosition));
+    ///
+    /// Only the headers recorded by `Bits::headers` are known, so headers
+    /// pushed as raw numbers are missing. `bits::inspect` reads them from the
+    /// encoded data instead.
+    pub fn headers(&self) -> bits::HeaderFlags {
+        self.headers
+    }
+
+    /// Gets how the bits of this QR code are spent, e.g. to log the mode mix
+    /// and the padding of every generated code.
+    ///
+    ///     use qrqrpar::{EcLevel, QrCode};
+    ///
+    ///     let code = QrCode::with_error_correction_level(b"01234567", EcLevel::H).unwrap();
+    ///     let stats = code.encode_stats().unwrap();
+    ///     assert_eq!(stats.numeric_bits, 27);
+    ///     assert_eq!(stats.header_bits, 4 + 10);
+    ///     assert_eq!(stats.padding_bits, 72 - 27 - 14);
+    ///     assert_eq!(stats.ec_bits, 17 * 8);
+    ///     assert_eq!(stats.total_bits(), 26 * 8);
+    ///
+    /// The statistics are recorded by the constructors which encode the data
+    /// themselves. Codes constructed with `with_bits` return `None`, since
+    /// their bits may have been pushed as raw numbers.
+    pub fn encode_stats(&self) -> Option<bits::BitStats> {
+        self.encode_stats
+    }
+
+    /// Gets the version of this QR code.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Gets the error correction level of this QR code.
+    pub fn error_correction_level(&self) -> EcLevel {
+        self.ec_level
+    }
+
+    /// Gets the number of modules per side, i.e. the width of this QR code.
+    ///
+    /// The width here does not contain the quiet zone paddings.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Gets the number of modules per side, i.e. the height of this QR code.
+    ///
+    /// The height here does not contain the quiet zone paddings.
+    pub fn height(&self) -> usize {
         self.height
     }
 
-    /// Converts the QR code to a vector of colors.
-    pub fn to_colors(&self) -> Vec<Color> {
-        self.content.clone()
+    /// Gets the final codeword sequence placed in the symbol, i.e. the
+    /// interleaved data codewords followed by the interleaved error correction
+    /// codewords.
+    pub fn final_codewords(&self) -> Vec<u8> {
+        self.codewords.clone()
+    }
+
+    /// Formats the final codewords as a hex dump, 16 bytes per line, each line
+    /// prefixed with the offset of its first byte.
+    ///
+    ///     use qrqrpar::{QrCode, Version, EcLevel};
+    ///
+    ///     let code = QrCode::with_version(b"01234567", Version::Normal(1), EcLevel::M).unwrap();
+    ///     assert_eq!(
+    ///         code.to_hex_dump(),
+    ///         "00000000: 10 20 0c 56 61 80 ec 11 ec 11 ec 11 ec 11 ec 11\n\
+    ///          00000010: a5 24 d4 c1 ed 36 c7 87 2c 55\n"
+    ///     );
+    pub fn to_hex_dump(&self) -> String {
+        let mut s = String::new();
+        for (i, line) in self.codewords.chunks(16).enumerate() {
+            let bytes = line
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            s.push_str(&format!("{:08x}: {bytes}\n", i * 16));
+        }
+        s
+    }
+
+    /// Gets the color of the module at the given point.
+    ///
+    ///     use qrqrpar::{Color, QrCode};
+    ///
+    ///     let code = QrCode::new(b"Some data").unwrap();
+    ///     assert_eq!(code.get((0, 0)), Some(Color::Dark));
+    ///     assert_eq!(code.get((21, 0)), None);
+    ///
+    /// Returns `None` if the point lies outside the symbol.
+    pub fn get(&self, point: impl Into<geometry::ModulePoint>) -> Option<Color> {
+        let geometry::ModulePoint { x, y } = point.into();
+        let (x, y) = (x as usize, y as usize);
+        if x < self.width && y < self.height {
+            Some(self.content[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the data modules in the order the codeword bits are placed
+    /// into them, from the most significant bit of the first codeword to the
+    /// remainder bits.
+    ///
+    ///     use qrqrpar::QrCode;
+    ///
+    ///     let code = QrCode::new(b"Some data").unwrap();
+    ///     let order = code.placement_order();
+    ///     assert_eq!((order[0].x, order[0].y), (20, 20));
+    ///     assert_eq!(order.len(), 26 * 8);
+    ///
+    pub fn placement_order(&self) -> Vec<geometry::ModulePoint> {
+        self.functional_canvas()
+            .data_module_order()
+            .into_iter()
+            .map(|(x, y)| geometry::ModulePoint::new(x as u32, y as u32))
+            .collect()
+    }
+
+    /// Constructs a view of this code showing all the functional patterns and
+    /// only the first `revealed` data modules of `placement_order`, the
+    /// others being light.
+    ///
+    /// The view shares the modules of this code. Use `PartialQr::with_revealed`
+    /// to create the other frames of an animation, which also shares the
+    /// placement order.
+    ///
+    ///     use qrqrpar::{QrCode, Renderable};
+    ///
+    ///     let code = QrCode::new(b"Some data").unwrap();
+    ///     let partial = code.partial(10);
+    ///     assert_eq!(partial.total(), 26 * 8);
+    ///     assert!(partial.to_svg(&Default::default()).len() < code.to_svg(&Default::default()).len());
+    ///
+    pub fn partial(&self, revealed: usize) -> PartialQr {
+        let placement_order = self
+            .functional_canvas()
+            .data_module_order()
+            .into_iter()
+            .map(|(x, y)| (x as usize, y as usize));
+        PartialQr::new(
+            Arc::clone(&self.content),
+            placement_order,
+            revealed,
+            self.version,
+            self.width,
+            self.height,
+        )
+    }
+
+    /// Converts the QR code to a vector of colors.
+    pub fn to_colors(&self) -> Vec<Color> {
+        self.content.to_vec()
+    }
+
+    /// Converts the QR code to a vector of colors.
+    pub fn into_colors(self) -> Vec<Color> {
+        Arc::unwrap_or_clone(self.content)
+    }
+
+    /// Converts the QR code into a human-readable string.
+    ///
+    /// Every module is one character, without quiet zone. The widths of the
+    /// characters are not checked, see `to_text` to draw modules with glyphs
+    /// of any width.
+    pub fn to_str(&self, dark: char, light: char) -> String {
+        let mut s = String::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let color = self.content[y * self.width + x];
+                s.push(if color == Color::Dark { dark } else { light });
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Returns the color of the module at (x, y), treating every module outside
+    /// the symbol as part of a quiet zone of the given width.
+    fn color_with_quiet_zone(&self, x: isize, y: isize, quiet_zone: isize) -> Color {
+        let (x, y) = (x - quiet_zone, y - quiet_zone);
+        if x < 0 || y < 0 || x >= self.width as isize || y >= self.height as isize {
+            Color::Light
+        } else {
+            self.content[y as usize * self.width + x as usize]
+        }
+    }
+
+    /// Returns the given quiet zone, or the recommended one of this version if
+    /// `None`.
+    fn resolve_text_quiet_zone(&self, quiet_zone: Option<u8>) -> isize {
+        quiet_zone.unwrap_or_else(|| self.version.recommended_quiet_zone()) as isize
+    }
+
+    /// Converts the QR code into a string drawing every module with the dark
+    /// or light glyph, e.g. two full blocks so that the modules look square in
+    /// a terminal.
+    ///
+    ///     use qrqrpar::{GlyphError, QrCode};
+    ///
+    ///     let code = QrCode::new(b"01234567").unwrap();
+    ///     let text = code.to_text("██", "  ", Some(0)).unwrap();
+    ///     assert!(text.starts_with("██████████████  "));
+    ///
+    ///     // The ideographic space is as wide as two full blocks.
+    ///     assert!(code.to_text("██", "\u{3000}", None).is_ok());
+    ///     assert_eq!(
+    ///         code.to_text("█", "\u{3000}", None),
+    ///         Err(GlyphError::WidthMismatch { dark: 1, light: 2 })
+    ///     );
+    ///
+    /// The glyphs may be several characters long, and their display widths
+    /// are counted in terminal columns rather than characters, with the wide
+    /// East Asian characters taking two columns. So every row takes the same
+    /// number of columns, `display width × (width + 2 × quiet zone)`.
+    ///
+    /// The quiet zone defaults to `Version::recommended_quiet_zone` if `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GlyphError::ZeroWidth` if a glyph takes no column, e.g. is
+    /// empty, and `GlyphError::WidthMismatch` if the glyphs take different
+    /// numbers of columns.
+    pub fn to_text(
+        &self,
+        dark: &str,
+        light: &str,
+        quiet_zone: Option<u8>,
+    ) -> Result<String, GlyphError> {
+        let (dark_width, light_width) = (display_width(dark), display_width(light));
+        if dark_width == 0 || light_width == 0 {
+            return Err(GlyphError::ZeroWidth);
+        }
+        if dark_width != light_width {
+            return Err(GlyphError::WidthMismatch {
+                dark: dark_width,
+                light: light_width,
+            });
+        }
+        let quiet = self.resolve_text_quiet_zone(quiet_zone);
+        let total_width = self.width as isize + quiet * 2;
+        let total_height = self.height as isize + quiet * 2;
+        let mut s = String::new();
+        for y in 0..total_height {
+            for x in 0..total_width {
+                let color = self.color_with_quiet_zone(x, y, quiet);
+                s.push_str(color.select(dark, light));
+            }
+            s.push('\n');
+        }
+        Ok(s)
+    }
+
+    /// Converts the QR code into a string using Unicode half blocks, so that
+    /// every character represents two vertically stacked modules.
+    ///
+    /// The quiet zone defaults to `Version::recommended_quiet_zone` if `None`.
+    /// Dark modules are drawn with the block characters, so the text should
+    /// be printed dark on light.
+    pub fn to_unicode(&self, quiet_zone: Option<u8>) -> String {
+        let quiet = self.resolve_text_quiet_zone(quiet_zone);
+        let total_width = self.width as isize + quiet * 2;
+        let total_height = self.height as isize + quiet * 2;
+        let mut s = String::new();
+        for y in (0..total_height).step_by(2) {
+            for x in 0..total_width {
+                let top = self.color_with_quiet_zone(x, y, quiet);
+                let bottom = self.color_with_quiet_zone(x, y + 1, quiet);
+                s.push(match (top, bottom) {
+                    (Color::Dark, Color::Dark) => '█',
+                    (Color::Dark, Color::Light) => '▀',
+                    (Color::Light, Color::Dark) => '▄',
+                    (Color::Light, Color::Light) => ' ',
+                });
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Converts the QR code into a string of ANSI escape sequences which draws
+    /// every module as two black or white background-colored spaces.
+    ///
+    /// The quiet zone defaults to `Version::recommended_quiet_zone` if `None`.
+    pub fn to_ansi(&self, quiet_zone: Option<u8>) -> String {
+        let quiet = self.resolve_text_quiet_zone(quiet_zone);
+        let total_width = self.width as isize + quiet * 2;
+        let total_height = self.height as isize + quiet * 2;
+        let mut s = String::new();
+        for y in 0..total_height {
+            let mut current = None;
+            for x in 0..total_width {
+                let color = self.color_with_quiet_zone(x, y, quiet);
+                if current != Some(color) {
+                    s.push_str(match color {
+                        Color::Dark => "\x1b[40m",
+                        Color::Light => "\x1b[47m",
+                    });
+                    current = Some(color);
+                }
+                s.push_str("  ");
+            }
+            s.push_str("\x1b[0m\n");
+        }
+        s
+    }
+
+    /// Converts the QR code into a string using Unicode braille patterns, so
+    /// that every character represents a block of 2x4 modules.
+    ///
+    /// The quiet zone defaults to `Version::recommended_quiet_zone` if `None`.
+    /// Dark modules are drawn as raised dots.
+    pub fn to_braille(&self, quiet_zone: Option<u8>) -> String {
+        // Dot bits of a braille pattern, indexed by [y][x] inside the cell.
+        const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+        let quiet = self.resolve_text_quiet_zone(quiet_zone);
+        let total_width = self.width as isize + quiet * 2;
+        let total_height = self.height as isize + quiet * 2;
+        let mut s = String::new();
+        for y in (0..total_height).step_by(4) {
+            for x in (0..total_width).step_by(2) {
+                let mut pattern = 0;
+                for (dy, row) in DOTS.iter().enumerate() {
+                    for (dx, dot) in row.iter().enumerate() {
+                        let color =
+                            self.color_with_quiet_zone(x + dx as isize, y + dy as isize, quiet);
+                        if color == Color::Dark {
+                            pattern |= dot;
+                        }
+                    }
+                }
+                s.push(char::from_u32(0x2800 + pattern).unwrap());
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Constructs a new rMQR code which automatically encodes the given data.
+    /// This method uses the "medium" error correction level and automatically
+    ///
+    ///     use qrqrpar::QrCode;
+    ///    
+    ///     let code = QrCode::rmqr(b"Some data").unwrap();
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, e.g. when the data
+    /// is too long.
+    pub fn rmqr<D: AsRef<[u8]>>(data: D) -> QrResult<Self> {
+        Self::rmqr_with_options(data, EcLevel::M, bits::RmqrStrategy::Area)
+    }
+
+    /// Constructs a new rMQR code which automatically encodes the given data at a
+    /// specific error correction level and rmqr strategy.
+    ///
+    ///     use qrqrpar::{QrCode, EcLevel,RmqrStrategy};
+    ///
+    ///     let code = QrCode::rmqr_with_options(b"Some data", EcLevel::H, RmqrStrategy::Area).unwrap();
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, e.g. when the data
+    /// is too long.
+    pub fn rmqr_with_options<D: AsRef<[u8]>>(
+        data: D,
+        ec_level: EcLevel,
+        strategy: bits::RmqrStrategy,
+    ) -> QrResult<Self> {
+        let bits = bits::encode_auto_rmqr(data.as_ref(), ec_level, strategy)?;
+        Self::with_auto_bits(bits, ec_level)
+    }
+
+    /// Constructs a new rMQR code which automatically encodes the given text
+    /// as UTF-8, preceded by the ECI designator 26, like `new_utf8` does for
+    /// QR codes.
+    ///
+    ///     use qrqrpar::{EcLevel, QrCode, RmqrStrategy};
+    ///
+    ///     let code = QrCode::rmqr_utf8("日本語", EcLevel::M, RmqrStrategy::Area).unwrap();
+    ///     assert!(code.version().is_rmqr());
+    ///     assert_eq!(code.headers().eci, Some(26));
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the rMQR code cannot be constructed, e.g.
+    /// `Err(QrError::TextTooLong)` with the character and byte counts of the
+    /// text if it is too long, see `bits::encode_auto_rmqr_utf8`.
+    pub fn rmqr_utf8(
+        text: &str,
+        ec_level: EcLevel,
+        strategy: bits::RmqrStrategy,
+    ) -> QrResult<Self> {
+        let bits = bits::encode_auto_rmqr_utf8(text, ec_level, strategy)?;
+        Self::with_auto_bits(bits, ec_level)
+    }
+
+    /// Constructs a new rMQR code which encodes the whole data in the given
+    /// mode, like `with_forced_mode` does for QR codes.
+    ///
+    ///     use qrqrpar::types::Mode;
+    ///     use qrqrpar::{EcLevel, QrCode, RmqrStrategy};
+    ///
+    ///     let strategy = RmqrStrategy::Area;
+    ///     let code = QrCode::rmqr_with_forced_mode(b"0123", Mode::Byte, EcLevel::M, strategy).unwrap();
+    ///     assert!(code.version().is_rmqr());
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidCharacter)` if the mode cannot encode the
+    /// data, and `Err(QrError::DataTooLong)` if the data is too long, see
+    /// `bits::encode_auto_rmqr_forced`.
+    pub fn rmqr_with_forced_mode<D: AsRef<[u8]>>(
+        data: D,
+        mode: types::Mode,
+        ec_level: EcLevel,
+        strategy: bits::RmqrStrategy,
+    ) -> QrResult<Self> {
+        let bits = bits::encode_auto_rmqr_forced(data.as_ref(), ec_level, mode, strategy)?;
+        Self::with_auto_bits(bits, ec_level)
+    }
+
+    /// Constructs a new rMQR code which automatically encodes the given data,
+    /// falling back to a regular QR code if the data does not fit in any rMQR
+    /// version. Use `version()` to tell which family was used.
+    ///
+    ///     use qrqrpar::{QrCode, EcLevel, RmqrStrategy, Version};
+    ///
+    ///     let code = QrCode::rmqr_or_qr(b"Some data", EcLevel::M, RmqrStrategy::Area).unwrap();
+    ///     assert!(code.version().is_rmqr());
+    ///
+    ///     let code = QrCode::rmqr_or_qr(&[b'a'; 500], EcLevel::M, RmqrStrategy::Area).unwrap();
+    ///     assert!(matches!(code.version(), Version::Normal(_)));
+    ///
+    /// rMQR codes only support the M and H error correction levels, so the rMQR
+    /// attempt raises L to M and Q to H, never weakening the requested level.
+    /// The regular QR code fallback uses `ec_level` as given.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, e.g. when the data
+    /// is too long even for a regular QR code.
+    pub fn rmqr_or_qr<D: AsRef<[u8]>>(
+        data: D,
+        ec_level: EcLevel,
+        strategy: bits::RmqrStrategy,
+    ) -> QrResult<Self> {
+        let data = data.as_ref();
+        let rmqr_ec_level = match ec_level {
+            EcLevel::L | EcLevel::M => EcLevel::M,
+            EcLevel::Q | EcLevel::H => EcLevel::H,
+        };
+        match Self::rmqr_with_options(data, rmqr_ec_level, strategy) {
+            Err(types::QrError::DataTooLong) => Self::with_error_correction_level(data, ec_level),
+            result => result,
+        }
+    }
+
+    /// Constructs a new QR, Micro QR or rMQR code which automatically encodes
+    /// the given data in the version best satisfying the strategy among every
+    /// family. Use `version()` to tell which family was used.
+    ///
+    ///     use qrqrpar::{EcLevel, QrCode, ShapeStrategy, Version};
+    ///
+    ///     let code = QrCode::smallest(b"0123456789", EcLevel::L, ShapeStrategy::Area).unwrap();
+    ///     assert_eq!(code.version(), Version::Micro(2));
+    ///
+    ///     let code = QrCode::smallest(b"0123456789", EcLevel::L, ShapeStrategy::Height).unwrap();
+    ///     assert_eq!(code.version(), Version::Rmqr(7, 43));
+    ///     assert_eq!(code.error_correction_level(), EcLevel::M);
+    ///
+    /// The error correction level may be raised to the weakest level at least
+    /// as strong which the version supports, see `bits::encode_auto_smallest`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the code cannot be constructed, e.g. when the data is
+    /// too long for every version.
+    pub fn smallest<D: AsRef<[u8]>>(
+        data: D,
+        ec_level: EcLevel,
+        strategy: bits::ShapeStrategy,
+    ) -> QrResult<Self> {
+        let (bits, ec_level) = bits::encode_auto_smallest(data.as_ref(), ec_level, strategy)?;
+        Self::with_auto_bits(bits, ec_level)
+    }
+}
+
+impl QrCode {
+    /// The ratio of dark modules in the symbol, between 0 and 1.
+    pub fn dark_ratio(&self) -> f64 {
+        let dark_count = self.content.iter().filter(|&&c| c == Color::Dark).count();
+        dark_count as f64 / self.content.len() as f64
+    }
+
+    /// The length of the longest horizontal or vertical run of dark modules.
+    pub fn largest_dark_run(&self) -> usize {
+        let longest_run = |colors: &mut dyn Iterator<Item = Color>| {
+            let mut longest = 0;
+            let mut run = 0;
+            for color in colors {
+                run = if color == Color::Dark { run + 1 } else { 0 };
+                longest = longest.max(run);
+            }
+            longest
+        };
+        let rows = (0..self.height).map(|y| {
+            longest_run(
+                &mut self.content[y * self.width..(y + 1) * self.width]
+                    .iter()
+                    .copied(),
+            )
+        });
+        let columns = (0..self.width)
+            .map(|x| longest_run(&mut self.content.iter().skip(x).step_by(self.width).copied()));
+        rows.chain(columns).max().unwrap_or(0)
+    }
+
+    /// Constructs the same code masked with the pattern which optimizes
+    /// `preference` rather than the penalty score of the specification, e.g.
+    /// for decorative styles which suffer from large dark areas.
+    ///
+    /// Any mask pattern can be decoded, so the code stays valid. Among the
+    /// patterns which are equally good, the one with the lowest pattern number
+    /// is chosen. rMQR codes only have one mask pattern, so they are unchanged.
+    ///
+    ///     use qrqrpar::{MaskPreference, QrCode};
+    ///
+    ///     let code = QrCode::new(b"Some data").unwrap();
+    ///     let remasked = code.remask_preferring(MaskPreference::ShortestDarkRun).unwrap();
+    ///     assert!(remasked.largest_dark_run() <= code.largest_dark_run());
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidVersion)` if the version and error
+    /// correction level of this code are incompatible, which only happens for
+    /// codes constructed from hand-made bits.
+    pub fn remask_preferring(&self, preference: MaskPreference) -> QrResult<Self> {
+        let data_len = bits::Bits::new(self.version)
+            .max_len(self.ec_level)?
+            .div_ceil(8);
+        let (encoded_data, ec_data) = self.codewords.split_at(data_len);
+        let mut unmasked = internals::canvas::Canvas::new(self.version, self.ec_level);
+        Self::draw_canvas(
+            &mut unmasked,
+            self.version,
+            self.ec_level,
+            encoded_data,
+            ec_data,
+        );
+
+        let candidates = internals::canvas::mask_patterns(self.version)
+            .iter()
+            .map(|pattern| {
+                let mut masked = unmasked.clone();
+                masked.apply_mask(*pattern);
+                let mut code = Self::from_masked_canvas(
+                    &masked,
+                    self.version,
+                    self.ec_level,
+                    encoded_data.to_vec(),
+                    ec_data.to_vec(),
+                );
+                code.headers = self.headers;
+                code.encode_stats = self.encode_stats;
+                code
+            });
+        let best = match preference {
+            // The deviation is compared on the dark module count, which is
+            // exact.
+            MaskPreference::BalancedDarkRatio => candidates.min_by_key(|code| {
+                let dark_count = code.content.iter().filter(|&&c| c == Color::Dark).count();
+                (2 * dark_count).abs_diff(code.content.len())
+            }),
+            MaskPreference::ShortestDarkRun => candidates.min_by_key(Self::largest_dark_run),
+        };
+        Ok(best.expect("every version has a mask pattern"))
+    }
+
+    /// Finds a region suitable to place a logo on, which avoids all functional
+    /// patterns (finder, timing and alignment patterns, and format and version
+    /// information).
+    ///
+    /// The area of the region is limited to `max_area_fraction` of the symbol,
+    /// and additionally to the fraction recoverable at the error correction
+    /// level of this code (7%, 15%, 25% and 30% for L, M, Q and H).
+    ///
+    /// The largest such region is returned, preferring the one closest to the
+    /// center of the symbol. QR and Micro QR codes use square regions, while
+    /// rMQR codes may use any rectangle. Note that when an alignment pattern
+    /// sits at the very center (e.g. version 7), the region is necessarily off
+    /// center.
+    ///
+    /// Returns `None` if there is no such region.
+    pub fn safe_center_region(&self, max_area_fraction: f64) -> Option<geometry::ModuleRect> {
+        let ec_fraction = match self.ec_level {
+            EcLevel::L => 0.07,
+            EcLevel::M => 0.15,
+            EcLevel::Q => 0.25,
+            EcLevel::H => 0.30,
+        };
+        let (width, height) = (self.width, self.height);
+        let max_area = (max_area_fraction.min(ec_fraction) * (width * height) as f64) as usize;
+
+        let canvas = self.functional_canvas();
+        // `drawn[y][x]` is the number of functional modules in the rectangle
+        // from (0, 0) to (x, y), exclusively.
+        let mut drawn = vec![vec![0; width + 1]; height + 1];
+        for y in 0..height {
+            for x in 0..width {
+                let is_drawn = usize::from(canvas.is_drawn(x as i16, y as i16));
+                drawn[y + 1][x + 1] = drawn[y][x + 1] + drawn[y + 1][x] - drawn[y][x] + is_drawn;
+            }
+        }
+
+        let mut sizes = (1..=height)
+            .flat_map(|h| (1..=width).map(move |w| (w, h)))
+            .filter(|&(w, h)| w * h <= max_area && (self.version.is_rmqr() || w == h))
+            .collect::<Vec<_>>();
+        sizes.sort_by_key(|&(w, h)| {
+            let aspect_diff = (w * height).abs_diff(h * width);
+            (core::cmp::Reverse(w * h), aspect_diff)
+        });
+
+        for (w, h) in sizes {
+            let best = (0..=height - h)
+                .flat_map(|y| (0..=width - w).map(move |x| (x, y)))
+                .filter(|&(x, y)| {
+                    drawn[y + h][x + w] + drawn[y][x] == drawn[y][x + w] + drawn[y + h][x]
+                })
+                .min_by_key(|&(x, y)| {
+                    let dx = (2 * x + w).abs_diff(width);
+                    let dy = (2 * y + h).abs_diff(height);
+                    dx * dx + dy * dy
+                });
+            if let Some((x, y)) = best {
+                return geometry::ModuleRect::new(x as u32, y as u32, w as u32, h as u32);
+            }
+        }
+        None
+    }
+}
+
+impl QrCode {
+    /// Return `viewbox_width`, `viewbox_height`, `image_width`, `image_height`
+    /// including the prepress bleed, see `trim_sizes` for the trim box.
+    ///
+    /// The image sizes are whole pixels: `image_width` is `QrStyle::width`,
+    /// plus the bleed, and `image_height` is rounded from it. `to_pixmap`
+    /// always has these dimensions, and `to_svg` declares them as its width
+    /// and height. Since rounding changes the aspect ratio slightly, the SVG
+    /// stretches its viewBox to fill the image rather than leaving a margin,
+    /// so both outputs scale the viewBox by `image_width / viewbox_width`
+    /// horizontally and `image_height / viewbox_height` vertically.
+    pub fn image_sizes(&self, style: &QrStyle) -> (f64, f64, u32, u32) {
+        Renderable::image_sizes(self, style)
+    }
+
+    /// Same as `image_sizes`, for the trim box excluding the prepress bleed.
+    pub fn trim_sizes(&self, style: &QrStyle) -> (f64, f64, u32, u32) {
+        Renderable::trim_sizes(self, style)
+    }
+
+    /// Returns the rectangles covering the dark modules in the pixel
+    /// coordinates of the image rendered with `style`, e.g. to draw them with
+    /// a native canvas API instead of rasterizing the SVG.
+    ///
+    /// The dark modules of each row are merged into maximal horizontal runs to
+    /// reduce draw calls, see `pixel_module_rects` for one rectangle per
+    /// module. The rectangles are placed exactly like the modules of the SVG
+    /// path, so filling them all as a single non-zero path rasterizes like
+    /// `to_pixmap` with `QrShape::Square`. The shape and the caption are
+    /// ignored.
+    ///
+    ///     use qrqrpar::{QrCode, QrStyle};
+    ///
+    ///     let code = QrCode::new(b"Some data").unwrap();
+    ///     let style = QrStyle { width: 250, ..Default::default() };
+    ///     let first = code.pixel_rects(&style).next().unwrap();
+    ///     assert_eq!((first.x, first.y, first.w, first.h), (20.0, 20.0, 70.0, 10.0));
+    ///
+    pub fn pixel_rects(&self, style: &QrStyle) -> impl Iterator<Item = geometry::PixelRect> + '_ {
+        self.pixel_rects_with(style, true)
+    }
+
+    /// Same as `pixel_rects`, with one rectangle per dark module.
+    pub fn pixel_module_rects(
+        &self,
+        style: &QrStyle,
+    ) -> impl Iterator<Item = geometry::PixelRect> + '_ {
+        self.pixel_rects_with(style, false)
+    }
+
+    /// Returns the rectangles of the dark modules, merging the runs of each
+    /// row if `merge_runs` is true.
+    fn pixel_rects_with(
+        &self,
+        style: &QrStyle,
+        merge_runs: bool,
+    ) -> impl Iterator<Item = geometry::PixelRect> + '_ {
+        let (vb_width, vb_height, image_width, image_height) = self.image_sizes(style);
+        let [top, _, _, left] = style.resolve_quiet_zone(self.version);
+        let (top, left) = (top + style.bleed(), left + style.bleed());
+        // The viewBox is stretched to fill the image, see `svg_open_tag`. The
+        // SVG renderer computes the transform of the path in single
+        // precision, so the same is done here for the edges to fall on the
+        // same side of the pixel samples.
+        let scale_x = image_width as f32 / vb_width as f32;
+        let scale_y = image_height as f32 / vb_height as f32;
+        let offset_x = scale_x * left as f32;
+        let offset_y = scale_y * top as f32;
+        let to_pixels = move |x: usize, y: usize| {
+            let x = x as f32 * scale_x + offset_x;
+            let y = y as f32 * scale_y + offset_y;
+            (f64::from(x), f64::from(y))
+        };
+
+        (0..self.height).flat_map(move |y| {
+            self.row_runs(y)
+                .into_iter()
+                .flat_map(move |(start, length)| {
+                    let (start, end) = (start as usize, (start + length) as usize);
+                    if merge_runs {
+                        vec![(start, end)]
+                    } else {
+                        (start..end).map(|x| (x, x + 1)).collect()
+                    }
+                })
+                .map(move |(begin, end)| {
+                    let (left, top) = to_pixels(begin, y);
+                    let (right, bottom) = to_pixels(end, y + 1);
+                    geometry::PixelRect {
+                        x: left,
+                        y: top,
+                        w: right - left,
+                        h: bottom - top,
+                    }
+                })
+        })
+    }
+
+    /// Returns the runs of dark modules of the row `y`, as `(start, length)`
+    /// pairs from left to right, e.g. for hardware rasterizers and printer
+    /// languages drawing horizontal lines.
+    ///
+    ///     use qrqrpar::QrCode;
+    ///
+    ///     let code = QrCode::new(b"Some data").unwrap();
+    ///     assert_eq!(code.row_runs(0)[0], (0, 7));
+    ///
+    /// The runs are maximal, so two runs are always separated by at least one
+    /// light module. Rows outside the symbol have no runs.
+    pub fn row_runs(&self, y: usize) -> Vec<(u32, u32)> {
+        if y >= self.height {
+            return vec![];
+        }
+        dark_runs(&self.content[y * self.width..(y + 1) * self.width], 0)
+    }
+
+    /// Returns the runs of dark modules of every row, see `row_runs`.
+    pub fn all_runs(&self) -> Vec<Vec<(u32, u32)>> {
+        self.all_runs_with_quiet_zone(0)
+    }
+
+    /// Returns the runs of dark modules of every row in the coordinates of an
+    /// output surrounded by `quiet_zone` light modules, so the runs start
+    /// `quiet_zone` modules further right and the first and last `quiet_zone`
+    /// rows are empty.
+    ///
+    ///     use qrqrpar::QrCode;
+    ///
+    ///     let code = QrCode::new(b"Some data").unwrap();
+    ///     let runs = code.all_runs_with_quiet_zone(4);
+    ///     assert_eq!(runs.len(), 4 + 21 + 4);
+    ///     assert_eq!(runs[4][0], (4, 7));
+    pub fn all_runs_with_quiet_zone(&self, quiet_zone: u32) -> Vec<Vec<(u32, u32)>> {
+        let margin = (0..quiet_zone).map(|_| vec![]);
+        let rows = self
+            .content
+            .chunks(self.width)
+            .map(|row| dark_runs(row, quiet_zone));
+        margin.clone().chain(rows).chain(margin).collect()
+    }
+
+    /// Converts the QR to a simple SVG string.
+    ///
+    /// The image is as many pixels wide as the code is modules wide, and has
+    /// no quiet zone. `to_svg_minimal(0.0)` gives the same symbol, scaling to
+    /// its container instead, and never fails for that quiet zone.
+    #[deprecated(since = "0.1.7", note = "use `to_svg_minimal(0.0)` instead")]
+    pub fn to_simple_svg(&self) -> String {
+        let style = QrStyle {
+            quiet_zone: 0.0,
+            width: self.width as u32,
+            ..Default::default()
+        };
+        self.to_svg(&style)
+    }
+
+    /// Converts the QR to a SVG string with the default colors and the given
+    /// quiet zone in modules, which scales to the size of its container.
+    ///
+    /// The `<svg>` element only has a `viewBox` attribute and no `width` or
+    /// `height` attributes, so that the size is set by the page or the
+    /// application embedding it.
+    ///
+    ///     use qrqrpar::QrCode;
+    ///
+    ///     let code = QrCode::new(b"Some data").unwrap();
+    ///     let svg = code.to_svg_minimal(4.0).unwrap();
+    ///     assert!(svg.contains(r#"viewBox="0 0 29 29""#));
+    ///
+    /// # Errors
+    ///
+    /// Returns `StyleError::NegativeQuietZone` or
+    /// `StyleError::NonFiniteQuietZone` if the quiet zone is negative or not
+    /// finite.
+    pub fn to_svg_minimal(&self, quiet_zone: f64) -> Result<String, StyleError> {
+        let style = QrStyle {
+            quiet_zone,
+            ..Default::default()
+        };
+        style.check()?;
+        Ok(format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}",
+            svg_fragment(self, &style, false)
+        ))
+    }
+
+    /// Converts the QR to a SVG string.
+    ///
+    /// The style is not validated, since the SVG does not depend on the image
+    /// resolution. Use `QrStyle::validate` to check it beforehand. The colors
+    /// are XML-escaped, so an invalid color cannot inject markup.
+    pub fn to_svg(&self, style: &QrStyle) -> String {
+        Renderable::to_svg(self, style)
+    }
+
+    /// Converts the QR to a SVG `<svg>` element without the XML prolog, e.g. for
+    /// direct insertion into a HTML document.
+    pub fn to_svg_fragment(&self, style: &QrStyle) -> String {
+        Renderable::to_svg_fragment(self, style)
+    }
+
+    /// Converts the QR to separate SVG documents for every color layer, e.g.
+    /// for spot-color screen printing.
+    ///
+    /// The layers are returned as (name, document) pairs, in stacking order:
+    ///
+    /// - `"background"`: the background including the quiet zone,
+    /// - `"function"`: the dark modules of the functional patterns (finder,
+    ///   timing and alignment patterns, and format and version information),
+    /// - `"data"`: the remaining dark modules,
+    /// - `"caption"`: the caption, only if the style has one,
+    /// - `"crop-marks"`: the prepress crop marks, only if the style has them.
+    ///
+    /// Every document shares the same size and viewBox, so the layers register
+    /// perfectly when stacked. Since the function and data modules are traced
+    /// separately, `QrShape::Round` rounds the corners where the two layers
+    /// meet.
+    pub fn to_svg_layers(&self, style: &QrStyle) -> Vec<(String, String)> {
+        let open_tag = svg_open_tag(self, style, true);
+        self.svg_layers(style)
+            .into_iter()
+            .map(|(name, element)| {
+                let document = [
+                    String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"),
+                    open_tag.clone(),
+                    element,
+                    String::from("</svg>"),
+                ]
+                .join("\n");
+                (name.to_owned(), document)
+            })
+            .collect()
+    }
+
+    /// Converts the QR to a SVG string whose path traces the light modules
+    /// instead of the dark ones, e.g. as the cut geometry for laser engraving
+    /// the background while leaving the dark modules raised.
+    ///
+    /// The path is the bounding rectangle of the symbol, or of the symbol and
+    /// its quiet zone if `include_quiet_zone` is true, followed by the
+    /// outlines of the dark modules drawn by `to_svg`. With the even-odd fill
+    /// rule, the dark outlines become holes, so the path is the exact
+    /// complement of the dark modules within the rectangle, rounded corners
+    /// included. The colors are swapped: the path is filled with the
+    /// background color over a rectangle of the module color, so the symbol
+    /// looks the same as with `to_svg`. Outside the rectangle, e.g. in the
+    /// quiet zone if `include_quiet_zone` is false, the background color is
+    /// drawn as usual. The caption and the crop marks are not drawn.
+    ///
+    ///     use qrqrpar::{QrCode, QrStyle};
+    ///
+    ///     let code = QrCode::new(b"Some data").unwrap();
+    ///     let svg = code.to_svg_inverse_geometry(&QrStyle::default(), false);
+    ///     assert!(svg.contains(r#"d="M0 0h21v21h-21ZM"#));
+    pub fn to_svg_inverse_geometry(&self, style: &QrStyle, include_quiet_zone: bool) -> String {
+        let [top, right, bottom, left] = style.resolve_quiet_zone(self.version);
+        let (width, height) = (self.width as f64, self.height as f64);
+        let (x, y, width, height) = if include_quiet_zone {
+            (
+                0.0 - left,
+                0.0 - top,
+                width + left + right,
+                height + top + bottom,
+            )
+        } else {
+            (0.0, 0.0, width, height)
+        };
+        let dark = svg_path_data(self, style, |_, _| true);
+        let (x, y) = (Number(x), Number(y));
+        let (width, height, back) = (Number(width), Number(height), Number(-width));
+        let path_string = format!("M{x} {y}h{width}v{height}h{back}Z{dark}");
+
+        let (vb_width, vb_height, _, _) = self.image_sizes(style);
+        let (vb_width, vb_height) = (Number(vb_width), Number(vb_height));
+        let origin = Number(-style.bleed());
+        let (top, left) = (Number(top), Number(left));
+        let color = render::escape_xml(&style.color);
+        let background_color = render::escape_xml(&style.background_color);
+        [
+            String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"),
+            svg_open_tag(self, style, true),
+            format!(
+                r#"<rect x="{origin}" y="{origin}" width="{vb_width}" height="{vb_height}" fill="{background_color}"/>"#
+            ),
+            format!(
+                r#"<rect transform="translate({left},{top})" x="{x}" y="{y}" width="{width}" height="{height}" fill="{color}"/>"#
+            ),
+            format!(
+                r#"<path fill="{background_color}" transform="translate({left},{top})" fill-rule="evenodd" d="{path_string}"/>"#,
+            ),
+            String::from("</svg>"),
+        ]
+        .join("\n")
+    }
+
+    /// Converts the QR to a single SVG document previewing the layers of
+    /// `to_svg_layers`, each wrapped in a `<g id="layer-{name}">` group.
+    pub fn to_svg_layers_preview(&self, style: &QrStyle) -> String {
+        let mut lines = vec![
+            String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"),
+            svg_open_tag(self, style, true),
+        ];
+        for (name, element) in self.svg_layers(style) {
+            lines.push(format!(r#"<g id="layer-{name}">"#));
+            lines.push(element);
+            lines.push(String::from("</g>"));
+        }
+        lines.push(String::from("</svg>"));
+        lines.join("\n")
+    }
+
+    /// Returns the SVG elements of every layer, see `to_svg_layers`.
+    fn svg_layers(&self, style: &QrStyle) -> Vec<(&'static str, String)> {
+        let canvas = self.functional_canvas();
+        let is_functional = |x: usize, y: usize| canvas.is_drawn(x as i16, y as i16);
+        let mut layers = vec![
+            ("background", svg_background(self, style)),
+            ("function", svg_path(self, style, is_functional)),
+            ("data", svg_path(self, style, |x, y| !is_functional(x, y))),
+        ];
+        if style.caption.is_some() {
+            layers.push(("caption", svg_caption(self, style)));
+        }
+        if style.prepress.is_some_and(Prepress::draws_crop_marks) {
+            layers.push(("crop-marks", svg_crop_marks(self, style, "crop-marks")));
+        }
+        layers
+    }
+
+    /// Checks that the `QrShape::Square` path drawn by `to_svg` covers exactly
+    /// the dark modules.
+    ///
+    /// The path is rasterized at 1 pixel per module without going through
+    /// resvg, and compared with `to_colors`.
+    ///
+    ///     use qrqrpar::QrCode;
+    ///
+    ///     let code = QrCode::new(b"Some data").unwrap();
+    ///     assert!(code.verify_render_geometry().is_ok());
+    ///
+    /// # Errors
+    ///
+    /// Returns a `GeometryError` listing the modules whose rendering does not
+    /// match.
+    pub fn verify_render_geometry(&self) -> Result<(), geometry::GeometryError> {
+        let path = directed_segments(self, |_, _| true).to_path_square_mut();
+        let pixels = render::rasterize_square_path(&path, self.width, self.height);
+        let mismatches = self
+            .content
+            .iter()
+            .zip(pixels)
+            .enumerate()
+            .filter(|(_, (&color, pixel))| (color == Color::Dark) != *pixel)
+            .map(|(i, _)| {
+                geometry::ModulePoint::new((i % self.width) as u32, (i / self.width) as u32)
+            })
+            .collect::<Vec<_>>();
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(geometry::GeometryError { mismatches })
+        }
+    }
+
+    /// Saves the QR to a SVG file.
+    pub fn save_svg<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        style: &QrStyle,
+    ) -> std::io::Result<()> {
+        Renderable::save_svg(self, path, style)
+    }
+}
+
+impl QrCode {
+    /// Converts the QR to a tiny-skia pixmap.
+    ///
+    /// Returns `RenderError::Style` if the style is invalid, see
+    /// `QrStyle::validate`, or `RenderError::ModuleTooSmall` if the modules
+    /// are too small.
+    pub fn to_pixmap(&self, style: &QrStyle) -> Result<resvg::tiny_skia::Pixmap, RenderError> {
+        Renderable::to_pixmap(self, style)
+    }
+
+    /// Converts the QR to a small black on white pixmap, e.g. for a live
+    /// preview, without going through the SVG renderer.
+    ///
+    /// The modules are square, 1 to 3 pixels wide, as large as fit in
+    /// `max_px` pixels along the longer side of the symbol and its
+    /// recommended quiet zone. The style is ignored, so the preview is cheap
+    /// even for version 40.
+    ///
+    ///     use qrqrpar::QrCode;
+    ///
+    ///     let code = QrCode::new(b"Some data").unwrap();
+    ///     let pixmap = code.to_preview_pixmap(64).unwrap();
+    ///     // 21 modules and 2 quiet zones of 4, at 2 px per module.
+    ///     assert_eq!((pixmap.width(), pixmap.height()), (58, 58));
+    ///
+    /// # Errors
+    ///
+    /// Returns `RenderError::ModuleTooSmall` if a module of 1 pixel does not
+    /// fit in `max_px`.
+    pub fn to_preview_pixmap(&self, max_px: u32) -> Result<resvg::tiny_skia::Pixmap, RenderError> {
+        let quiet_zone = usize::from(self.version.recommended_quiet_zone());
+        let (width, height) = (self.width + 2 * quiet_zone, self.height + 2 * quiet_zone);
+        let modules = width.max(height);
+        let module_px = (max_px as usize / modules).min(3);
+        if module_px == 0 {
+            return Err(RenderError::ModuleTooSmall {
+                module_px: f64::from(max_px) / modules as f64,
+                minimum: 1.0,
+            });
+        }
+
+        let (px_width, px_height) = ((width * module_px) as u32, (height * module_px) as u32);
+        let mut pixmap =
+            resvg::tiny_skia::Pixmap::new(px_width, px_height).ok_or(RenderError::ImageSize {
+                width: px_width,
+                height: px_height,
+            })?;
+        pixmap.fill(resvg::tiny_skia::Color::WHITE);
+        let black = resvg::tiny_skia::PremultipliedColorU8::from_rgba(0, 0, 0, 255).unwrap();
+        let stride = width * module_px;
+        let pixels = pixmap.pixels_mut();
+        for (i, color) in self.content.iter().enumerate() {
+            if *color == Color::Light {
+                continue;
+            }
+            let (x, y) = (i % self.width + quiet_zone, i / self.width + quiet_zone);
+            for row in y * module_px..(y + 1) * module_px {
+                let start = row * stride + x * module_px;
+                pixels[start..start + module_px].fill(black);
+            }
+        }
+        Ok(pixmap)
+    }
+
+    /// Saves the QR to a PNG file.
+    pub fn save_png<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        style: &QrStyle,
+    ) -> Result<(), RenderError> {
+        Renderable::save_png(self, path, style)
+    }
+
+    /// Encodes QR into a PNG data.
+    pub fn to_png(&self, style: &QrStyle) -> Result<Vec<u8>, RenderError> {
+        Renderable::to_png(self, style)
+    }
+}
+
+/// A grid of modules that can be rendered to SVG and PNG with a `QrStyle`.
+///
+/// `QrCode` implements it, as well as `PartialQr` for drawing the code
+/// partially, e.g. as the frames of an animation. Only the grid accessors are
+/// required, the rendering methods are provided.
+pub trait Renderable {
+    /// Returns the version of the code, which determines the quiet zone.
+    fn version(&self) -> Version;
+
+    /// Returns the number of modules in a row.
+    fn width(&self) -> usize;
+
+    /// Returns the number of modules in a column.
+    fn height(&self) -> usize;
+
+    /// Returns the color of the module at (x, y), in modules from the top left
+    /// corner without the quiet zone.
+    fn module_color(&self, x: usize, y: usize) -> Color;
+
+    /// Return `viewbox_width`, `viewbox_height`, `image_width`, `image_height`
+    /// including the prepress bleed, see `trim_sizes` for the trim box.
+    fn image_sizes(&self, style: &QrStyle) -> (f64, f64, u32, u32) {
+        let (vb_width, vb_height, width, height) = self.trim_sizes(style);
+        let bleed = style.bleed();
+        if bleed == 0.0 {
+            return (vb_width, vb_height, width, height);
+        }
+        let module_px = f64::from(width) / vb_width;
+        (
+            vb_width + 2.0 * bleed,
+            vb_height + 2.0 * bleed,
+            (f64::from(width) + 2.0 * bleed * module_px).round() as u32,
+            (f64::from(height) + 2.0 * bleed * module_px).round() as u32,
+        )
+    }
+
+    /// Same as `image_sizes`, for the trim box excluding the prepress bleed.
+    /// The image width of the trim box is `QrStyle::width`.
+    fn trim_sizes(&self, style: &QrStyle) -> (f64, f64, u32, u32) {
+        let [top, right, bottom, left] = style.resolve_quiet_zone(self.version());
+        let vb_width = self.width() as f64 + left + right;
+        let caption_height = style.caption.as_ref().map_or(0.0, Caption::line_height);
+        let vb_height = self.height() as f64 + top + bottom + caption_height;
+        let width = style.width;
+        let height = (width as f64 * vb_height / vb_width).round() as u32;
+        (vb_width, vb_height, width, height)
+    }
+
+    /// Converts the modules to a SVG string.
+    fn to_svg(&self, style: &QrStyle) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}",
+            self.to_svg_fragment(style)
+        )
+    }
+
+    /// Converts the modules to a SVG `<svg>` element without the XML prolog.
+    fn to_svg_fragment(&self, style: &QrStyle) -> String {
+        svg_fragment(self, style, true)
+    }
+
+    /// Saves the modules to a SVG file.
+    fn save_svg<P: AsRef<std::path::Path>>(&self, path: P, style: &QrStyle) -> std::io::Result<()> {
+        std::fs::write(path, self.to_svg(style))
+    }
+
+    /// Converts the modules to a tiny-skia pixmap.
+    ///
+    /// Returns `RenderError::Style` if the style is invalid, see
+    /// `QrStyle::validate`, or `RenderError::ModuleTooSmall` if the modules
+    /// are smaller than `QrStyle::minimum_module_px` and `QrStyle::allow_tiny`
+    /// is not set.
+    fn to_pixmap(&self, style: &QrStyle) -> Result<resvg::tiny_skia::Pixmap, RenderError> {
+        style.validate(self.version())?;
+        let module_px = style.module_px(self.version());
+        if module_px < style.minimum_module_px && !style.allow_tiny {
+            return Err(RenderError::ModuleTooSmall {
+                module_px,
+                minimum: style.minimum_module_px,
+            });
+        }
+        let (_, _, width, height) = self.image_sizes(style);
+        let svg_string = self.to_svg(style);
+        rasterize_svg(&svg_string, width, height)
+    }
+
+    /// Saves the modules to a PNG file.
+    fn save_png<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        style: &QrStyle,
+    ) -> Result<(), RenderError> {
+        std::fs::write(path, self.to_png(style)?)?;
+        Ok(())
+    }
+
+    /// Encodes the modules into a PNG data.
+    fn to_png(&self, style: &QrStyle) -> Result<Vec<u8>, RenderError> {
+        let pixmap = self.to_pixmap(style)?;
+        pixmap
+            .encode_png()
+            .map_err(|e| RenderError::Png(e.to_string()))
+    }
+}
+
+impl Renderable for QrCode {
+    fn version(&self) -> Version {
+        self.version
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn module_color(&self, x: usize, y: usize) -> Color {
+        self.content[y * self.width + x]
+    }
+}
+
+/// Returns the `<svg>` element drawing the code, with the `width` and `height`
+/// attributes of the style only if `sized` is true.
+fn svg_fragment(code: &(impl Renderable + ?Sized), style: &QrStyle, sized: bool) -> String {
+    [
+        svg_open_tag(code, style, sized),
+        svg_background(code, style),
+        svg_path(code, style, |_, _| true),
+        svg_caption(code, style),
+        svg_crop_marks(code, style, "crop-marks"),
+        String::from("</svg>"),
+    ]
+    .join("\n")
+}
+
+/// Returns the maximal runs of dark modules of a row as `(start, length)`
+/// pairs, with `offset` added to the starts.
+fn dark_runs(row: &[Color], offset: u32) -> Vec<(u32, u32)> {
+    let mut runs = vec![];
+    let mut x = 0;
+    while let Some(start) = row[x..].iter().position(|&c| c == Color::Dark) {
+        x += start;
+        let length = row[x..].iter().take_while(|&&c| c == Color::Dark).count();
+        runs.push((x as u32 + offset, length as u32));
+        x += length;
+    }
+    runs
+}
+
+/// Returns the opening `<svg>` tag, with the `width` and `height` attributes
+/// only if `sized` is true.
+fn svg_open_tag(code: &(impl Renderable + ?Sized), style: &QrStyle, sized: bool) -> String {
+    let (vb_width, vb_height, image_width, image_height) = code.image_sizes(style);
+    // The image sizes are rounded to whole pixels, so the aspect ratio of the
+    // viewBox is not preserved, which would leave a sliver of the image
+    // uncovered: the viewBox is stretched to fill the image exactly instead.
+    let size = if sized {
+        format!(r#" width="{image_width}" height="{image_height}" preserveAspectRatio="none""#)
+    } else {
+        String::new()
+    };
+    let (vb_width, vb_height) = (Number(vb_width), Number(vb_height));
+    let origin = Number(-style.bleed());
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1"{size} viewBox="{origin} {origin} {vb_width} {vb_height}">"#
+    )
+}
+
+/// Returns the `<rect>` element filling the whole image with the
+/// background color.
+fn svg_background(code: &(impl Renderable + ?Sized), style: &QrStyle) -> String {
+    let background_color = render::escape_xml(&style.background_color);
+    let (vb_width, vb_height, _, _) = code.image_sizes(style);
+    let (vb_width, vb_height) = (Number(vb_width), Number(vb_height));
+    let origin = Number(-style.bleed());
+    format!(
+        r#"<rect x="{origin}" y="{origin}" width="{vb_width}" height="{vb_height}" fill="{background_color}"/>"#
+    )
+}
+
+/// Returns the `<g>` element with the given id holding the 8 `<line>`
+/// elements of the crop marks, or an empty string if the style has none.
+fn svg_crop_marks(code: &(impl Renderable + ?Sized), style: &QrStyle, id: &str) -> String {
+    let Some(prepress) = style
+        .prepress
+        .filter(|prepress| prepress.draws_crop_marks())
+    else {
+        return String::new();
+    };
+    let (width, height, _, _) = code.trim_sizes(style);
+    let bleed = prepress.bleed_modules;
+    let (near, far) = (bleed / 2.0, bleed);
+    let color = if prepress.registration_color {
+        String::from("#000000")
+    } else {
+        render::escape_xml(&style.color)
+    };
+    let mut lines = vec![format!(
+        r#"<g id="{id}" stroke="{color}" stroke-width="{}">"#,
+        Number(bleed / 20.0)
+    )];
+    for (x, y, dx, dy) in [
+        (0.0, 0.0, -1.0, -1.0),
+        (width, 0.0, 1.0, -1.0),
+        (0.0, height, -1.0, 1.0),
+        (width, height, 1.0, 1.0),
+    ] {
+        let (x1, x2) = (Number(x + dx * near), Number(x + dx * far));
+        let (y1, y2) = (Number(y + dy * near), Number(y + dy * far));
+        let (x, y) = (Number(x), Number(y));
+        lines.push(format!(r#"<line x1="{x1}" y1="{y}" x2="{x2}" y2="{y}"/>"#));
+        lines.push(format!(r#"<line x1="{x}" y1="{y1}" x2="{x}" y2="{y2}"/>"#));
+    }
+    lines.push(String::from("</g>"));
+    lines.join("\n")
+}
+
+/// Returns the `<path>` element drawing the dark modules at (x, y) for
+/// which `include(x, y)` is true.
+fn svg_path(
+    code: &(impl Renderable + ?Sized),
+    style: &QrStyle,
+    include: impl Fn(usize, usize) -> bool,
+) -> String {
+    let path_string = svg_path_data(code, style, include);
+    let color = render::escape_xml(&style.color);
+    let [top, _, _, left] = style.resolve_quiet_zone(code.version());
+    let (top, left) = (Number(top), Number(left));
+    format!(
+        r#"<path fill="{color}" transform="translate({left},{top})" fill-rule="evenodd" d="{path_string}"/>"#,
+    )
+}
+
+/// Returns the path data of the dark modules at (x, y) for which
+/// `include(x, y)` is true, in modules from the top left corner of the
+/// symbol.
+fn svg_path_data(
+    code: &(impl Renderable + ?Sized),
+    style: &QrStyle,
+    include: impl Fn(usize, usize) -> bool,
+) -> String {
+    let mut directed_segments = directed_segments(code, include);
+    match style.shape {
+        QrShape::Square => directed_segments.to_path_square_mut(),
+        QrShape::Round => {
+            let square_border = match style.border_corners {
+                BorderCorners::Round => None,
+                BorderCorners::Square => Some((code.width() as i16, code.height() as i16)),
+            };
+            directed_segments.to_path_round_mut(square_border)
+        }
+    }
+}
+
+/// Returns the outlines of the dark modules at (x, y) for which
+/// `include(x, y)` is true.
+fn directed_segments(
+    code: &(impl Renderable + ?Sized),
+    include: impl Fn(usize, usize) -> bool,
+) -> render::DirectedSegments {
+    let mut directed_segments = render::DirectedSegments::with_capacity(code.width());
+    for y in 0..code.height() {
+        for x in 0..code.width() {
+            if code.module_color(x, y) == Color::Dark && include(x, y) {
+                directed_segments.add_or_remove(x as i16, y as i16);
+            }
+        }
+    }
+    directed_segments
+}
+
+/// Returns the element drawing the caption under the bottom quiet zone, or
+/// an empty string if the style has no caption.
+fn svg_caption(code: &(impl Renderable + ?Sized), style: &QrStyle) -> String {
+    let Some(caption) = &style.caption else {
+        return String::new();
+    };
+    let [top, _, bottom, left] = style.resolve_quiet_zone(code.version());
+    let size = caption.size_modules;
+    let text = render::truncate_text(&caption.text, code.width() as f64 / size);
+    let (anchor, x) = match caption.align {
+        CaptionAlign::Start => ("start", left),
+        CaptionAlign::Middle => ("middle", left + code.width() as f64 / 2.0),
+        CaptionAlign::End => ("end", left + code.width() as f64),
+    };
+    let baseline = top + code.height() as f64 + bottom + size;
+    let color = render::escape_xml(&style.color);
+    if caption.path_fallback {
+        let text_width = render::estimated_text_width(&text) * size;
+        let start = match caption.align {
+            CaptionAlign::Start => x,
+            CaptionAlign::Middle => x - text_width / 2.0,
+            CaptionAlign::End => x - text_width,
+        };
+        let path_string = render::text_placeholder_path(&text, start, baseline, size);
+        let stroke_width = Number(size / 12.0);
+        format!(
+            r#"<path fill="none" stroke="{color}" stroke-width="{stroke_width}" d="{path_string}"/>"#
+        )
+    } else {
+        let font_family = render::escape_xml(&caption.font_family);
+        let text = render::escape_xml(&text);
+        let (x, baseline, size) = (Number(x), Number(baseline), Number(size));
+        format!(
+            r#"<text x="{x}" y="{baseline}" font-family="{font_family}" font-size="{size}" text-anchor="{anchor}" fill="{color}">{text}</text>"#
+        )
+    }
+}
+
+/// Renders a SVG string into a new pixmap of the given size.
+///
+/// Text is drawn with the system fonts, which are only loaded once the first
+/// text is rendered.
+fn rasterize_svg(
+    svg_string: &str,
+    width: u32,
+    height: u32,
+) -> Result<resvg::tiny_skia::Pixmap, RenderError> {
+    static SYSTEM_FONTS: std::sync::OnceLock<resvg::usvg::fontdb::Database> =
+        std::sync::OnceLock::new();
+    let fonts = svg_string.contains("<text").then(|| {
+        SYSTEM_FONTS.get_or_init(|| {
+            let mut fonts = resvg::usvg::fontdb::Database::new();
+            fonts.load_system_fonts();
+            fonts
+        })
+    });
+    rasterize_svg_with_fonts(svg_string, width, height, fonts)
+}
+
+/// Renders a SVG string into a new pixmap of the given size, drawing text
+/// with the given fonts. Text is skipped if no font matches.
+fn rasterize_svg_with_fonts(
+    svg_string: &str,
+    width: u32,
+    height: u32,
+    fonts: Option<&resvg::usvg::fontdb::Database>,
+) -> Result<resvg::tiny_skia::Pixmap, RenderError> {
+    let opt = resvg::usvg::Options::default();
+    let mut tree = resvg::usvg::TreeParsing::from_str(&strip_cmyk(svg_string), &opt)
+        .map_err(|e: resvg::usvg::Error| RenderError::Svg(e.to_string()))?;
+    if let Some(fonts) = fonts {
+        resvg::usvg::TreeTextToPath::convert_text(&mut tree, fonts);
+    }
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(width, height)
+        .ok_or(RenderError::ImageSize { width, height })?;
+    resvg::Tree::from_usvg(&tree)
+        .render(resvg::tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    Ok(pixmap)
+}
+
+impl std::fmt::Display for QrCode {
+    /// Formats the QR code with `to_unicode` and the recommended quiet zone.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_unicode(None))
+    }
+}
+
+#[cfg(test)]
+mod text_tests {
+    use super::*;
+
+    #[test]
+    fn test_default_quiet_zone() {
+        let code = QrCode::new(b"01234567").unwrap();
+        let lines: Vec<_> = code.to_ansi(None).lines().map(str::to_owned).collect();
+        assert_eq!(lines.len(), 21 + 4 * 2);
+
+        let code = QrCode::with_version(b"01234567", Version::Micro(2), EcLevel::L).unwrap();
+        let lines: Vec<_> = code.to_ansi(None).lines().map(str::to_owned).collect();
+        assert_eq!(lines.len(), code.height() + 2 * 2);
+
+        let code = QrCode::rmqr(b"01234567").unwrap();
+        let unicode = code.to_unicode(None);
+        assert_eq!(unicode.lines().count(), (code.height() + 2 * 2).div_ceil(2));
+        assert!(unicode
+            .lines()
+            .all(|line| line.chars().count() == code.width() + 2 * 2));
+        assert_eq!(code.to_string(), unicode);
+    }
+
+    #[test]
+    fn test_override_quiet_zone() {
+        let code = QrCode::new(b"01234567").unwrap();
+        let unicode = code.to_unicode(Some(0));
+        assert_eq!(unicode.lines().count(), 11);
+        assert!(unicode.starts_with("█▀▀▀▀▀█"));
+
+        let braille = code.to_braille(Some(1));
+        assert_eq!(braille.lines().count(), 6);
+        assert!(braille.lines().all(|line| line.chars().count() == 12));
+        assert!(braille.lines().next().unwrap().starts_with('\u{28b0}'));
+    }
+
+    #[test]
+    fn test_text_glyphs() {
+        let code = QrCode::new(b"01234567").unwrap();
+        for (dark, light, width) in [
+            ("█", " ", 1),
+            ("██", "  ", 2),
+            ("#", ".", 1),
+            ("██", "\u{3000}", 2),
+            ("\u{25a0}", "\u{25a1}", 1),
+            ("黒", "白", 2),
+            ("\u{ff03}", "..", 2),
+            ("⬛\u{fe0f}", "⬜\u{fe0f}", 2),
+        ] {
+            let text = code.to_text(dark, light, None).unwrap();
+            assert_eq!(text.lines().count(), 21 + 2 * 4);
+            for line in text.lines() {
+                assert_eq!(
+                    display_width(line),
+                    width * (21 + 2 * 4),
+                    "{dark:?} {light:?}"
+                );
+            }
+        }
+
+        let text = code.to_text("#", ".", Some(0)).unwrap();
+        assert_eq!(text, code.to_str('#', '.'));
+        let text = code.to_text("██", "  ", Some(0)).unwrap();
+        assert_eq!(
+            text,
+            code.to_str('█', ' ').replace('█', "██").replace(' ', "  ")
+        );
+    }
+
+    #[test]
+    fn test_text_glyph_errors() {
+        let code = QrCode::new(b"01234567").unwrap();
+        for (dark, light, expected) in [
+            ("█", "  ", GlyphError::WidthMismatch { dark: 1, light: 2 }),
+            (
+                "█",
+                "\u{3000}",
+                GlyphError::WidthMismatch { dark: 1, light: 2 },
+            ),
+            ("黒", " ", GlyphError::WidthMismatch { dark: 2, light: 1 }),
+            ("", " ", GlyphError::ZeroWidth),
+            ("█", "\u{200b}", GlyphError::ZeroWidth),
+            ("\u{301}", "\u{301}", GlyphError::ZeroWidth),
+        ] {
+            assert_eq!(code.to_text(dark, light, Some(1)), Err(expected));
+        }
+        assert_eq!(
+            GlyphError::WidthMismatch { dark: 1, light: 2 }.to_string(),
+            "dark glyph is 1 columns wide but light glyph is 2"
+        );
+    }
+
+    #[test]
+    fn test_char_width() {
+        for (c, width) in [
+            ('a', 1),
+            ('█', 1),
+            ('▀', 1),
+            ('\u{2800}', 1),
+            ('\u{3000}', 2),
+            ('あ', 2),
+            ('漢', 2),
+            ('한', 2),
+            ('Ａ', 2),
+            ('ｱ', 1),
+            ('\u{1f600}', 2),
+            ('\n', 0),
+            ('\u{200d}', 0),
+            ('\u{fe0f}', 0),
+        ] {
+            assert_eq!(char_width(c), width, "{c:?}");
+        }
+    }
+
+    #[test]
+    fn test_image_sizes_auto_quiet_zone() {
+        let style = QrStyle::default();
+        assert_eq!((style.quiet_zone, style.quiet_zone_sides), (2.0, None));
+        let code = QrCode::new(b"01234567").unwrap();
+        assert_eq!(code.image_sizes(&style).0, 25.0);
+
+        let style = style.with_quiet_zone(QuietZone::Auto);
+
+        let code = QrCode::new(b"01234567").unwrap();
+        let (vb_width, vb_height, _, _) = code.image_sizes(&style);
+        assert_eq!((vb_width, vb_height), (29.0, 29.0));
+
+        let code = QrCode::rmqr(b"01234567").unwrap();
+        let (vb_width, vb_height, _, _) = code.image_sizes(&style);
+        assert_eq!(
+            (vb_width, vb_height),
+            (code.width() as f64 + 4.0, code.height() as f64 + 4.0)
+        );
+
+        let style = QrStyle::new("#000000", "#ffffff", QrShape::Square, 720, 1.5);
+        assert_eq!((style.quiet_zone, style.quiet_zone_sides), (1.5, None));
+        assert_eq!(code.image_sizes(&style).1, code.height() as f64 + 3.0);
+    }
+}
+
+#[cfg(test)]
+mod empty_data_tests {
+    use super::*;
+
+    /// The code holding only the terminator and the padding.
+    fn padding_only(version: Version, ec_level: EcLevel) -> QrCode {
+        let mut bits = bits::Bits::new(version);
+        bits.push_terminator(ec_level).unwrap();
+        QrCode::with_bits(bits, ec_level).unwrap()
+    }
+
+    fn assert_padding_only(code: &QrCode) {
+        let expected = padding_only(code.version(), code.error_correction_level());
+        assert_eq!(
+            code.to_colors(),
+            expected.to_colors(),
+            "{:?}",
+            code.version()
+        );
+    }
+
+    #[test]
+    fn test_auto_selection() {
+        for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+            let code = QrCode::with_error_correction_level(b"", ec_level).unwrap();
+            assert_eq!(code.version(), Version::Normal(1));
+            assert_padding_only(&code);
+            assert_padding_only(&Encoder::new().encode(b"", ec_level).unwrap());
+        }
+
+        let strategies = [
+            (RmqrStrategy::Width, Version::Rmqr(11, 27)),
+            (RmqrStrategy::Height, Version::Rmqr(7, 43)),
+            (RmqrStrategy::Area, Version::Rmqr(11, 27)),
+            (RmqrStrategy::Perimeter, Version::Rmqr(11, 27)),
+        ];
+        for (strategy, version) in strategies {
+            for ec_level in [EcLevel::M, EcLevel::H] {
+                let code = QrCode::rmqr_with_options("", ec_level, strategy).unwrap();
+                assert_eq!(code.version(), version);
+                assert_padding_only(&code);
+            }
+        }
+        let code = QrCode::rmqr_or_qr(b"", EcLevel::L, RmqrStrategy::Area).unwrap();
+        assert_eq!(code.version(), Version::Rmqr(11, 27));
+        assert_padding_only(&code);
+    }
+
+    #[test]
+    fn test_every_version() {
+        let versions = (1..=10)
+            .map(Version::Normal)
+            .chain((1..=4).map(Version::Micro))
+            .chain(Version::rmqr_all());
+        for version in versions {
+            assert_padding_only(&QrCode::with_version_max_ec(b"", version).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_truncating() {
+        for (version, ec_level) in [
+            (Version::Normal(1), EcLevel::H),
+            (Version::Micro(1), EcLevel::L),
+            (Version::Rmqr(7, 43), EcLevel::M),
+        ] {
+            let (code, kept) =
+                QrCode::with_version_truncating(b"", version, ec_level, Some(b"...")).unwrap();
+            assert_eq!(kept, 0);
+            assert_padding_only(&code);
+        }
+    }
+
+    #[test]
+    fn test_bits() {
+        let mut bits = bits::Bits::new(Version::Micro(1));
+        bits.push_optimal_data(b"").unwrap();
+        assert_eq!(bits.len(), 0);
+        assert_eq!(
+            bits::encode_auto_rmqr(b"", EcLevel::M, RmqrStrategy::Area)
+                .unwrap()
+                .into_bytes(),
+            [0, 0xec, 0x11, 0xec, 0x11, 0xec, 0x11]
+        );
+    }
+}
+
+#[cfg(test)]
+mod remask_tests {
+    use super::*;
+
+    /// The code masked with every pattern available to its version.
+    fn all_masks(data: &[u8], version: Version, ec_level: EcLevel) -> Vec<QrCode> {
+        let mut bits = bits::Bits::new(version);
+        bits.push_optimal_data(data).unwrap();
+        bits.push_terminator(ec_level).unwrap();
+        let (encoded_data, ec_data) =
+            internals::ec::construct_codewords(&bits.into_bytes(), version, ec_level).unwrap();
+        internals::canvas::mask_patterns(version)
+            .iter()
+            .map(|pattern| {
+                let mut canvas = internals::canvas::Canvas::new(version, ec_level);
+                canvas.draw_all_functional_patterns();
+                canvas.draw_data(&encoded_data, &ec_data);
+                canvas.apply_mask(*pattern);
+                QrCode::from_masked_canvas(
+                    &canvas,
+                    version,
+                    ec_level,
+                    encoded_data.clone(),
+                    ec_data.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_metrics() {
+        let code = QrCode::with_version(b"01234567", Version::Normal(1), EcLevel::M).unwrap();
+        assert!(code.dark_ratio() > 0.3 && code.dark_ratio() < 0.7);
+        // The finder patterns have runs of 7 dark modules.
+        assert!(code.largest_dark_run() >= 7);
+    }
+
+    #[test]
+    fn test_remask_preferring() {
+        for (version, ec_level) in [
+            (Version::Normal(1), EcLevel::M),
+            (Version::Normal(7), EcLevel::H),
+            (Version::Micro(2), EcLevel::L),
+            (Version::Micro(4), EcLevel::Q),
+            (Version::Rmqr(11, 43), EcLevel::M),
+        ] {
+            let data = b"0123456789";
+            let code = QrCode::with_version(data, version, ec_level).unwrap();
+            let candidates = all_masks(data, version, ec_level);
+            assert!(candidates.iter().any(|c| c.to_colors() == code.to_colors()));
+
+            let remasked = code
+                .remask_preferring(MaskPreference::ShortestDarkRun)
+                .unwrap();
+            assert!(candidates
+                .iter()
+                .any(|c| c.to_colors() == remasked.to_colors()));
+            assert_eq!(remasked.final_codewords(), code.final_codewords());
+            assert_eq!(remasked.verify_render_geometry(), Ok(()));
+            let shortest = candidates.iter().map(QrCode::largest_dark_run).min();
+            assert_eq!(Some(remasked.largest_dark_run()), shortest, "{version:?}");
+
+            let remasked = code
+                .remask_preferring(MaskPreference::BalancedDarkRatio)
+                .unwrap();
+            assert!(candidates
+                .iter()
+                .any(|c| c.to_colors() == remasked.to_colors()));
+            let deviation = |c: &QrCode| (c.dark_ratio() - 0.5).abs();
+            for candidate in &candidates {
+                assert!(deviation(&remasked) <= deviation(candidate) + 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_keeps_part_info() {
+        let part = structured_append::PartInfo {
+            index: 0,
+            total: 2,
+            parity: 0x12,
+        };
+        let code = QrCode::with_structured_append(b"Hello", part, EcLevel::M).unwrap();
+        let remasked = code
+            .remask_preferring(MaskPreference::BalancedDarkRatio)
+            .unwrap();
+        assert_eq!(remasked.part_info(), Some(part));
+    }
+}
+
+#[cfg(test)]
+mod micro_tests {
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::QrCode;
+
+    fn version(data: &[u8]) -> Version {
+        QrCode::micro(data).unwrap().version()
+    }
+
+    #[test]
+    fn test_capacities() {
+        // The capacities of each version at L, then one character more.
+        let kanji = b"\x93\x5f";
+        for (micro, numeric, alphanumeric, kanji_count) in
+            [(1, 5, 0, 0), (2, 10, 6, 0), (3, 23, 14, 6), (4, 35, 21, 9)]
+        {
+            let expected = Version::Micro(micro);
+            assert_eq!(version(&vec![b'1'; numeric]), expected);
+            if micro < 4 {
+                assert!(version(&vec![b'1'; numeric + 1]) > expected);
+            }
+            if alphanumeric > 0 {
+                assert_eq!(version(&vec![b'A'; alphanumeric]), expected);
+            }
+            if kanji_count > 0 {
+                assert_eq!(version(&kanji.repeat(kanji_count)), expected);
+            }
+        }
+        assert_eq!(version(&kanji.repeat(7)), Version::Micro(4));
+        assert_eq!(version(b"A"), Version::Micro(2));
+        assert_eq!(version(kanji), Version::Micro(3));
+        assert_eq!(
+            QrCode::micro(kanji.repeat(10)).err(),
+            Some(QrError::DataTooLong)
+        );
+        assert_eq!(QrCode::micro([b'1'; 36]).err(), Some(QrError::DataTooLong));
+    }
+
+    #[test]
+    fn test_ec_levels() {
+        let code = QrCode::micro_with_options(b"HELLO", EcLevel::Q).unwrap();
+        assert_eq!(code.version(), Version::Micro(4));
+        assert_eq!(code.error_correction_level(), EcLevel::Q);
+        assert_eq!(
+            code.to_colors(),
+            QrCode::micro_with_error_correction_level(b"HELLO", EcLevel::Q)
+                .unwrap()
+                .to_colors()
+        );
+
+        // Fits M4-L and M4-M, but not M4-Q, although M4 supports Q.
+        for data in [&[b'A'; 15][..], &[b'7'; 30]] {
+            assert!(QrCode::micro_with_options(data, EcLevel::L).is_ok());
+            assert_eq!(
+                QrCode::micro_with_options(data, EcLevel::Q).err(),
+                Some(QrError::DataTooLong)
+            );
+        }
+        assert!(matches!(
+            QrCode::micro_with_options(b"1", EcLevel::H),
+            Err(QrError::EcLevelUnavailable(_))
+        ));
+        assert_eq!(
+            QrCode::micro_with_options([b'A'; 22], EcLevel::M).err(),
+            Some(QrError::DataTooLong)
+        );
+    }
+}
+
+#[cfg(test)]
+mod rmqr_or_qr_tests {
+    use super::*;
+
+    #[test]
+    fn test_small_payload_is_rmqr() {
+        let code = QrCode::rmqr_or_qr([b'a'; 100], EcLevel::M, RmqrStrategy::Area).unwrap();
+        assert!(code.version().is_rmqr());
+        assert_eq!(code.error_correction_level(), EcLevel::M);
+    }
+
+    #[test]
+    fn test_large_payload_is_normal() {
+        let code = QrCode::rmqr_or_qr([b'a'; 1000], EcLevel::M, RmqrStrategy::Area).unwrap();
+        assert!(matches!(code.version(), Version::Normal(_)));
+        assert_eq!(code.error_correction_level(), EcLevel::M);
+    }
+
+    #[test]
+    fn test_ec_level_mapping() {
+        let code = QrCode::rmqr_or_qr(b"Some data", EcLevel::L, RmqrStrategy::Area).unwrap();
+        assert_eq!(code.error_correction_level(), EcLevel::M);
+        let code = QrCode::rmqr_or_qr(b"Some data", EcLevel::Q, RmqrStrategy::Area).unwrap();
+        assert_eq!(code.error_correction_level(), EcLevel::H);
+
+        let code = QrCode::rmqr_or_qr([b'a'; 1000], EcLevel::Q, RmqrStrategy::Area).unwrap();
+        assert!(matches!(code.version(), Version::Normal(_)));
+        assert_eq!(code.error_correction_level(), EcLevel::Q);
+    }
+
+    #[test]
+    fn test_too_long() {
+        assert_eq!(
+            QrCode::rmqr_or_qr([b'a'; 3000], EcLevel::M, RmqrStrategy::Area).err(),
+            Some(types::QrError::DataTooLong)
+        );
+    }
+}
+
+#[cfg(test)]
+mod boosted_tests {
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::QrCode;
+
+    #[test]
+    fn test_boost() {
+        let code = QrCode::with_error_correction_level_boosted("HELLO WORLD", EcLevel::M).unwrap();
+        let expected = QrCode::with_version("HELLO WORLD", Version::Normal(1), EcLevel::Q).unwrap();
+        assert_eq!(code.version(), Version::Normal(1));
+        assert_eq!(code.error_correction_level(), EcLevel::Q);
+        assert_eq!(code.to_colors(), expected.to_colors());
+
+        // Version 1-L holds 17 bytes, and version 2-Q 20.
+        let data = [b'a'; 20];
+        let code = QrCode::with_error_correction_level_boosted(data, EcLevel::L).unwrap();
+        assert_eq!(code.version(), Version::Normal(2));
+        assert_eq!(code.error_correction_level(), EcLevel::Q);
+
+        let code = QrCode::with_error_correction_level_boosted(b"1", EcLevel::L).unwrap();
+        assert_eq!(code.error_correction_level(), EcLevel::H);
+    }
+
+    #[test]
+    fn test_no_boost() {
+        // Version 1-M holds 14 bytes, and version 1-Q only 11.
+        let data = [b'a'; 14];
+        let code = QrCode::with_error_correction_level_boosted(data, EcLevel::M).unwrap();
+        let expected = QrCode::with_error_correction_level(data, EcLevel::M).unwrap();
+        assert_eq!(code.version(), Version::Normal(1));
+        assert_eq!(code.error_correction_level(), EcLevel::M);
+        assert_eq!(code.to_colors(), expected.to_colors());
+
+        let code = QrCode::with_error_correction_level_boosted(b"HELLO", EcLevel::H).unwrap();
+        assert_eq!(code.error_correction_level(), EcLevel::H);
+
+        let result = QrCode::with_error_correction_level_boosted([b'a'; 3000], EcLevel::L);
+        assert_eq!(result.err(), Some(QrError::DataTooLong));
+    }
+}
+
+#[cfg(test)]
+mod forced_mode_tests {
+    use crate::bits::RmqrStrategy;
+    use crate::types::{EcLevel, Mode, QrError, Version};
+    use crate::QrCode;
+
+    #[test]
+    fn test_numeric_as_byte() {
+        let data = [b'7'; 40];
+        let auto = QrCode::with_error_correction_level(data, EcLevel::M).unwrap();
+        let byte = QrCode::with_forced_mode(data, Mode::Byte, EcLevel::M).unwrap();
+        assert_eq!(auto.version(), Version::Normal(2));
+        assert_eq!(byte.version(), Version::Normal(3));
+        let stats = byte.encode_stats().unwrap();
+        assert_eq!(stats.byte_bits, 320);
+        assert_eq!(stats.numeric_bits, 0);
+        assert_eq!(stats.header_bits, 4 + 8);
+
+        let numeric = QrCode::with_forced_mode(data, Mode::Numeric, EcLevel::M).unwrap();
+        assert_eq!(numeric.version(), auto.version());
+        assert_eq!(numeric.to_colors(), auto.to_colors());
+    }
+
+    #[test]
+    fn test_mixed_as_byte() {
+        // The optimal encoding mixes the numeric and byte modes.
+        let data = b"0123456789012345678901234567890123456789abc";
+        let auto = QrCode::with_error_correction_level(data, EcLevel::L).unwrap();
+        assert_ne!(auto.encode_stats().unwrap().numeric_bits, 0);
+        let byte = QrCode::with_forced_mode(data, Mode::Byte, EcLevel::L).unwrap();
+        let stats = byte.encode_stats().unwrap();
+        assert_eq!(stats.byte_bits, 8 * data.len());
+        assert_eq!(stats.data_bits(), stats.byte_bits);
+    }
+
+    #[test]
+    fn test_invalid_character() {
+        assert_eq!(
+            QrCode::with_forced_mode(b"hello", Mode::Alphanumeric, EcLevel::M).err(),
+            Some(QrError::InvalidCharacter)
+        );
+        assert_eq!(
+            QrCode::with_forced_mode(b"12a", Mode::Numeric, EcLevel::M).err(),
+            Some(QrError::InvalidCharacter)
+        );
+        // An odd number of bytes, and "点" followed by ASCII.
+        assert_eq!(
+            QrCode::with_forced_mode(b"\x93\x5f\x93", Mode::Kanji, EcLevel::M).err(),
+            Some(QrError::InvalidCharacter)
+        );
+        assert_eq!(
+            QrCode::with_forced_mode(b"\x93\x5fab", Mode::Kanji, EcLevel::M).err(),
+            Some(QrError::InvalidCharacter)
+        );
+        let strategy = RmqrStrategy::Area;
+        let rmqr =
+            QrCode::rmqr_with_forced_mode(b"hello", Mode::Alphanumeric, EcLevel::M, strategy);
+        assert_eq!(rmqr.err(), Some(QrError::InvalidCharacter));
+        assert!(QrCode::with_forced_mode(b"HELLO", Mode::Alphanumeric, EcLevel::M).is_ok());
+        assert!(QrCode::with_forced_mode(b"\x93\x5f", Mode::Kanji, EcLevel::M).is_ok());
+        assert!(QrCode::with_forced_mode(b"", Mode::Numeric, EcLevel::M).is_ok());
+    }
+
+    #[test]
+    fn test_rmqr() {
+        let digits = [b'1'; 12];
+        let strategy = RmqrStrategy::Area;
+        let auto = QrCode::rmqr_with_options(digits, EcLevel::M, strategy).unwrap();
+        let numeric = QrCode::rmqr_with_forced_mode(digits, Mode::Numeric, EcLevel::M, strategy);
+        assert_eq!(numeric.unwrap().version(), auto.version());
+        let byte = QrCode::rmqr_with_forced_mode(digits, Mode::Byte, EcLevel::M, strategy).unwrap();
+        assert!(byte.version().area() > auto.version().area());
+        assert_eq!(byte.encode_stats().unwrap().byte_bits, 96);
+    }
+
+    #[test]
+    fn test_too_long() {
+        assert_eq!(
+            QrCode::with_forced_mode([b'1'; 3000], Mode::Byte, EcLevel::L).err(),
+            Some(QrError::DataTooLong)
+        );
+        assert!(QrCode::with_forced_mode([b'1'; 3000], Mode::Numeric, EcLevel::L).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod with_segments_tests {
+    use crate::internals::coding::{optimal_segments, Segment};
+    use crate::types::{EcLevel, Mode, QrError, Version};
+    use crate::QrCode;
+
+    fn segment(mode: Mode, begin: usize, end: usize) -> Segment {
+        Segment { mode, begin, end }
+    }
+
+    #[test]
+    fn test_optimizer_segments() {
+        let long = b"Order 0123456789 for ACME \x93\x5f\x93\x5f";
+        let short = b"A1 0123456789\x93\x5f";
+        for (data, version, ec_level) in [
+            (&long[..], Version::Normal(3), EcLevel::M),
+            (long, Version::Normal(10), EcLevel::Q),
+            (long, Version::Normal(27), EcLevel::H),
+            (short, Version::Micro(4), EcLevel::L),
+            (long, Version::Rmqr(13, 43), EcLevel::M),
+        ] {
+            let segments = optimal_segments(data, version);
+            let code = QrCode::with_segments(data, &segments, version, ec_level).unwrap();
+            let expected = QrCode::with_version(data, version, ec_level).unwrap();
+            assert_eq!(code.to_colors(), expected.to_colors(), "{version:?}");
+            assert_eq!(code.encode_stats(), expected.encode_stats());
+        }
+    }
+
+    #[test]
+    fn test_custom_segments() {
+        let data = b"0123456789";
+        let byte = [segment(Mode::Byte, 0, 10)];
+        let code = QrCode::with_segments(data, &byte, Version::Normal(1), EcLevel::M).unwrap();
+        assert_eq!(code.encode_stats().unwrap().byte_bits, 80);
+        let split = [
+            segment(Mode::Numeric, 0, 5),
+            segment(Mode::Alphanumeric, 5, 10),
+        ];
+        let code = QrCode::with_segments(data, &split, Version::Normal(1), EcLevel::M).unwrap();
+        let stats = code.encode_stats().unwrap();
+        assert_eq!((stats.numeric_bits, stats.alphanumeric_bits), (17, 28));
+
+        let code = QrCode::with_segments(b"", &[], Version::Micro(1), EcLevel::L).unwrap();
+        assert_eq!(code.encode_stats().unwrap().data_bits(), 0);
+    }
+
+    #[test]
+    fn test_invalid_segments() {
+        let data = b"0123456789";
+        for segments in [
+            &[segment(Mode::Numeric, 0, 4)][..],
+            &[segment(Mode::Numeric, 0, 11)],
+            &[segment(Mode::Numeric, 1, 10)],
+            &[segment(Mode::Numeric, 0, 4), segment(Mode::Numeric, 5, 10)],
+            &[segment(Mode::Numeric, 0, 6), segment(Mode::Numeric, 4, 10)],
+            &[segment(Mode::Numeric, 0, 6), segment(Mode::Numeric, 6, 5)],
+            &[],
+        ] {
+            let result = QrCode::with_segments(data, segments, Version::Normal(1), EcLevel::M);
+            assert_eq!(result.err(), Some(QrError::InvalidSegment), "{segments:?}");
+        }
+        let kanji = [segment(Mode::Kanji, 0, 10)];
+        let result = QrCode::with_segments(data, &kanji, Version::Normal(1), EcLevel::M);
+        assert_eq!(result.err(), Some(QrError::InvalidCharacter));
+        let byte = [segment(Mode::Byte, 0, 10)];
+        let result = QrCode::with_segments(data, &byte, Version::Micro(2), EcLevel::L);
+        assert_eq!(
+            result.err(),
+            Some(QrError::ModeUnavailable {
+                version: Version::Micro(2),
+                mode: Mode::Byte,
+                smallest: Version::Micro(3),
+            })
+        );
+        let result = QrCode::with_segments(data, &byte, Version::Micro(3), EcLevel::L);
+        assert_eq!(result.err(), Some(QrError::DataTooLong));
+    }
+}
+
+#[cfg(test)]
+mod version_or_larger_tests {
+    use crate::types::{EcLevel, EcLevelUnavailable, QrError, Version};
+    use crate::QrCode;
+
+    fn version(data: &[u8], version: Version, ec_level: EcLevel) -> Result<Version, QrError> {
+        QrCode::with_version_or_larger(data, version, ec_level).map(|code| code.version())
+    }
+
+    #[test]
+    fn test_exact_fit() {
+        // Version 1-L holds 17 bytes.
+        let data = [b'a'; 17];
+        let code = QrCode::with_version_or_larger(data, Version::Normal(1), EcLevel::L).unwrap();
+        assert_eq!(code.version(), Version::Normal(1));
+        let expected = QrCode::with_version(data, Version::Normal(1), EcLevel::L).unwrap();
+        assert_eq!(code.to_colors(), expected.to_colors());
+        // A larger version is kept even if a smaller one would do.
+        assert_eq!(
+            version(&data, Version::Normal(5), EcLevel::L),
+            Ok(Version::Normal(5))
+        );
+    }
+
+    #[test]
+    fn test_growth() {
+        assert_eq!(
+            version(&[b'a'; 18], Version::Normal(1), EcLevel::L),
+            Ok(Version::Normal(2))
+        );
+        // M1 only holds digits, and M2 has no byte mode.
+        assert_eq!(
+            version(b"A", Version::Micro(1), EcLevel::L),
+            Ok(Version::Micro(2))
+        );
+        assert_eq!(
+            version(b"a", Version::Micro(1), EcLevel::L),
+            Ok(Version::Micro(3))
+        );
+        // R7x43-M holds 12 digits. R9x43 and R7x59 are larger than R13x27,
+        // but hold fewer than 28 digits.
+        let rmqr = Version::Rmqr(7, 43);
+        assert_eq!(version(&[b'1'; 12], rmqr, EcLevel::M), Ok(rmqr));
+        assert_eq!(
+            version(&[b'1'; 13], rmqr, EcLevel::M),
+            Ok(Version::Rmqr(13, 27))
+        );
+        assert_eq!(
+            version(&[b'1'; 28], rmqr, EcLevel::M),
+            Ok(Version::Rmqr(11, 43))
+        );
+    }
+
+    #[test]
+    fn test_exhaustion() {
+        assert_eq!(
+            version(&[b'a'; 3000], Version::Normal(38), EcLevel::L),
+            Err(QrError::DataTooLong)
+        );
+        assert_eq!(
+            version(&[b'a'; 20], Version::Micro(2), EcLevel::L),
+            Err(QrError::DataTooLong)
+        );
+        assert_eq!(
+            version(&[b'a'; 400], Version::Rmqr(7, 43), EcLevel::H),
+            Err(QrError::DataTooLong)
+        );
+        assert_eq!(
+            version(b"1", Version::Micro(1), EcLevel::M),
+            Err(QrError::EcLevelUnavailable(EcLevelUnavailable {
+                version: Version::Micro(1),
+                ec_level: EcLevel::M,
+                smallest: Some(Version::Micro(2)),
+            }))
+        );
+        assert_eq!(
+            version(b"1", Version::Normal(41), EcLevel::M),
+            Err(QrError::InvalidVersion)
+        );
+    }
+}
+
+#[cfg(test)]
+mod checked_version_tests {
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::{OversizedVersion, QrCode, SizePolicy};
+
+    // Version 10-M holds 213 bytes, version 9-M only 180.
+    const DATA: [u8; 200] = [b'a'; 200];
+
+    #[test]
+    fn test_exact() {
+        let (code, warning) =
+            QrCode::with_version_checked(DATA, Version::Normal(20), EcLevel::M, SizePolicy::Exact)
+                .unwrap();
+        assert_eq!(code.version(), Version::Normal(20));
+        assert_eq!(warning, None);
+    }
+
+    #[test]
+    fn test_warn_if_oversized() {
+        let policy = SizePolicy::WarnIfOversized(10);
+        let (_, warning) =
+            QrCode::with_version_checked(DATA, Version::Normal(20), EcLevel::M, policy).unwrap();
+        assert_eq!(warning, None);
+
+        let policy = SizePolicy::WarnIfOversized(9);
+        let (code, warning) =
+            QrCode::with_version_checked(DATA, Version::Normal(20), EcLevel::M, policy).unwrap();
+        assert_eq!(code.version(), Version::Normal(20));
+        assert_eq!(
+            warning,
+            Some(OversizedVersion {
+                version: Version::Normal(20),
+                minimum: Version::Normal(10),
+                gap: 10,
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_if_oversized() {
+        let policy = SizePolicy::ErrorIfOversized(10);
+        let (code, warning) =
+            QrCode::with_version_checked(DATA, Version::Normal(20), EcLevel::M, policy).unwrap();
+        assert_eq!(code.version(), Version::Normal(20));
+        assert_eq!(warning, None);
+
+        let policy = SizePolicy::ErrorIfOversized(9);
+        let result = QrCode::with_version_checked(DATA, Version::Normal(20), EcLevel::M, policy);
+        let error = QrError::VersionOversized {
+            requested: Version::Normal(20),
+            minimal: Version::Normal(10),
+        };
+        assert_eq!(result.err(), Some(error));
+        assert_eq!(
+            error.to_string(),
+            "version 20 larger than needed; 10 holds the data"
+        );
+        let result = QrCode::with_version_checked(DATA, Version::Normal(9), EcLevel::M, policy);
+        assert!(matches!(result, Err(QrError::DataTooLong)));
+    }
+
+    #[test]
+    fn test_micro_and_rmqr() {
+        let policy = SizePolicy::WarnIfOversized(0);
+        let (_, warning) =
+            QrCode::with_version_checked(b"123", Version::Micro(3), EcLevel::L, policy).unwrap();
+        let warning = warning.unwrap();
+        assert_eq!((warning.minimum, warning.gap), (Version::Micro(1), 2));
+
+        let (_, warning) =
+            QrCode::with_version_checked(b"123", Version::Rmqr(7, 59), EcLevel::M, policy).unwrap();
+        let warning = warning.unwrap();
+        // R11x27 has 297 modules, fewer than the 301 of R7x43.
+        assert_eq!(warning.minimum, Version::Rmqr(11, 27));
+        let (_, warning) =
+            QrCode::with_version_checked(b"123", Version::Rmqr(11, 27), EcLevel::M, policy)
+                .unwrap();
+        assert_eq!(warning, None);
+    }
+}
+
+#[cfg(test)]
+mod headers_tests {
+    use crate::bits::{Bits, Fnc1Kind, HeaderFlags};
+    use crate::structured_append::{parity, PartInfo};
+    use crate::{EcLevel, QrCode, Version};
+
+    fn encode(version: Version, push_headers: impl Fn(&mut Bits)) -> QrCode {
+        let mut bits = Bits::new(version);
+        push_headers(&mut bits);
+        bits.push_byte_data(b"data").unwrap();
+        bits.push_terminator(EcLevel::M).unwrap();
+        QrCode::with_bits(bits, EcLevel::M).unwrap()
+    }
+
+    #[test]
+    fn test_each_header() {
+        let code = encode(Version::Normal(1), |bits| {
+            bits.push_eci_designator(26).unwrap();
+        });
+        assert_eq!(
+            code.headers(),
+            HeaderFlags {
+                eci: Some(26),
+                ..Default::default()
+            }
+        );
+
+        let code = encode(Version::Rmqr(11, 27), |bits| {
+            bits.push_fnc1_first_position().unwrap();
+        });
+        assert_eq!(code.headers().fnc1, Some(Fnc1Kind::FirstPosition));
+        assert_eq!(code.headers().eci, None);
+
+        let part = PartInfo {
+            index: 0,
+            total: 2,
+            parity: parity(b"data"),
+        };
+        let code = QrCode::with_structured_append(b"data", part, EcLevel::M).unwrap();
+        assert_eq!(
+            code.headers(),
+            HeaderFlags {
+                structured_append: Some(part),
+                ..Default::default()
+            }
+        );
+        assert_eq!(code.part_info(), Some(part));
+
+        assert_eq!(
+            QrCode::new(b"data").unwrap().headers(),
+            HeaderFlags::default()
+        );
+    }
+
+    #[test]
+    fn test_combined_headers() {
+        let part = PartInfo {
+            index: 1,
+            total: 2,
+            parity: 0x42,
+        };
+        let code = encode(Version::Normal(2), |bits| {
+            bits.push_structured_append_header(part).unwrap();
+            bits.push_eci_designator(3).unwrap();
+            bits.push_fnc1_second_position(37).unwrap();
+        });
+        let headers = HeaderFlags {
+            eci: Some(3),
+            fnc1: Some(Fnc1Kind::SecondPosition {
+                application_indicator: 37,
+            }),
+            structured_append: Some(part),
+        };
+        assert_eq!(code.headers(), headers);
+        assert_eq!(code.part_info(), Some(part));
+
+        // The headers are kept when remasking.
+        let remasked = code
+            .remask_preferring(crate::MaskPreference::BalancedDarkRatio)
+            .unwrap();
+        assert_eq!(remasked.headers(), headers);
+    }
+}
+
+#[cfg(test)]
+mod encode_stats_tests {
+    use crate::bits::{BitStats, Bits};
+    use crate::types::Mode;
+    use crate::{EcLevel, MaskPreference, QrCode, RmqrStrategy, Version};
+
+    #[test]
+    fn test_totals() {
+        let codes = [
+            QrCode::with_error_correction_level(b"Hello, world! 0123456789", EcLevel::Q).unwrap(),
+            QrCode::micro_with_error_correction_level(b"HELLO 42", EcLevel::L).unwrap(),
+            QrCode::rmqr_with_options(b"SKU-0042-ABCDEFGH", EcLevel::H, RmqrStrategy::Area)
+                .unwrap(),
+        ];
+        for code in codes {
+            let stats = code.encode_stats().unwrap();
+            let data_len = Bits::new(code.version())
+                .max_len(code.error_correction_level())
+                .unwrap();
+            assert_eq!(stats.data_capacity_bits(), data_len, "{:?}", code.version());
+            assert_eq!(stats.total_bits(), data_len + stats.ec_bits);
+            // The 4-bit codeword of M3-L is stored in a whole byte.
+            assert_eq!(
+                data_len.div_ceil(8) * 8 + stats.ec_bits,
+                code.final_codewords().len() * 8
+            );
+            assert!(stats.header_bits > 0 && stats.padding_bits > 0);
+        }
+    }
+
+    #[test]
+    fn test_mode_mix() {
+        let code = QrCode::new(b"0123456789012345").unwrap();
+        assert_eq!(
+            code.encode_stats(),
+            Some(BitStats {
+                numeric_bits: 54,
+                alphanumeric_bits: 0,
+                byte_bits: 0,
+                kanji_bits: 0,
+                header_bits: 4 + 10,
+                padding_bits: 128 - 54 - 14,
+                ec_bits: 10 * 8,
+            })
+        );
+
+        let code = QrCode::new_utf8("ありがとう", EcLevel::M).unwrap();
+        let stats = code.encode_stats().unwrap();
+        assert_eq!(stats.byte_bits, 15 * 8);
+        assert_eq!(stats.kanji_bits, 0);
+        // The ECI header, then the mode and character count indicators.
+        assert_eq!(stats.header_bits, 4 + 8 + 4 + 8);
+
+        let code = QrCode::gs1(&[("01", "09501101530003"), ("10", "AB-123")], EcLevel::M).unwrap();
+        let stats = code.encode_stats().unwrap();
+        // The AI 10 is numeric too.
+        assert_eq!(stats.numeric_bits, Mode::Numeric.data_bits_count(18));
+        assert!(stats.alphanumeric_bits > 0);
+    }
+
+    #[test]
+    fn test_with_bits() {
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_numeric_data(b"01234567").unwrap();
+        bits.push_terminator(EcLevel::M).unwrap();
+        assert_eq!(
+            QrCode::with_bits(bits, EcLevel::M).unwrap().encode_stats(),
+            None
+        );
+
+        // The statistics are kept when remasking.
+        let code = QrCode::new(b"Some data").unwrap();
+        let remasked = code
+            .remask_preferring(MaskPreference::BalancedDarkRatio)
+            .unwrap();
+        assert_eq!(remasked.encode_stats(), code.encode_stats());
+    }
+}
+
+#[cfg(test)]
+mod utf8_tests {
+    use crate::bits::{inspect, Bits, InspectedSegment};
+    use crate::internals::canvas::{mask_patterns, Canvas};
+    use crate::types::{Color, QrError};
+    use crate::{EcLevel, QrCode, RmqrStrategy, Version};
+
+    /// Reads the codewords back from the modules of the symbol, as a reader
+    /// does: the mask is the one whose format information matches, and the
+    /// codeword bits are unmasked in placement order.
+    fn read_codewords(code: &QrCode) -> Vec<u8> {
+        let (version, ec_level) = (code.version(), code.error_correction_level());
+        let colors = code.to_colors();
+        let mut functional = Canvas::new(version, ec_level);
+        functional.draw_all_functional_patterns();
+        let order = functional.data_module_order();
+        let index = |(x, y): (i16, i16)| y as usize * code.width() + x as usize;
+        let masks = mask_patterns(version).iter().map(|pattern| {
+            let mut canvas = Canvas::new(version, ec_level);
+            canvas.draw_all_functional_patterns();
+            canvas.apply_mask(*pattern);
+            canvas.to_colors()
+        });
+        let data_modules = order
+            .iter()
+            .map(|module| index(*module))
+            .collect::<Vec<_>>();
+        let mut is_data = vec![false; colors.len()];
+        for i in &data_modules {
+            is_data[*i] = true;
+        }
+        let mask = masks
+            .filter(|mask| (0..colors.len()).all(|i| is_data[i] || mask[i] == colors[i]))
+            .collect::<Vec<_>>();
+        assert_eq!(mask.len(), 1, "ambiguous format information");
+        let bits = data_modules
+            .iter()
+            .map(|i| u8::from((colors[*i] == Color::Dark) != (mask[0][*i] == Color::Dark)));
+        let bits = bits.collect::<Vec<u8>>();
+        bits.chunks_exact(8)
+            .take(code.final_codewords().len())
+            .map(|byte| byte.iter().fold(0, |acc, bit| acc << 1 | bit))
+            .collect()
+    }
+
+    /// Decodes the text of a single block symbol.
+    fn decode(code: &QrCode) -> (Option<u32>, String) {
+        let codewords = read_codewords(code);
+        let data_len = Bits::new(code.version())
+            .max_len(code.error_correction_level())
+            .unwrap()
+            .div_ceil(8);
+        assert!(codewords.len() >= data_len);
+        assert!(code.final_codewords().starts_with(&codewords));
+        let (mut eci, mut data) = (None, vec![]);
+        for segment in inspect(&codewords[..data_len], code.version()).unwrap() {
+            match segment {
+                InspectedSegment::Eci(designator) => eci = Some(designator),
+                InspectedSegment::Data { data: bytes, .. } => data.extend(bytes),
+                _ => {}
+            }
+        }
+        (eci, String::from_utf8(data).unwrap())
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for text in ["ありがとう", "Grüße aus 東京 2024", "ok"] {
+            let code = QrCode::new_utf8(text, EcLevel::M).unwrap();
+            assert!(matches!(code.version(), Version::Normal(1..=3)));
+            assert_eq!(decode(&code), (Some(26), String::from(text)));
+
+            let code = QrCode::rmqr_utf8(text, EcLevel::M, RmqrStrategy::Width).unwrap();
+            assert!(code.version().is_rmqr());
+            assert_eq!(decode(&code), (Some(26), String::from(text)));
+        }
+    }
+
+    #[test]
+    fn test_version_boundary() {
+        let text = String::from("aééé");
+        let plain = QrCode::with_error_correction_level(&text, EcLevel::H).unwrap();
+        let code = QrCode::new_utf8(&text, EcLevel::H).unwrap();
+        assert_eq!(plain.version(), Version::Normal(1));
+        assert_eq!(code.version(), Version::Normal(2));
+        assert_eq!(decode(&code), (Some(26), text));
+    }
+
+    #[test]
+    fn test_too_long() {
+        let text = "🦀".repeat(800);
+        let error = QrError::TextTooLong {
+            chars: 800,
+            bytes: 3200,
+        };
+        assert_eq!(QrCode::new_utf8(&text, EcLevel::L).err(), Some(error));
+        let result = QrCode::rmqr_utf8(&text, EcLevel::M, RmqrStrategy::Area);
+        assert_eq!(result.err(), Some(error));
+    }
+}
+
+#[cfg(test)]
+mod truncating_tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_fit_is_untouched() {
+        // 17 bytes is the capacity of version 1-L in byte mode.
+        let data = b"abcdefghijklmnopq";
+        let (code, kept) =
+            QrCode::with_version_truncating(data, Version::Normal(1), EcLevel::L, Some(b"~"))
+                .unwrap();
+        assert_eq!(kept, data.len());
+        let expected = QrCode::with_version(data, Version::Normal(1), EcLevel::L).unwrap();
+        assert_eq!(code.to_colors(), expected.to_colors());
+    }
+
+    #[test]
+    fn test_oversized_is_truncated() {
+        let data = "日本語のテキストが長すぎる場合は切り詰められます";
+        let marker = "…".as_bytes();
+        let version = Version::Rmqr(11, 43);
+        let (code, kept) =
+            QrCode::with_version_truncating(data, version, EcLevel::M, Some(marker)).unwrap();
+        assert!(kept < data.len());
+        assert!(data.is_char_boundary(kept));
+
+        let truncated = [&data.as_bytes()[..kept], marker].concat();
+        let expected = QrCode::with_version(&truncated, version, EcLevel::M).unwrap();
+        assert_eq!(code.to_colors(), expected.to_colors());
+
+        let next = kept + data[kept..].chars().next().unwrap().len_utf8();
+        let longer = [&data.as_bytes()[..next], marker].concat();
+        assert!(QrCode::with_version(longer, version, EcLevel::M).is_err());
+    }
+
+    #[test]
+    fn test_marker_too_long() {
+        let res = QrCode::with_version_truncating(
+            b"0123456789",
+            Version::Micro(1),
+            EcLevel::L,
+            Some(b"..."),
+        );
+        assert!(res.is_err());
+    }
+}
+
+#[cfg(test)]
+mod codewords_tests {
+    use super::*;
+
+    #[test]
+    fn test_iso_18004_example() {
+        let code = QrCode::with_version(b"01234567", Version::Normal(1), EcLevel::M).unwrap();
+        assert_eq!(
+            code.final_codewords(),
+            vec![
+                0b0001_0000,
+                0b0010_0000,
+                0b0000_1100,
+                0b0101_0110,
+                0b0110_0001,
+                0b1000_0000,
+                0b1110_1100,
+                0b0001_0001,
+                0b1110_1100,
+                0b0001_0001,
+                0b1110_1100,
+                0b0001_0001,
+                0b1110_1100,
+                0b0001_0001,
+                0b1110_1100,
+                0b0001_0001,
+                0b1010_0101,
+                0b0010_0100,
+                0b1101_0100,
+                0b1100_0001,
+                0b1110_1101,
+                0b0011_0110,
+                0b1100_0111,
+                0b1000_0111,
+                0b0010_1100,
+                0b0101_0101,
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod safe_center_region_tests {
+    use super::*;
+
+    fn assert_avoids_functional_patterns(code: &QrCode, rect: geometry::ModuleRect) {
+        let mut canvas =
+            internals::canvas::Canvas::new(code.version(), code.error_correction_level());
+        canvas.draw_all_functional_patterns();
+        for point in rect.iter_points() {
+            assert!(
+                !canvas.is_drawn(point.x as i16, point.y as i16),
+                "{point:?} in {rect:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_normal_7_center_alignment_pattern() {
+        let code = QrCode::with_version(b"Some data", Version::Normal(7), EcLevel::H).unwrap();
+        let rect = code.safe_center_region(1.0).unwrap();
+        assert_avoids_functional_patterns(&code, rect);
+        // The center alignment pattern covers 20..=24 in both directions.
+        let overlaps_x = rect.x <= 24 && rect.x + rect.w > 20;
+        let overlaps_y = rect.y <= 24 && rect.y + rect.h > 20;
+        assert!(!(overlaps_x && overlaps_y));
+    }
+
+    #[test]
+    fn test_normal_2_fraction_cap() {
+        let code = QrCode::with_version(b"Some data", Version::Normal(2), EcLevel::M).unwrap();
+        let rect = code.safe_center_region(0.5).unwrap();
+        assert_avoids_functional_patterns(&code, rect);
+        assert_eq!(
+            rect,
+            geometry::ModuleRect {
+                x: 9,
+                y: 8,
+                w: 8,
+                h: 8
+            }
+        );
+
+        let rect = code.safe_center_region(0.05).unwrap();
+        assert!(rect.area() as f64 <= 0.05 * 625.0);
+        assert_eq!(rect.w, rect.h);
+    }
+
+    #[test]
+    fn test_rmqr_and_micro() {
+        let code = QrCode::with_version(b"Some data", Version::Rmqr(13, 77), EcLevel::H).unwrap();
+        let rect = code.safe_center_region(1.0).unwrap();
+        assert_avoids_functional_patterns(&code, rect);
+        assert!(rect.area() as f64 <= 0.3 * 13.0 * 77.0);
+
+        let code = QrCode::with_version(b"123", Version::Micro(2), EcLevel::M).unwrap();
+        let rect = code.safe_center_region(1.0).unwrap();
+        assert_avoids_functional_patterns(&code, rect);
+        assert_eq!(rect.w, rect.h);
+    }
+}
+
+#[cfg(test)]
+mod image_test {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_save_png() {
+        let test_dir = TempDir::new("__test__").unwrap();
+        let path = test_dir.path().join("rmqr.png");
+        let code = QrCode::new(b"Hello, rmqr!").unwrap();
+        let style = QrStyle::default();
+        code.save_png(path, &style).unwrap();
+    }
+    #[test]
+    fn test_save_svg() {
+        let test_dir = TempDir::new("__test__").unwrap();
+        let path = test_dir.path().join("rmqr.svg");
+        let code = QrCode::new(b"Hello, rmqr!").unwrap();
+        let style = QrStyle::default();
+        code.save_svg(path, &style).unwrap();
+    }
+
+    #[test]
+    fn test_svg_inverse_geometry() {
+        fn path_data(svg: &str) -> &str {
+            let start = svg.find(r#" d=""#).unwrap() + 4;
+            let len = svg[start..].find('"').unwrap();
+            &svg[start..start + len]
+        }
+
+        for version in [Version::Normal(2), Version::Micro(3), Version::Rmqr(11, 43)] {
+            let code = QrCode::with_version(b"HELLO 42", version, EcLevel::M).unwrap();
+            let (width, height) = (code.width(), code.height());
+            let style = QrStyle::default();
+            let dark =
+                render::rasterize_square_path(path_data(&code.to_svg(&style)), width, height);
+            for include_quiet_zone in [false, true] {
+                let svg = code.to_svg_inverse_geometry(&style, include_quiet_zone);
+                let light = render::rasterize_square_path(path_data(&svg), width, height);
+                assert!(dark.iter().zip(&light).all(|(dark, light)| dark ^ light));
+            }
+        }
+
+        let code = QrCode::with_version(b"Hello, rmqr!", Version::Normal(2), EcLevel::L).unwrap();
+        let style = QrStyle {
+            width: 33 * 4,
+            quiet_zone: 4.0,
+            ..Default::default()
+        };
+        let svg = code.to_svg_inverse_geometry(&style, true);
+        assert!(path_data(&svg).starts_with("M-4 -4h33v33h-33ZM"));
+        assert!(svg.contains(r##"<path fill="#ffffff""##));
+        assert!(svg.contains(r##"width="33" height="33" fill="#ffffff"/>"##));
+        assert!(svg.contains(r##"x="-4" y="-4" width="33" height="33" fill="#000000"/>"##));
+        let pixmap = code.to_pixmap(&style).unwrap();
+        assert_eq!(rasterize_svg(&svg, 132, 132).unwrap().data(), pixmap.data());
+        let svg = code.to_svg_inverse_geometry(&style, false);
+        assert!(path_data(&svg).starts_with("M0 0h25v25h-25ZM"));
+        assert!(svg.contains(r##"x="0" y="0" width="25" height="25" fill="#000000"/>"##));
+        assert_eq!(rasterize_svg(&svg, 132, 132).unwrap().data(), pixmap.data());
+    }
+
+    #[test]
+    fn test_fractional_height() {
+        let code = QrCode::with_version(b"12345", Version::Rmqr(7, 43), EcLevel::M).unwrap();
+        for width in 100..120 {
+            let style = QrStyle {
+                width,
+                ..Default::default()
+            };
+            let (vb_width, vb_height, image_width, image_height) = code.image_sizes(&style);
+            let pixmap = code.to_pixmap(&style).unwrap();
+            assert_eq!(
+                (pixmap.width(), pixmap.height()),
+                (image_width, image_height)
+            );
+
+            // Every pixel lying within a single module, or within the quiet
+            // zone, has its color, so the quiet zone is neither thickened nor
+            // clipped.
+            let (scale_x, scale_y) = (
+                f64::from(image_width) / vb_width,
+                f64::from(image_height) / vb_height,
+            );
+            let module = |px: u32, scale: f64, len: usize| {
+                let (start, end) = (f64::from(px) / scale - 2.0, f64::from(px + 1) / scale - 2.0);
+                let module = start.floor();
+                let inside = end <= module + 1.0 || start < 0.0 && end <= 0.0;
+                let inside = inside || start >= len as f64;
+                inside.then_some(module)
+            };
+            let mut dark_modules = std::collections::HashSet::new();
+            for (i, pixel) in pixmap.pixels().iter().enumerate() {
+                let (px, py) = (i as u32 % image_width, i as u32 / image_width);
+                let (Some(x), Some(y)) = (
+                    module(px, scale_x, code.width()),
+                    module(py, scale_y, code.height()),
+                ) else {
+                    continue;
+                };
+                let color = if x < 0.0 || y < 0.0 {
+                    Color::Light
+                } else {
+                    code.get((x as u32, y as u32)).unwrap_or(Color::Light)
+                };
+                let expected = match color {
+                    Color::Dark => (0, 0, 0),
+                    Color::Light => (255, 255, 255),
+                };
+                assert_eq!(
+                    (pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()),
+                    (expected.0, expected.1, expected.2, 255),
+                    "width {width}, pixel ({px}, {py})"
+                );
+                if color == Color::Dark {
+                    dark_modules.insert((x as u32, y as u32));
+                }
+            }
+            let dark_count = code
+                .to_colors()
+                .iter()
+                .filter(|c| **c == Color::Dark)
+                .count();
+            assert_eq!(dark_modules.len(), dark_count, "width {width}");
+        }
+    }
+
+    #[test]
+    fn test_degenerate_matrices() {
+        struct Matrix(usize, usize, Color);
+
+        impl Renderable for Matrix {
+            fn version(&self) -> Version {
+                Version::Micro(1)
+            }
+
+            fn width(&self) -> usize {
+                self.0
+            }
+
+            fn height(&self) -> usize {
+                self.1
+            }
+
+            fn module_color(&self, _: usize, _: usize) -> Color {
+                self.2
+            }
+        }
+
+        for shape in [QrShape::Square, QrShape::Round] {
+            let style = QrStyle {
+                shape,
+                width: 50,
+                ..Default::default()
+            };
+            for (width, height) in [(1, 1), (1, 6), (6, 1), (4, 4)] {
+                let light = Matrix(width, height, Color::Light);
+                assert!(Renderable::to_svg(&light, &style).contains(r#" d=""/>"#));
+                let pixmap = Renderable::to_pixmap(&light, &style).unwrap();
+                assert!(pixmap.pixels().iter().all(|p| p.red() == 255));
+
+                let dark = Matrix(width, height, Color::Dark);
+                let svg = Renderable::to_svg(&dark, &style);
+                assert_eq!(svg.matches('M').count(), 1, "{svg}");
+                assert_eq!(matches!(shape, QrShape::Round), svg.contains('q'), "{svg}");
+                assert!(Renderable::to_pixmap(&dark, &style).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_preview_pixmap() {
+        for version in [Version::Normal(40), Version::Micro(3), Version::Rmqr(9, 59)] {
+            let code = QrCode::with_version(b"HELLO 42", version, EcLevel::M).unwrap();
+            let quiet_zone = usize::from(version.recommended_quiet_zone());
+            let (width, height) = (
+                code.width() + 2 * quiet_zone,
+                code.height() + 2 * quiet_zone,
+            );
+            for module_px in 1..=3 {
+                let max_px = (width.max(height) * module_px) as u32;
+                for max_px in [max_px, max_px + 1] {
+                    let preview = code.to_preview_pixmap(max_px).unwrap();
+                    assert_eq!(preview.width() as usize, width * module_px);
+                    assert_eq!(preview.height() as usize, height * module_px);
+                }
+                let style = QrStyle {
+                    width: (width * module_px) as u32,
+                    quiet_zone_sides: Some(QuietZone::Auto),
+                    ..Default::default()
+                };
+                let preview = code.to_preview_pixmap(max_px).unwrap();
+                assert!(preview.data() == code.to_pixmap(&style).unwrap().data());
+            }
+            let preview = code.to_preview_pixmap(10_000).unwrap();
+            assert_eq!(preview.width() as usize, width * 3);
+            assert!(matches!(
+                code.to_preview_pixmap(width.max(height) as u32 - 1),
+                Err(RenderError::ModuleTooSmall { minimum: 1.0, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_svg_minimal() {
+        let code =
+            QrCode::with_version(b"Hello, rmqr!", Version::Rmqr(11, 43), EcLevel::M).unwrap();
+        let svg = code.to_svg_minimal(2.0).unwrap();
+        let open_tag = svg.lines().nth(1).unwrap();
+        assert_eq!(
+            open_tag,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" viewBox="0 0 47 15">"#
+        );
+        assert!(svg.contains("translate(2,2)"));
+        assert!(svg.contains(r#"<rect x="0" y="0" width="47" height="15""#));
+
+        let svg = code.to_svg_minimal(0.5).unwrap();
+        assert!(svg.contains(r#"viewBox="0 0 44 12""#));
+        assert!(!svg.lines().nth(1).unwrap().contains("width="));
+
+        assert_eq!(
+            code.to_svg_minimal(-1.0),
+            Err(StyleError::NegativeQuietZone(-1.0))
+        );
+        assert!(matches!(
+            code.to_svg_minimal(f64::NAN),
+            Err(StyleError::NonFiniteQuietZone(modules)) if modules.is_nan()
+        ));
+    }
+
+    #[test]
+    fn test_svg_numbers() {
+        // 0.1 + 0.2 is 0.30000000000000004, and the viewBox 21.6 is
+        // 21.600000000000001 before rounding.
+        let code = QrCode::new(b"Hello").unwrap();
+        let style = QrStyle {
+            quiet_zone: 0.1 + 0.2,
+            prepress: Some(Prepress {
+                bleed_modules: 0.1,
+                crop_marks: true,
+                registration_color: false,
+            }),
+            ..Default::default()
+        };
+        let svg = code.to_svg(&style);
+        assert_eq!(
+            svg.lines().nth(1).unwrap(),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="727" height="727" preserveAspectRatio="none" viewBox="-0.1 -0.1 21.8 21.8">"#
+        );
+        assert!(svg.contains(r#"<rect x="-0.1" y="-0.1" width="21.8" height="21.8""#));
+        assert!(svg.contains("translate(0.3,0.3)"));
+        assert!(svg.contains(r#"stroke-width="0.005""#));
+        assert!(svg.contains(r#"<line x1="21.65" y1="0" x2="21.7" y2="0"/>"#));
+
+        let svg = code.to_svg_inverse_geometry(&style, true);
+        assert!(svg.contains(r#"d="M-0.3 -0.3h21.6v21.6h-21.6ZM"#));
+
+        // Without bleed, the origin is 0 rather than -0.
+        let svg = code.to_svg(&QrStyle::default());
+        assert!(svg.contains(r#"viewBox="0 0 "#));
+        assert!(!svg.contains("-0 "));
+    }
+
+    /// Fills the rectangles as a single path over the background layer, as a
+    /// native canvas would.
+    fn fill_pixel_rects(
+        code: &QrCode,
+        style: &QrStyle,
+        rects: impl Iterator<Item = geometry::PixelRect>,
+    ) -> resvg::tiny_skia::Pixmap {
+        use resvg::tiny_skia::{Color, FillRule, Paint, PathBuilder, Rect, Transform};
+
+        let (_, _, width, height) = code.image_sizes(style);
+        let (_, background) = &code.to_svg_layers(style)[0];
+        let mut pixmap = rasterize_svg(background, width, height).unwrap();
+        let mut builder = PathBuilder::new();
+        for rect in rects {
+            let (right, bottom) = (rect.x + rect.w, rect.y + rect.h);
+            let rect = Rect::from_ltrb(rect.x as f32, rect.y as f32, right as f32, bottom as f32);
+            builder.push_rect(rect.unwrap());
+        }
+        let mut paint = Paint::default();
+        paint.set_color(Color::BLACK);
+        paint.anti_alias = true;
+        let path = builder.finish().unwrap();
+        pixmap.fill_path(
+            &path,
+            &paint,
+            FillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+        pixmap
+    }
+
+    #[test]
+    fn test_pixel_rects() {
+        let codes = [
+            QrCode::new(b"Hello, pixel rects!").unwrap(),
+            QrCode::with_version(b"0123", Version::Micro(2), EcLevel::L).unwrap(),
+            QrCode::rmqr(b"Hello, rmqr!").unwrap(),
+        ];
+        let quiet_zones = [
+            QuietZone::Auto,
+            QuietZone::Sides {
+                top: 1.0,
+                right: 2.5,
+                bottom: 3.0,
+                left: 0.5,
+            },
+        ];
+        for code in &codes {
+            for quiet_zone in quiet_zones {
+                for width in [code.width() as u32 * 8, 333, 720] {
+                    let style = QrStyle {
+                        width,
+                        quiet_zone_sides: Some(quiet_zone),
+                        ..Default::default()
+                    };
+                    let expected = code.to_pixmap(&style).unwrap();
+                    let runs = fill_pixel_rects(code, &style, code.pixel_rects(&style));
+                    assert!(
+                        runs.data() == expected.data(),
+                        "{:?} {width}",
+                        code.version()
+                    );
+
+                    // Adjacent modules share edges, which may round the
+                    // coverage of the pixels along them differently.
+                    let modules = fill_pixel_rects(code, &style, code.pixel_module_rects(&style));
+                    assert!(modules
+                        .data()
+                        .iter()
+                        .zip(expected.data())
+                        .all(|(a, b)| a.abs_diff(*b) <= 1));
+                }
+            }
+        }
+
+        let code = &codes[0];
+        let style = QrStyle::default();
+        let dark_count = code
+            .to_colors()
+            .iter()
+            .filter(|&&c| c == Color::Dark)
+            .count();
+        assert_eq!(code.pixel_module_rects(&style).count(), dark_count);
+        assert!(code.pixel_rects(&style).count() < dark_count);
+    }
+
+    #[test]
+    fn test_verify_render_geometry() {
+        let versions = (1..=40)
+            .map(Version::Normal)
+            .chain((1..=4).map(Version::Micro))
+            .chain(Version::rmqr_all());
+        for version in versions {
+            let ec_level = match version {
+                Version::Rmqr(_, _) => EcLevel::M,
+                _ => EcLevel::L,
+            };
+            let code = QrCode::with_version(b"01234", version, ec_level).unwrap();
+            assert_eq!(code.verify_render_geometry(), Ok(()), "{version:?}");
+        }
+    }
+
+    #[test]
+    fn test_caption() {
+        let code = QrCode::new(b"Hello").unwrap();
+        let plain = QrStyle {
+            quiet_zone: 4.0,
+            width: 29 * 10,
+            ..Default::default()
+        };
+        let style = QrStyle {
+            caption: Some(Caption::new("<Tom & \"Jerry\">")),
+            ..plain
+        };
+        // 21 modules + 8 for the quiet zone + 3 for the caption.
+        assert_eq!(code.image_sizes(&style), (29.0, 32.0, 290, 320));
+
+        let svg = code.to_svg(&style);
+        assert!(svg.contains(
+            r##"<text x="14.5" y="31" font-family="sans-serif" font-size="2" text-anchor="middle" fill="#000000">&lt;Tom &amp; &quot;Jerry&quot;&gt;</text>"##
+        ));
+        let layers = code.to_svg_layers(&style);
+        assert_eq!(layers.last().unwrap().0, "caption");
+
+        // 21 modules fit 15 half-width characters of 1.2 modules each, plus
+        // the 2-module ellipsis.
+        let style = QrStyle {
+            caption: Some(Caption::new("01234567890123456789")),
+            ..Default::default()
+        };
+        assert!(code.to_svg(&style).contains(">012345678901234…</text>"));
+
+        let style = QrStyle {
+            caption: Some(Caption {
+                path_fallback: true,
+                align: CaptionAlign::Start,
+                ..Caption::new("a b")
+            }),
+            ..Default::default()
+        };
+        let svg = code.to_svg(&style);
+        assert!(!svg.contains("<text"));
+        assert_eq!(
+            svg.matches('Z').count() - code.to_svg(&QrStyle::default()).matches('Z').count(),
+            2
+        );
+
+        let style = QrStyle {
+            caption: Some(Caption {
+                size_modules: 0.0,
+                ..Caption::new("a")
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            code.to_pixmap(&style).unwrap_err().to_string(),
+            "invalid caption size of 0 modules"
+        );
+    }
+
+    #[test]
+    fn test_prepress() {
+        let code = QrCode::new(b"Hello").unwrap();
+        let plain = || QrStyle {
+            color: String::from("#0000cc"),
+            quiet_zone: 4.0,
+            width: 29 * 10,
+            ..Default::default()
+        };
+        let style = QrStyle {
+            prepress: Some(Prepress {
+                bleed_modules: 3.0,
+                crop_marks: true,
+                registration_color: true,
+            }),
+            ..plain()
+        };
+        assert_eq!(code.trim_sizes(&style), (29.0, 29.0, 290, 290));
+        assert_eq!(code.image_sizes(&style), (35.0, 35.0, 350, 350));
+        assert_eq!(style.module_px(code.version()), 10.0);
+
+        let svg = code.to_svg(&style);
+        assert!(svg.contains(r#"viewBox="-3 -3 35 35""#));
+        assert!(svg.contains(r#"<rect x="-3" y="-3" width="35" height="35""#));
+        assert_eq!(svg.matches("<line ").count(), 8);
+        assert!(svg.contains(r##"<g id="crop-marks" stroke="#000000""##));
+        assert!(svg.contains(r#"<line x1="-1.5" y1="0" x2="-3" y2="0"/>"#));
+        assert!(svg.contains(r#"<line x1="29" y1="30.5" x2="29" y2="32"/>"#));
+        let path = |svg: &str| {
+            svg.lines()
+                .find(|line| line.starts_with("<path"))
+                .unwrap()
+                .to_owned()
+        };
+        assert_eq!(path(&svg), path(&code.to_svg(&plain())));
+        assert_eq!(code.to_svg_layers(&style).last().unwrap().0, "crop-marks");
+
+        // The symbol is shifted by the 30-pixel bleed in the image.
+        let pixmap = code.to_pixmap(&style).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (350, 350));
+        let is_dark = |x: u32, y: u32| pixmap.pixel(x, y).unwrap().red() < 128;
+        assert!(is_dark(75, 75));
+        assert!(!is_dark(65, 75));
+        let first = code.pixel_rects(&style).next().unwrap();
+        assert_eq!((first.x, first.y), (70.0, 70.0));
+
+        let style = QrStyle {
+            prepress: Some(Prepress {
+                bleed_modules: 2.0,
+                crop_marks: false,
+                registration_color: false,
+            }),
+            ..Default::default()
+        };
+        assert!(!code.to_svg(&style).contains("<line "));
+        // Without bleed, the crop marks would have no length.
+        let style = QrStyle {
+            prepress: Some(Prepress {
+                bleed_modules: 0.0,
+                crop_marks: true,
+                registration_color: false,
+            }),
+            ..Default::default()
+        };
+        let svg = code.to_svg(&style);
+        assert!(!svg.contains("crop-marks") && !svg.contains("<line "));
+        assert_ne!(code.to_svg_layers(&style).last().unwrap().0, "crop-marks");
+        let style = QrStyle {
+            prepress: Some(Prepress {
+                bleed_modules: -1.0,
+                crop_marks: true,
+                registration_color: false,
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            code.to_pixmap(&style),
+            Err(RenderError::Style(StyleError::InvalidBleed(_)))
+        ));
+    }
+
+    #[test]
+    fn test_caption_without_fonts() {
+        let code = QrCode::new(b"Hello").unwrap();
+        let style = QrStyle {
+            caption: Some(Caption::new("Hello")),
+            width: 100,
+            ..Default::default()
+        };
+        let (_, _, width, height) = code.image_sizes(&style);
+        let fonts = resvg::usvg::fontdb::Database::new();
+        let pixmap =
+            rasterize_svg_with_fonts(&code.to_svg(&style), width, height, Some(&fonts)).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (width, height));
+        code.to_pixmap(&style).unwrap();
+    }
+
+    #[test]
+    fn test_save_svg2() {
+        let test_dir = TempDir::new("__test__").unwrap();
+        let path = test_dir.path().join("micro_qr_m3_l.svg");
+        let code =
+            QrCode::with_version("11111111111111111111", Version::Micro(3), EcLevel::L).unwrap();
+        let style = QrStyle::default();
+        code.save_svg(path, &style).unwrap();
+    }
+
+    #[test]
+    fn test_svg_fragment() {
+        let code = QrCode::rmqr(b"Hello, rmqr!").unwrap();
+        let style = QrStyle::default();
+        let fragment = code.to_svg_fragment(&style);
+        assert!(fragment.starts_with("<svg "));
+        assert!(fragment.ends_with("</svg>"));
+        assert!(fragment
+            .lines()
+            .all(|line| !line.starts_with(char::is_whitespace)));
+        let opt = resvg::usvg::Options::default();
+        assert!(<resvg::usvg::Tree as resvg::usvg::TreeParsing>::from_str(&fragment, &opt).is_ok());
+
+        let svg = code.to_svg(&style);
+        assert_eq!(
+            svg,
+            format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{fragment}")
+        );
+        assert!(svg
+            .lines()
+            .all(|line| !line.starts_with(char::is_whitespace)));
+    }
+
+    #[test]
+    fn test_svg_hostile_colors() {
+        let code = QrCode::new(b"Hello").unwrap();
+        let hostile = r#"red"/><script>alert(1)</script><rect fill="#;
+        let style = QrStyle {
+            color: String::from(hostile),
+            background_color: String::from("white' onload='alert(1)"),
+            caption: Some(Caption::new("caption")),
+            prepress: Some(Prepress {
+                bleed_modules: 2.0,
+                crop_marks: true,
+                registration_color: false,
+            }),
+            ..Default::default()
+        };
+        assert!(style.check().is_err());
+
+        let opt = resvg::usvg::Options::default();
+        for svg in [
+            code.to_svg(&style),
+            code.to_svg_fragment(&style),
+            code.to_svg_inverse_geometry(&style, true),
+        ] {
+            assert!(!svg.contains("<script"), "{svg}");
+            assert!(!svg.contains("onload='"), "{svg}");
+            assert!(svg.contains("red&quot;/&gt;&lt;script&gt;"), "{svg}");
+            let tree = <resvg::usvg::Tree as resvg::usvg::TreeParsing>::from_str(&svg, &opt);
+            assert!(tree.is_ok(), "{svg}");
+        }
+    }
+
+    #[test]
+    fn test_svg_layers() {
+        let code = QrCode::new(b"Hello, layers!").unwrap();
+        let style = QrStyle {
+            width: (code.width() as u32 + 8) * 4,
+            quiet_zone: 4.0,
+            ..Default::default()
+        };
+        let layers = code.to_svg_layers(&style);
+        assert_eq!(
+            layers
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            ["background", "function", "data"]
+        );
+
+        let expected = code.to_pixmap(&style).unwrap();
+        let mut stacked =
+            resvg::tiny_skia::Pixmap::new(expected.width(), expected.height()).unwrap();
+        for (_, document) in &layers {
+            let layer = rasterize_svg(document, expected.width(), expected.height()).unwrap();
+            stacked.draw_pixmap(
+                0,
+                0,
+                layer.as_ref(),
+                &resvg::tiny_skia::PixmapPaint::default(),
+                resvg::tiny_skia::Transform::identity(),
+                None,
+            );
+        }
+        assert!(stacked.data() == expected.data());
+
+        let preview = code.to_svg_layers_preview(&style);
+        for id in ["layer-background", "layer-function", "layer-data"] {
+            assert!(preview.contains(&format!(r#"<g id="{id}">"#)));
+        }
+        let preview = rasterize_svg(&preview, expected.width(), expected.height()).unwrap();
+        assert!(preview.data() == expected.data());
+    }
+
+    #[test]
+    fn test_asymmetric_quiet_zone() {
+        let code = QrCode::new(b"01234567").unwrap();
+        let style = QrStyle {
+            width: (21 + 6 + 2) * 10,
+            quiet_zone_sides: Some(QuietZone::Sides {
+                top: 1.0,
+                right: 2.0,
+                bottom: 3.0,
+                left: 6.0,
+            }),
+            ..Default::default()
+        };
+        let (vb_width, vb_height, width, height) = code.image_sizes(&style);
+        assert_eq!((vb_width, vb_height), (29.0, 25.0));
+        assert_eq!((width, height), (290, 250));
+        assert!(code.to_svg(&style).contains(r#"viewBox="0 0 29 25""#));
+        assert!(code.to_svg(&style).contains("translate(6,1)"));
+
+        // The top left module of the finder pattern is dark, and the modules
+        // to the left and above of it are light.
+        let pixmap = code.to_pixmap(&style).unwrap();
+        assert_eq!(pixmap.pixel(65, 15).unwrap().red(), 0);
+        assert_eq!(pixmap.pixel(55, 15).unwrap().red(), 255);
+        assert_eq!(pixmap.pixel(65, 5).unwrap().red(), 255);
+        // The bottom right corner of the symbol.
+        assert_eq!(pixmap.pixel(265, 215).unwrap().red(), 255);
+        assert_eq!(pixmap.pixel(275, 225).unwrap().red(), 255);
     }
 
-    /// Converts the QR code to a vector of colors.
-    pub fn into_colors(self) -> Vec<Color> {
-        self.content
+    #[test]
+    fn test_cmyk_colors() {
+        let code = QrCode::new(b"01234567").unwrap();
+        let style = |color: String, background_color: String| QrStyle {
+            color,
+            background_color,
+            width: 100,
+            caption: Some(Caption::new("CMYK")),
+            prepress: Some(Prepress {
+                bleed_modules: 2.0,
+                crop_marks: true,
+                registration_color: false,
+            }),
+            ..Default::default()
+        };
+        let rgb = style(String::from("#1a3366"), String::from("#ffffff"));
+        let rgb_svg = code.to_svg(&rgb);
+        assert!(!rgb_svg.contains("device-cmyk"));
+
+        let color = Cmyk::new(0.75, 0.5, 0.0, 0.6);
+        assert_eq!(color.to_rgb(), [0x1a, 0x33, 0x66]);
+        let cmyk = style(color.to_string(), Cmyk::new(0.0, 0.0, 0.0, 0.0).to_string());
+        assert!(QrStyle::check(&cmyk).is_ok());
+        let svg = code.to_svg(&cmyk);
+        assert!(svg.contains(r##"<path fill="#1a3366 device-cmyk(0.75 0.5 0 0.6)""##));
+        assert!(svg.contains(r##"fill="#ffffff device-cmyk(0 0 0 0)"/>"##));
+        assert!(
+            svg.contains(r##"text-anchor="middle" fill="#1a3366 device-cmyk(0.75 0.5 0 0.6)""##)
+        );
+        assert!(svg.contains(r##"stroke="#1a3366 device-cmyk(0.75 0.5 0 0.6)""##));
+        assert_eq!(strip_cmyk(&svg), rgb_svg);
+
+        // Text mentioning a CMYK color is not an attribute value.
+        let caption = Some(Caption::new("a device-cmyk(1)"));
+        let rgb_caption = QrStyle {
+            caption: caption.clone(),
+            ..style(String::from("#1a3366"), String::from("#ffffff"))
+        };
+        let cmyk_caption = QrStyle {
+            caption,
+            ..style(color.to_string(), Cmyk::new(0.0, 0.0, 0.0, 0.0).to_string())
+        };
+        let rgb_svg = code.to_svg(&rgb_caption);
+        assert!(rgb_svg.contains(">a device-cmyk(1)</text>"));
+        assert_eq!(strip_cmyk(&rgb_svg), rgb_svg);
+        assert_eq!(strip_cmyk(&code.to_svg(&cmyk_caption)), rgb_svg);
+
+        // The rasterized image uses the RGB fallback.
+        let pixmap = code.to_pixmap(&cmyk).unwrap();
+        assert!(pixmap.data() == code.to_pixmap(&rgb).unwrap().data());
+        assert!(pixmap
+            .pixels()
+            .iter()
+            .any(|p| (p.red(), p.green(), p.blue()) == (0x1a, 0x33, 0x66)));
     }
 
-    /// Converts the QR code into a human-readable string.
-    pub fn to_str(&self, dark: char, light: char) -> String {
-        let mut s = String::new();
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let color = self.content[y * self.width + x];
-                s.push(if color == Color::Dark { dark } else { light });
+    #[test]
+    fn test_cmyk_clamping() {
+        assert_eq!(
+            Cmyk::new(-1.0, 2.0, f64::NAN, 0.5),
+            Cmyk {
+                c: 0.0,
+                m: 1.0,
+                y: 0.0,
+                k: 0.5
             }
-            s.push('\n');
+        );
+        assert_eq!(
+            Cmyk::new(1.5, 0.0, 0.0, -0.1).to_string(),
+            "#00ffff device-cmyk(1 0 0 0)"
+        );
+        assert_eq!(
+            Cmyk::parse("red device-cmyk(0, 1.2, 0.5, -3)"),
+            Some(Cmyk::new(0.0, 1.0, 0.5, 0.0))
+        );
+        for color in [
+            "#000 device-cmyk(0 0 0)",
+            "#000 device-cmyk(0 0 0 0 0)",
+            "#000 device-cmyk(0 0 0 x)",
+            "#000 device-cmyk(0 0 0 inf)",
+            "#000 device-cmyk(0 0 0 0",
+            "#000 device-cmyk(0 0 0 0) x",
+            "#00000g device-cmyk(0 0 0 1)",
+        ] {
+            let style = QrStyle {
+                color: String::from(color),
+                ..Default::default()
+            };
+            assert_eq!(
+                QrStyle::check(&style),
+                Err(StyleError::InvalidColor(String::from(color)))
+            );
         }
-        s
     }
 
-    /// Constructs a new rMQR code which automatically encodes the given data.
-    /// This method uses the "medium" error correction level and automatically
-    ///
-    ///     use qrqrpar::QrCode;
-    ///    
-    ///     let code = QrCode::rmqr(b"Some data").unwrap();
-    ///
-    /// # Errors
-    ///
-    /// Returns error if the QR code cannot be constructed, e.g. when the data
-    /// is too long.
-    pub fn rmqr<D: AsRef<[u8]>>(data: D) -> QrResult<Self> {
-        Self::rmqr_with_options(data, EcLevel::M, bits::RmqrStrategy::Area)
+    #[test]
+    fn test_style_errors() {
+        let code = QrCode::new(b"01234567").unwrap();
+        let cases = [
+            (
+                QrStyle::try_new("#000", "#fff", QrShape::Square, 0, 2.0),
+                StyleError::ZeroWidth,
+            ),
+            (
+                QrStyle::try_new("#000", "#fff", QrShape::Square, 100, f64::NAN),
+                StyleError::NonFiniteQuietZone(f64::NAN),
+            ),
+            (
+                QrStyle::try_new("#000", "#fff", QrShape::Square, 100, -1.0),
+                StyleError::NegativeQuietZone(-1.0),
+            ),
+            (
+                QrStyle::try_new("#00000g", "#fff", QrShape::Square, 100, 2.0),
+                StyleError::InvalidColor(String::from("#00000g")),
+            ),
+            (
+                QrStyle::try_new("#000", "whiteish", QrShape::Square, 100, 2.0),
+                StyleError::InvalidColor(String::from("whiteish")),
+            ),
+        ];
+        for (result, expected) in cases {
+            let error = result.unwrap_err();
+            // NaN is not equal to itself, so compare the messages.
+            assert_eq!(error.to_string(), expected.to_string());
+        }
+
+        let mut style = QrStyle::try_new("black", "none", QrShape::Round, 100, 2.0).unwrap();
+        assert!(code.to_pixmap(&style).is_ok());
+        style.width = 0;
+        let error = code.to_pixmap(&style).unwrap_err();
+        assert!(matches!(error, RenderError::Style(StyleError::ZeroWidth)));
+        style.width = 100;
+        style.quiet_zone = f64::INFINITY;
+        let error = code.to_pixmap(&style).unwrap_err();
+        assert!(matches!(
+            error,
+            RenderError::Style(StyleError::NonFiniteQuietZone(_))
+        ));
+        style.quiet_zone_sides = Some(QuietZone::Auto);
+        let error = code.to_pixmap(&style).unwrap_err();
+        assert!(matches!(
+            error,
+            RenderError::Style(StyleError::ConflictingQuietZone(modules)) if modules.is_infinite()
+        ));
+        assert_eq!(
+            error.to_string(),
+            "quiet zone of inf modules conflicts with quiet_zone_sides"
+        );
+        style = style.with_quiet_zone(QuietZone::Auto);
+        assert!(code.to_pixmap(&style).is_ok());
+        style.color = String::from("rgb(0, 0)");
+        let error = code.to_pixmap(&style).unwrap_err();
+        assert!(matches!(
+            error,
+            RenderError::Style(StyleError::InvalidColor(color)) if color == "rgb(0, 0)"
+        ));
     }
 
-    /// Constructs a new rMQR code which automatically encodes the given data at a
-    /// specific error correction level and rmqr strategy.
-    ///
-    ///     use qrqrpar::{QrCode, EcLevel,RmqrStrategy};
-    ///
-    ///     let code = QrCode::rmqr_with_options(b"Some data", EcLevel::H, RmqrStrategy::Area).unwrap();
-    ///
-    /// # Errors
-    ///
-    /// Returns error if the QR code cannot be constructed, e.g. when the data
-    /// is too long.
-    pub fn rmqr_with_options<D: AsRef<[u8]>>(
-        data: D,
-        ec_level: EcLevel,
-        strategy: bits::RmqrStrategy,
-    ) -> QrResult<Self> {
-        let bits = bits::encode_auto_rmqr(data.as_ref(), ec_level, strategy)?;
-        Self::with_bits(bits, ec_level)
+    #[test]
+    fn test_validate_style() {
+        let mut style = QrStyle {
+            quiet_zone_sides: Some(QuietZone::Sides {
+                top: 2.0,
+                right: 2.0,
+                bottom: 4.0,
+                left: 4.0,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(style.validate(Version::Rmqr(7, 43)), Ok(vec![]));
+        assert_eq!(
+            style.validate(Version::Normal(1)),
+            Ok(vec![StyleWarning::QuietZoneBelowMinimum {
+                modules: 2.0,
+                minimum: 4
+            }])
+        );
+        let auto = QrStyle::default().with_quiet_zone(QuietZone::Auto);
+        assert_eq!(auto.validate(Version::Normal(1)), Ok(vec![]));
+
+        style.quiet_zone_sides = Some(QuietZone::Sides {
+            top: 2.0,
+            right: -1.0,
+            bottom: 4.0,
+            left: 4.0,
+        });
+        assert_eq!(
+            style.validate(Version::Normal(1)),
+            Err(StyleError::NegativeQuietZone(-1.0))
+        );
+        let code = QrCode::new(b"01234567").unwrap();
+        assert!(code.to_pixmap(&style).is_err());
     }
-}
 
-impl QrCode {
-    /// Return `viewbox_width`, `viewbox_height`, `image_width`, `image_height`
-    pub fn image_sizes(&self, style: &QrStyle) -> (f64, f64, u32, u32) {
-        let quiet = style.quiet_zone;
-        let vb_width = self.width as f64 + quiet * 2.0;
-        let vb_height = self.height as f64 + quiet * 2.0;
-        let width = style.width;
-        let height = (width as f64 * vb_height / vb_width).round() as u32;
-        (vb_width, vb_height, width, height)
+    #[test]
+    fn test_module_too_small() {
+        let code = QrCode::with_version(b"01234567", Version::Normal(10), EcLevel::L).unwrap();
+        let mut style = QrStyle {
+            width: 10,
+            quiet_zone: 4.0,
+            ..Default::default()
+        };
+        let module_px = 10.0 / 65.0;
+        assert_eq!(
+            style.validate(code.version()),
+            Ok(vec![StyleWarning::ModuleTooSmall {
+                module_px,
+                minimum: 1.0
+            }])
+        );
+        let error = code.to_pixmap(&style).unwrap_err();
+        assert!(matches!(
+            error,
+            RenderError::ModuleTooSmall { module_px: px, minimum: 1.0 } if px == module_px
+        ));
+        let error = code.to_png(&style).unwrap_err();
+        assert!(matches!(error, RenderError::ModuleTooSmall { .. }));
+        assert!(code.to_svg(&style).contains(r#"width="10""#));
+
+        style.allow_tiny = true;
+        let pixmap = code.to_pixmap(&style).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (10, 10));
+
+        style.allow_tiny = false;
+        style.minimum_module_px = 0.1;
+        assert!(code.to_pixmap(&style).is_ok());
+        style.width = 65;
+        style.minimum_module_px = 1.0;
+        assert_eq!(style.validate(code.version()), Ok(vec![]));
+        assert!(code.to_pixmap(&style).is_ok());
+
+        style.minimum_module_px = f64::NAN;
+        assert!(matches!(
+            style.validate(code.version()),
+            Err(StyleError::InvalidMinimumModuleSize(_))
+        ));
     }
 
-    /// Converts the QR to a simple SVG string.
-    pub fn to_simple_svg(&self) -> String {
-        let style = QrStyle {
+    #[test]
+    fn test_round_border_corners_without_quiet_zone() {
+        let code = QrCode::new(b"Hello, rmqr!").unwrap();
+        let mut style = QrStyle {
+            shape: QrShape::Round,
+            width: code.width() as u32 * 10,
             quiet_zone: 0.0,
-            width: self.width as u32,
             ..Default::default()
         };
-        self.to_svg(&style)
+        let last = style.width - 1;
+        let corners = [(0, 0), (last, 0), (0, last)];
+
+        let pixmap = code.to_pixmap(&style).unwrap();
+        for (x, y) in corners {
+            assert_eq!(pixmap.pixel(x, y).unwrap().red(), 255);
+        }
+
+        style.border_corners = BorderCorners::Square;
+        let pixmap = code.to_pixmap(&style).unwrap();
+        for (x, y) in corners {
+            assert_eq!(pixmap.pixel(x, y).unwrap().red(), 0);
+        }
     }
+}
 
-    /// Converts the QR to a SVG string.
-    pub fn to_svg(&self, style: &QrStyle) -> String {
-        let mut directed_segments = render::DirectedSegments::new();
-        for y in 0..self.height {
-            for x in 0..self.width {
-                if self.content[y * self.width + x] == Color::Dark {
-                    directed_segments.add_or_remove(x as i16, y as i16);
+#[cfg(test)]
+mod runs_tests {
+    use crate::{Color, EcLevel, QrCode, Version};
+
+    #[test]
+    fn test_runs_cover_dark_modules() {
+        for version in [Version::Normal(7), Version::Micro(3), Version::Rmqr(13, 99)] {
+            let code = QrCode::with_version(b"01234", version, EcLevel::M).unwrap();
+            let colors = code.to_colors();
+            let all_runs = code.all_runs();
+            assert_eq!(all_runs.len(), code.height());
+            for (y, runs) in all_runs.iter().enumerate() {
+                assert_eq!(runs, &code.row_runs(y));
+                let row = &colors[y * code.width()..(y + 1) * code.width()];
+                let dark_count = row.iter().filter(|&&c| c == Color::Dark).count();
+                assert_eq!(runs.iter().map(|r| r.1 as usize).sum::<usize>(), dark_count);
+                for &(start, length) in runs {
+                    assert!(length > 0);
+                    let end = (start + length) as usize;
+                    assert!(row[start as usize..end].iter().all(|&c| c == Color::Dark));
+                    assert!(end == row.len() || row[end] == Color::Light);
+                }
+                // Runs neither touch nor overlap.
+                for pair in runs.windows(2) {
+                    assert!(pair[0].0 + pair[0].1 < pair[1].0);
                 }
             }
+            assert_eq!(code.row_runs(code.height()), vec![]);
         }
-        let path_string = match style.shape {
-            QrShape::Square => directed_segments.to_path_square_mut(),
-            QrShape::Round => directed_segments.to_path_round_mut(),
-        };
+    }
 
-        let color = &style.color;
-        let background_color = &style.background_color;
-        let quiet = style.quiet_zone;
-        let (vb_width, vb_height, image_width, image_height) = self.image_sizes(style);
-        let path = format!(
-            r#"<path fill="{color}" transform="translate({quiet},{quiet})" fill-rule="evenodd" d="{path_string}"/>"#,
-        );
-        format!(
-            r#"<?xml version="1.0" encoding="UTF-8"?>
-            <svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{image_width}" height="{image_height}" viewBox="0 0 {vb_width} {vb_height}">
-            <rect x="0" y="0" width="{vb_width}" height="{vb_height}" fill="{background_color}"/>
-            {path}
-            </svg>"#,
-        )
+    #[test]
+    fn test_quiet_zone() {
+        let code = QrCode::with_version(b"01234", Version::Micro(2), EcLevel::L).unwrap();
+        let runs = code.all_runs_with_quiet_zone(2);
+        assert_eq!(runs.len(), 2 + 13 + 2);
+        assert!(runs[..2].iter().chain(&runs[15..]).all(Vec::is_empty));
+        for (shifted, runs) in runs[2..15].iter().zip(code.all_runs()) {
+            let unshifted = shifted.iter().map(|&(x, len)| (x - 2, len));
+            assert_eq!(unshifted.collect::<Vec<_>>(), runs);
+        }
     }
-    /// Saves the QR to a SVG file.
-    pub fn save_svg<P: AsRef<std::path::Path>>(
-        &self,
-        path: P,
-        style: &QrStyle,
-    ) -> std::io::Result<()> {
-        let svg_string = self.to_svg(style);
-        std::fs::write(path, svg_string)
+}
+
+#[cfg(test)]
+mod error_tests {
+    use crate::batch::BatchError;
+    use crate::geometry::GeometryError;
+    use crate::payload::fmt::FmtError;
+    use crate::profile::QrProfileError;
+    use crate::structured_append::SetError;
+    use crate::types::QrError;
+    use crate::{QrCode, QrStyle, RenderError, StyleError};
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<QrError>();
+        assert_send_sync::<StyleError>();
+        assert_send_sync::<RenderError>();
+        assert_send_sync::<SetError>();
+        assert_send_sync::<GeometryError>();
+        assert_send_sync::<FmtError>();
+        assert_send_sync::<QrProfileError>();
+        assert_send_sync::<BatchError>();
+        assert_send_sync::<crate::SpriteError>();
+        assert_send_sync::<crate::GlyphError>();
+    }
+
+    #[test]
+    fn test_render_error_source() {
+        use std::error::Error;
+
+        let code = QrCode::new(b"01234567").unwrap();
+        let style = QrStyle {
+            width: 0,
+            ..Default::default()
+        };
+        let error = code.to_png(&style).unwrap_err();
+        assert!(matches!(error, RenderError::Style(StyleError::ZeroWidth)));
+        assert_eq!(error.to_string(), "zero image width");
+        assert!(error.source().is_none());
+
+        let dir = tempdir::TempDir::new("qrqrpar").unwrap();
+        let path = dir.path().join("missing").join("code.png");
+        let error = code.save_png(path, &QrStyle::default()).unwrap_err();
+        assert!(matches!(error, RenderError::Io(_)));
+        assert!(error.source().unwrap().is::<std::io::Error>());
     }
 }
 
-impl QrCode {
-    /// Converts the QR to a tiny-skia pixmap.
-    pub fn to_pixmap(
-        &self,
-        style: &QrStyle,
-    ) -> Result<resvg::tiny_skia::Pixmap, Box<dyn std::error::Error>> {
-        let (_, _, width, height) = self.image_sizes(style);
-        let svg_string = self.to_svg(style);
-        let opt = resvg::usvg::Options::default();
-        let tree = &resvg::usvg::TreeParsing::from_str(&svg_string, &opt)?;
-        let mut pixmap =
-            resvg::tiny_skia::Pixmap::new(width, height).ok_or("failed to create pixmap")?;
-        resvg::Tree::from_usvg(tree)
-            .render(resvg::tiny_skia::Transform::default(), &mut pixmap.as_mut());
-        Ok(pixmap)
+#[cfg(test)]
+mod no_panic_tests {
+    use crate::bits::Bits;
+    use crate::internals::coding::Segment;
+    use crate::types::{Mode, QrError};
+    use crate::{fitting_ec_levels, EcLevel, QrCode, Version};
+
+    fn invalid_versions() -> Vec<Version> {
+        vec![
+            Version::Normal(0),
+            Version::Normal(41),
+            Version::Normal(255),
+            Version::Micro(0),
+            Version::Micro(5),
+            Version::Micro(255),
+            Version::Rmqr(7, 27),
+            Version::Rmqr(8, 43),
+            Version::Rmqr(255, 255),
+        ]
     }
 
-    /// Saves the QR to a PNG file.
-    pub fn save_png<P: AsRef<std::path::Path>>(
-        &self,
-        path: P,
-        style: &QrStyle,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let pixmap = self.to_pixmap(style)?;
-        pixmap.save_png(path)?;
-        Ok(())
+    #[test]
+    fn test_invalid_versions() {
+        for version in invalid_versions() {
+            assert!(!version.is_valid());
+            for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+                for data in [&b""[..], b"1", b"HELLO", b"hello", "点".as_bytes()] {
+                    let result = QrCode::with_version(data, version, ec_level);
+                    assert_eq!(result.err(), Some(QrError::InvalidVersion), "{version:?}");
+                    let result = QrCode::with_version_max_ec(data, version);
+                    assert_eq!(result.err(), Some(QrError::InvalidVersion), "{version:?}");
+                    let result = fitting_ec_levels(data, version);
+                    assert_eq!(result.err(), Some(QrError::InvalidVersion), "{version:?}");
+                }
+                let mut bits = Bits::new(version);
+                assert_eq!(bits.push_byte_data(b"a"), Err(QrError::InvalidVersion));
+                assert_eq!(
+                    bits.push_mode_indicator(Mode::Numeric),
+                    Err(QrError::InvalidVersion)
+                );
+                assert_eq!(bits.push_terminator(ec_level), Err(QrError::InvalidVersion));
+            }
+            let _ = version.area();
+            let _ = version.mode_bits_count();
+            let _ = Segment {
+                mode: Mode::Byte,
+                begin: 0,
+                end: 3,
+            }
+            .encoded_len(version);
+        }
     }
 
-    /// Encodes QR into a PNG data.
-    pub fn to_png(&self, style: &QrStyle) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let pixmap = self.to_pixmap(style)?;
-        Ok(pixmap.encode_png()?)
+    #[test]
+    fn test_invalid_rmqr_length_bits() {
+        // The length field is looked up for invalid rMQR versions too, which
+        // must not be encoded with the counts of R17x139.
+        let mut bits = Bits::new(Version::Rmqr(9, 27));
+        assert_eq!(bits.push_numeric_data(b"1"), Err(QrError::InvalidVersion));
+        assert!(bits.is_empty());
+    }
+
+    #[test]
+    fn test_unterminated_bits() {
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_byte_data(b"x").unwrap();
+        assert_eq!(
+            QrCode::with_bits(bits, EcLevel::L).err(),
+            Some(QrError::DataTooShort)
+        );
+
+        let mut bits = Bits::new(Version::Normal(1));
+        bits.push_byte_data(b"x").unwrap();
+        bits.push_terminator(EcLevel::H).unwrap();
+        assert_eq!(
+            QrCode::with_bits(bits, EcLevel::L).err(),
+            Some(QrError::DataTooShort)
+        );
+    }
+
+    #[test]
+    fn test_invalid_characters() {
+        let mut bits = Bits::new(Version::Normal(1));
+        assert_eq!(
+            bits.push_numeric_data(b"12 3"),
+            Err(QrError::InvalidCharacter)
+        );
+        assert_eq!(
+            bits.push_numeric_data(b"abc"),
+            Err(QrError::InvalidCharacter)
+        );
+        assert_eq!(
+            bits.push_alphanumeric_data(b"abc"),
+            Err(QrError::InvalidCharacter)
+        );
+        for kanji in [
+            &b"\x81\x3f"[..],
+            b"\x81\x7f",
+            b"\xeb\xc0",
+            b"\xa0\x40",
+            b"\x81",
+        ] {
+            assert_eq!(bits.push_kanji_data(kanji), Err(QrError::InvalidCharacter));
+        }
+        assert!(bits.is_empty());
+
+        bits.push_kanji_data(b"\x81\x40\x9f\xfc\xe0\x40\xeb\xbf")
+            .unwrap();
+        let segment = |begin, end| Segment {
+            mode: Mode::Byte,
+            begin,
+            end,
+        };
+        for segments in [[segment(0, 4)], [segment(2, 1)]] {
+            let result = bits.push_segments(b"abc", segments.into_iter());
+            assert_eq!(result, Err(QrError::InvalidSegment));
+        }
     }
 }
 
 #[cfg(test)]
-mod image_test {
-    use super::*;
-    use tempdir::TempDir;
+mod stable_api_tests {
+    use std::collections::BTreeSet;
+
+    /// The source files of the modules the supported surface is re-exported
+    /// from, and of the private modules adding `impl` blocks to its types.
+    const SOURCES: &[(&str, &str)] = &[
+        ("", include_str!("lib.rs")),
+        ("batch", include_str!("batch.rs")),
+        ("bits", include_str!("bits.rs")),
+        ("compare", include_str!("compare.rs")),
+        ("encoder", include_str!("encoder.rs")),
+        ("geometry", include_str!("geometry.rs")),
+        ("internals::canvas", include_str!("canvas.rs")),
+        ("internals::coding", include_str!("coding.rs")),
+        ("partial", include_str!("partial.rs")),
+        ("prefix", include_str!("prefix.rs")),
+        ("print", include_str!("print.rs")),
+        ("profile", include_str!("profile.rs")),
+        ("quick", include_str!("quick.rs")),
+        ("recommend", include_str!("recommend.rs")),
+        ("render", include_str!("render.rs")),
+        ("sprite", include_str!("sprite.rs")),
+        ("structured_append", include_str!("structured_append.rs")),
+        ("types", include_str!("types.rs")),
+    ];
+
+    /// The items re-exported by the `pub use crate::...;` statements at the
+    /// start of a line, with the module they come from.
+    fn reexport_modules(source: &str) -> Vec<(String, String)> {
+        let mut items = Vec::new();
+        for statement in source.split("\npub use crate::").skip(1) {
+            let statement = &statement[..statement.find(';').unwrap()];
+            let (module, names) = match statement.split_once('{') {
+                Some((module, names)) => (module, names.trim_end_matches('}')),
+                None => statement.rsplit_once("::").unwrap_or(("", statement)),
+            };
+            assert!(!names.contains('{'), "nested braces in {statement:?}");
+            let module = module.trim_end_matches("::");
+            items.extend(
+                names
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(|name| (module.to_owned(), name.to_owned())),
+            );
+        }
+        items
+    }
+
+    /// The items re-exported by the `pub use crate::...;` statements at the
+    /// start of a line.
+    fn reexports(source: &str) -> BTreeSet<String> {
+        reexport_modules(source)
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect()
+    }
+
+    /// The public items defined at the top level of the crate root, and its
+    /// documented public modules which are not only built for tests.
+    fn root_items(source: &str) -> (BTreeSet<String>, BTreeSet<String>) {
+        let (mut items, mut modules) = (BTreeSet::new(), BTreeSet::new());
+        let mut skipped = false;
+        for line in source.lines() {
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words[..] {
+                ["pub", "mod", name, ..] if !skipped => {
+                    modules.insert(name.trim_end_matches(';').to_owned());
+                }
+                ["pub", "struct" | "enum" | "trait" | "fn" | "type" | "const", name, ..]
+                    if !line.starts_with(' ') =>
+                {
+                    items.insert(identifier(name).to_owned());
+                }
+                _ => {}
+            }
+            if !line.starts_with("#[") {
+                skipped = false;
+            } else if line.starts_with("#[doc(hidden)]")
+                || line.starts_with("#[cfg(") && line.contains("test")
+            {
+                skipped = true;
+            }
+        }
+        (items, modules)
+    }
+
+    fn identifier(word: &str) -> &str {
+        let end = word
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(word.len());
+        &word[..end]
+    }
+
+    /// The declaration starting at `lines[start]`, up to the `{`, `{}` or `;`
+    /// ending it, on a single line.
+    fn header(lines: &[&str], start: usize) -> String {
+        let (mut parts, mut depth) = (Vec::new(), 0);
+        for line in &lines[start..] {
+            let line = line.trim();
+            depth += line.matches(['(', '[']).count() as isize;
+            depth -= line.matches([')', ']']).count() as isize;
+            parts.push(line);
+            if depth == 0 && line.ends_with(['{', '}', ';']) {
+                break;
+            }
+        }
+        let header = parts.join(" ").replace("( ", "(").replace(", )", ")");
+        let header = header.strip_suffix("{}").unwrap_or(&header);
+        header.trim_end_matches(['{', ';']).trim_end().to_owned()
+    }
+
+    /// The lines of the body of the block whose header starts at
+    /// `lines[start]`, that is up to the closing brace at the same indent.
+    fn body<'a>(lines: &'a [&'a str], start: usize) -> &'a [&'a str] {
+        let indent = lines[start].len() - lines[start].trim_start().len();
+        let close = format!("{}}}", " ".repeat(indent));
+        let open = (start..lines.len())
+            .find(|&i| lines[i].trim_end().ends_with('{'))
+            .unwrap();
+        let end = (open..lines.len()).find(|&i| lines[i] == close).unwrap();
+        &lines[open + 1..end]
+    }
+
+    /// The line at `index` is preceded by a `#[doc(hidden)]` attribute.
+    fn is_hidden(lines: &[&str], index: usize) -> bool {
+        lines[..index]
+            .iter()
+            .rev()
+            .take_while(|line| line.trim_start().starts_with("#["))
+            .any(|line| line.trim_start().starts_with("#[doc(hidden)]"))
+    }
+
+    /// The kind and name of the item declared on a line at the top level.
+    fn declared(line: &str) -> Option<(&str, &str)> {
+        let mut words = line.strip_prefix("pub ")?.split_whitespace();
+        let mut kind = words.next()?;
+        if kind == "const" && line.contains(" const fn ") {
+            kind = words.next()?;
+        }
+        let kinds = ["struct", "enum", "trait", "fn", "type", "const"];
+        kinds
+            .contains(&kind)
+            .then(|| Some((kind, identifier(words.next()?))))?
+    }
+
+    /// The declaration of the item `name` in `source`: the signature of a
+    /// function, the public fields of a struct (with `..` when some are
+    /// private), the variants of an enum and the items of a trait.
+    fn declaration(source: &str, name: &str) -> Option<String> {
+        let lines = source.lines().collect::<Vec<_>>();
+        let start = lines
+            .iter()
+            .position(|line| declared(line).is_some_and(|(_, item)| item == name))?;
+        let (kind, _) = declared(lines[start])?;
+        let mut declaration = header(&lines, start);
+        if kind == "const" {
+            declaration.truncate(declaration.find(" =").unwrap_or(declaration.len()));
+        }
+        if !lines[start].trim_end().ends_with('{') || kind == "fn" {
+            return Some(declaration);
+        }
+        let mut members = Vec::new();
+        let body = body(&lines, start);
+        for (index, line) in body.iter().enumerate() {
+            let member = line.trim();
+            if line.starts_with("        ") && kind != "enum"
+                || member.is_empty()
+                || member.starts_with("//")
+                || member.starts_with("#[")
+            {
+                continue;
+            }
+            match kind {
+                "struct" if member.starts_with("pub ") => members.push(member.to_owned()),
+                "struct" if !members.contains(&String::from("..")) => {
+                    members.push(String::from(".."));
+                }
+                "trait"
+                    if ["fn ", "type ", "const "]
+                        .iter()
+                        .any(|k| member.starts_with(k)) =>
+                {
+                    members.push(header(body, index) + ";");
+                }
+                "enum" => members.push(member.to_owned()),
+                _ => {}
+            }
+        }
+        Some(format!("{declaration} {{ {} }}", members.join(" ")))
+    }
+
+    /// The signatures of the public inherent methods of the type `name`,
+    /// and the headers of its trait implementations, in all of `SOURCES`.
+    fn implementations(name: &str) -> BTreeSet<String> {
+        let mut lines = BTreeSet::new();
+        for (_, source) in SOURCES {
+            let source = source.lines().collect::<Vec<_>>();
+            for (start, line) in source.iter().enumerate() {
+                if !line.starts_with("impl") {
+                    continue;
+                }
+                let implementation = header(&source, start);
+                let target = implementation.rsplit(" for ").next().unwrap();
+                let target = match target.strip_prefix("impl") {
+                    Some(target) if target.starts_with('<') => {
+                        let mut depth = 0;
+                        let end = target
+                            .find(|c| {
+                                depth += (c == '<') as isize - (c == '>') as isize;
+                                depth == 0
+                            })
+                            .unwrap();
+                        &target[end + 1..]
+                    }
+                    Some(target) => target,
+                    None => target,
+                };
+                if identifier(target.trim_start()) != name {
+                    continue;
+                }
+                if implementation.contains(" for ") {
+                    lines.insert(format!("impl {name}: {implementation}"));
+                    continue;
+                }
+                let body = body(&source, start);
+                for (index, line) in body.iter().enumerate() {
+                    let Some((_, method)) = line.strip_prefix("    ").and_then(declared) else {
+                        continue;
+                    };
+                    if !line.starts_with("     ") && !is_hidden(body, index) {
+                        let signature = header(body, index);
+                        lines.insert(format!("method {name}::{method}: {signature}"));
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    fn snapshot() -> String {
+        let (mut root, modules) = root_items(include_str!("lib.rs"));
+        root.extend(reexports(include_str!("lib.rs")));
+        let prelude = reexports(include_str!("prelude.rs"));
+        let mut lines = vec![
+            String::from("# The stable public surface of the crate, see the crate documentation."),
+            String::from("# Regenerate with QRQRPAR_UPDATE_API_SNAPSHOT=1 cargo test stable_api."),
+        ];
+        lines.extend(modules.iter().map(|name| format!("mod {name}")));
+        lines.extend(root.iter().map(|name| format!("root {name}")));
+        lines.extend(prelude.iter().map(|name| format!("prelude {name}")));
+        let mut origins = reexport_modules(include_str!("prelude.rs"));
+        origins.extend(reexport_modules(include_str!("lib.rs")));
+        let origin = |name: &str| {
+            origins
+                .iter()
+                .rev()
+                .find(|(module, item)| item == name && !module.is_empty())
+                .map_or("", |(module, _)| module.as_str())
+        };
+        for name in root.union(&prelude) {
+            let module = origin(name);
+            let (_, source) = SOURCES.iter().find(|(path, _)| *path == module).unwrap();
+            let declaration = declaration(source, name)
+                .unwrap_or_else(|| panic!("{name} is not declared in {module:?}"));
+            lines.push(format!("item {name}: {declaration}"));
+            lines.extend(implementations(name));
+        }
+        lines.join("\n") + "\n"
+    }
 
     #[test]
-    fn test_save_png() {
-        let test_dir = TempDir::new("__test__").unwrap();
-        let path = test_dir.path().join("rmqr.png");
-        let code = QrCode::new(b"Hello, rmqr!").unwrap();
-        let style = QrStyle::default();
-        code.save_png(path, &style).unwrap();
+    fn test_snapshot() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/stable-api.txt");
+        let actual = snapshot();
+        if std::env::var_os("QRQRPAR_UPDATE_API_SNAPSHOT").is_some() {
+            std::fs::write(path, &actual).unwrap();
+        }
+        let expected = std::fs::read_to_string(path).unwrap_or_default();
+        let (expected, actual) = (
+            expected.lines().collect::<BTreeSet<_>>(),
+            actual.lines().collect::<BTreeSet<_>>(),
+        );
+        assert!(
+            expected == actual,
+            "the stable API changed, removed: {:?}, added: {:?}; rerun with \
+             QRQRPAR_UPDATE_API_SNAPSHOT=1 if intended",
+            expected.difference(&actual).collect::<Vec<_>>(),
+            actual.difference(&expected).collect::<Vec<_>>()
+        );
     }
+
     #[test]
-    fn test_save_svg() {
-        let test_dir = TempDir::new("__test__").unwrap();
-        let path = test_dir.path().join("rmqr.svg");
-        let code = QrCode::new(b"Hello, rmqr!").unwrap();
-        let style = QrStyle::default();
-        code.save_svg(path, &style).unwrap();
+    fn test_prelude_is_stable() {
+        // The names the prelude takes from private modules are also exported
+        // at the root, so that both paths stay put.
+        let (mut root, modules) = root_items(include_str!("lib.rs"));
+        root.extend(reexports(include_str!("lib.rs")));
+        for statement in include_str!("prelude.rs")
+            .split("\npub use crate::")
+            .skip(1)
+        {
+            let module = statement.split(['{', ';', ':']).next().unwrap();
+            let names = reexports(&format!("\npub use crate::{statement}"));
+            assert!(
+                modules.contains(module) || names.is_subset(&root),
+                "{names:?} from {module:?} are not exported at the root"
+            );
+        }
     }
+
     #[test]
-    fn test_save_svg2() {
-        let test_dir = TempDir::new("__test__").unwrap();
-        let path = test_dir.path().join("micro_qr_m3_l.svg");
-        let code =
-            QrCode::with_version("11111111111111111111", Version::Micro(3), EcLevel::L).unwrap();
-        let style = QrStyle::default();
-        code.save_svg(path, &style).unwrap();
+    fn test_readme_uses_stable_surface() {
+        let (mut root, _) = root_items(include_str!("lib.rs"));
+        root.extend(reexports(include_str!("lib.rs")));
+        let readme = include_str!("../README.md");
+        let uses = readme
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("use qrqrpar::"))
+            .collect::<Vec<_>>();
+        assert!(!uses.is_empty());
+        for statement in uses {
+            let names = statement
+                .trim_end_matches(';')
+                .trim_start_matches('{')
+                .trim_end_matches('}');
+            for name in names.split(',').map(str::trim) {
+                assert!(root.contains(name), "README uses {name} outside the root");
+            }
+        }
     }
 }
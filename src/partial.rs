@@ -0,0 +1,186 @@
+//! The `partial` module provides a view of a QR code revealing only a part of
+//! its data modules, e.g. to render the frames of an assembling animation.
+
+use std::sync::Arc;
+
+use crate::types::{Color, Version};
+use crate::Renderable;
+
+/// The rank of the modules which are not data modules.
+const FUNCTIONAL: u32 = u32::MAX;
+
+/// A QR code whose data modules are only shown up to a number of modules in
+/// placement order, see `QrCode::partial`.
+///
+/// The functional patterns are always shown, and the hidden data modules are
+/// light. The module colors and the placement order are shared by every view
+/// created with `with_revealed`, so creating a frame does not copy the
+/// modules.
+///
+///     use qrqrpar::{QrCode, Renderable, QrStyle};
+///
+///     let code = QrCode::new(b"Some data").unwrap();
+///     let first = code.partial(0);
+///     let frames = (0..=first.total())
+///         .step_by(16)
+///         .map(|k| first.with_revealed(k).to_svg(&QrStyle::default()))
+///         .collect::<Vec<_>>();
+///     assert_eq!(frames.len(), 14);
+///
+#[derive(Clone, Debug)]
+pub struct PartialQr {
+    content: Arc<Vec<Color>>,
+    ranks: Arc<[u32]>,
+    revealed: usize,
+    total: usize,
+    version: Version,
+    width: usize,
+    height: usize,
+}
+
+impl PartialQr {
+    /// Constructs the view from the module colors and the placement order of
+    /// the data modules.
+    pub(crate) fn new(
+        content: Arc<Vec<Color>>,
+        placement_order: impl IntoIterator<Item = (usize, usize)>,
+        revealed: usize,
+        version: Version,
+        width: usize,
+        height: usize,
+    ) -> Self {
+        let mut ranks = vec![FUNCTIONAL; content.len()];
+        let mut total = 0;
+        for (x, y) in placement_order {
+            ranks[y * width + x] = total as u32;
+            total += 1;
+        }
+        Self {
+            content,
+            ranks: ranks.into(),
+            revealed: revealed.min(total),
+            total,
+            version,
+            width,
+            height,
+        }
+    }
+
+    /// Gets the number of data modules shown.
+    pub fn revealed(&self) -> usize {
+        self.revealed
+    }
+
+    /// Gets the number of data modules of the code, i.e. the largest useful
+    /// value of `revealed`.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Constructs the view of the same code showing the first `revealed` data
+    /// modules instead, sharing the modules with this view.
+    ///
+    /// `revealed` is clamped to `total`.
+    pub fn with_revealed(&self, revealed: usize) -> Self {
+        Self {
+            content: Arc::clone(&self.content),
+            ranks: Arc::clone(&self.ranks),
+            revealed: revealed.min(self.total),
+            ..*self
+        }
+    }
+
+    /// Returns true if the two views show the modules of the same allocation.
+    pub fn shares_modules_with(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.content, &other.content)
+    }
+}
+
+impl Renderable for PartialQr {
+    fn version(&self) -> Version {
+        self.version
+    }
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn module_color(&self, x: usize, y: usize) -> Color {
+        let i = y * self.width + x;
+        let rank = self.ranks[i];
+        if rank == FUNCTIONAL || (rank as usize) < self.revealed {
+            self.content[i]
+        } else {
+            Color::Light
+        }
+    }
+}
+
+#[cfg(test)]
+mod partial_tests {
+    use crate::{Color, EcLevel, QrCode, QrStyle, Renderable, Version};
+
+    fn colors(code: &impl Renderable) -> Vec<Color> {
+        (0..code.height())
+            .flat_map(|y| (0..code.width()).map(move |x| (x, y)))
+            .map(|(x, y)| code.module_color(x, y))
+            .collect()
+    }
+
+    #[test]
+    fn test_fully_revealed() {
+        let code = QrCode::rmqr("Hello, rmqr!").unwrap();
+        let partial = code.partial(usize::MAX);
+        assert_eq!(partial.revealed(), partial.total());
+        assert_eq!(colors(&partial), code.to_colors());
+
+        let style = QrStyle {
+            width: 200,
+            ..Default::default()
+        };
+        assert_eq!(Renderable::to_svg(&partial, &style), code.to_svg(&style));
+        assert_eq!(
+            Renderable::to_png(&partial, &style).unwrap(),
+            code.to_png(&style).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_nothing_revealed() {
+        let code = QrCode::with_version(b"Some data", Version::Normal(7), EcLevel::M).unwrap();
+        let partial = code.partial(0);
+        let order = code.placement_order();
+        assert_eq!(partial.total(), order.len());
+        let mut expected = code.to_colors();
+        for point in order {
+            expected[point.y as usize * code.width() + point.x as usize] = Color::Light;
+        }
+        assert_eq!(colors(&partial), expected);
+    }
+
+    #[test]
+    fn test_frames() {
+        let code = QrCode::micro_with_error_correction_level("01234567", EcLevel::L).unwrap();
+        let order = code.placement_order();
+        let first = code.partial(0);
+        let mut previous = colors(&first);
+        for (k, point) in order.iter().enumerate() {
+            let frame = first.with_revealed(k + 1);
+            assert!(frame.shares_modules_with(&first));
+            let current = colors(&frame);
+            let i = point.y as usize * code.width() + point.x as usize;
+            for (j, (a, b)) in previous.iter().zip(&current).enumerate() {
+                if j != i {
+                    assert_eq!(a, b);
+                }
+            }
+            assert_eq!(current[i], code.get(*point).unwrap());
+            previous = current;
+        }
+        assert_eq!(previous, code.to_colors());
+    }
+}
@@ -0,0 +1,131 @@
+//! The `payload` module provides helpers for preparing text payloads.
+//!
+//! Payload formats have their own rules about line endings and control
+//! characters. Each payload builder picks the [`SanitizePolicy`] appropriate
+//! for its format, applies it with [`sanitize`] and documents it. The only
+//! builder so far is the GS1 one of [`gs1`], which rejects any control
+//! character in the values since it inserts the group separators itself.
+//! The generic constructors such as `QrCode::new` never sanitize their input
+//! and encode the given bytes as-is.
+//!
+//! Numbers are formatted with the locale-independent helpers of [`fmt`].
+use crate::types::{QrError, QrResult};
+
+pub mod binary;
+pub mod fmt;
+pub mod gs1;
+
+/// How control characters in a payload should be handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanitizePolicy {
+    /// Rejects every C0 control character (U+0000 to U+001F) and DEL, except
+    /// those listed in the given slice which the payload format requires (e.g.
+    /// `b"\n"` for multi-line text, or `b"\t"` for tab-separated fields).
+    Strict(&'static [u8]),
+
+    /// Converts CRLF and lone CR into LF, and strips every other C0 control
+    /// character and DEL except the horizontal tab.
+    Normalize,
+
+    /// Keeps the input unchanged.
+    Preserve,
+}
+
+/// Applies the given policy to a payload.
+///
+///     use qrqrpar::payload::{sanitize, SanitizePolicy};
+///
+///     assert_eq!(sanitize("a\r\nb\rc", SanitizePolicy::Normalize).unwrap(), "a\nb\nc");
+///     assert!(sanitize("a\nb", SanitizePolicy::Strict(&[])).is_err());
+///     assert_eq!(sanitize("a\nb", SanitizePolicy::Strict(b"\n")).unwrap(), "a\nb");
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidCharacter)` if the policy is `Strict` and the
+/// input contains a control character which is not explicitly allowed.
+pub fn sanitize(input: &str, policy: SanitizePolicy) -> QrResult<String> {
+    match policy {
+        SanitizePolicy::Preserve => Ok(input.to_owned()),
+        SanitizePolicy::Strict(allowed) => {
+            let rejected = input
+                .bytes()
+                .any(|b| is_control(b) && !allowed.contains(&b));
+            if rejected {
+                Err(QrError::InvalidCharacter)
+            } else {
+                Ok(input.to_owned())
+            }
+        }
+        SanitizePolicy::Normalize => {
+            let mut res = String::with_capacity(input.len());
+            let mut chars = input.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\r' => {
+                        chars.next_if_eq(&'\n');
+                        res.push('\n');
+                    }
+                    '\n' | '\t' => res.push(c),
+                    c if c.is_ascii() && is_control(c as u8) => {}
+                    c => res.push(c),
+                }
+            }
+            Ok(res)
+        }
+    }
+}
+
+/// Checks whether a byte is a C0 control character or DEL.
+fn is_control(b: u8) -> bool {
+    b < 0x20 || b == 0x7f
+}
+
+#[cfg(test)]
+mod sanitize_tests {
+    use crate::payload::{sanitize, SanitizePolicy};
+    use crate::types::QrError;
+
+    #[test]
+    fn test_strict() {
+        assert_eq!(
+            sanitize("WIFI:S:ssid;;", SanitizePolicy::Strict(&[])),
+            Ok("WIFI:S:ssid;;".to_owned())
+        );
+        assert_eq!(
+            sanitize("abc\0def", SanitizePolicy::Strict(b"\n")),
+            Err(QrError::InvalidCharacter)
+        );
+        assert_eq!(
+            sanitize("line1\r\nline2", SanitizePolicy::Strict(b"\n")),
+            Err(QrError::InvalidCharacter)
+        );
+        assert_eq!(
+            sanitize("line1\r\nline2", SanitizePolicy::Strict(b"\r\n")),
+            Ok("line1\r\nline2".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            sanitize("a\r\nb\rc\nd\r\n\r\n", SanitizePolicy::Normalize),
+            Ok("a\nb\nc\nd\n\n".to_owned())
+        );
+        assert_eq!(
+            sanitize("abc\0d\tef\x7f", SanitizePolicy::Normalize),
+            Ok("abcd\tef".to_owned())
+        );
+        assert_eq!(
+            sanitize("日本\r\n語", SanitizePolicy::Normalize),
+            Ok("日本\n語".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_preserve() {
+        assert_eq!(
+            sanitize("abc\0d\r\ne\rf", SanitizePolicy::Preserve),
+            Ok("abc\0d\r\ne\rf".to_owned())
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! The `binary` module converts binary payloads, e.g. hashes or signed
+//! certificates, into text which the alphanumeric mode can encode.
+//!
+//! The conversions do not make the symbols smaller: both cost at least as
+//! many bits as the byte mode, see `best_binary_encoding`. They are useful
+//! when the reader of the payload expects it, like the base45 text of the EU
+//! Digital COVID Certificates.
+
+use crate::types::{alphanumeric_value, Mode, QrError, QrResult, ALPHANUMERIC_CHARSET};
+
+/// The base45 alphabet of RFC 9285, which is also the QR code alphanumeric
+/// character set.
+const BASE45_ALPHABET: &[u8; 45] = ALPHANUMERIC_CHARSET;
+
+/// How a binary payload is represented in the symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryEncoding {
+    /// The bytes themselves, in byte mode.
+    Byte,
+    /// The base45 text of RFC 9285, in alphanumeric mode.
+    Base45,
+    /// The uppercase hexadecimal text, in alphanumeric mode.
+    HexUpper,
+}
+
+impl BinaryEncoding {
+    /// Converts the data into the bytes to encode.
+    pub fn apply(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            BinaryEncoding::Byte => data.to_vec(),
+            BinaryEncoding::Base45 => to_base45(data).into_bytes(),
+            BinaryEncoding::HexUpper => to_hex_upper(data).into_bytes(),
+        }
+    }
+
+    /// The mode encoding the converted data.
+    pub fn mode(self) -> Mode {
+        match self {
+            BinaryEncoding::Byte => Mode::Byte,
+            BinaryEncoding::Base45 | BinaryEncoding::HexUpper => Mode::Alphanumeric,
+        }
+    }
+
+    /// Computes the number of bits needed to encode `len` bytes of binary data,
+    /// excluding the mode indicator and the length field.
+    ///
+    ///     use qrqrpar::payload::binary::BinaryEncoding;
+    ///
+    ///     assert_eq!(BinaryEncoding::Byte.data_bits_count(32), 256);
+    ///     assert_eq!(BinaryEncoding::Base45.data_bits_count(32), 264);
+    ///     assert_eq!(BinaryEncoding::HexUpper.data_bits_count(32), 352);
+    pub fn data_bits_count(self, len: usize) -> usize {
+        let chars_count = match self {
+            BinaryEncoding::Byte => len,
+            BinaryEncoding::Base45 => len / 2 * 3 + len % 2 * 2,
+            BinaryEncoding::HexUpper => len * 2,
+        };
+        self.mode().data_bits_count(chars_count)
+    }
+}
+
+/// Chooses the encoding of `len` bytes of binary data costing the fewest data
+/// bits, preferring `Byte` on ties.
+///
+///     use qrqrpar::payload::binary::{best_binary_encoding, BinaryEncoding};
+///
+///     assert_eq!(best_binary_encoding(32), BinaryEncoding::Byte);
+///
+/// Base45 needs 16.5 bits for every 2 bytes, and hexadecimal 11 bits for every
+/// byte, so the byte mode always wins. The comparison is kept advisory rather
+/// than assumed, since it only counts the data bits: the length field of the
+/// alphanumeric mode is also longer than that of the byte mode.
+pub fn best_binary_encoding(len: usize) -> BinaryEncoding {
+    [
+        BinaryEncoding::Byte,
+        BinaryEncoding::Base45,
+        BinaryEncoding::HexUpper,
+    ]
+    .into_iter()
+    .min_by_key(|encoding| encoding.data_bits_count(len))
+    .expect("non-empty encodings")
+}
+
+/// Encodes the data as base45, as specified by RFC 9285.
+///
+///     use qrqrpar::payload::binary::to_base45;
+///
+///     assert_eq!(to_base45(b"Hello!!"), "%69 VD92EX0");
+pub fn to_base45(data: &[u8]) -> String {
+    let mut res = String::with_capacity(data.len().div_ceil(2) * 3);
+    for chunk in data.chunks(2) {
+        let (mut n, digits) = match *chunk {
+            [a, b] => (usize::from(a) << 8 | usize::from(b), 3),
+            [a] => (usize::from(a), 2),
+            _ => unreachable!(),
+        };
+        for _ in 0..digits {
+            res.push(char::from(BASE45_ALPHABET[n % 45]));
+            n /= 45;
+        }
+    }
+    res
+}
+
+/// Decodes base45 text, as specified by RFC 9285.
+///
+///     use qrqrpar::payload::binary::from_base45;
+///
+///     assert_eq!(from_base45("QED8WEX0").unwrap(), b"ietf!");
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidCharacter)` if the text contains a character
+/// outside the base45 alphabet, if its length leaves a single character at the
+/// end, or if a group of characters encodes a value too large for its bytes.
+pub fn from_base45(text: &str) -> QrResult<Vec<u8>> {
+    let mut res = Vec::with_capacity(text.len() / 3 * 2 + 1);
+    for chunk in text.as_bytes().chunks(3) {
+        let mut n = 0;
+        for &c in chunk.iter().rev() {
+            let digit = alphanumeric_value(c).ok_or(QrError::InvalidCharacter)?;
+            n = n * 45 + usize::from(digit);
+        }
+        match chunk.len() {
+            3 if n <= 0xffff => res.extend_from_slice(&(n as u16).to_be_bytes()),
+            2 if n <= 0xff => res.push(n as u8),
+            _ => return Err(QrError::InvalidCharacter),
+        }
+    }
+    Ok(res)
+}
+
+/// Encodes the data as uppercase hexadecimal.
+///
+///     use qrqrpar::payload::binary::to_hex_upper;
+///
+///     assert_eq!(to_hex_upper(&[0x01, 0xab, 0xff]), "01ABFF");
+pub fn to_hex_upper(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+#[cfg(test)]
+mod binary_tests {
+    use crate::payload::binary::{
+        best_binary_encoding, from_base45, to_base45, to_hex_upper, BinaryEncoding,
+    };
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::QrCode;
+
+    #[test]
+    fn test_base45_rfc_vectors() {
+        for (data, text) in [
+            (&b"AB"[..], "BB8"),
+            (b"Hello!!", "%69 VD92EX0"),
+            (b"base-45", "UJCLQE7W581"),
+            (b"ietf!", "QED8WEX0"),
+            (b"", ""),
+            (&[0xff, 0xff], "FGW"),
+            (&[0xff], "U5"),
+        ] {
+            assert_eq!(to_base45(data), text);
+            assert_eq!(from_base45(text).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_base45_invalid() {
+        for text in ["GGW", "ZZ", "A", "BB8A", "bb8", "BB8!"] {
+            assert_eq!(from_base45(text), Err(QrError::InvalidCharacter));
+        }
+    }
+
+    #[test]
+    fn test_hex_upper() {
+        assert_eq!(to_hex_upper(b""), "");
+        assert_eq!(to_hex_upper(&[0, 0x0f, 0xf0]), "000FF0");
+    }
+
+    #[test]
+    fn test_costs() {
+        assert_eq!(BinaryEncoding::Byte.data_bits_count(1), 8);
+        assert_eq!(BinaryEncoding::Base45.data_bits_count(1), 11);
+        assert_eq!(BinaryEncoding::HexUpper.data_bits_count(1), 11);
+        assert_eq!(BinaryEncoding::Base45.data_bits_count(2), 17);
+        assert_eq!(BinaryEncoding::Base45.data_bits_count(3), 28);
+        for len in 0..=1024 {
+            let data = vec![0x5a; len];
+            for encoding in [BinaryEncoding::Base45, BinaryEncoding::HexUpper] {
+                let text = encoding.apply(&data);
+                assert_eq!(
+                    encoding.data_bits_count(len),
+                    encoding.mode().data_bits_count(text.len())
+                );
+            }
+            assert_eq!(best_binary_encoding(len), BinaryEncoding::Byte);
+        }
+    }
+
+    #[test]
+    fn test_qr_code() {
+        let hash = [0xa5; 32];
+        let byte = QrCode::binary(&hash, BinaryEncoding::Byte, EcLevel::M).unwrap();
+        assert_eq!(byte.version(), Version::Normal(3));
+        let base45 = QrCode::binary(&hash, BinaryEncoding::Base45, EcLevel::M).unwrap();
+        let expected = QrCode::with_error_correction_level(to_base45(&hash), EcLevel::M).unwrap();
+        assert_eq!(base45.to_colors(), expected.to_colors());
+        let hex = QrCode::binary(&hash, BinaryEncoding::HexUpper, EcLevel::M).unwrap();
+        assert_eq!(hex.version(), Version::Normal(4));
+    }
+}
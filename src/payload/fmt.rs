@@ -0,0 +1,222 @@
+//! The `fmt` module formats numbers for payload builders.
+//!
+//! Payload formats expect numbers in a fixed, locale-independent notation:
+//! a dot as decimal separator, no thousands separators, no exponent and no
+//! negative zero. The value is rounded from its shortest decimal
+//! representation, i.e. the digits `f64`'s `Display` prints, so that an
+//! amount written as `1.005` rounds as the decimal `1.005` rather than as the
+//! nearest binary value `1.00499999999999989...`.
+//!
+//! Both functions round half away from zero ("commercial" rounding), as bank
+//! statements and most payment formats do, rather than half to even.
+
+/// An error formatting a number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FmtError {
+    /// The value is NaN or infinite.
+    NonFinite(f64),
+    /// The value is too large to be formatted with the required precision.
+    TooLarge(f64),
+}
+
+impl std::fmt::Display for FmtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FmtError::NonFinite(value) => write!(f, "non-finite number {value}"),
+            FmtError::TooLarge(value) => write!(f, "number {value} is too large"),
+        }
+    }
+}
+
+impl std::error::Error for FmtError {}
+
+/// The magnitude from which `f64` cannot represent every cent anymore.
+const MAX_AMOUNT: f64 = 1e13;
+
+/// Formats a monetary amount with exactly two decimals.
+///
+///     use qrqrpar::payload::fmt::fmt_amount;
+///
+///     assert_eq!(fmt_amount(12.5).unwrap(), "12.50");
+///     assert_eq!(fmt_amount(1.005).unwrap(), "1.01");
+///     assert_eq!(fmt_amount(1234567.891).unwrap(), "1234567.89");
+///     assert_eq!(fmt_amount(-0.001).unwrap(), "0.00");
+///
+/// # Errors
+///
+/// Returns `Err(FmtError::NonFinite)` if the amount is NaN or infinite, and
+/// `Err(FmtError::TooLarge)` if its magnitude is 10¹³ or more, where cents
+/// are no longer exact.
+pub fn fmt_amount(amount: f64) -> Result<String, FmtError> {
+    if !amount.is_finite() {
+        return Err(FmtError::NonFinite(amount));
+    }
+    if amount.abs() >= MAX_AMOUNT {
+        return Err(FmtError::TooLarge(amount));
+    }
+    Ok(round_half_up(amount, 2))
+}
+
+/// Formats a geographic coordinate in degrees with up to 6 decimals, about
+/// 0.1 meter, without trailing zeros.
+///
+///     use qrqrpar::payload::fmt::fmt_coord;
+///
+///     assert_eq!(fmt_coord(35.681236).unwrap(), "35.681236");
+///     assert_eq!(fmt_coord(139.7671249).unwrap(), "139.767125");
+///     assert_eq!(fmt_coord(-12.5).unwrap(), "-12.5");
+///     assert_eq!(fmt_coord(-0.0000001).unwrap(), "0");
+///
+/// # Errors
+///
+/// Returns `Err(FmtError::NonFinite)` if the coordinate is NaN or infinite.
+pub fn fmt_coord(degrees: f64) -> Result<String, FmtError> {
+    if !degrees.is_finite() {
+        return Err(FmtError::NonFinite(degrees));
+    }
+    let mut res = round_half_up(degrees, 6);
+    let trimmed = res.trim_end_matches('0').trim_end_matches('.').len();
+    res.truncate(trimmed);
+    Ok(res)
+}
+
+/// Rounds the shortest decimal representation of a finite value half away
+/// from zero to `decimals` digits, and formats it with exactly that many
+/// digits after the dot.
+fn round_half_up(value: f64, decimals: usize) -> String {
+    // `Display` never uses an exponent, and prints the shortest digits which
+    // parse back to the same value.
+    let shortest = value.abs().to_string();
+    let (int_part, frac_part) = shortest.split_once('.').unwrap_or((&shortest, ""));
+
+    let mut digits = int_part.bytes().collect::<Vec<_>>();
+    digits.extend(
+        frac_part
+            .bytes()
+            .chain(std::iter::repeat(b'0'))
+            .take(decimals),
+    );
+    if frac_part
+        .as_bytes()
+        .get(decimals)
+        .is_some_and(|&d| d >= b'5')
+    {
+        // Propagates the carry, e.g. 9.995 to 10.00.
+        match digits.iter().rposition(|&d| d != b'9') {
+            Some(i) => {
+                digits[i] += 1;
+                digits[i + 1..].fill(b'0');
+            }
+            None => {
+                digits.fill(b'0');
+                digits.insert(0, b'1');
+            }
+        }
+    }
+
+    let is_zero = digits.iter().all(|&d| d == b'0');
+    let mut res = String::with_capacity(digits.len() + 2);
+    if value < 0.0 && !is_zero {
+        res.push('-');
+    }
+    let int_len = digits.len() - decimals;
+    res.extend(digits[..int_len].iter().map(|&d| d as char));
+    if decimals > 0 {
+        res.push('.');
+        res.extend(digits[int_len..].iter().map(|&d| d as char));
+    }
+    res
+}
+
+#[cfg(test)]
+mod fmt_tests {
+    use crate::payload::fmt::{fmt_amount, fmt_coord, FmtError};
+    use crate::test_util::Rng;
+
+    /// Returns random values of every magnitude, and both signs.
+    fn random_values(seed: u64) -> Vec<f64> {
+        let mut rng = Rng(seed);
+        (0..2000)
+            .map(|_| {
+                let mantissa = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+                let exponent = rng.between(0, 19) as i32 - 9;
+                let sign = if rng.below(2) == 0 { 1.0 } else { -1.0 };
+                sign * mantissa * 10f64.powi(exponent)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_amount_rounding() {
+        assert_eq!(fmt_amount(0.0).unwrap(), "0.00");
+        assert_eq!(fmt_amount(-0.0).unwrap(), "0.00");
+        assert_eq!(fmt_amount(0.005).unwrap(), "0.01");
+        assert_eq!(fmt_amount(0.004999).unwrap(), "0.00");
+        assert_eq!(fmt_amount(2.675).unwrap(), "2.68");
+        assert_eq!(fmt_amount(-2.675).unwrap(), "-2.68");
+        assert_eq!(fmt_amount(9.995).unwrap(), "10.00");
+        assert_eq!(fmt_amount(999.999).unwrap(), "1000.00");
+        assert_eq!(fmt_amount(1e12).unwrap(), "1000000000000.00");
+        assert_eq!(fmt_amount(1e-10).unwrap(), "0.00");
+        assert_eq!(fmt_amount(1e13), Err(FmtError::TooLarge(1e13)));
+        assert!(matches!(fmt_amount(f64::NAN), Err(FmtError::NonFinite(_))));
+        assert_eq!(
+            fmt_amount(f64::NEG_INFINITY),
+            Err(FmtError::NonFinite(f64::NEG_INFINITY))
+        );
+    }
+
+    #[test]
+    fn test_coord_rounding() {
+        assert_eq!(fmt_coord(0.0).unwrap(), "0");
+        assert_eq!(fmt_coord(-0.0).unwrap(), "0");
+        assert_eq!(fmt_coord(90.0).unwrap(), "90");
+        assert_eq!(fmt_coord(-180.0).unwrap(), "-180");
+        assert_eq!(fmt_coord(0.0000005).unwrap(), "0.000001");
+        assert_eq!(fmt_coord(-0.0000004).unwrap(), "0");
+        assert_eq!(fmt_coord(51.4999995).unwrap(), "51.5");
+        assert_eq!(fmt_coord(1e-300).unwrap(), "0");
+        assert!(matches!(fmt_coord(f64::NAN), Err(FmtError::NonFinite(_))));
+        assert_eq!(
+            fmt_coord(f64::INFINITY),
+            Err(FmtError::NonFinite(f64::INFINITY))
+        );
+    }
+
+    #[test]
+    fn test_amount_invariants() {
+        for value in random_values(0xa0a0) {
+            let Ok(formatted) = fmt_amount(value) else {
+                assert!(value.abs() >= 1e13);
+                continue;
+            };
+            assert!(!formatted.contains(['e', 'E', ',']), "{formatted}");
+            assert_ne!(formatted, "-0.00");
+            let (_, decimals) = formatted.split_once('.').unwrap();
+            assert_eq!(decimals.len(), 2, "{formatted}");
+            let parsed = formatted.parse::<f64>().unwrap();
+            assert!(
+                (parsed - value).abs() <= 0.005 + value.abs() * 1e-15,
+                "{value} {formatted}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_coord_invariants() {
+        for value in random_values(0xc0c0) {
+            let formatted = fmt_coord(value).unwrap();
+            assert!(!formatted.contains(['e', 'E', ',']), "{formatted}");
+            assert_ne!(formatted, "-0");
+            if let Some((_, decimals)) = formatted.split_once('.') {
+                assert!((1..=6).contains(&decimals.len()), "{formatted}");
+                assert!(!decimals.ends_with('0'), "{formatted}");
+            }
+            let parsed = formatted.parse::<f64>().unwrap();
+            assert!(
+                (parsed - value).abs() <= 5e-7 + value.abs() * 1e-15,
+                "{value} {formatted}"
+            );
+        }
+    }
+}
@@ -0,0 +1,411 @@
+//! The `gs1` module encodes GS1 element strings, i.e. lists of application
+//! identifiers (AIs) and their values, in FNC1 first position mode.
+//!
+//! The value of an AI without a predefined length is ended by a group
+//! separator (GS) unless it is the last element. In the symbol, the
+//! separator is `%` in alphanumeric mode, where a literal `%` is doubled, and
+//! the byte 0x1d in byte mode.
+//!
+//! The builder inserts the separators itself, so the values are sanitized
+//! with `SanitizePolicy::Strict(&[])`: a control character in a value, even a
+//! GS, is rejected rather than encoded as is.
+//!
+//!     use qrqrpar::payload::gs1::element_string;
+//!
+//!     let data = element_string(&[("10", "ABC123"), ("17", "270226")]).unwrap();
+//!     assert_eq!(data, b"10ABC123\x1d17270226");
+
+use crate::bits::Bits;
+use crate::internals::coding::{Optimizer, Parser, Segment};
+use crate::payload::{sanitize, SanitizePolicy};
+use crate::types::{EcLevel, Mode, QrError, QrResult, Version};
+
+/// The group separator, as transmitted by the readers.
+const GS: u8 = 0x1d;
+
+/// The format of the AIs starting with a prefix.
+struct AiFormat {
+    prefix: &'static str,
+    /// The number of digits of the AI.
+    ai_len: usize,
+    /// Whether the value only holds digits.
+    numeric: bool,
+    min_len: usize,
+    max_len: usize,
+    /// Whether the length is predefined, so that no separator follows.
+    predefined: bool,
+}
+
+const fn fixed(prefix: &'static str, ai_len: usize, len: usize) -> AiFormat {
+    AiFormat {
+        prefix,
+        ai_len,
+        numeric: true,
+        min_len: len,
+        max_len: len,
+        predefined: true,
+    }
+}
+
+const fn variable(
+    prefix: &'static str,
+    ai_len: usize,
+    numeric: bool,
+    min_len: usize,
+    max_len: usize,
+) -> AiFormat {
+    AiFormat {
+        prefix,
+        ai_len,
+        numeric,
+        min_len,
+        max_len,
+        predefined: false,
+    }
+}
+
+/// The supported AIs, from the GS1 General Specifications.
+const AI_FORMATS: &[AiFormat] = &[
+    fixed("00", 2, 18),
+    fixed("01", 2, 14),
+    fixed("02", 2, 14),
+    variable("10", 2, false, 1, 20),
+    fixed("11", 2, 6),
+    fixed("12", 2, 6),
+    fixed("13", 2, 6),
+    fixed("15", 2, 6),
+    fixed("16", 2, 6),
+    fixed("17", 2, 6),
+    fixed("20", 2, 2),
+    variable("21", 2, false, 1, 20),
+    variable("22", 2, false, 1, 20),
+    variable("235", 3, false, 1, 28),
+    variable("240", 3, false, 1, 30),
+    variable("241", 3, false, 1, 30),
+    variable("242", 3, true, 1, 6),
+    variable("250", 3, false, 1, 30),
+    variable("251", 3, false, 1, 30),
+    variable("254", 3, false, 1, 20),
+    variable("30", 2, true, 1, 8),
+    fixed("31", 4, 6),
+    fixed("32", 4, 6),
+    fixed("33", 4, 6),
+    fixed("34", 4, 6),
+    fixed("35", 4, 6),
+    fixed("36", 4, 6),
+    variable("37", 2, true, 1, 8),
+    variable("390", 4, true, 1, 15),
+    variable("391", 4, true, 4, 18),
+    variable("392", 4, true, 1, 15),
+    variable("393", 4, true, 4, 18),
+    variable("400", 3, false, 1, 30),
+    variable("401", 3, false, 1, 30),
+    variable("402", 3, true, 17, 17),
+    variable("403", 3, false, 1, 30),
+    fixed("41", 3, 13),
+    variable("420", 3, false, 1, 20),
+    variable("421", 3, false, 4, 12),
+    variable("422", 3, true, 3, 3),
+    variable("423", 3, true, 3, 15),
+    variable("424", 3, true, 3, 3),
+    variable("425", 3, true, 3, 15),
+    variable("426", 3, true, 3, 3),
+    variable("7003", 4, true, 10, 10),
+    variable("8004", 4, false, 1, 30),
+    variable("8008", 4, true, 8, 12),
+    variable("8020", 4, false, 1, 25),
+    variable("90", 2, false, 1, 30),
+    variable("91", 2, false, 1, 90),
+    variable("92", 2, false, 1, 90),
+    variable("93", 2, false, 1, 90),
+    variable("94", 2, false, 1, 90),
+    variable("95", 2, false, 1, 90),
+    variable("96", 2, false, 1, 90),
+    variable("97", 2, false, 1, 90),
+    variable("98", 2, false, 1, 90),
+    variable("99", 2, false, 1, 90),
+];
+
+/// Checks whether the byte is in the GS1 character set 82.
+fn is_cset82(byte: u8) -> bool {
+    matches!(byte, b'!' | b'"' | b'%'..=b'?' | b'A'..=b'Z' | b'_' | b'a'..=b'z')
+}
+
+/// Validates an element, and returns whether a separator follows it unless it
+/// is the last one.
+fn check_element(ai: &str, value: &str) -> QrResult<bool> {
+    let format = AI_FORMATS
+        .iter()
+        .find(|format| ai.len() == format.ai_len && ai.starts_with(format.prefix))
+        .filter(|_| ai.bytes().all(|b| b.is_ascii_digit()))
+        .ok_or(QrError::InvalidCharacter)?;
+    let valid_char = |b: u8| {
+        if format.numeric {
+            b.is_ascii_digit()
+        } else {
+            is_cset82(b)
+        }
+    };
+    let len = value.len();
+    if len < format.min_len || len > format.max_len || !value.bytes().all(valid_char) {
+        return Err(QrError::InvalidCharacter);
+    }
+    Ok(!format.predefined)
+}
+
+/// Validates the elements and concatenates them into the element string, as
+/// readers transmit it, with 0x1d as the group separator.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidCharacter)` if an AI is not supported, if a
+/// value contains a control character, or if a value does not match the
+/// format of its AI, e.g. a date which is not 6 digits long.
+pub fn element_string(elements: &[(&str, &str)]) -> QrResult<Vec<u8>> {
+    let mut data = Vec::new();
+    for (i, (ai, value)) in elements.iter().enumerate() {
+        sanitize(value, SanitizePolicy::Strict(&[]))?;
+        let separated = check_element(ai, value)?;
+        data.extend(ai.bytes().chain(value.bytes()));
+        if separated && i + 1 < elements.len() {
+            data.push(GS);
+        }
+    }
+    Ok(data)
+}
+
+/// Rewrites the element string for the alphanumeric mode, with `%` for the
+/// separators and `%%` for the literal `%`.
+fn escape(data: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(data.len());
+    for &b in data {
+        match b {
+            GS => escaped.push(b'%'),
+            b'%' => escaped.extend(b"%%"),
+            b => escaped.push(b),
+        }
+    }
+    escaped
+}
+
+/// Reverts `escape` for a byte segment.
+fn unescape(escaped: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(escaped.len());
+    let mut iter = escaped.iter().copied().peekable();
+    while let Some(b) = iter.next() {
+        match b {
+            b'%' if iter.next_if_eq(&b'%').is_some() => data.push(b'%'),
+            b'%' => data.push(GS),
+            b => data.push(b),
+        }
+    }
+    data
+}
+
+/// Moves the segment boundaries falling inside a `%%` pair to after it, so
+/// that every segment holds whole escapes, and drops the emptied segments.
+fn keep_escapes_whole(escaped: &[u8], segments: Vec<Segment>) -> Vec<Segment> {
+    let mut pair_ends = vec![false; escaped.len() + 1];
+    let mut i = 0;
+    while i < escaped.len() {
+        if escaped[i] == b'%' && escaped.get(i + 1) == Some(&b'%') {
+            pair_ends[i + 1] = true;
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    let mut res: Vec<Segment> = Vec::with_capacity(segments.len());
+    for mut segment in segments {
+        if let Some(last) = res.last_mut() {
+            segment.begin = last.end;
+        }
+        if pair_ends[segment.end] {
+            segment.end += 1;
+        }
+        if segment.begin < segment.end {
+            res.push(segment);
+        }
+    }
+    res
+}
+
+/// Encodes the escaped element string in the given segments, at the version.
+fn encode_at(
+    escaped: &[u8],
+    segments: Vec<Segment>,
+    version: Version,
+    ec_level: EcLevel,
+) -> QrResult<Bits> {
+    let mut bits = Bits::new(version);
+    bits.push_fnc1_first_position()?;
+    for segment in keep_escapes_whole(escaped, segments) {
+        let slice = &escaped[segment.begin..segment.end];
+        match segment.mode {
+            Mode::Numeric => bits.push_numeric_data(slice)?,
+            Mode::Alphanumeric => bits.push_alphanumeric_data(slice)?,
+            Mode::Byte | Mode::Kanji => bits.push_byte_data(&unescape(slice))?,
+        }
+    }
+    bits.push_terminator(ec_level)?;
+    Ok(bits)
+}
+
+/// Encodes the elements in FNC1 first position mode into the smallest QR
+/// code, segmenting the escaped element string optimally.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidCharacter)` if an element is invalid, see
+/// `element_string`, or `Err(QrError::TextTooLong)` with the length of the
+/// element string if the elements do not fit any QR code.
+pub(crate) fn encode(elements: &[(&str, &str)], ec_level: EcLevel) -> QrResult<Bits> {
+    let data = element_string(elements)?;
+    let escaped = escape(&data);
+    let segments = Parser::new(&escaped).collect::<Vec<_>>();
+    for version in (1..=40).map(Version::Normal) {
+        // The character count of a segment may also overflow its field in the
+        // smaller versions, which the larger ones may still hold.
+        let optimized = Optimizer::new(segments.iter().copied(), version).collect();
+        match encode_at(&escaped, optimized, version, ec_level) {
+            Err(QrError::DataTooLong) => {}
+            result => return result,
+        }
+    }
+    // The values are checked to be ASCII.
+    Err(QrError::TextTooLong {
+        chars: data.len(),
+        bytes: data.len(),
+    })
+}
+
+#[cfg(test)]
+mod gs1_tests {
+    use crate::bits::{inspect, Fnc1Kind, InspectedSegment};
+    use crate::payload::gs1::{element_string, encode, GS};
+    use crate::types::{EcLevel, Mode, QrError, Version};
+    use crate::QrCode;
+
+    /// Decodes the data as a reader does, turning the alphanumeric `%` back
+    /// into separators.
+    fn decode(elements: &[(&str, &str)]) -> (Version, Vec<Mode>, Vec<u8>) {
+        let bits = encode(elements, EcLevel::M).unwrap();
+        let version = bits.version();
+        let segments = inspect(&bits.into_bytes(), version).unwrap();
+        assert_eq!(segments[0], InspectedSegment::Fnc1(Fnc1Kind::FirstPosition));
+        let (mut modes, mut data) = (vec![], vec![]);
+        for segment in segments {
+            let InspectedSegment::Data {
+                mode, data: bytes, ..
+            } = segment
+            else {
+                continue;
+            };
+            modes.push(mode);
+            if mode != Mode::Alphanumeric {
+                data.extend(bytes);
+                continue;
+            }
+            let mut iter = bytes.into_iter().peekable();
+            while let Some(b) = iter.next() {
+                match b {
+                    b'%' if iter.next_if_eq(&b'%').is_some() => data.push(b'%'),
+                    b'%' => data.push(GS),
+                    b => data.push(b),
+                }
+            }
+        }
+        (version, modes, data)
+    }
+
+    #[test]
+    fn test_known_bit_stream() {
+        let elements = [("01", "09501101530003"), ("17", "270226")];
+        let bits = encode(&elements, EcLevel::M).unwrap();
+        assert_eq!(bits.version(), Version::Normal(1));
+        let expected = concat!(
+            "0101",
+            "0001",
+            "0000011000",
+            "0000001010",
+            "1110110110",
+            "0001101110",
+            "0010011001",
+            "0000000000",
+            "0100111101",
+            "0100001110",
+            "0011100010",
+            "0000",
+        );
+        let stream = bits
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:08b}"))
+            .collect::<String>();
+        assert_eq!(&stream[..expected.len()], expected);
+
+        let code = QrCode::gs1(&elements, EcLevel::M).unwrap();
+        assert_eq!(code.headers().fnc1, Some(Fnc1Kind::FirstPosition));
+    }
+
+    #[test]
+    fn test_separators() {
+        let elements = [("10", "ABC123"), ("17", "270226")];
+        assert_eq!(element_string(&elements).unwrap(), b"10ABC123\x1d17270226");
+        let (_, modes, data) = decode(&elements);
+        assert_eq!(data, element_string(&elements).unwrap());
+        assert!(modes.contains(&Mode::Alphanumeric));
+        assert!(!modes.contains(&Mode::Byte));
+
+        // The last element is not followed by a separator.
+        let elements = [("17", "270226"), ("10", "ABC123")];
+        assert_eq!(element_string(&elements).unwrap(), b"1727022610ABC123");
+
+        // Literal `%` are escaped in alphanumeric mode, and kept in byte mode
+        // next to 0x1d separators.
+        for elements in [
+            [("21", "A%B%%C"), ("10", "XYZ%")],
+            [("21", "ab%cd"), ("10", "x%y")],
+            [("21", "%"), ("10", "%a")],
+        ] {
+            let (_, _, data) = decode(&elements);
+            assert_eq!(data, element_string(&elements).unwrap(), "{elements:?}");
+        }
+        let (_, modes, _) = decode(&[("21", "ab%cd"), ("10", "x%y")]);
+        assert_eq!(modes, vec![Mode::Byte]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        for elements in [
+            [("01", "0950110153000")],
+            [("17", "2702261")],
+            [("17", "27O226")],
+            [("10", "")],
+            [("10", "ABC\n")],
+            [("10", "AB\x1dC")],
+            [("10", "ABC\x7f")],
+            [("10", "ÄBC")],
+            [("05", "1234")],
+            [("1A", "1234")],
+            [("310", "001250")],
+            [("3103", "001250x")],
+        ] {
+            assert_eq!(
+                element_string(&elements),
+                Err(QrError::InvalidCharacter),
+                "{elements:?}"
+            );
+        }
+        assert!(element_string(&[("3103", "001250"), ("8008", "26022712")]).is_ok());
+        let value = "1".repeat(90);
+        let elements = [("91", value.as_str()); 40];
+        assert_eq!(
+            encode(&elements, EcLevel::H).err(),
+            Some(QrError::TextTooLong {
+                chars: 3719,
+                bytes: 3719,
+            })
+        );
+    }
+}
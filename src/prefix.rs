@@ -0,0 +1,192 @@
+//! The `prefix` module provides an encoder for many payloads sharing the same
+//! beginning.
+use crate::bits::{self, RmqrStrategy};
+use crate::internals::coding::{optimal_segments, CharSegmentation};
+use crate::types::{EcLevel, QrResult, Version};
+use crate::QrCode;
+
+/// The symbols a `PrefixEncoder` chooses the version from.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Family {
+    /// QR code versions, as `QrCode::with_error_correction_level`.
+    Normal,
+    /// rMQR code versions, as `QrCode::rmqr_with_options`.
+    Rmqr(RmqrStrategy),
+}
+
+/// An encoder for payloads sharing a common prefix, e.g. URLs which only
+/// differ in their last characters.
+///
+/// The prefix is optimized once, so that encoding a payload only optimizes its
+/// suffix. The codes are identical to those encoded from the whole payload.
+///
+///     use qrqrpar::{EcLevel, Family, PrefixEncoder, QrCode};
+///
+///     let encoder = PrefixEncoder::new(b"https://example.com/items/", EcLevel::M, Family::Normal);
+///     let code = encoder.encode_suffix(b"0042").unwrap();
+///     let expected = QrCode::with_error_correction_level("https://example.com/items/0042", EcLevel::M)
+///         .unwrap();
+///     assert_eq!(code.to_colors(), expected.to_colors());
+#[derive(Debug, Clone)]
+pub struct PrefixEncoder {
+    prefix: Vec<u8>,
+    ec_level: EcLevel,
+    family: Family,
+    /// The optimization of the prefix for each candidate version.
+    segmentations: Vec<CharSegmentation>,
+}
+
+impl PrefixEncoder {
+    /// Constructs a new encoder for payloads starting with `prefix`.
+    pub fn new(prefix: &[u8], ec_level: EcLevel, family: Family) -> Self {
+        let versions = match family {
+            Family::Normal => bits::AUTO_VERSIONS.to_vec(),
+            Family::Rmqr(_) => Version::rmqr_all().to_vec(),
+        };
+        let segmentations = versions
+            .into_iter()
+            .map(|version| {
+                let mut segmentation = CharSegmentation::new(version);
+                segmentation.extend(prefix);
+                segmentation
+            })
+            .collect();
+        Self {
+            prefix: prefix.to_vec(),
+            ec_level,
+            family,
+            segmentations,
+        }
+    }
+
+    /// Encodes the prefix followed by `suffix`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the QR code cannot be constructed, e.g. when the data
+    /// is too long.
+    pub fn encode_suffix(&self, suffix: &[u8]) -> QrResult<QrCode> {
+        let bits = self.encode_suffix_bits(suffix)?;
+        QrCode::with_auto_bits(bits, self.ec_level)
+    }
+
+    /// Encodes the prefix followed by `suffix` into bits, choosing the
+    /// smallest version.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::DataTooLong)` if the data is too long to fit even
+    /// the highest version, and `Err(QrError::InvalidVersion)` if the fixed
+    /// dimension of an rMQR strategy is not the height or width of any rMQR
+    /// code version.
+    pub fn encode_suffix_bits(&self, suffix: &[u8]) -> QrResult<bits::Bits> {
+        let len = self.prefix.len() + suffix.len();
+        match self.family {
+            Family::Normal => bits::check_len_lower_bound(len, bits::AUTO_VERSIONS, self.ec_level)?,
+            Family::Rmqr(strategy) => bits::check_rmqr_request(len, self.ec_level, strategy)?,
+        }
+        let data = [&self.prefix[..], suffix].concat();
+        let optimize = |version: Version| {
+            let Some(prefix) = self.segmentations.iter().find(|s| s.version() == version) else {
+                return Ok(optimal_segments(&data, version));
+            };
+            let mut segmentation = prefix.clone();
+            segmentation.extend(suffix);
+            Ok(segmentation.segments())
+        };
+        match self.family {
+            Family::Normal => bits::encode_auto_with(&data, self.ec_level, None, optimize),
+            Family::Rmqr(strategy) => {
+                bits::encode_auto_rmqr_with(&data, self.ec_level, strategy, None, optimize)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod prefix_tests {
+    use crate::bits::{encode_auto, encode_auto_rmqr, RmqrStrategy};
+    use crate::prefix::{Family, PrefixEncoder};
+    use crate::test_util::{payloads, Content, Lengths, PayloadSpec};
+    use crate::types::EcLevel;
+    use crate::QrCode;
+
+    /// Payloads mixing every content, of 0 to `max_len` characters.
+    fn corpus(seed: u64, max_len: usize) -> impl Iterator<Item = Vec<u8>> {
+        let spec = PayloadSpec {
+            contents: vec![
+                Content::Numeric,
+                Content::Alphanumeric,
+                Content::Kanji,
+                Content::Utf8,
+                Content::Binary,
+            ],
+            max_run: 6,
+            lengths: Lengths::Between(0, max_len),
+        };
+        payloads(seed, spec)
+    }
+
+    #[test]
+    fn test_same_bits_as_whole_data() {
+        let levels = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+        let suffixes = corpus(0x5eee, 30).take(200 * 10).collect::<Vec<_>>();
+        let prefixes = corpus(0x5eed, 30);
+        for (i, (prefix, suffixes)) in prefixes.zip(suffixes.chunks(10)).enumerate() {
+            let ec_level = levels[i % levels.len()];
+            let encoder = PrefixEncoder::new(&prefix, ec_level, Family::Normal);
+            for suffix in suffixes {
+                let data = [&prefix[..], &suffix[..]].concat();
+                assert_eq!(
+                    encoder.encode_suffix_bits(suffix).map(|b| b.into_bytes()),
+                    encode_auto(&data, ec_level).map(|b| b.into_bytes()),
+                    "{data:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_same_bits_as_whole_data_rmqr() {
+        let strategies = [
+            RmqrStrategy::Width,
+            RmqrStrategy::Height,
+            RmqrStrategy::Area,
+            RmqrStrategy::Perimeter,
+            RmqrStrategy::AspectRatio(4.0),
+        ];
+        let suffixes = corpus(0xfacf, 20).take(100 * 5).collect::<Vec<_>>();
+        let prefixes = corpus(0xface, 20);
+        for (i, (prefix, suffixes)) in prefixes.zip(suffixes.chunks(5)).enumerate() {
+            // Every pair of a level and a strategy, in turn.
+            let ec_level = [EcLevel::M, EcLevel::H][i % 2];
+            let strategy = strategies[i / 2 % strategies.len()];
+            let encoder = PrefixEncoder::new(&prefix, ec_level, Family::Rmqr(strategy));
+            for suffix in suffixes {
+                let data = [&prefix[..], &suffix[..]].concat();
+                assert_eq!(
+                    encoder.encode_suffix_bits(suffix).map(|b| b.into_bytes()),
+                    encode_auto_rmqr(&data, ec_level, strategy).map(|b| b.into_bytes()),
+                    "{data:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_same_code() {
+        let encoder = PrefixEncoder::new(b"https://example.com/", EcLevel::M, Family::Normal);
+        for suffix in ["", "a", "ABC123", "12345678", "テスト"] {
+            let code = encoder.encode_suffix(suffix.as_bytes()).unwrap();
+            let expected = QrCode::new(format!("https://example.com/{suffix}")).unwrap();
+            assert_eq!(code.to_colors(), expected.to_colors());
+        }
+
+        let strategy = RmqrStrategy::Area;
+        let encoder = PrefixEncoder::new(b"SKU-", EcLevel::M, Family::Rmqr(strategy));
+        let code = encoder.encode_suffix(b"00001234").unwrap();
+        let expected = QrCode::rmqr_with_options(b"SKU-00001234", EcLevel::M, strategy).unwrap();
+        assert_eq!(code.to_colors(), expected.to_colors());
+    }
+}
@@ -0,0 +1,71 @@
+//! The `prelude` module re-exports the supported public surface of the crate.
+//!
+//!     use qrqrpar::prelude::*;
+//!
+//! Unlike the deeper module paths, the items here are kept stable as modules
+//! are reorganized. See the crate documentation for the stability tiers.
+//!
+//! The README examples only need the prelude:
+//!
+//! ```no_run
+//! use qrqrpar::prelude::*;
+//!
+//! let code = QrCode::rmqr("Hello, rmqr!").unwrap();
+//! code.save_png("rmqr.png", &QrStyle::default()).unwrap();
+//!
+//! let code = QrCode::rmqr_with_options("驫驫驫驫", EcLevel::H, RmqrStrategy::Width).unwrap();
+//! let style = QrStyle::new("#0000cc", "#ffffcc", QrShape::Round, 720, 2.0);
+//! code.save_svg("rmqr_round.svg", &style).unwrap();
+//!
+//! let code = QrCode::new("Normal QR code").unwrap();
+//! let style = QrStyle {
+//!     background_color: String::from("rgba(0,0,0,0)"),
+//!     ..Default::default()
+//! };
+//! code.save_png("normal_qr.png", &style).unwrap();
+//! ```
+//!
+//! Lower level encoding goes through the same imports:
+//!
+//! ```
+//! use qrqrpar::prelude::*;
+//!
+//! let mut bits = Bits::new(Version::Normal(1));
+//! bits.push_optimal_data(b"01234567").unwrap();
+//! bits.push_terminator(EcLevel::M).unwrap();
+//! let code = QrCode::with_bits(bits, EcLevel::M).unwrap();
+//! assert_eq!(code.get((0, 0)), Some(Color::Dark));
+//!
+//! let segment = Segment { mode: Mode::Numeric, begin: 0, end: 8 };
+//! assert_eq!(segment.encoded_len(Version::Normal(1)), 4 + 10 + 27);
+//!
+//! let result: Result<QrCode, QrError> = Encoder::new().encode(b"Some data", EcLevel::L);
+//! assert!(result.is_ok());
+//! ```
+
+pub use crate::batch::{save_png_batch, BatchError, BatchFailure, BatchReport};
+pub use crate::bits::{BitStats, Bits, Fnc1Kind, HeaderFlags, RmqrStrategy, ShapeStrategy};
+pub use crate::compare::{compare, Comparison, ComparisonDelta, EncodeSummary};
+pub use crate::encoder::Encoder;
+pub use crate::geometry::{GeometryError, PixelRect};
+pub use crate::internals::coding::{
+    optimal_segments, total_encoded_len, Optimizer, Parser, Segment,
+};
+pub use crate::partial::PartialQr;
+pub use crate::prefix::{Family, PrefixEncoder};
+pub use crate::print::{PrintSize, RobustConstraints, ScannerClass};
+pub use crate::profile::{QrProfile, QrProfileError};
+pub use crate::recommend::{
+    best_micro_symbol, recommend, Constraints, Recommendation, SymbolOption,
+};
+pub use crate::sprite::{render_sprite, sprite_use, SpriteError, SpriteUse};
+pub use crate::structured_append::SetError;
+pub use crate::types::{
+    alphanumeric_value, Color, EcLevel, EcLevelUnavailable, Mode, QrError, QrResult, Version,
+    ALPHANUMERIC_CHARSET, NUMERIC_CHARSET,
+};
+pub use crate::{
+    BorderCorners, Caption, CaptionAlign, Cmyk, GlyphError, MaskPreference, OversizedVersion,
+    Prepress, QrCode, QrShape, QrStyle, QuietZone, RenderError, Renderable, SizePolicy, StyleError,
+    StyleWarning,
+};
@@ -0,0 +1,336 @@
+//! The `print` module estimates how large a symbol must be printed to be
+//! scanned from a given distance, and sizes robust symbols for print.
+
+use crate::types::{EcLevel, QrError, QrResult};
+use crate::{QrCode, QrStyle, StyleError, StyleWarning};
+
+/// The module size, in millimeters per millimeter of scanning distance, which
+/// a phone camera resolves.
+///
+/// This is the "distance / 10" rule of thumb quoted by QR code vendors for the
+/// size of a typical symbol of 25 modules, e.g. version 2, so
+/// `1 / (10 * 25)`.
+pub const PHONE_MODULE_FACTOR: f64 = 0.004;
+
+/// The module size, in millimeters per millimeter of scanning distance, which
+/// an industrial imager resolves.
+///
+/// Reading-range charts of handheld and fixed-mount 2D imagers commonly list
+/// 10 mil (0.254 mm) modules at about 100 mm, i.e. about 1 / 400.
+pub const INDUSTRIAL_MODULE_FACTOR: f64 = 0.0025;
+
+/// The kind of scanner reading the printed symbol, see
+/// `QrCode::recommended_print_size`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScannerClass {
+    /// A phone camera, see `PHONE_MODULE_FACTOR`.
+    Phone,
+    /// A dedicated barcode imager, see `INDUSTRIAL_MODULE_FACTOR`.
+    Industrial,
+    /// A scanner resolving modules of the given size, in millimeters per
+    /// millimeter of scanning distance, e.g. measured with the actual devices.
+    Custom(f64),
+}
+
+impl ScannerClass {
+    /// The module size, in millimeters per millimeter of scanning distance,
+    /// which the scanner resolves.
+    pub fn module_factor(self) -> f64 {
+        match self {
+            ScannerClass::Phone => PHONE_MODULE_FACTOR,
+            ScannerClass::Industrial => INDUSTRIAL_MODULE_FACTOR,
+            ScannerClass::Custom(factor) => factor,
+        }
+    }
+}
+
+/// The smallest printed size of a symbol, see
+/// `QrCode::recommended_print_size`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrintSize {
+    /// The width and height of the symbol in millimeters, excluding the quiet
+    /// zone.
+    pub symbol_mm: (f64, f64),
+    /// The size of a module in millimeters.
+    pub module_mm: f64,
+}
+
+/// The limits of `QrCode::robust`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RobustConstraints {
+    /// The largest width of the symbol in modules, excluding the quiet zone,
+    /// e.g. 33 to allow up to version 4.
+    pub max_modules: usize,
+    /// The smallest printed size of a module in millimeters.
+    pub min_module_mm: f64,
+    /// The printed width of the image in millimeters, including the quiet
+    /// zone. The modules grow to fill it, but never shrink below
+    /// `min_module_mm`.
+    pub width_mm: Option<f64>,
+    /// The resolution of the printer in dots per inch.
+    pub dpi: f64,
+}
+
+impl Default for RobustConstraints {
+    fn default() -> Self {
+        Self {
+            max_modules: 33,
+            min_module_mm: 1.0,
+            width_mm: None,
+            dpi: 300.0,
+        }
+    }
+}
+
+/// The quiet zone of the styles returned by `QrCode::robust`, in modules.
+const ROBUST_QUIET_ZONE: u8 = 4;
+
+impl QrCode {
+    /// Constructs a QR code meant to survive glare and partial occlusion,
+    /// e.g. on museum placards, with a style to print it.
+    ///
+    /// The code uses the error correction level H, or Q if the data does not
+    /// fit at H within `RobustConstraints::max_modules`, in the smallest
+    /// version. The style has a quiet zone of 4 modules, and modules of a
+    /// whole number of pixels at `RobustConstraints::dpi`, rounded up so they
+    /// are never printed smaller than `RobustConstraints::min_module_mm`.
+    ///
+    ///     use qrqrpar::{EcLevel, QrCode, RobustConstraints};
+    ///
+    ///     let constraints = RobustConstraints { max_modules: 33, ..Default::default() };
+    ///     let (code, style) = QrCode::robust(b"https://museum.example/a/42", &constraints).unwrap();
+    ///     assert_eq!(code.width(), 33);
+    ///     assert_eq!(code.error_correction_level(), EcLevel::H);
+    ///     assert_eq!(style.quiet_zone, 4.0);
+    ///     // 1 mm is 11.8 dots at 300 dpi, rounded up to 12.
+    ///     assert_eq!(style.width, 12 * (33 + 8));
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::DataTooLong)` if the data does not fit at Q
+    /// within `RobustConstraints::max_modules`.
+    pub fn robust<D: AsRef<[u8]>>(
+        data: D,
+        constraints: &RobustConstraints,
+    ) -> QrResult<(Self, QrStyle)> {
+        let data = data.as_ref();
+        let code = [EcLevel::H, EcLevel::Q]
+            .into_iter()
+            .filter_map(|ec_level| Self::with_error_correction_level(data, ec_level).ok())
+            .find(|code| code.width() <= constraints.max_modules)
+            .ok_or(QrError::DataTooLong)?;
+
+        let modules = code.width() as f64 + 2.0 * f64::from(ROBUST_QUIET_ZONE);
+        let module_mm = constraints
+            .width_mm
+            .map_or(constraints.min_module_mm, |width_mm| {
+                constraints.min_module_mm.max(width_mm / modules)
+            });
+        let module_px = (module_mm / 25.4 * constraints.dpi).ceil().max(1.0);
+        let style = QrStyle {
+            width: (module_px * modules) as u32,
+            quiet_zone: f64::from(ROBUST_QUIET_ZONE),
+            ..Default::default()
+        };
+        Ok((code, style))
+    }
+
+    /// Estimates the smallest printed size at which the symbol can be scanned
+    /// from `scan_distance_mm` millimeters away.
+    ///
+    ///     use qrqrpar::{QrCode, ScannerClass};
+    ///
+    ///     let code = QrCode::new(b"https://example.com/").unwrap();
+    ///     let size = code.recommended_print_size(300.0, ScannerClass::Phone);
+    ///     assert_eq!(size.module_mm, 1.2);
+    ///     assert_eq!(size.symbol_mm, (30.0, 30.0));
+    ///
+    /// The module size is the distance times the module factor of the scanner,
+    /// see `ScannerClass::module_factor`, so symbols with more modules must be
+    /// printed larger. This is a heuristic: the lighting, the print contrast
+    /// and the camera all matter, so the result should be checked with the
+    /// actual scanners.
+    pub fn recommended_print_size(
+        &self,
+        scan_distance_mm: f64,
+        scanner: ScannerClass,
+    ) -> PrintSize {
+        let module_mm = scan_distance_mm * scanner.module_factor();
+        PrintSize {
+            symbol_mm: (
+                module_mm * self.width() as f64,
+                module_mm * self.height() as f64,
+            ),
+            module_mm,
+        }
+    }
+
+    /// Checks the style for printing the symbol at `dpi` dots per inch, one
+    /// dot per pixel, and scanning it from `scan_distance_mm` millimeters
+    /// away.
+    ///
+    /// The warnings are those of `QrStyle::validate`, followed by
+    /// `StyleWarning::PrintedModuleTooSmall` if the printed modules are
+    /// smaller than `recommended_print_size` recommends.
+    ///
+    ///     use qrqrpar::{QrCode, QrStyle, ScannerClass, StyleWarning};
+    ///
+    ///     let code = QrCode::new(b"https://example.com/").unwrap();
+    ///     // 100 pixels for 25 modules and the quiet zone, i.e. 0.26 mm modules.
+    ///     let style = QrStyle { width: 100, quiet_zone: 4.0, ..Default::default() };
+    ///     let warnings = code.validate_print(&style, 300.0, 300.0, ScannerClass::Phone).unwrap();
+    ///     assert!(matches!(
+    ///         warnings[..],
+    ///         [StyleWarning::PrintedModuleTooSmall { recommended_mm: 1.2, .. }]
+    ///     ));
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `QrStyle::validate`.
+    pub fn validate_print(
+        &self,
+        style: &QrStyle,
+        dpi: f64,
+        scan_distance_mm: f64,
+        scanner: ScannerClass,
+    ) -> Result<Vec<StyleWarning>, StyleError> {
+        let mut warnings = style.validate(self.version())?;
+        let module_mm = style.module_px(self.version()) / dpi * 25.4;
+        let recommended_mm = self
+            .recommended_print_size(scan_distance_mm, scanner)
+            .module_mm;
+        // Also warns about a resolution which is not positive.
+        if !(dpi.is_finite() && dpi > 0.0) || module_mm < recommended_mm {
+            warnings.push(StyleWarning::PrintedModuleTooSmall {
+                module_mm,
+                recommended_mm,
+            });
+        }
+        Ok(warnings)
+    }
+}
+
+#[cfg(test)]
+mod print_tests {
+    use crate::print::{PrintSize, RobustConstraints, ScannerClass};
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::{QrCode, QrStyle, StyleWarning};
+
+    fn assert_close(size: PrintSize, module_mm: f64, symbol_mm: (f64, f64)) {
+        assert!((size.module_mm - module_mm).abs() < 1e-9, "{size:?}");
+        assert!((size.symbol_mm.0 - symbol_mm.0).abs() < 1e-9, "{size:?}");
+        assert!((size.symbol_mm.1 - symbol_mm.1).abs() < 1e-9, "{size:?}");
+    }
+
+    #[test]
+    fn test_version_2() {
+        let code = QrCode::with_version(b"Some data", Version::Normal(2), EcLevel::M).unwrap();
+        let size = code.recommended_print_size(300.0, ScannerClass::Phone);
+        assert_close(size, 1.2, (30.0, 30.0));
+        let size = code.recommended_print_size(300.0, ScannerClass::Industrial);
+        assert_close(size, 0.75, (18.75, 18.75));
+    }
+
+    #[test]
+    fn test_rmqr() {
+        let code = QrCode::with_version(b"Some data", Version::Rmqr(13, 99), EcLevel::M).unwrap();
+        let size = code.recommended_print_size(300.0, ScannerClass::Phone);
+        assert_close(size, 1.2, (118.8, 15.6));
+        let size = code.recommended_print_size(300.0, ScannerClass::Industrial);
+        assert_close(size, 0.75, (74.25, 9.75));
+        let size = code.recommended_print_size(300.0, ScannerClass::Custom(0.001));
+        assert_close(size, 0.3, (29.7, 3.9));
+    }
+
+    #[test]
+    fn test_validate_print() {
+        let code = QrCode::with_version(b"Some data", Version::Normal(2), EcLevel::M).unwrap();
+        // 33 modules with the quiet zone, i.e. 2.1 mm at 300 dpi.
+        let style = QrStyle {
+            width: 33 * 24,
+            quiet_zone: 4.0,
+            ..Default::default()
+        };
+        let phone = ScannerClass::Phone;
+        assert_eq!(code.validate_print(&style, 300.0, 300.0, phone), Ok(vec![]));
+        let warnings = code.validate_print(&style, 600.0, 300.0, phone).unwrap();
+        assert_eq!(
+            warnings,
+            vec![StyleWarning::PrintedModuleTooSmall {
+                module_mm: 24.0 / 600.0 * 25.4,
+                recommended_mm: 1.2
+            }]
+        );
+        assert!(code
+            .validate_print(&style, 600.0, 300.0, ScannerClass::Industrial)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            code.validate_print(&style, 0.0, 300.0, phone)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_robust_falls_back_to_q() {
+        // 60 alphanumeric characters need 343 bits: version 4 holds 288 at H
+        // and 384 at Q.
+        let url = "HTTPS://MUSEUM.EXAMPLE/COLLECTION/GALLERY-3/OBJECT/1234-5678";
+        let constraints = RobustConstraints {
+            max_modules: 33,
+            ..Default::default()
+        };
+        let (code, style) = QrCode::robust(url, &constraints).unwrap();
+        assert_eq!(code.version(), Version::Normal(4));
+        assert_eq!(code.error_correction_level(), EcLevel::Q);
+        assert_eq!(style.quiet_zone, 4.0);
+        assert_eq!(style.width, 12 * 41);
+
+        // Without the cap, H is kept in a larger version.
+        let constraints = RobustConstraints {
+            max_modules: 177,
+            ..Default::default()
+        };
+        let (code, _) = QrCode::robust(url, &constraints).unwrap();
+        assert_eq!(code.version(), Version::Normal(5));
+        assert_eq!(code.error_correction_level(), EcLevel::H);
+    }
+
+    #[test]
+    fn test_robust_sizing() {
+        let constraints = RobustConstraints {
+            width_mm: Some(82.0),
+            dpi: 600.0,
+            ..Default::default()
+        };
+        let (code, style) = QrCode::robust("https://museum.example/a/42", &constraints).unwrap();
+        assert_eq!(code.version(), Version::Normal(4));
+        assert_eq!(code.error_correction_level(), EcLevel::H);
+        // 82 mm over 41 modules is 2 mm, i.e. 47.2 dots at 600 dpi.
+        assert_eq!(style.width, 48 * 41);
+        assert_eq!(style.module_px(code.version()), 48.0);
+
+        // The modules never shrink below the minimum.
+        let constraints = RobustConstraints {
+            width_mm: Some(10.0),
+            ..Default::default()
+        };
+        let (_, style) = QrCode::robust("https://museum.example/a/42", &constraints).unwrap();
+        assert_eq!(style.width, 12 * 41);
+    }
+
+    #[test]
+    fn test_robust_too_long() {
+        let constraints = RobustConstraints {
+            max_modules: 21,
+            ..Default::default()
+        };
+        let url = "HTTPS://MUSEUM.EXAMPLE/COLLECTION/GALLERY-3/OBJECT/1234-5678";
+        assert!(matches!(
+            QrCode::robust(url, &constraints),
+            Err(QrError::DataTooLong)
+        ));
+    }
+}
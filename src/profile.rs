@@ -0,0 +1,274 @@
+//! The `profile` module groups the encoding and rendering settings of a kind
+//! of symbol, so that they can be passed around, and loaded from a
+//! configuration file with the `serde` feature, as a single value.
+
+use crate::bits::Bits;
+use crate::prefix::Family;
+use crate::types::{EcLevel, QrError, Version};
+use crate::{QrCode, QrStyle, RenderError, Renderable, StyleError};
+
+/// The settings to encode and render a kind of symbol, e.g. the labels of a
+/// product line.
+///
+/// The profile is validated when constructed, so encoding and rendering only
+/// fail on the data itself, e.g. when it is too long.
+///
+///     use qrqrpar::{EcLevel, Family, QrProfile, QrStyle, RmqrStrategy};
+///
+///     let style = QrStyle { width: 300, ..Default::default() };
+///     let profile = QrProfile::new(EcLevel::H, Family::Rmqr(RmqrStrategy::Height), style).unwrap();
+///     let svg = profile.encode_and_render_svg(b"SKU-0042").unwrap();
+///     assert!(svg.contains(r#"width="300""#));
+///
+/// With the `serde` feature, the profile can be serialized, and named profiles
+/// can be loaded as a map from e.g. a TOML or JSON file. Missing style fields
+/// take their default values, and the profile is validated while
+/// deserialized.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedProfile"))]
+pub struct QrProfile {
+    ec_level: EcLevel,
+    family: Family,
+    style: QrStyle,
+}
+
+/// The fields of a `QrProfile` before validation.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct UncheckedProfile {
+    ec_level: EcLevel,
+    family: Family,
+    #[serde(default)]
+    style: QrStyle,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedProfile> for QrProfile {
+    type Error = QrProfileError;
+
+    fn try_from(profile: UncheckedProfile) -> Result<Self, QrProfileError> {
+        QrProfile::new(profile.ec_level, profile.family, profile.style)
+    }
+}
+
+/// An error building a `QrProfile`, or encoding and rendering a symbol with
+/// it.
+#[derive(Debug)]
+pub enum QrProfileError {
+    /// The style cannot be rendered.
+    Style(StyleError),
+    /// The data cannot be encoded, or the error correction level is not
+    /// supported by the family.
+    Encode(QrError),
+    /// The symbol cannot be rasterized, or the image cannot be encoded.
+    Render(RenderError),
+}
+
+impl std::fmt::Display for QrProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QrProfileError::Style(e) => write!(f, "invalid style: {e}"),
+            QrProfileError::Encode(e) => write!(f, "cannot encode: {e}"),
+            QrProfileError::Render(e) => write!(f, "cannot render: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for QrProfileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QrProfileError::Style(e) => Some(e),
+            QrProfileError::Encode(e) => Some(e),
+            QrProfileError::Render(e) => Some(e),
+        }
+    }
+}
+
+impl From<StyleError> for QrProfileError {
+    fn from(e: StyleError) -> Self {
+        QrProfileError::Style(e)
+    }
+}
+
+impl From<QrError> for QrProfileError {
+    fn from(e: QrError) -> Self {
+        QrProfileError::Encode(e)
+    }
+}
+
+impl QrProfile {
+    /// Constructs a new profile, checking that the style can be rendered and
+    /// that the family supports the error correction level.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrProfileError::Style` with the errors of `QrStyle::try_new`,
+    /// or `QrProfileError::Encode(QrError::EcLevelUnavailable)` if the family
+    /// is rMQR and the level is L or Q, which rMQR codes do not support.
+    pub fn new(ec_level: EcLevel, family: Family, style: QrStyle) -> Result<Self, QrProfileError> {
+        style.check()?;
+        if let Family::Rmqr(_) = family {
+            Bits::new(Version::Rmqr(7, 43)).max_len(ec_level)?;
+        }
+        Ok(Self {
+            ec_level,
+            family,
+            style,
+        })
+    }
+
+    /// Gets the error correction level.
+    pub fn ec_level(&self) -> EcLevel {
+        self.ec_level
+    }
+
+    /// Gets the family the version is chosen from.
+    pub fn family(&self) -> Family {
+        self.family
+    }
+
+    /// Gets the style.
+    pub fn style(&self) -> &QrStyle {
+        &self.style
+    }
+
+    /// Encodes the data into the smallest symbol of the family.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrProfileError::Encode` if the data cannot be encoded, e.g.
+    /// when it is too long.
+    pub fn encode<D: AsRef<[u8]>>(&self, data: D) -> Result<QrCode, QrProfileError> {
+        let code = match self.family {
+            Family::Normal => QrCode::with_error_correction_level(data, self.ec_level)?,
+            Family::Rmqr(strategy) => QrCode::rmqr_with_options(data, self.ec_level, strategy)?,
+        };
+        Ok(code)
+    }
+
+    /// Encodes the data and renders it to a SVG string.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrProfileError::Encode` if the data cannot be encoded, e.g.
+    /// when it is too long.
+    pub fn encode_and_render_svg<D: AsRef<[u8]>>(&self, data: D) -> Result<String, QrProfileError> {
+        Ok(self.encode(data)?.to_svg(&self.style))
+    }
+
+    /// Encodes the data and renders it to PNG data.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QrProfileError::Encode` if the data cannot be encoded, e.g.
+    /// when it is too long, and `QrProfileError::Render` if the symbol cannot
+    /// be rasterized, e.g. when its modules are smaller than
+    /// `QrStyle::minimum_module_px`.
+    pub fn encode_and_render_png<D: AsRef<[u8]>>(
+        &self,
+        data: D,
+    ) -> Result<Vec<u8>, QrProfileError> {
+        let code = self.encode(data)?;
+        Renderable::to_png(&code, &self.style).map_err(QrProfileError::Render)
+    }
+}
+
+#[cfg(test)]
+mod profile_tests {
+    use crate::profile::{QrProfile, QrProfileError};
+    use crate::types::{EcLevel, QrError};
+    use crate::{Family, QrCode, QrStyle, RmqrStrategy, StyleError};
+
+    #[test]
+    fn test_validation() {
+        let style = QrStyle {
+            width: 0,
+            ..Default::default()
+        };
+        assert!(matches!(
+            QrProfile::new(EcLevel::M, Family::Normal, style),
+            Err(QrProfileError::Style(StyleError::ZeroWidth))
+        ));
+        let family = Family::Rmqr(RmqrStrategy::Area);
+        assert!(matches!(
+            QrProfile::new(EcLevel::Q, family, QrStyle::default()),
+            Err(QrProfileError::Encode(QrError::EcLevelUnavailable(_)))
+        ));
+        assert!(QrProfile::new(EcLevel::H, family, QrStyle::default()).is_ok());
+    }
+
+    #[test]
+    fn test_render() {
+        let style = QrStyle {
+            width: 100,
+            ..Default::default()
+        };
+        let profile = QrProfile::new(EcLevel::Q, Family::Normal, style).unwrap();
+        let code = QrCode::with_error_correction_level("Some data", EcLevel::Q).unwrap();
+        assert_eq!(
+            profile.encode_and_render_svg("Some data").unwrap(),
+            code.to_svg(profile.style())
+        );
+        assert_eq!(
+            profile.encode_and_render_png("Some data").unwrap(),
+            code.to_png(profile.style()).unwrap()
+        );
+        assert!(matches!(
+            profile.encode_and_render_svg([b'a'; 3000]),
+            Err(QrProfileError::Encode(QrError::DataTooLong))
+        ));
+
+        // 29 modules including the quiet zone do not fit 20 pixels.
+        let style = QrStyle {
+            width: 20,
+            ..Default::default()
+        };
+        let profile = QrProfile::new(EcLevel::Q, Family::Normal, style).unwrap();
+        assert!(profile.encode_and_render_svg("Some data").is_ok());
+        assert!(matches!(
+            profile.encode_and_render_png("Some data"),
+            Err(QrProfileError::Render(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json() {
+        use std::collections::BTreeMap;
+
+        let json = r##"{
+            "label": {
+                "ec_level": "H",
+                "family": { "Rmqr": "Height" },
+                "style": { "color": "#0000cc", "width": 400, "quiet_zone": 2.0 }
+            },
+            "poster": { "ec_level": "L", "family": "Normal" }
+        }"##;
+        let profiles: BTreeMap<String, QrProfile> = serde_json::from_str(json).unwrap();
+
+        let label = &profiles["label"];
+        assert_eq!(label.style().width, 400);
+        let code = QrCode::rmqr_with_options("SKU-0042", EcLevel::H, RmqrStrategy::Height).unwrap();
+        assert_eq!(
+            label.encode_and_render_svg("SKU-0042").unwrap(),
+            code.to_svg(label.style())
+        );
+        assert!(!label.encode_and_render_png("SKU-0042").unwrap().is_empty());
+
+        let poster = &profiles["poster"];
+        assert_eq!(poster.style().width, QrStyle::default().width);
+        assert!(poster
+            .encode_and_render_svg("SKU-0042")
+            .unwrap()
+            .contains(r##"fill="#000000""##));
+
+        let round_trip: QrProfile =
+            serde_json::from_str(&serde_json::to_string(label).unwrap()).unwrap();
+        assert_eq!(round_trip.style().color, "#0000cc");
+
+        let invalid = r#"{ "ec_level": "M", "family": "Normal", "style": { "color": "nope" } }"#;
+        let error = serde_json::from_str::<QrProfile>(invalid).unwrap_err();
+        assert!(error.to_string().contains("invalid color"));
+    }
+}
@@ -0,0 +1,235 @@
+//! The `quick` module encodes and renders text in a single call, with
+//! defaults suiting most uses.
+//!
+//!     let png = qrqrpar::quick::png("https://example.com").unwrap();
+//!     assert!(png.starts_with(b"\x89PNG"));
+//!
+//!     let svg = qrqrpar::quick::rmqr_svg("https://example.com").unwrap();
+//!     assert!(svg.contains(r#"width="512""#));
+//!
+//! The symbols use the error correction level M and square modules, and the
+//! images are 512 pixels wide with a quiet zone of 4 modules. Text which is
+//! not ASCII is encoded as UTF-8 after the ECI designator 26, see
+//! `QrCode::new_utf8`, so that readers do not have to guess its character
+//! set. ASCII text is encoded as is, since every reader decodes it alike.
+//!
+//! Use `QrProfile`, or `QrCode` and `QrStyle`, for other settings.
+
+use crate::prefix::Family;
+use crate::types::{EcLevel, QrError};
+use crate::{QrCode, QrProfile, QrShape, QrStyle, RenderError, RmqrStrategy};
+
+/// The error correction level of the symbols.
+const EC_LEVEL: EcLevel = EcLevel::M;
+
+/// The width of the images in pixels.
+const WIDTH: u32 = 512;
+
+/// The quiet zone of the images in modules.
+const QUIET_ZONE: f64 = 4.0;
+
+/// The error of the functions of the `quick` module.
+#[derive(Debug)]
+pub enum QuickError {
+    /// The text cannot be encoded, e.g. when it is too long.
+    Encode(QrError),
+    /// The image cannot be rendered. The defaults fit every symbol into the
+    /// image, so only encoding the PNG data may fail.
+    Render(RenderError),
+}
+
+impl std::fmt::Display for QuickError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuickError::Encode(e) => write!(f, "cannot encode: {e}"),
+            QuickError::Render(e) => write!(f, "cannot render: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for QuickError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QuickError::Encode(e) => Some(e),
+            QuickError::Render(e) => Some(e),
+        }
+    }
+}
+
+impl From<QrError> for QuickError {
+    fn from(e: QrError) -> Self {
+        QuickError::Encode(e)
+    }
+}
+
+impl From<RenderError> for QuickError {
+    fn from(e: RenderError) -> Self {
+        QuickError::Render(e)
+    }
+}
+
+/// The profile of the symbols of the family.
+fn profile(family: Family) -> QrProfile {
+    let style = QrStyle {
+        shape: QrShape::Square,
+        width: WIDTH,
+        quiet_zone: QUIET_ZONE,
+        ..Default::default()
+    };
+    QrProfile::new(EC_LEVEL, family, style).expect("invalid quick defaults")
+}
+
+/// Encodes the text into the smallest symbol of the profile, as UTF-8 with
+/// ECI unless it is ASCII.
+fn encode(text: &str, profile: &QrProfile) -> Result<QrCode, QuickError> {
+    let ec_level = profile.ec_level();
+    let code = match (profile.family(), text.is_ascii()) {
+        (Family::Normal, true) => QrCode::with_error_correction_level(text, ec_level),
+        (Family::Normal, false) => QrCode::new_utf8(text, ec_level),
+        (Family::Rmqr(strategy), true) => QrCode::rmqr_with_options(text, ec_level, strategy),
+        (Family::Rmqr(strategy), false) => QrCode::rmqr_utf8(text, ec_level, strategy),
+    };
+    Ok(code.map_err(|e| e.for_text(text))?)
+}
+
+fn render_svg(text: &str, family: Family) -> Result<String, QuickError> {
+    let profile = profile(family);
+    Ok(encode(text, &profile)?.to_svg(profile.style()))
+}
+
+fn render_png(text: &str, family: Family) -> Result<Vec<u8>, QuickError> {
+    let profile = profile(family);
+    Ok(encode(text, &profile)?.to_png(profile.style())?)
+}
+
+/// Encodes the text into the smallest QR code and renders it to PNG data.
+///
+///     let png = qrqrpar::quick::png("https://example.com").unwrap();
+///     assert!(png.starts_with(b"\x89PNG"));
+///
+/// # Errors
+///
+/// Returns `QuickError::Encode` if the text is too long, and
+/// `QuickError::Render` if the PNG data cannot be encoded.
+pub fn png(text: &str) -> Result<Vec<u8>, QuickError> {
+    render_png(text, Family::Normal)
+}
+
+/// Encodes the text into the smallest QR code and renders it to a SVG string.
+///
+///     let svg = qrqrpar::quick::svg("こんにちは").unwrap();
+///     assert!(svg.starts_with("<?xml"));
+///
+/// # Errors
+///
+/// Returns `QuickError::Encode` if the text is too long.
+pub fn svg(text: &str) -> Result<String, QuickError> {
+    render_svg(text, Family::Normal)
+}
+
+/// Encodes the text into the rMQR code of the smallest area and renders it to
+/// PNG data.
+///
+/// # Errors
+///
+/// Returns `QuickError::Encode` if the text is too long for every rMQR code,
+/// and `QuickError::Render` if the PNG data cannot be encoded.
+pub fn rmqr_png(text: &str) -> Result<Vec<u8>, QuickError> {
+    render_png(text, Family::Rmqr(RmqrStrategy::Area))
+}
+
+/// Encodes the text into the rMQR code of the smallest area and renders it to
+/// a SVG string.
+///
+/// # Errors
+///
+/// Returns `QuickError::Encode` if the text is too long for every rMQR code.
+pub fn rmqr_svg(text: &str) -> Result<String, QuickError> {
+    render_svg(text, Family::Rmqr(RmqrStrategy::Area))
+}
+
+#[cfg(test)]
+mod quick_tests {
+    use crate::prefix::Family;
+    use crate::quick::{png, profile, rmqr_png, rmqr_svg, svg, QuickError};
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::{QrCode, RmqrStrategy};
+
+    #[test]
+    fn test_defaults() {
+        for family in [Family::Normal, Family::Rmqr(RmqrStrategy::Area)] {
+            let profile = profile(family);
+            assert_eq!(profile.ec_level(), EcLevel::M);
+            assert_eq!(profile.style().width, 512);
+            assert_eq!(profile.style().quiet_zone, 4.0);
+            assert!(profile.style().check().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_ascii() {
+        let text = "https://example.com";
+        let code = QrCode::new(text).unwrap();
+        let defaults = profile(Family::Normal);
+        assert_eq!(svg(text).unwrap(), code.to_svg(defaults.style()));
+        assert_eq!(png(text).unwrap(), code.to_png(defaults.style()).unwrap());
+        assert_eq!(code.headers().eci, None);
+
+        let code = QrCode::rmqr(text).unwrap();
+        let defaults = profile(Family::Rmqr(RmqrStrategy::Area));
+        assert_eq!(rmqr_svg(text).unwrap(), code.to_svg(defaults.style()));
+        assert_eq!(
+            rmqr_png(text).unwrap(),
+            code.to_png(defaults.style()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unicode() {
+        let text = "ありがとう";
+        let code = QrCode::new_utf8(text, EcLevel::M).unwrap();
+        assert_eq!(code.headers().eci, Some(26));
+        let defaults = profile(Family::Normal);
+        assert_eq!(svg(text).unwrap(), code.to_svg(defaults.style()));
+        assert_eq!(png(text).unwrap(), code.to_png(defaults.style()).unwrap());
+        assert_ne!(
+            svg(text).unwrap(),
+            QrCode::new(text).unwrap().to_svg(defaults.style())
+        );
+
+        let code = QrCode::rmqr_utf8(text, EcLevel::M, RmqrStrategy::Area).unwrap();
+        assert!(matches!(code.version(), Version::Rmqr(..)));
+        let defaults = profile(Family::Rmqr(RmqrStrategy::Area));
+        assert_eq!(rmqr_svg(text).unwrap(), code.to_svg(defaults.style()));
+    }
+
+    #[test]
+    fn test_oversized() {
+        let text = "a".repeat(3000);
+        for result in [svg(&text).map(|_| ()), png(&text).map(|_| ())] {
+            assert!(matches!(
+                result,
+                Err(QuickError::Encode(QrError::TextTooLong {
+                    chars: 3000,
+                    bytes: 3000,
+                }))
+            ));
+        }
+        let text = "é".repeat(200);
+        assert!(matches!(
+            rmqr_svg(&text),
+            Err(QuickError::Encode(QrError::TextTooLong {
+                chars: 200,
+                bytes: 400,
+            }))
+        ));
+        assert!(svg(&text).is_ok());
+
+        // Version 40 with its quiet zone needs 185 of the 512 pixels.
+        let text = "a".repeat(2000);
+        assert!(png(&text).is_ok());
+        let error = svg(&"a".repeat(3000)).unwrap_err();
+        let message = "cannot encode: text too long: 3000 characters in 3000 bytes";
+        assert_eq!(error.to_string(), message);
+    }
+}
@@ -0,0 +1,350 @@
+//! The `recommend` module suggests the symbol type and version to use for a
+//! payload, given the room available on a label.
+
+use crate::bits::{optimal_encoded_len, Bits};
+use crate::types::{EcLevel, Version};
+
+/// The limits a recommended symbol must respect, see `recommend`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Constraints {
+    /// The largest width of the symbol in modules, excluding the quiet zone.
+    pub max_width: Option<usize>,
+    /// The largest height of the symbol in modules, excluding the quiet zone.
+    pub max_height: Option<usize>,
+    /// The weakest error correction level accepted.
+    pub min_ec_level: EcLevel,
+    /// The printed size of a module, e.g. in millimeters, used to compute the
+    /// physical area of the symbols.
+    pub module_size: f64,
+}
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            max_height: None,
+            min_ec_level: EcLevel::L,
+            module_size: 1.0,
+        }
+    }
+}
+
+/// A symbol able to hold the payload, see `Recommendation`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolOption {
+    /// The version of the symbol, which also tells its type.
+    pub version: Version,
+    /// The strongest error correction level at which the payload fits.
+    pub ec_level: EcLevel,
+    /// The physical area of the symbol including its recommended quiet zone,
+    /// in squared units of `Constraints::module_size`.
+    pub area: f64,
+    /// Why the symbol is a candidate, compared with the other symbol types.
+    pub reason: String,
+}
+
+/// The symbols able to hold a payload under some constraints, returned by
+/// `recommend`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recommendation {
+    /// The feasible symbols, from the smallest physical area to the largest.
+    ///
+    /// A symbol is left out if another one is neither wider nor taller and
+    /// has an error correction level at least as strong.
+    pub options: Vec<SymbolOption>,
+    /// The smallest symbol of each type the payload needs, ignoring the width
+    /// and height constraints, e.g. to explain an empty `options`.
+    pub explanation: String,
+}
+
+impl Recommendation {
+    /// Gets the recommended symbol, i.e. the one with the smallest area.
+    pub fn best(&self) -> Option<&SymbolOption> {
+        self.options.first()
+    }
+}
+
+/// The name of the symbol type of a version.
+fn family_name(version: Version) -> &'static str {
+    match version {
+        Version::Normal(_) => "QR",
+        Version::Micro(_) => "Micro QR",
+        Version::Rmqr(..) => "rMQR",
+    }
+}
+
+/// Ranks the symbols able to hold `data` within the `constraints`, e.g. to
+/// answer which symbol type suits a payload and a label size.
+///
+///     use qrqrpar::{recommend, Constraints, EcLevel, Version};
+///
+///     let constraints = Constraints {
+///         max_height: Some(15),
+///         min_ec_level: EcLevel::M,
+///         module_size: 0.5,
+///         ..Default::default()
+///     };
+///     let recommendation = recommend(b"https://example.com/", &constraints);
+///     let best = recommendation.best().unwrap();
+///     assert_eq!(best.version, Version::Rmqr(13, 43));
+///     assert_eq!(
+///         best.reason,
+///         "rMQR R13x43-M: fits in 43x13 modules; QR would need 25x25, Micro QR cannot hold the data"
+///     );
+///
+/// Every QR, Micro QR and rMQR version is tried, with the strongest error
+/// correction level at which the optimally segmented data fits.
+pub fn recommend(data: &[u8], constraints: &Constraints) -> Recommendation {
+    let ec_levels = [EcLevel::H, EcLevel::Q, EcLevel::M, EcLevel::L]
+        .into_iter()
+        .filter(|ec_level| *ec_level >= constraints.min_ec_level);
+    let versions = (1..=40)
+        .map(Version::Normal)
+        .chain((1..=4).map(Version::Micro))
+        .chain(Version::rmqr_all());
+
+    let mut fitting = vec![];
+    for version in versions {
+        let Some(len) = optimal_encoded_len(data, version) else {
+            continue;
+        };
+        let ec_level = ec_levels
+            .clone()
+            .filter(|ec_level| version.available_ec_levels().contains(ec_level))
+            .find(|ec_level| {
+                Bits::new(version)
+                    .max_len(*ec_level)
+                    .is_ok_and(|capacity| len <= capacity)
+            });
+        if let Some(ec_level) = ec_level {
+            fitting.push((version, ec_level));
+        }
+    }
+
+    let area = |version: Version| {
+        let quiet_zone = 2 * i32::from(version.recommended_quiet_zone());
+        let width = f64::from(i32::from(version.width()) + quiet_zone);
+        let height = f64::from(i32::from(version.height()) + quiet_zone);
+        width * height * constraints.module_size * constraints.module_size
+    };
+    let size = |version: Version| format!("{}x{}", version.width(), version.height());
+
+    // The smallest symbol of each type, whatever the constraints.
+    let smallest = [Version::Normal(1), Version::Micro(1), Version::Rmqr(7, 43)].map(|first| {
+        let family = family_name(first);
+        let version = fitting
+            .iter()
+            .map(|(version, _)| *version)
+            .filter(|version| family_name(*version) == family)
+            .min_by(|a, b| area(*a).total_cmp(&area(*b)));
+        // Whether some version of the type supports a level allowed by the
+        // constraints, to tell an unsupported level from too much data.
+        let supported = ec_levels
+            .clone()
+            .any(|ec_level| match first.check_ec_level(ec_level) {
+                Ok(()) => true,
+                Err(error) => error.smallest.is_some(),
+            });
+        (family, version, supported)
+    });
+    let needs = |except: Option<&str>| {
+        smallest
+            .iter()
+            .filter(|(family, _, _)| Some(*family) != except)
+            .map(|(family, version, supported)| match version {
+                Some(version) => format!("{family} would need {}", size(*version)),
+                None if *supported => format!("{family} cannot hold the data"),
+                None => format!(
+                    "{family} does not support error correction level {:?}",
+                    constraints.min_ec_level
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let within = |version: Version| {
+        constraints
+            .max_width
+            .is_none_or(|max| version.width() as usize <= max)
+            && constraints
+                .max_height
+                .is_none_or(|max| version.height() as usize <= max)
+    };
+    let feasible = fitting
+        .iter()
+        .copied()
+        .filter(|(version, _)| within(*version))
+        .collect::<Vec<_>>();
+    let dominates = |(a, a_ec): (Version, EcLevel), (b, b_ec): (Version, EcLevel)| {
+        a != b && a.width() <= b.width() && a.height() <= b.height() && a_ec >= b_ec
+    };
+
+    let mut options = feasible
+        .iter()
+        .filter(|&&option| !feasible.iter().any(|&other| dominates(other, option)))
+        .map(|&(version, ec_level)| SymbolOption {
+            version,
+            ec_level,
+            area: area(version),
+            reason: format!(
+                "{} {version}-{ec_level:?}: fits in {} modules; {}",
+                family_name(version),
+                size(version),
+                needs(Some(family_name(version)))
+            ),
+        })
+        .collect::<Vec<_>>();
+    options.sort_by(|a, b| a.area.total_cmp(&b.area).then(b.ec_level.cmp(&a.ec_level)));
+
+    let explanation = if options.is_empty() {
+        format!("no symbol fits the constraints: {}", needs(None))
+    } else {
+        needs(None)
+    };
+    Recommendation {
+        options,
+        explanation,
+    }
+}
+
+/// Picks the smallest Micro QR code holding `data` at `min_ec_level` or a
+/// stronger level, with the strongest level available at that version, e.g.
+/// for tiny payloads printed on small parts.
+///
+///     use qrqrpar::{best_micro_symbol, EcLevel, QrCode, Version};
+///
+///     assert_eq!(best_micro_symbol(b"12345", EcLevel::L), Some((Version::Micro(1), EcLevel::L)));
+///     assert_eq!(best_micro_symbol(b"12345", EcLevel::M), Some((Version::Micro(2), EcLevel::M)));
+///     assert_eq!(best_micro_symbol(b"12345", EcLevel::Q), Some((Version::Micro(4), EcLevel::Q)));
+///
+///     // No Micro QR code version supports H.
+///     assert_eq!(best_micro_symbol(b"12345", EcLevel::H), None);
+///     let error = Version::Micro(4).check_ec_level(EcLevel::H).unwrap_err();
+///     assert_eq!(error.to_string(), "M4 supports L, M and Q only; no Micro QR version supports H");
+///
+///     let (version, ec_level) = best_micro_symbol(b"AB-12", EcLevel::L).unwrap();
+///     let code = QrCode::with_version(b"AB-12", version, ec_level).unwrap();
+///     assert_eq!((code.version(), code.error_correction_level()), (Version::Micro(2), EcLevel::M));
+///
+/// Only the levels of `Version::available_ec_levels` are tried, so Micro QR
+/// code version 1, which only has error detection, is skipped when
+/// `min_ec_level` is above L.
+pub fn best_micro_symbol(data: &[u8], min_ec_level: EcLevel) -> Option<(Version, EcLevel)> {
+    (1..=4).map(Version::Micro).find_map(|version| {
+        let len = optimal_encoded_len(data, version)?;
+        let ec_level = version
+            .available_ec_levels()
+            .iter()
+            .rev()
+            .filter(|ec_level| **ec_level >= min_ec_level)
+            .find(|ec_level| {
+                Bits::new(version)
+                    .max_len(**ec_level)
+                    .is_ok_and(|capacity| len <= capacity)
+            })?;
+        Some((version, *ec_level))
+    })
+}
+
+#[cfg(test)]
+mod recommend_tests {
+    use crate::recommend::{best_micro_symbol, recommend, Constraints};
+    use crate::types::{EcLevel, Version};
+
+    #[test]
+    fn test_height_constraint() {
+        let constraints = Constraints {
+            max_height: Some(9),
+            min_ec_level: EcLevel::M,
+            ..Default::default()
+        };
+        let recommendation = recommend(b"ABC-123", &constraints);
+        let best = recommendation.best().unwrap();
+        assert_eq!(best.version, Version::Rmqr(7, 43));
+        assert_eq!(best.ec_level, EcLevel::M);
+        assert_eq!(
+            best.reason,
+            "rMQR R7x43-M: fits in 43x7 modules; QR would need 21x21, Micro QR would need 15x15"
+        );
+        for option in &recommendation.options {
+            assert!(option.version.is_rmqr());
+            assert!(option.version.height() <= 9);
+        }
+    }
+
+    #[test]
+    fn test_long_payload() {
+        let data = [b'a'; 400];
+        let recommendation = recommend(&data, &Constraints::default());
+        let best = recommendation.best().unwrap();
+        assert_eq!(best.version, Version::Normal(13));
+        assert_eq!(best.ec_level, EcLevel::L);
+        assert!(best
+            .reason
+            .ends_with("Micro QR cannot hold the data, rMQR cannot hold the data"));
+        assert!(recommendation
+            .options
+            .windows(2)
+            .all(|pair| pair[0].area <= pair[1].area));
+    }
+
+    #[test]
+    fn test_dominated_options_are_left_out() {
+        let recommendation = recommend(b"1", &Constraints::default());
+        let versions = recommendation
+            .options
+            .iter()
+            .map(|option| (option.version, option.ec_level))
+            .collect::<Vec<_>>();
+        assert!(versions.contains(&(Version::Micro(1), EcLevel::L)));
+        assert!(versions.contains(&(Version::Micro(2), EcLevel::M)));
+        assert!(versions.contains(&(Version::Normal(1), EcLevel::H)));
+        assert!(!versions.contains(&(Version::Normal(2), EcLevel::H)));
+    }
+
+    #[test]
+    fn test_infeasible() {
+        let constraints = Constraints {
+            max_width: Some(30),
+            max_height: Some(15),
+            min_ec_level: EcLevel::H,
+            ..Default::default()
+        };
+        let recommendation = recommend(&[b'a'; 100], &constraints);
+        assert!(recommendation.options.is_empty());
+        assert_eq!(recommendation.best(), None);
+        assert_eq!(
+            recommendation.explanation,
+            "no symbol fits the constraints: QR would need 57x57, Micro QR does not support error correction level H, rMQR cannot hold the data"
+        );
+    }
+
+    #[test]
+    fn test_best_micro_symbol() {
+        use EcLevel::*;
+        let table = [
+            (&b"1"[..], [Some((1, L)), Some((2, M)), Some((4, Q)), None]),
+            (b"123456", [Some((2, M)), Some((2, M)), Some((4, Q)), None]),
+            (b"AB-12", [Some((2, M)), Some((2, M)), Some((4, Q)), None]),
+            (
+                b"abcdefgh",
+                [Some((3, L)), Some((4, Q)), Some((4, Q)), None],
+            ),
+            (&[b'a'; 14], [Some((4, L)), None, None, None]),
+        ];
+        for (data, expected) in table {
+            for (min_ec_level, expected) in [L, M, Q, H].into_iter().zip(expected) {
+                let best = best_micro_symbol(data, min_ec_level);
+                assert_eq!(
+                    best,
+                    expected.map(|(v, ec_level)| (Version::Micro(v), ec_level)),
+                    "{data:?} {min_ec_level:?}"
+                );
+                if let Some((version, ec_level)) = best {
+                    assert!(crate::QrCode::with_version(data, version, ec_level).is_ok());
+                }
+            }
+        }
+    }
+}
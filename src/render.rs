@@ -1,4 +1,5 @@
 //! Render a QR code into svg string.
+use std::fmt::Write;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Direction {
@@ -53,31 +54,43 @@ impl DirectedSegment {
         }
     }
 
-    fn start_coord(&self) -> [i16; 2] {
-        [self.sx, self.sy]
-    }
-
     fn end_coord(&self) -> [i16; 2] {
         [self.ex, self.ey]
     }
+
+    /// Packs the segment into a single integer, which is smaller to store and
+    /// faster to hash.
+    fn to_key(self) -> u64 {
+        [self.sx, self.sy, self.ex, self.ey]
+            .iter()
+            .fold(0, |key, &c| key << 16 | u64::from(c as u16))
+    }
+
+    /// Unpacks a segment packed by `to_key`.
+    fn from_key(key: u64) -> DirectedSegment {
+        let coord = |shift: u32| (key >> shift) as u16 as i16;
+        Self::new(coord(48), coord(32), coord(16), coord(0))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct DirectedSegments {
-    segments: hashbrown::HashSet<DirectedSegment>,
+    segments: hashbrown::HashSet<u64>,
 }
 
 impl DirectedSegments {
-    pub fn new() -> Self {
+    /// Creates an empty set with room for the outlines of `modules_count`
+    /// modules, which have at most 4 segments each.
+    pub fn with_capacity(modules_count: usize) -> Self {
         Self {
-            segments: hashbrown::HashSet::new(),
+            segments: hashbrown::HashSet::with_capacity(modules_count * 4),
         }
     }
 
     /// if the opposite segment is exists, remove it, otherwise add it
     fn add_or_remove_segment(&mut self, segment: DirectedSegment) {
-        if !self.segments.remove(&segment.reversed()) {
-            self.segments.insert(segment);
+        if !self.segments.remove(&segment.reversed().to_key()) {
+            self.segments.insert(segment.to_key());
         }
     }
 
@@ -88,28 +101,34 @@ impl DirectedSegments {
     }
 
     fn pop(&mut self) -> Option<DirectedSegment> {
-        if let Some(segment) = self.segments.iter().next().copied() {
-            self.segments.remove(&segment);
-            return Some(segment);
-        }
-        None
-    }
-
-    fn pop_segment(&mut self, segment: DirectedSegment) -> Option<DirectedSegment> {
-        if self.segments.remove(&segment) {
-            return Some(segment);
+        if let Some(key) = self.segments.iter().next().copied() {
+            self.segments.remove(&key);
+            return Some(DirectedSegment::from_key(key));
         }
         None
     }
 
-    /// Returns the next segment and removes it from hashset
-    fn pop_next(&mut self, segment: DirectedSegment) -> Option<DirectedSegment> {
-        for alternative in &Self::alternative_segments(segment) {
-            if self.segments.contains(alternative) {
-                return self.pop_segment(*alternative);
-            }
-        }
-        None
+    /// Returns the next segment and removes it from hashset, or `None` if the
+    /// next segment is `start`, i.e. the contour is closed.
+    ///
+    /// Where two modules touch diagonally, both contours pass through the same
+    /// corner, so the contour must only be closed once it continues to `start`
+    /// itself rather than when it merely reaches its start point. Otherwise the
+    /// result would depend on which segment the contour started from.
+    fn pop_next(
+        &mut self,
+        segment: DirectedSegment,
+        start: DirectedSegment,
+    ) -> Option<DirectedSegment> {
+        Self::alternative_segments(segment)
+            .into_iter()
+            .find(|alternative| {
+                *alternative == start || self.segments.contains(&alternative.to_key())
+            })
+            .filter(|next| *next != start)
+            .inspect(|next| {
+                self.segments.remove(&next.to_key());
+            })
     }
 
     fn alternative_segments(segment: DirectedSegment) -> [DirectedSegment; 3] {
@@ -137,53 +156,50 @@ impl DirectedSegments {
         }
     }
 
-    /// Returns a list of directed line segments whose endpoints are corners
-    /// and removes the line segments related to them from hashset
-    fn pop_corners(&mut self) -> Option<Vec<DirectedSegment>> {
-        if let Some(start_segment) = self.pop() {
-            let mut corners = vec![];
+    /// Collects the directed line segments of a contour whose endpoints are
+    /// corners into `corners`, and removes the line segments related to them
+    /// from hashset.
+    ///
+    /// Returns `false` if there are no contours left. A closed contour has at
+    /// least 4 corners; contours with fewer, which only an unclosed set of
+    /// segments could produce, are removed and skipped rather than handed to
+    /// the path generators, which index the corners.
+    fn pop_corners(&mut self, corners: &mut Vec<DirectedSegment>) -> bool {
+        loop {
+            corners.clear();
+            let Some(start_segment) = self.pop() else {
+                return false;
+            };
             let mut current_segment = start_segment;
-            while let Some(next_segment) = self.pop_next(current_segment) {
+            while let Some(next_segment) = self.pop_next(current_segment, start_segment) {
                 if current_segment.direction() != next_segment.direction() {
                     corners.push(current_segment);
                 }
                 current_segment = next_segment;
-                if current_segment.end_coord() == start_segment.start_coord() {
-                    break;
-                }
             }
             if current_segment.direction() != start_segment.direction() {
                 corners.push(current_segment);
             }
-            return Some(corners);
-        }
-        None
-    }
-
-    /// Returns a list of directed line segments whose endpoints are corners
-    /// and removes the line segments related to them from hashset
-    fn pop_corners_list(&mut self) -> Vec<Vec<DirectedSegment>> {
-        let mut corners_list = vec![];
-        while let Some(corners) = self.pop_corners() {
-            corners_list.push(corners);
+            if corners.len() >= 4 {
+                return true;
+            }
         }
-        corners_list
     }
 
     /// Convert to path string.
     /// Breaking change
     pub fn to_path_square_mut(&mut self) -> String {
         let mut s = String::new();
-        let corners_list = self.pop_corners_list();
-        for corners in corners_list.iter() {
-            s.push_str(&format!("M{} {}", corners[0].ex, corners[0].ey));
+        let mut corners = vec![];
+        while self.pop_corners(&mut corners) {
+            write!(s, "M{} {}", corners[0].ex, corners[0].ey).unwrap();
             for seg in corners.windows(2) {
                 if let [before, current] = seg {
                     let offset_x = current.ex - before.ex;
                     let offset_y = current.ey - before.ey;
                     match offset_x {
-                        0 => s.push_str(&format!("v{}", offset_y)),
-                        _ => s.push_str(&format!("h{}", offset_x)),
+                        0 => write!(s, "v{}", offset_y).unwrap(),
+                        _ => write!(s, "h{}", offset_x).unwrap(),
                     }
                 }
             }
@@ -194,18 +210,29 @@ impl DirectedSegments {
 
     /// Convert to path string.
     /// Breaking change
-    pub fn to_path_round_mut(&mut self) -> String {
+    ///
+    /// If `square_border` is `Some((width, height))`, corners lying on the
+    /// border of a symbol of that size are kept square instead of rounded.
+    ///
+    /// Every corner is rounded by a quadratic curve between the midpoints of
+    /// its edges, with the corner itself as control point. The curve always
+    /// stays on the corner side of the midpoints, whichever way the contour
+    /// runs, so the corners of holes (e.g. a light module enclosed by dark
+    /// ones) are rounded towards the inside of the hole just like the inner
+    /// corners of outer contours, and need no special case.
+    pub fn to_path_round_mut(&mut self, square_border: Option<(i16, i16)>) -> String {
         let mut s = String::new();
-        let corners_list = self.pop_corners_list();
-        for corners in corners_list.iter() {
+        let mut corners = vec![];
+        while self.pop_corners(&mut corners) {
             let start_segment = corners[0];
             let [start_x, start_y] = start_segment.end_coord();
             match start_segment.direction() {
-                Direction::Right => s.push_str(&format!("M{}.5 {}", start_x - 1, start_y)),
-                Direction::Down => s.push_str(&format!("M{} {}.5", start_x, start_y - 1)),
-                Direction::Left => s.push_str(&format!("M{}.5 {}", start_x, start_y)),
-                Direction::Up => s.push_str(&format!("M{} {}.5", start_x, start_y)),
+                Direction::Right => write!(s, "M{}.5 {}", start_x - 1, start_y),
+                Direction::Down => write!(s, "M{} {}.5", start_x, start_y - 1),
+                Direction::Left => write!(s, "M{}.5 {}", start_x, start_y),
+                Direction::Up => write!(s, "M{} {}.5", start_x, start_y),
             }
+            .unwrap();
 
             let mut before_segment = corners[0];
             for current_segment in corners.iter().skip(1).chain(corners.iter().take(1)) {
@@ -219,18 +246,30 @@ impl DirectedSegments {
                     (Direction::Down, _) | (_, Direction::Down) => " .5",
                     _ => unreachable!(),
                 };
-                let (dx1, dy1) = match current_segment.direction() {
-                    Direction::Up | Direction::Down => (dx, " 0"),
-                    _ => ("0 ", dy),
-                };
-                s.push_str(&format!("q{dx1}{dy1}{dx}{dy}"));
+                let [corner_x, corner_y] = before_segment.end_coord();
+                let is_border_corner = square_border.is_some_and(|(width, height)| {
+                    corner_x == 0 || corner_y == 0 || corner_x == width || corner_y == height
+                });
+                if is_border_corner {
+                    match current_segment.direction() {
+                        Direction::Up | Direction::Down => write!(s, "h{dx}v{dy}"),
+                        _ => write!(s, "v{dy}h{dx}"),
+                    }
+                    .unwrap();
+                } else {
+                    let (dx1, dy1) = match current_segment.direction() {
+                        Direction::Up | Direction::Down => (dx, " 0"),
+                        _ => ("0 ", dy),
+                    };
+                    write!(s, "q{dx1}{dy1}{dx}{dy}").unwrap();
+                }
 
                 let offset_x = current_segment.ex - before_segment.ex;
                 let offset_y = current_segment.ey - before_segment.ey;
                 if offset_y.abs() > 1 {
-                    s.push_str(&format!("v{}", offset_y - offset_y / offset_y.abs()));
+                    write!(s, "v{}", offset_y - offset_y / offset_y.abs()).unwrap();
                 } else if offset_x.abs() > 1 {
-                    s.push_str(&format!("h{}", offset_x - offset_x / offset_x.abs()));
+                    write!(s, "h{}", offset_x - offset_x / offset_x.abs()).unwrap();
                 }
                 before_segment = *current_segment;
             }
@@ -239,3 +278,469 @@ impl DirectedSegments {
         s
     }
 }
+
+/// A length written into SVG markup with `Display`, without allocating.
+///
+/// The value is rounded to 3 decimals, a thousandth of a module, and written
+/// with a dot as decimal separator, without trailing zeros, exponent or
+/// negative zero, e.g. "2" for `2.0000000000000004` and "0" for `-0.0`.
+/// Non-finite values, which a valid style never produces, are written as
+/// `f64`'s `Display` does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Number(pub f64);
+
+impl std::fmt::Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Number(value) = *self;
+        if !value.is_finite() {
+            return write!(f, "{value}");
+        }
+        let sign = if value < 0.0 { "-" } else { "" };
+        let thousandths = (value.abs() * 1000.0).round();
+        // Beyond 2^53 not every integer is representable, so the value has no
+        // fractional part to write anymore.
+        if thousandths >= 9007199254740992.0 {
+            return write!(f, "{sign}{:.0}", value.abs());
+        }
+        let thousandths = thousandths as u64;
+        if thousandths == 0 {
+            return f.write_str("0");
+        }
+        write!(f, "{sign}{}", thousandths / 1000)?;
+        let (mut fraction, mut digits) = (thousandths % 1000, 3);
+        if fraction == 0 {
+            return Ok(());
+        }
+        while fraction % 10 == 0 {
+            fraction /= 10;
+            digits -= 1;
+        }
+        write!(f, ".{fraction:0digits$}")
+    }
+}
+
+/// Escapes the characters which are not allowed in SVG text and attribute
+/// values.
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Estimates the advance width of a character, in ems.
+///
+/// Without access to the font metrics, half-width characters are assumed to
+/// be 0.6 em wide and everything else 1 em.
+fn estimated_char_width(c: char) -> f64 {
+    if (c as u32) < 0x1100 || ('\u{ff61}'..='\u{ffdc}').contains(&c) {
+        0.6
+    } else {
+        1.0
+    }
+}
+
+/// Estimates the width of a line of text, in ems.
+pub fn estimated_text_width(text: &str) -> f64 {
+    text.chars().map(estimated_char_width).sum()
+}
+
+/// Truncates the text with an ellipsis so that it is estimated to be at most
+/// `max_width` ems wide.
+pub fn truncate_text(text: &str, max_width: f64) -> String {
+    if estimated_text_width(text) <= max_width {
+        return text.to_owned();
+    }
+    let mut width = estimated_char_width('…');
+    let mut truncated = String::new();
+    for c in text.chars() {
+        width += estimated_char_width(c);
+        if width > max_width {
+            break;
+        }
+        truncated.push(c);
+    }
+    truncated.push('…');
+    truncated
+}
+
+/// Returns a path outlining a box for every visible character, as a stand-in
+/// for text when no font is available.
+///
+/// The text starts at `x` on the `baseline`, and `size` is the font size.
+pub fn text_placeholder_path(text: &str, x: f64, baseline: f64, size: f64) -> String {
+    let mut s = String::new();
+    let mut x = x;
+    for c in text.chars() {
+        let advance = estimated_char_width(c) * size;
+        if !c.is_whitespace() {
+            let width = advance * 0.8;
+            let height = size * 0.7;
+            let (left, baseline) = (Number(x + advance * 0.1), Number(baseline));
+            let (width, height) = (Number(width), Number(height));
+            write!(s, "M{left} {baseline}v-{height}h{width}v{height}Z").unwrap();
+        }
+        x += advance;
+    }
+    s
+}
+
+/// Rasterizes a path generated by `to_path_square_mut` at 1 pixel per unit,
+/// without anti-aliasing, using the even-odd fill rule.
+///
+/// Returns whether each pixel is filled, in row-major order.
+pub fn rasterize_square_path(path: &str, width: usize, height: usize) -> Vec<bool> {
+    // Since the path only consists of horizontal and vertical lines between
+    // integer coordinates, a row is crossed by exactly the vertical edges
+    // spanning it.
+    let mut crossings = vec![vec![]; height];
+    let (mut x, mut y) = (0i32, 0i32);
+    let mut start = (0i32, 0i32);
+    let tokens = path.split_inclusive(|c: char| c.is_ascii_alphabetic());
+    let mut command = ' ';
+    let mut numbers = vec![];
+    for token in tokens.chain([""]) {
+        // Each token is the arguments of the previous command, followed by the
+        // next command. The trailing empty token completes the last command.
+        let (args, next) = match token.chars().last() {
+            Some(c) if c.is_ascii_alphabetic() => (&token[..token.len() - 1], c),
+            _ => (token, ' '),
+        };
+        numbers.clear();
+        numbers.extend(
+            args.split_whitespace()
+                .filter_map(|n| n.parse::<i32>().ok()),
+        );
+        let dy = match (command, numbers.as_slice()) {
+            ('M', [mx, my]) => {
+                (x, y) = (*mx, *my);
+                start = (x, y);
+                0
+            }
+            ('h', [dx]) => {
+                x += dx;
+                0
+            }
+            ('v', [dy]) => *dy,
+            // The closing line is vertical if the last explicit one was not.
+            ('Z', []) => start.1 - y,
+            _ => 0,
+        };
+        let (top, bottom) = (y.min(y + dy), y.max(y + dy));
+        for row in top.max(0)..bottom.min(height as i32) {
+            crossings[row as usize].push(x);
+        }
+        y += dy;
+        command = next;
+    }
+
+    let mut pixels = vec![false; width * height];
+    for (row, xs) in crossings.iter_mut().enumerate() {
+        xs.sort_unstable();
+        for span in xs.chunks_exact(2) {
+            let start = span[0].clamp(0, width as i32) as usize;
+            let end = span[1].clamp(0, width as i32) as usize;
+            for pixel in &mut pixels[row * width + start..row * width + end] {
+                *pixel = true;
+            }
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod render_tests {
+    use crate::render::{
+        rasterize_square_path, text_placeholder_path, DirectedSegment, DirectedSegments, Number,
+    };
+
+    #[test]
+    fn test_diagonal_modules_are_separate_contours() {
+        // Different capacities give different iteration orders, so the
+        // contours start from different segments.
+        for capacity in 0..64 {
+            let mut segments = DirectedSegments::with_capacity(capacity);
+            segments.add_or_remove(0, 0);
+            segments.add_or_remove(1, 1);
+            let path = segments.to_path_round_mut(None);
+            assert_eq!(path.matches('M').count(), 2, "{path}");
+            assert_eq!(path.matches('q').count(), 8, "{path}");
+        }
+    }
+
+    /// Rasterizes the round path of the dark modules at 100 pixels per module,
+    /// returning the coverage of the given pixels.
+    fn round_path_coverage(
+        modules: impl Iterator<Item = (i16, i16)>,
+        size: u32,
+        pixels: &[(u32, u32)],
+    ) -> Vec<u8> {
+        let mut segments = DirectedSegments::with_capacity(0);
+        for (x, y) in modules {
+            segments.add_or_remove(x, y);
+        }
+        let path = segments.to_path_round_mut(None);
+        let (width, modules) = (size * 100, size);
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{width}" viewBox="0 0 {modules} {modules}"><path fill-rule="evenodd" d="{path}"/></svg>"#
+        );
+        let pixmap = crate::rasterize_svg(&svg, width, width).unwrap();
+        pixels
+            .iter()
+            .map(|&(x, y)| pixmap.pixel(x, y).unwrap().alpha())
+            .collect()
+    }
+
+    #[test]
+    fn test_round_path_hole() {
+        // A 5×5 dark block with a light center.
+        let ring = (0..5)
+            .flat_map(|y| (0..5).map(move |x| (x, y)))
+            .filter(|&p| p != (2, 2));
+        let coverage = round_path_coverage(
+            ring,
+            5,
+            &[
+                // The center of the hole, and the middle of its edges.
+                (250, 250),
+                (250, 205),
+                (205, 250),
+                // The corners of the hole are rounded inward.
+                (203, 203),
+                (297, 203),
+                (203, 297),
+                (297, 297),
+                // The corners of the block are rounded too.
+                (3, 3),
+                (497, 497),
+                // The dark modules around the hole.
+                (150, 150),
+                (250, 150),
+            ],
+        );
+        assert_eq!(
+            coverage,
+            [0, 0, 0, 255, 255, 255, 255, 0, 0, 255, 255],
+            "{coverage:?}"
+        );
+    }
+
+    #[test]
+    fn test_round_path_large_hole() {
+        // A 7×7 dark block with a 3×3 light center, whose corners are rounded
+        // while its edges stay straight.
+        let ring = (0..7)
+            .flat_map(|y| (0..7).map(move |x| (x, y)))
+            .filter(|&(x, y)| !(2..5).contains(&x) || !(2..5).contains(&y));
+        let coverage = round_path_coverage(
+            ring,
+            7,
+            &[(350, 350), (350, 203), (203, 350), (203, 203), (497, 497)],
+        );
+        assert_eq!(coverage, [0, 0, 0, 255, 255], "{coverage:?}");
+    }
+
+    /// Traces the dark modules of a `width`×`height` matrix with both path
+    /// generators.
+    fn paths(width: i16, height: i16, is_dark: impl Fn(i16, i16) -> bool) -> (String, String) {
+        let mut segments = DirectedSegments::with_capacity(0);
+        for y in 0..height {
+            for x in 0..width {
+                if is_dark(x, y) {
+                    segments.add_or_remove(x, y);
+                }
+            }
+        }
+        let mut square = segments.clone();
+        (
+            square.to_path_square_mut(),
+            segments.to_path_round_mut(Some((width, height))),
+        )
+    }
+
+    #[test]
+    fn test_all_light() {
+        for (width, height) in [(0, 0), (1, 1), (5, 3)] {
+            assert_eq!(
+                paths(width, height, |_, _| false),
+                (String::new(), String::new())
+            );
+        }
+    }
+
+    #[test]
+    fn test_all_dark() {
+        let (square, round) = paths(5, 3, |_, _| true);
+        assert_eq!(square.matches('M').count(), 1, "{square}");
+        assert_eq!(square.matches(['h', 'v']).count(), 3, "{square}");
+        assert!(rasterize_square_path(&square, 5, 3).iter().all(|&p| p));
+        // Every corner lies on the border, so none is rounded.
+        assert_eq!(round.matches('M').count(), 1, "{round}");
+        assert!(!round.contains('q'), "{round}");
+    }
+
+    #[test]
+    fn test_single_module() {
+        let (square, round) = paths(1, 1, |_, _| true);
+        assert_eq!(rasterize_square_path(&square, 1, 1), [true]);
+        assert_eq!(round.matches('M').count(), 1, "{round}");
+
+        let mut segments = DirectedSegments::with_capacity(1);
+        segments.add_or_remove(0, 0);
+        let round = segments.to_path_round_mut(None);
+        assert_eq!(round.matches('q').count(), 4, "{round}");
+    }
+
+    #[test]
+    fn test_alternating_row() {
+        for (width, height) in [(7, 1), (1, 7)] {
+            let (square, round) = paths(width, height, |x, y| (x + y) % 2 == 0);
+            assert_eq!(square.matches('M').count(), 4, "{square}");
+            assert_eq!(round.matches('M').count(), 4, "{round}");
+            let pixels = rasterize_square_path(&square, width as usize, height as usize);
+            assert_eq!(pixels, [true, false, true, false, true, false, true]);
+        }
+    }
+
+    #[test]
+    fn test_unclosed_segments_are_skipped() {
+        let mut segments = DirectedSegments::with_capacity(1);
+        segments.add_or_remove_segment(DirectedSegment::new(0, 0, 1, 0));
+        segments.add_or_remove_segment(DirectedSegment::new(1, 0, 1, 1));
+        let mut round = segments.clone();
+        assert_eq!(segments.to_path_square_mut(), "");
+        assert_eq!(round.to_path_round_mut(None), "");
+
+        // A lone segment has no corner at all.
+        let mut segments = DirectedSegments::with_capacity(1);
+        segments.add_or_remove_segment(DirectedSegment::new(0, 0, 1, 0));
+        let mut round = segments.clone();
+        assert_eq!(segments.to_path_square_mut(), "");
+        assert_eq!(round.to_path_round_mut(None), "");
+
+        // The closed contours are still traced.
+        let mut segments = DirectedSegments::with_capacity(2);
+        segments.add_or_remove_segment(DirectedSegment::new(5, 5, 6, 5));
+        segments.add_or_remove(0, 0);
+        assert_eq!(segments.to_path_square_mut().matches('M').count(), 1);
+    }
+
+    #[test]
+    fn test_square_path() {
+        let mut segments = DirectedSegments::with_capacity(3);
+        segments.add_or_remove(0, 0);
+        segments.add_or_remove(1, 0);
+        segments.add_or_remove(1, 1);
+        let path = segments.to_path_square_mut();
+        assert_eq!(path.matches('M').count(), 1, "{path}");
+        assert_eq!(path.matches(['h', 'v']).count(), 5, "{path}");
+    }
+
+    #[test]
+    fn test_rasterize_square_path() {
+        let mut segments = DirectedSegments::with_capacity(9);
+        // A ring of 8 modules around a hole, plus a diagonal neighbour.
+        for (x, y) in [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (0, 1),
+            (2, 1),
+            (0, 2),
+            (1, 2),
+            (2, 2),
+            (3, 3),
+        ] {
+            segments.add_or_remove(x, y);
+        }
+        let pixels = rasterize_square_path(&segments.to_path_square_mut(), 4, 4);
+        #[rustfmt::skip]
+        let expected = [
+            true, true, true, false,
+            true, false, true, false,
+            true, true, true, false,
+            false, false, false, true,
+        ];
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn test_number() {
+        for (value, expected) in [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (1.0, "1"),
+            (-1.0, "-1"),
+            (29.0, "29"),
+            (0.5, "0.5"),
+            (-1.5, "-1.5"),
+            (0.25, "0.25"),
+            (0.125, "0.125"),
+            (1.0 / 3.0, "0.333"),
+            (2.0 / 3.0, "0.667"),
+            (-2.0 / 3.0, "-0.667"),
+            (0.0004, "0"),
+            (-0.0004, "0"),
+            (0.0006, "0.001"),
+            (-0.0006, "-0.001"),
+            (0.9996, "1"),
+            (-0.9996, "-1"),
+            (1.05, "1.05"),
+            (10.01, "10.01"),
+            (100.001, "100.001"),
+            (1e-7, "0"),
+            (1e21, "1000000000000000000000"),
+            (-1e21, "-1000000000000000000000"),
+            (1e12 + 0.5, "1000000000000.5"),
+            (f64::NAN, "NaN"),
+            (f64::INFINITY, "inf"),
+        ] {
+            assert_eq!(Number(value).to_string(), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn test_number_quiet_zone_arithmetic() {
+        // The sums and differences the emitters compute from fractional
+        // quiet zones, bleeds and caption sizes.
+        for (value, expected) in [
+            (0.1 + 0.2, "0.3"),
+            (21.0 + 0.1 + 0.2, "21.3"),
+            (0.0 - 0.1 - 0.2, "-0.3"),
+            (0.0 - 0.0, "0"),
+            (0.7 + 0.1 - 0.8, "0"),
+            (2.0000000000000004, "2"),
+            (1.9999999999999998, "2"),
+            (4.35 * 100.0 / 100.0, "4.35"),
+            (1.1 * 1.1, "1.21"),
+            (3.0 / 20.0, "0.15"),
+            (1.0 / 12.0, "0.083"),
+            (25.0 + 2.0 * 0.3, "25.6"),
+            (1.5 / 2.0, "0.75"),
+        ] {
+            assert_eq!(Number(value).to_string(), expected, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn test_text_placeholder_path() {
+        let path = text_placeholder_path("a b", 0.1 + 0.2, 29.0, 1.0);
+        assert!(!path.contains('e'), "{path}");
+        assert!(path.starts_with("M0."), "{path}");
+        assert_eq!(path.matches('M').count(), 2);
+        assert!(path
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .all(|n| n
+                .split('.')
+                .nth(1)
+                .is_none_or(|decimals| decimals.len() <= 3)));
+    }
+}
@@ -0,0 +1,311 @@
+//! The `sprite` module renders many codes into a single SVG sprite, whose
+//! symbols are drawn with small `<use>` references, e.g. on a web page
+//! listing hundreds of codes.
+
+use crate::render::Number;
+use crate::{
+    svg_background, svg_caption, svg_crop_marks, svg_path, Prepress, QrCode, QrStyle, StyleError,
+};
+
+/// An error rendering a sprite with `render_sprite`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpriteError {
+    /// The id is not a valid XML name, see `render_sprite`.
+    InvalidId(String),
+    /// Two codes have the same id, or an id is the same as the id of the crop
+    /// marks of another code, see `render_sprite`.
+    DuplicateId(String),
+    /// The style cannot be rendered, see `QrStyle::try_new`.
+    Style(StyleError),
+}
+
+impl std::fmt::Display for SpriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpriteError::InvalidId(id) => write!(f, "invalid sprite id {id:?}"),
+            SpriteError::DuplicateId(id) => write!(f, "duplicate sprite id {id:?}"),
+            SpriteError::Style(e) => write!(f, "invalid style: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SpriteError {}
+
+impl From<StyleError> for SpriteError {
+    fn from(e: StyleError) -> Self {
+        SpriteError::Style(e)
+    }
+}
+
+/// A reference to a symbol of a sprite, returned by `sprite_use`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpriteUse {
+    /// The `<svg>` element drawing the symbol, to be placed in the page which
+    /// includes the sprite.
+    pub markup: String,
+    /// The width of the image in pixels, see `QrCode::image_sizes`.
+    pub width: u32,
+    /// The height of the image in pixels, see `QrCode::image_sizes`.
+    pub height: u32,
+}
+
+/// Checks that the id is an ASCII XML name, i.e. starts with a letter or `_`
+/// and only contains letters, digits, `_`, `-` and `.`.
+fn is_valid_id(id: &str) -> bool {
+    let mut chars = id.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Renders the codes into a single SVG document holding one `<symbol>` per
+/// code, with the given id, inside `<defs>`.
+///
+///     use qrqrpar::{render_sprite, sprite_use, QrCode, QrStyle};
+///
+///     let style = QrStyle { width: 120, ..Default::default() };
+///     let codes = (1..=3)
+///         .map(|n| (format!("code-{n}"), QrCode::new(format!("item {n}")).unwrap()))
+///         .collect::<Vec<_>>();
+///     let sprite = render_sprite(&codes, &style).unwrap();
+///     assert_eq!(sprite.matches("<symbol ").count(), 3);
+///
+///     let reference = sprite_use("code-2", &codes[1].1, &style);
+///     assert_eq!(
+///         reference.markup,
+///         r##"<svg width="120" height="120"><use href="#code-2"/></svg>"##
+///     );
+///
+/// Each symbol has the viewBox of `to_svg`, and holds its background, modules,
+/// caption and crop marks, so a code drawn with `sprite_use` looks the same
+/// as its standalone document. The crop marks of the symbol `id` are grouped
+/// under the id `{id}.crop-marks`, so that ids stay unique in the sprite. The
+/// sprite itself draws nothing, and can be inlined in a HTML page or served
+/// as a file.
+///
+/// # Errors
+///
+/// Returns `SpriteError::Style` if the style cannot be rendered, see
+/// `QrStyle::try_new`. Returns `SpriteError::InvalidId` if an id is not an
+/// ASCII XML name, i.e. does not start with a letter or `_`, or contains
+/// other characters than letters, digits, `_`, `-` and `.`. Returns
+/// `SpriteError::DuplicateId` if two codes have the same id, or if an id is
+/// the same as the id of the crop marks of another code.
+pub fn render_sprite(codes: &[(String, QrCode)], style: &QrStyle) -> Result<String, SpriteError> {
+    style.check()?;
+    let draws_crop_marks = style.prepress.is_some_and(Prepress::draws_crop_marks);
+    let mut ids = hashbrown::HashSet::new();
+    let mut lines = vec![
+        String::from(r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1">"#),
+        String::from("<defs>"),
+    ];
+    for (id, code) in codes {
+        if !is_valid_id(id) {
+            return Err(SpriteError::InvalidId(id.clone()));
+        }
+        if !ids.insert(id.clone()) {
+            return Err(SpriteError::DuplicateId(id.clone()));
+        }
+        let crop_marks_id = format!("{id}.crop-marks");
+        if draws_crop_marks && !ids.insert(crop_marks_id.clone()) {
+            return Err(SpriteError::DuplicateId(crop_marks_id));
+        }
+        let (vb_width, vb_height, _, _) = code.image_sizes(style);
+        let (vb_width, vb_height) = (Number(vb_width), Number(vb_height));
+        let origin = Number(-style.bleed());
+        lines.push(format!(
+            r#"<symbol id="{id}" viewBox="{origin} {origin} {vb_width} {vb_height}" preserveAspectRatio="none">"#
+        ));
+        lines.extend(
+            [
+                svg_background(code, style),
+                svg_path(code, style, |_, _| true),
+                svg_caption(code, style),
+                svg_crop_marks(code, style, &crop_marks_id),
+            ]
+            .into_iter()
+            .filter(|element| !element.is_empty()),
+        );
+        lines.push(String::from("</symbol>"));
+    }
+    lines.push(String::from("</defs>"));
+    lines.push(String::from("</svg>"));
+    Ok(lines.join("\n"))
+}
+
+/// Returns the markup drawing the symbol `id` of a sprite rendered by
+/// `render_sprite`, at the size of the image of the code, see
+/// `QrCode::image_sizes`.
+///
+/// The id is not checked, since `render_sprite` already rejects invalid ones.
+pub fn sprite_use(id: &str, code: &QrCode, style: &QrStyle) -> SpriteUse {
+    let (_, _, width, height) = code.image_sizes(style);
+    SpriteUse {
+        markup: format!(r##"<svg width="{width}" height="{height}"><use href="#{id}"/></svg>"##),
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod sprite_tests {
+    use crate::sprite::{render_sprite, sprite_use, SpriteError};
+    use crate::types::{EcLevel, Version};
+    use crate::{rasterize_svg, Caption, Prepress, QrCode, QrStyle, StyleError};
+
+    fn codes() -> Vec<(String, QrCode)> {
+        vec![
+            (String::from("qr"), QrCode::new(b"Some data").unwrap()),
+            (
+                String::from("rmqr_1.a"),
+                QrCode::with_version(b"12345", Version::Rmqr(7, 43), EcLevel::M).unwrap(),
+            ),
+            (
+                String::from("micro-2"),
+                QrCode::with_version(b"123", Version::Micro(1), EcLevel::L).unwrap(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_symbols() {
+        let style = QrStyle {
+            width: 200,
+            ..Default::default()
+        };
+        let codes = codes();
+        let sprite = render_sprite(&codes, &style).unwrap();
+        assert!(<resvg::usvg::Tree as resvg::usvg::TreeParsing>::from_str(
+            &sprite,
+            &Default::default()
+        )
+        .is_ok());
+        assert_eq!(sprite.matches("<symbol ").count(), codes.len());
+        assert_eq!(sprite.matches("</symbol>").count(), codes.len());
+
+        for (id, code) in &codes {
+            let start = sprite.find(&format!(r#"<symbol id="{id}""#)).unwrap();
+            let end = start + sprite[start..].find("</symbol>").unwrap();
+            let symbol = &sprite[start..end];
+            let path = |svg: &str| {
+                svg.lines()
+                    .find(|line| line.starts_with("<path"))
+                    .map(str::to_owned)
+            };
+            assert_eq!(path(symbol), path(&code.to_svg(&style)));
+
+            // Drawing the symbol renders like the standalone document.
+            let reference = sprite_use(id, code, &style);
+            let page = format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">{}{}</svg>"#,
+                reference.width,
+                reference.height,
+                &sprite[sprite.find("<defs>").unwrap()..sprite.find("</defs>").unwrap() + 7],
+                reference.markup
+            );
+            let expected = code.to_pixmap(&style).unwrap();
+            let actual = rasterize_svg(&page, reference.width, reference.height).unwrap();
+            assert_eq!(
+                (expected.width(), expected.height()),
+                (reference.width, reference.height)
+            );
+            assert!(actual.data() == expected.data(), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_caption() {
+        let style = QrStyle {
+            caption: Some(Caption::new("<A&B>")),
+            ..Default::default()
+        };
+        let sprite = render_sprite(&codes()[..1], &style).unwrap();
+        assert!(sprite.contains("&lt;A&amp;B&gt;"));
+        assert!(<resvg::usvg::Tree as resvg::usvg::TreeParsing>::from_str(
+            &sprite,
+            &Default::default()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_crop_marks() {
+        let style = QrStyle {
+            prepress: Some(Prepress {
+                bleed_modules: 2.0,
+                crop_marks: true,
+                registration_color: false,
+            }),
+            ..Default::default()
+        };
+        let codes = codes();
+        let sprite = render_sprite(&codes, &style).unwrap();
+        assert_eq!(sprite.matches(r#"<g id="#).count(), codes.len());
+        for (id, _) in &codes {
+            assert_eq!(
+                sprite.matches(&format!(r#"id="{id}.crop-marks""#)).count(),
+                1
+            );
+        }
+        assert!(!sprite.contains(r#"id="crop-marks""#));
+
+        // An id may not take the id of the crop marks of another code.
+        let code = QrCode::new(b"Some data").unwrap();
+        for ids in [["a", "a.crop-marks"], ["a.crop-marks", "a"]] {
+            let codes = ids.map(|id| (id.to_owned(), code.clone()));
+            assert_eq!(
+                render_sprite(&codes, &style),
+                Err(SpriteError::DuplicateId(String::from("a.crop-marks")))
+            );
+        }
+        // Without crop marks, the same ids are fine.
+        let codes = ["a", "a.crop-marks"].map(|id| (id.to_owned(), code.clone()));
+        assert!(render_sprite(&codes, &QrStyle::default()).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_style() {
+        let style = QrStyle {
+            color: String::from("rgb(0, 0)"),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_sprite(&codes(), &style),
+            Err(SpriteError::Style(StyleError::InvalidColor(String::from(
+                "rgb(0, 0)"
+            ))))
+        );
+        let style = QrStyle {
+            width: 0,
+            ..Default::default()
+        };
+        let error = render_sprite(&codes(), &style).unwrap_err();
+        assert_eq!(error, SpriteError::Style(StyleError::ZeroWidth));
+        assert_eq!(error.to_string(), "invalid style: zero image width");
+    }
+
+    #[test]
+    fn test_invalid_ids() {
+        let code = QrCode::new(b"Some data").unwrap();
+        let style = QrStyle::default();
+        for id in ["", "1st", "-a", "a b", "a\"b", "a<b", "é", "a:b"] {
+            assert_eq!(
+                render_sprite(&[(id.to_owned(), code.clone())], &style),
+                Err(SpriteError::InvalidId(id.to_owned()))
+            );
+        }
+        let codes = [(String::from("a"), code.clone()), (String::from("a"), code)];
+        assert_eq!(
+            render_sprite(&codes, &style),
+            Err(SpriteError::DuplicateId(String::from("a")))
+        );
+        assert_eq!(
+            render_sprite(&[], &style)
+                .unwrap()
+                .matches("<symbol")
+                .count(),
+            0
+        );
+    }
+}
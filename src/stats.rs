@@ -0,0 +1,173 @@
+//! The `stats` module measures the time spent in each stage of encoding and
+//! rendering, to help spotting performance regressions.
+//!
+//! This module is only available with the `stats` feature.
+use core::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+use crate::{
+    bits,
+    internals::{canvas, ec},
+    rasterize_svg, EcLevel, QrCode, QrResult, QrStyle, RenderError, RmqrStrategy,
+};
+
+/// Time spent in each stage of encoding a QR code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncodeStats {
+    /// Parsing and optimizing the segments, choosing the version and pushing
+    /// the bits.
+    pub segmentation: Duration,
+    /// Computing the error correction codewords.
+    pub error_correction: Duration,
+    /// Drawing the functional patterns and the codewords on the canvas.
+    pub placement: Duration,
+    /// Evaluating the mask patterns and applying the best one.
+    pub masking: Duration,
+}
+
+impl EncodeStats {
+    /// The total time spent encoding.
+    pub fn total(&self) -> Duration {
+        self.segmentation + self.error_correction + self.placement + self.masking
+    }
+}
+
+impl AddAssign for EncodeStats {
+    fn add_assign(&mut self, other: Self) {
+        self.segmentation += other.segmentation;
+        self.error_correction += other.error_correction;
+        self.placement += other.placement;
+        self.masking += other.masking;
+    }
+}
+
+/// Time spent in each stage of rendering a QR code into a pixmap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Tracing the module contours and generating the SVG document.
+    pub path: Duration,
+    /// Rasterizing the SVG document.
+    pub rasterize: Duration,
+}
+
+impl RenderStats {
+    /// The total time spent rendering.
+    pub fn total(&self) -> Duration {
+        self.path + self.rasterize
+    }
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, other: Self) {
+        self.path += other.path;
+        self.rasterize += other.rasterize;
+    }
+}
+
+/// Same as `QrCode::with_error_correction_level`, but also returns the time
+/// spent in each stage.
+///
+/// # Errors
+///
+/// Returns error if the QR code cannot be constructed, e.g. when the data
+/// is too long.
+pub fn encode(data: &[u8], ec_level: EcLevel) -> QrResult<(QrCode, EncodeStats)> {
+    let start = Instant::now();
+    let bits = bits::encode_auto(data, ec_level)?;
+    encode_bits(bits, ec_level, start.elapsed())
+}
+
+/// Same as `QrCode::rmqr_with_options`, but also returns the time spent in each
+/// stage.
+///
+/// # Errors
+///
+/// Returns error if the QR code cannot be constructed, e.g. when the data
+/// is too long.
+pub fn encode_rmqr(
+    data: &[u8],
+    ec_level: EcLevel,
+    strategy: RmqrStrategy,
+) -> QrResult<(QrCode, EncodeStats)> {
+    let start = Instant::now();
+    let bits = bits::encode_auto_rmqr(data, ec_level, strategy)?;
+    encode_bits(bits, ec_level, start.elapsed())
+}
+
+fn encode_bits(
+    bits: bits::Bits,
+    ec_level: EcLevel,
+    segmentation: Duration,
+) -> QrResult<(QrCode, EncodeStats)> {
+    let version = bits.version();
+    let bits_stats = bits.encode_stats(ec_level);
+    let data = bits.into_bytes();
+
+    let start = Instant::now();
+    let (encoded_data, ec_data) = ec::construct_codewords(&data, version, ec_level)?;
+    let error_correction = start.elapsed();
+
+    let start = Instant::now();
+    let mut canvas = canvas::Canvas::new(version, ec_level);
+    QrCode::draw_canvas(&mut canvas, version, ec_level, &encoded_data, &ec_data);
+    let placement = start.elapsed();
+
+    let start = Instant::now();
+    let canvas = canvas.apply_best_mask();
+    let masking = start.elapsed();
+
+    let ec_bits = ec_data.len() * 8;
+    let mut code = QrCode::from_masked_canvas(&canvas, version, ec_level, encoded_data, ec_data);
+    code.encode_stats = bits_stats.map(|stats| bits::BitStats { ec_bits, ..stats });
+    let stats = EncodeStats {
+        segmentation,
+        error_correction,
+        placement,
+        masking,
+    };
+    Ok((code, stats))
+}
+
+/// Same as `QrCode::to_pixmap`, but also returns the time spent in each stage.
+///
+/// # Errors
+///
+/// Returns error if the SVG document cannot be rasterized.
+pub fn render(
+    code: &QrCode,
+    style: &QrStyle,
+) -> Result<(resvg::tiny_skia::Pixmap, RenderStats), RenderError> {
+    let (_, _, width, height) = code.image_sizes(style);
+
+    let start = Instant::now();
+    let svg_string = code.to_svg(style);
+    let path = start.elapsed();
+
+    let start = Instant::now();
+    let pixmap = rasterize_svg(&svg_string, width, height)?;
+    let rasterize = start.elapsed();
+
+    Ok((pixmap, RenderStats { path, rasterize }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::{encode, encode_rmqr, render};
+    use crate::{EcLevel, QrCode, QrStyle, RmqrStrategy};
+
+    #[test]
+    fn test_same_result() {
+        let (code, _) = encode(b"https://example.com/", EcLevel::M).unwrap();
+        let expected = QrCode::new(b"https://example.com/").unwrap();
+        assert_eq!(code.to_colors(), expected.to_colors());
+
+        let (code, _) = encode_rmqr(b"SKU-0001", EcLevel::H, RmqrStrategy::Width).unwrap();
+        let expected =
+            QrCode::rmqr_with_options(b"SKU-0001", EcLevel::H, RmqrStrategy::Width).unwrap();
+        assert_eq!(code.to_colors(), expected.to_colors());
+
+        let style = QrStyle::default();
+        let (pixmap, _) = render(&code, &style).unwrap();
+        assert_eq!(pixmap, code.to_pixmap(&style).unwrap());
+    }
+}
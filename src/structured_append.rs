@@ -0,0 +1,479 @@
+//! The `structured_append` module helps to split a message across a sequence
+//! of up to 16 QR codes, and to cross-check such a sequence.
+//!
+//! Every symbol of a sequence carries its position, the total number of
+//! symbols and a parity byte computed over the whole message, see
+//! `QrCode::with_structured_append`.
+use core::fmt::{Display, Error, Formatter};
+
+use crate::bits::{optimal_encoded_len, Bits};
+use crate::internals::coding::Parser;
+use crate::types::{EcLevel, Mode, QrError, QrResult, Version};
+use crate::QrCode;
+
+/// The maximum number of symbols in a structured append sequence.
+pub const MAX_TOTAL: u8 = 16;
+
+/// The number of bits of the structured append header: the mode indicator,
+/// the symbol position, the total number of symbols and the parity.
+const HEADER_BITS_COUNT: usize = 4 + 4 + 4 + 8;
+
+/// Computes the parity byte of a message, i.e. the XOR of all its bytes.
+///
+///     use qrqrpar::structured_append::parity;
+///
+///     assert_eq!(parity(b"ABC"), 0x40);
+///
+/// The parity must be computed over the whole message, not over the part
+/// stored in a single symbol.
+pub fn parity(data: &[u8]) -> u8 {
+    data.iter().fold(0, |acc, b| acc ^ b)
+}
+
+/// The position of a symbol in a structured append sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PartInfo {
+    /// The zero-based position of this symbol in the sequence.
+    pub index: u8,
+    /// The total number of symbols in the sequence, from 1 to 16.
+    pub total: u8,
+    /// The parity of the whole message, see `parity`.
+    pub parity: u8,
+}
+
+impl PartInfo {
+    /// Checks whether the index and the total can be encoded.
+    pub(crate) fn is_valid(self) -> bool {
+        (1..=MAX_TOTAL).contains(&self.total) && self.index < self.total
+    }
+}
+
+/// An inconsistency found by `validate_set`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetError {
+    /// The set contains no codes.
+    Empty,
+    /// The code at the given position was not built as a part of a structured
+    /// append sequence.
+    MissingPartInfo { position: usize },
+    /// The code at the given position has the wrong index, i.e. the indices
+    /// are not contiguous starting from 0.
+    UnexpectedIndex { position: usize, index: u8 },
+    /// The code at the given position disagrees with the first code about the
+    /// total number of symbols.
+    InconsistentTotal { position: usize },
+    /// The code at the given position disagrees with the first code about the
+    /// parity.
+    InconsistentParity { position: usize },
+    /// The number of codes differs from the total number of symbols.
+    IncompleteSet { total: u8, count: usize },
+}
+
+impl Display for SetError {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), Error> {
+        match *self {
+            SetError::Empty => write!(fmt, "empty structured append set"),
+            SetError::MissingPartInfo { position } => {
+                write!(fmt, "code {position} is not a structured append part")
+            }
+            SetError::UnexpectedIndex { position, index } => {
+                write!(fmt, "code {position} has unexpected index {index}")
+            }
+            SetError::InconsistentTotal { position } => {
+                write!(fmt, "code {position} has an inconsistent total")
+            }
+            SetError::InconsistentParity { position } => {
+                write!(fmt, "code {position} has an inconsistent parity")
+            }
+            SetError::IncompleteSet { total, count } => {
+                write!(fmt, "expected {total} codes, found {count}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SetError {}
+
+/// Checks that the codes form a complete structured append sequence, in
+/// order, with consistent totals and parities.
+///
+/// # Errors
+///
+/// Returns the first inconsistency found.
+pub fn validate_set(codes: &[QrCode]) -> Result<(), SetError> {
+    let first = codes
+        .first()
+        .ok_or(SetError::Empty)?
+        .part_info()
+        .ok_or(SetError::MissingPartInfo { position: 0 })?;
+    for (position, code) in codes.iter().enumerate() {
+        let part = code
+            .part_info()
+            .ok_or(SetError::MissingPartInfo { position })?;
+        if usize::from(part.index) != position {
+            return Err(SetError::UnexpectedIndex {
+                position,
+                index: part.index,
+            });
+        }
+        if part.total != first.total {
+            return Err(SetError::InconsistentTotal { position });
+        }
+        if part.parity != first.parity {
+            return Err(SetError::InconsistentParity { position });
+        }
+    }
+    if codes.len() != usize::from(first.total) {
+        return Err(SetError::IncompleteSet {
+            total: first.total,
+            count: codes.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Checks whether the data fits a structured append symbol of the version,
+/// after the header.
+fn part_fits(data: &[u8], version: Version, ec_level: EcLevel) -> bool {
+    let data_capacity = Bits::new(version).max_len(ec_level);
+    optimal_encoded_len(data, version)
+        .zip(data_capacity.ok())
+        .is_some_and(|(len, capacity)| HEADER_BITS_COUNT + len <= capacity)
+}
+
+/// Returns whether each position of the data may end a part, i.e. is not
+/// within a character: a UTF-8 character if the data is UTF-8, and a Shift
+/// JIS Kanji character otherwise. A reader decoding each symbol alone then
+/// does not see broken characters.
+fn cut_positions(data: &[u8]) -> Vec<bool> {
+    if let Ok(text) = std::str::from_utf8(data) {
+        return (0..=data.len()).map(|i| text.is_char_boundary(i)).collect();
+    }
+    let mut allowed = vec![true; data.len() + 1];
+    for segment in Parser::new(data).filter(|segment| segment.mode == Mode::Kanji) {
+        for i in (segment.begin + 1..segment.end).step_by(2) {
+            allowed[i] = false;
+        }
+    }
+    allowed
+}
+
+/// Splits the data into the longest parts which fit a structured append
+/// symbol of the version, or returns `None` if more than `max_parts` parts
+/// are needed.
+fn split_for_version<'a>(
+    data: &'a [u8],
+    cut_allowed: &[bool],
+    version: Version,
+    ec_level: EcLevel,
+    max_parts: usize,
+) -> Option<Vec<&'a [u8]>> {
+    let mut parts = vec![];
+    let mut start = 0;
+    while start < data.len() {
+        if parts.len() == max_parts {
+            return None;
+        }
+        // The encoded length only grows with the data, so the longest fitting
+        // part is found by bisection.
+        let rest = &data[start..];
+        let (mut fitting, mut overflowing) = (0, rest.len() + 1);
+        while overflowing - fitting > 1 {
+            let len = (fitting + overflowing) / 2;
+            if part_fits(&rest[..len], version, ec_level) {
+                fitting = len;
+            } else {
+                overflowing = len;
+            }
+        }
+        let len = (1..=fitting).rev().find(|&len| cut_allowed[start + len])?;
+        parts.push(&rest[..len]);
+        start += len;
+    }
+    Some(parts)
+}
+
+/// Splits the data into at most `max_parts` parts of a structured append
+/// sequence, using the smallest QR code version every part fits.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::DataTooLong)` if more than `max_parts` parts, or
+/// more than 16, would be needed even in version 40.
+pub(crate) fn split(data: &[u8], ec_level: EcLevel, max_parts: usize) -> QrResult<Vec<&[u8]>> {
+    let max_parts = max_parts.min(usize::from(MAX_TOTAL));
+    let cut_allowed = cut_positions(data);
+    (1..=40)
+        .map(Version::Normal)
+        .filter(|&version| {
+            // Splitting never shortens the encoded data, so versions which
+            // cannot hold it in `max_parts` symbols are skipped early.
+            let capacity = Bits::new(version).max_len(ec_level).unwrap_or(0);
+            let capacity = capacity.saturating_sub(HEADER_BITS_COUNT);
+            optimal_encoded_len(data, version).is_some_and(|len| len <= capacity * max_parts)
+        })
+        .find_map(|version| split_for_version(data, &cut_allowed, version, ec_level, max_parts))
+        .ok_or(QrError::DataTooLong)
+}
+
+/// Encodes a part of a structured append sequence in the smallest QR code
+/// version.
+///
+/// # Errors
+///
+/// Returns `Err(QrError::InvalidStructuredAppend)` if the part info is
+/// invalid, or `Err(QrError::DataTooLong)` if the data is too long to fit even
+/// the highest QR code version.
+pub(crate) fn encode_part(data: &[u8], part: PartInfo, ec_level: EcLevel) -> QrResult<Bits> {
+    if !part.is_valid() {
+        return Err(QrError::InvalidStructuredAppend);
+    }
+    let version = (1..=40)
+        .map(Version::Normal)
+        .find(|&version| part_fits(data, version, ec_level))
+        .ok_or(QrError::DataTooLong)?;
+
+    let mut bits = Bits::new(version);
+    bits.push_structured_append_header(part)?;
+    bits.push_optimal_data(data)?;
+    bits.push_terminator(ec_level)?;
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod structured_append_tests {
+    use crate::structured_append::{parity, split, validate_set, PartInfo, SetError};
+    use crate::types::{EcLevel, QrError};
+    use crate::QrCode;
+
+    fn build_set(data: &[u8], total: u8) -> Vec<QrCode> {
+        let parity = parity(data);
+        data.chunks(data.len().div_ceil(usize::from(total)))
+            .enumerate()
+            .map(|(index, chunk)| {
+                let part = PartInfo {
+                    index: index as u8,
+                    total,
+                    parity,
+                };
+                QrCode::with_structured_append(chunk, part, EcLevel::M).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parity() {
+        assert_eq!(parity(b""), 0);
+        assert_eq!(parity(b"\x01\x02\x04"), 0x07);
+        assert_eq!(parity(b"\xff\xff"), 0);
+    }
+
+    #[test]
+    fn test_header() {
+        let part = PartInfo {
+            index: 1,
+            total: 3,
+            parity: 0xa5,
+        };
+        let code = QrCode::with_structured_append(b"123", part, EcLevel::M).unwrap();
+        assert_eq!(code.part_info(), Some(part));
+        // Mode 0011, index 0001, total 0010, parity 1010_0101, then the
+        // numeric mode indicator 0001.
+        assert_eq!(
+            code.final_codewords()[..3],
+            [0b0011_0001, 0b0010_1010, 0b0101_0001]
+        );
+    }
+
+    #[test]
+    fn test_valid_set() {
+        let data = b"Structured append splits one message across several symbols.";
+        let codes = build_set(data, 3);
+        assert_eq!(codes.len(), 3);
+        assert_eq!(validate_set(&codes), Ok(()));
+    }
+
+    #[test]
+    fn test_beyond_version_40() {
+        // 3000 bytes exceed the 2953 bytes of version 40-L.
+        let data = (0..3000).map(|i| (i * 7 % 256) as u8).collect::<Vec<u8>>();
+        assert_eq!(
+            QrCode::with_error_correction_level(&data, EcLevel::L).err(),
+            Some(QrError::DataTooLong)
+        );
+
+        let parity = parity(&data);
+        let codes = data
+            .chunks(1500)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let part = PartInfo {
+                    index: index as u8,
+                    total: 2,
+                    parity,
+                };
+                QrCode::with_structured_append(chunk, part, EcLevel::L).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(codes.len(), 2);
+        for (index, code) in codes.iter().enumerate() {
+            assert_eq!(
+                code.part_info(),
+                Some(PartInfo {
+                    index: index as u8,
+                    total: 2,
+                    parity
+                })
+            );
+        }
+        assert_eq!(
+            parity,
+            super::parity(&data[..1500]) ^ super::parity(&data[1500..])
+        );
+        assert_eq!(validate_set(&codes), Ok(()));
+    }
+
+    #[test]
+    fn test_new_multi() {
+        let data = "Structured append, 構造的連接. ".repeat(120).into_bytes();
+        assert!(QrCode::with_error_correction_level(&data, EcLevel::M).is_err());
+        let codes = QrCode::new_multi(&data, EcLevel::M, 16).unwrap();
+        let parts = split(&data, EcLevel::M, 16).unwrap();
+        assert_eq!(codes.len(), parts.len());
+        assert!(codes.len() > 1);
+        assert_eq!(parts.concat(), data);
+        assert_eq!(validate_set(&codes), Ok(()));
+        let version = codes[0].version();
+        for (index, (code, part)) in codes.iter().zip(&parts).enumerate() {
+            let part_info = PartInfo {
+                index: index as u8,
+                total: codes.len() as u8,
+                parity: parity(&data),
+            };
+            assert_eq!(code.part_info(), Some(part_info));
+            let expected = QrCode::with_structured_append(part, part_info, EcLevel::M).unwrap();
+            assert_eq!(code.final_codewords(), expected.final_codewords());
+            // Every part is cut between two UTF-8 characters.
+            assert!(std::str::from_utf8(part).is_ok());
+            assert!(code.version() <= version);
+        }
+        // Fewer symbols need a larger version.
+        let codes = QrCode::new_multi(&data, EcLevel::M, 3).unwrap();
+        assert_eq!(codes.len(), 3);
+        assert!(codes[0].version() > version);
+        assert_eq!(validate_set(&codes), Ok(()));
+    }
+
+    #[test]
+    fn test_new_multi_limits() {
+        let code = QrCode::new_multi(b"short", EcLevel::M, 4).unwrap();
+        assert_eq!(code.len(), 1);
+        assert_eq!(code[0].part_info(), None);
+        assert_eq!(
+            code[0].final_codewords(),
+            QrCode::new(b"short").unwrap().final_codewords()
+        );
+
+        // Version 40-H holds 1273 bytes, so 17 symbols are needed.
+        let data = vec![b'x'; 1273 * 16 + 1];
+        assert_eq!(
+            QrCode::new_multi(&data, EcLevel::H, 20).err(),
+            Some(QrError::DataTooLong)
+        );
+        let data = vec![b'x'; 3000];
+        assert_eq!(
+            QrCode::new_multi(&data, EcLevel::H, 2).err(),
+            Some(QrError::DataTooLong)
+        );
+        assert_eq!(QrCode::new_multi(&data, EcLevel::H, 3).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_split_kanji() {
+        // Shift JIS "点" repeated, which is not UTF-8.
+        let data = b"\x93\x5f".repeat(1000);
+        let parts = split(&data, EcLevel::L, 4).unwrap();
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|part| part.len() % 2 == 0));
+        assert_eq!(parts.concat(), data);
+    }
+
+    #[test]
+    fn test_invalid_sets() {
+        let data = b"Structured append splits one message across several symbols.";
+        let codes = build_set(data, 3);
+
+        assert_eq!(validate_set(&[]), Err(SetError::Empty));
+        assert_eq!(
+            validate_set(&codes[..2]),
+            Err(SetError::IncompleteSet { total: 3, count: 2 })
+        );
+        assert_eq!(
+            validate_set(&[codes[0].clone(), codes[2].clone(), codes[1].clone()]),
+            Err(SetError::UnexpectedIndex {
+                position: 1,
+                index: 2
+            })
+        );
+
+        let mut corrupted = codes.clone();
+        corrupted[1] = QrCode::new(b"plain").unwrap();
+        assert_eq!(
+            validate_set(&corrupted),
+            Err(SetError::MissingPartInfo { position: 1 })
+        );
+
+        let mut corrupted = codes.clone();
+        let part = PartInfo {
+            index: 2,
+            total: 3,
+            parity: codes[2].part_info().unwrap().parity ^ 1,
+        };
+        corrupted[2] = QrCode::with_structured_append(b"symbols.", part, EcLevel::M).unwrap();
+        assert_eq!(
+            validate_set(&corrupted),
+            Err(SetError::InconsistentParity { position: 2 })
+        );
+
+        let mut corrupted = codes;
+        let part = PartInfo {
+            total: 4,
+            ..corrupted[1].part_info().unwrap()
+        };
+        corrupted[1] = QrCode::with_structured_append(b"one", part, EcLevel::M).unwrap();
+        assert_eq!(
+            validate_set(&corrupted),
+            Err(SetError::InconsistentTotal { position: 1 })
+        );
+    }
+
+    #[test]
+    fn test_invalid_part_info() {
+        for (index, total) in [(0, 0), (3, 3), (0, 17)] {
+            let part = PartInfo {
+                index,
+                total,
+                parity: 0,
+            };
+            assert_eq!(
+                QrCode::with_structured_append(b"123", part, EcLevel::M).err(),
+                Some(QrError::InvalidStructuredAppend)
+            );
+            // The part info is checked before the length of the data.
+            assert_eq!(
+                QrCode::with_structured_append(vec![b'x'; 3000], part, EcLevel::M).err(),
+                Some(QrError::InvalidStructuredAppend)
+            );
+        }
+
+        let part = PartInfo {
+            index: 0,
+            total: 2,
+            parity: 0,
+        };
+        assert_eq!(
+            QrCode::with_structured_append(vec![b'x'; 3000], part, EcLevel::M).err(),
+            Some(QrError::DataTooLong)
+        );
+    }
+}
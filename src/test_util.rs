@@ -0,0 +1,338 @@
+//! The `test_util` module generates corpora of payloads to check properties
+//! of the encoder over many cases, e.g. that it never panics.
+//!
+//! The payloads are pseudo-random but deterministic: the same seed and spec
+//! always generate the same payloads, so a failing case can be replayed.
+//!
+//!     use qrqrpar::test_util::{payloads, Content, Lengths, PayloadSpec};
+//!     use qrqrpar::QrCode;
+//!
+//!     let spec = PayloadSpec {
+//!         contents: vec![Content::Numeric, Content::Utf8],
+//!         max_run: 8,
+//!         lengths: Lengths::Between(0, 40),
+//!     };
+//!     for payload in payloads(42, spec).take(100) {
+//!         QrCode::new(&payload).unwrap();
+//!     }
+//!
+//! This module requires the `test-util` feature.
+
+use crate::bits::{fits, Bits};
+use crate::types::{EcLevel, Version, ALPHANUMERIC_CHARSET};
+
+/// The kind of characters of a run of a payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Content {
+    /// Digits.
+    Numeric,
+    /// Characters of the alphanumeric mode.
+    Alphanumeric,
+    /// Shift JIS double-byte characters of the Kanji mode.
+    Kanji,
+    /// UTF-8 encoded Latin, kana, CJK and emoji characters.
+    Utf8,
+    /// Any byte.
+    Binary,
+}
+
+/// How long the payloads are, counted in characters: a Kanji character is
+/// two bytes, and a UTF-8 character one to four bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lengths {
+    /// Lengths drawn uniformly between the bounds, inclusive.
+    Between(usize, usize),
+    /// For each version in turn, the longest payload fitting the version at
+    /// the level when optimally segmented, followed by the same payload with
+    /// one more character, which does not fit.
+    Boundaries {
+        versions: Vec<Version>,
+        ec_level: EcLevel,
+    },
+}
+
+/// What `payloads` generates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadSpec {
+    /// The contents mixed in the payloads. Each payload is a sequence of runs
+    /// of a content drawn from this list.
+    pub contents: Vec<Content>,
+    /// The largest number of characters of a run, at least 1.
+    pub max_run: usize,
+    /// The lengths of the payloads.
+    pub lengths: Lengths,
+}
+
+/// The SplitMix64 generator, which is small and good enough to draw
+/// payloads. The crate's own tests also draw plain numbers from it.
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Draws a number below `n`, which must not be 0.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Draws a number between the bounds, inclusive.
+    pub(crate) fn between(&mut self, low: usize, high: usize) -> usize {
+        low + self.below(high.saturating_sub(low) + 1)
+    }
+}
+
+/// The code points the UTF-8 content is drawn from.
+const UTF8_RANGES: [(u32, u32); 4] = [
+    (0xc0, 0xff),
+    (0x3041, 0x3096),
+    (0x4e00, 0x9fff),
+    (0x1f600, 0x1f64f),
+];
+
+/// Draws a character of the content, appended to `payload`.
+fn push_char(rng: &mut Rng, content: Content, payload: &mut Vec<u8>) {
+    match content {
+        Content::Numeric => payload.push(b'0' + rng.below(10) as u8),
+        Content::Alphanumeric => {
+            payload.push(ALPHANUMERIC_CHARSET[rng.below(ALPHANUMERIC_CHARSET.len())]);
+        }
+        Content::Kanji => {
+            // The Kanji mode covers 0x8140–0x9ffc and 0xe040–0xebbf.
+            let first = match rng.below(2) {
+                0 => rng.between(0x81, 0x9f),
+                _ => rng.between(0xe0, 0xea),
+            } as u8;
+            let second = match rng.between(0x40, 0xfb) as u8 {
+                0x7f => 0xfc,
+                second => second,
+            };
+            payload.extend([first, second]);
+        }
+        Content::Utf8 => {
+            let (low, high) = UTF8_RANGES[rng.below(UTF8_RANGES.len())];
+            let c = char::from_u32(rng.between(low as usize, high as usize) as u32)
+                .expect("invalid UTF-8 range");
+            payload.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+        Content::Binary => payload.push(rng.next_u64() as u8),
+    }
+}
+
+/// Draws `chars_count` characters in runs of the contents of the spec, and
+/// returns the payload with the end offset of every character.
+fn draw(rng: &mut Rng, spec: &PayloadSpec, chars_count: usize) -> (Vec<u8>, Vec<usize>) {
+    let (mut payload, mut ends) = (Vec::new(), Vec::with_capacity(chars_count));
+    if spec.contents.is_empty() {
+        return (payload, ends);
+    }
+    while ends.len() < chars_count {
+        let content = spec.contents[rng.below(spec.contents.len())];
+        let run = rng
+            .between(1, spec.max_run.max(1))
+            .min(chars_count - ends.len());
+        for _ in 0..run {
+            push_char(rng, content, &mut payload);
+            ends.push(payload.len());
+        }
+    }
+    (payload, ends)
+}
+
+/// Draws the payloads at the capacity boundary of the version: the longest
+/// one fitting it, and one character more.
+fn boundary(
+    rng: &mut Rng,
+    spec: &PayloadSpec,
+    version: Version,
+    ec_level: EcLevel,
+) -> [Vec<u8>; 2] {
+    let capacity = Bits::new(version).max_len(ec_level).unwrap_or(0);
+    // Every character needs more than 3 bits, even in numeric mode.
+    let (payload, ends) = draw(rng, spec, capacity / 3 + 1);
+    let prefix = |chars_count: usize| match chars_count {
+        0 => &payload[..0],
+        n => &payload[..ends[n - 1]],
+    };
+    let fitting = (1..=ends.len())
+        .collect::<Vec<_>>()
+        .partition_point(|&n| fits(prefix(n), version, ec_level).unwrap_or(false));
+    [
+        prefix(fitting).to_vec(),
+        prefix((fitting + 1).min(ends.len())).to_vec(),
+    ]
+}
+
+/// Generates an endless sequence of payloads following the spec, the same
+/// for the same seed.
+///
+///     use qrqrpar::test_util::{payloads, Content, Lengths, PayloadSpec};
+///     use qrqrpar::{EcLevel, QrCode, Version};
+///
+///     let spec = PayloadSpec {
+///         contents: vec![Content::Alphanumeric, Content::Kanji],
+///         max_run: 5,
+///         lengths: Lengths::Boundaries { versions: vec![Version::Normal(3)], ec_level: EcLevel::Q },
+///     };
+///     let mut corpus = payloads(7, spec);
+///     let (fitting, overflowing) = (corpus.next().unwrap(), corpus.next().unwrap());
+///     assert!(QrCode::with_version(&fitting, Version::Normal(3), EcLevel::Q).is_ok());
+///     assert!(QrCode::with_version(&overflowing, Version::Normal(3), EcLevel::Q).is_err());
+///
+/// With `Lengths::Boundaries`, the payloads come in pairs, the fitting one
+/// first, for each version in turn, then again from the first version with
+/// new characters. A version which cannot encode the first character gets an
+/// empty fitting payload. If the spec has no content, every payload is empty,
+/// and if it has no version, there is no payload.
+pub fn payloads(seed: u64, spec: PayloadSpec) -> impl Iterator<Item = Vec<u8>> {
+    let mut rng = Rng(seed);
+    let mut pending = Vec::new();
+    let mut boundaries = 0;
+    std::iter::from_fn(move || match &spec.lengths {
+        Lengths::Between(low, high) => {
+            let chars_count = rng.between(*low, *high);
+            Some(draw(&mut rng, &spec, chars_count).0)
+        }
+        Lengths::Boundaries { versions, ec_level } => {
+            if pending.is_empty() {
+                let version = *versions.get(boundaries % versions.len().max(1))?;
+                boundaries += 1;
+                let [fitting, overflowing] = boundary(&mut rng, &spec, version, *ec_level);
+                pending = vec![overflowing, fitting];
+            }
+            pending.pop()
+        }
+    })
+}
+
+#[cfg(test)]
+mod test_util_tests {
+    use crate::bits::{encode_auto, inspect, InspectedSegment};
+    use crate::test_util::{payloads, Content, Lengths, PayloadSpec};
+    use crate::types::{EcLevel, QrError, Version};
+    use crate::{QrCode, RmqrStrategy};
+
+    const ALL: [Content; 5] = [
+        Content::Numeric,
+        Content::Alphanumeric,
+        Content::Kanji,
+        Content::Utf8,
+        Content::Binary,
+    ];
+
+    fn mixed(lengths: Lengths) -> PayloadSpec {
+        PayloadSpec {
+            contents: ALL.to_vec(),
+            max_run: 12,
+            lengths,
+        }
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let spec = mixed(Lengths::Between(0, 50));
+        let a = payloads(1, spec.clone()).take(50).collect::<Vec<_>>();
+        let b = payloads(1, spec.clone()).take(50).collect::<Vec<_>>();
+        let c = payloads(2, spec).take(50).collect::<Vec<_>>();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        let utf8 = PayloadSpec {
+            contents: vec![Content::Utf8],
+            max_run: 3,
+            lengths: Lengths::Between(5, 5),
+        };
+        for payload in payloads(3, utf8).take(20) {
+            assert_eq!(String::from_utf8(payload).unwrap().chars().count(), 5);
+        }
+        let empty = PayloadSpec {
+            contents: vec![],
+            ..mixed(Lengths::Between(1, 5))
+        };
+        assert_eq!(payloads(4, empty).next(), Some(vec![]));
+    }
+
+    #[test]
+    fn test_no_panics() {
+        let levels = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+        let corpus = payloads(0x5eed, mixed(Lengths::Between(0, 200))).take(200);
+        for (i, payload) in corpus.enumerate() {
+            let ec_level = levels[i % levels.len()];
+            let _ = QrCode::with_error_correction_level(&payload, ec_level);
+            let _ = QrCode::micro_with_error_correction_level(&payload, ec_level);
+            let _ = QrCode::rmqr_with_options(&payload, ec_level, RmqrStrategy::Area);
+        }
+    }
+
+    #[test]
+    fn test_version_monotonic() {
+        let version = |data: &[u8]| encode_auto(data, EcLevel::M).map(|bits| bits.version());
+        for payload in payloads(0xb0b, mixed(Lengths::Between(0, 300))).take(100) {
+            let full = version(&payload).unwrap();
+            // A cut after an ASCII byte cannot split a Kanji pair.
+            let cuts = (0..payload.len()).filter(|&i| i == 0 || payload[i - 1].is_ascii());
+            for cut in cuts.step_by(7) {
+                assert!(version(&payload[..cut]).unwrap() <= full, "{payload:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for payload in payloads(0xcafe, mixed(Lengths::Between(0, 200))).take(300) {
+            let bits = encode_auto(&payload, EcLevel::L).unwrap();
+            let version = bits.version();
+            let decoded = inspect(&bits.into_bytes(), version)
+                .unwrap()
+                .into_iter()
+                .flat_map(|segment| match segment {
+                    InspectedSegment::Data { data, .. } => data,
+                    _ => vec![],
+                })
+                .collect::<Vec<u8>>();
+            assert_eq!(decoded, payload);
+        }
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let versions = vec![
+            Version::Micro(2),
+            Version::Micro(4),
+            Version::Normal(1),
+            Version::Normal(10),
+            Version::Normal(27),
+            Version::Rmqr(7, 43),
+            Version::Rmqr(17, 139),
+        ];
+        let spec = mixed(Lengths::Boundaries {
+            versions: versions.clone(),
+            ec_level: EcLevel::M,
+        });
+        let corpus = payloads(0xf00d, spec).take(versions.len() * 2 * 3);
+        let corpus = corpus.collect::<Vec<_>>();
+        for (i, pair) in corpus.chunks(2).enumerate() {
+            let version = versions[i % versions.len()];
+            let [fitting, overflowing] = pair else {
+                unreachable!()
+            };
+            assert!(overflowing.len() > fitting.len());
+            assert!(QrCode::with_version(fitting, version, EcLevel::M).is_ok());
+            assert!(matches!(
+                QrCode::with_version(overflowing, version, EcLevel::M).err(),
+                Some(QrError::DataTooLong | QrError::ModeUnavailable { .. })
+            ));
+            if let Version::Normal(_) = version {
+                let chosen = encode_auto(fitting, EcLevel::M).unwrap().version();
+                assert!(chosen <= version);
+                let chosen = encode_auto(overflowing, EcLevel::M).unwrap().version();
+                assert!(chosen > version);
+            }
+        }
+    }
+}
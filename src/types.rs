@@ -2,34 +2,153 @@ use core::cmp::{Ordering, PartialOrd};
 use core::fmt::{Display, Error, Formatter};
 use core::ops::Not;
 
+/// An error which prevents encoding. New variants may be added in minor
+/// releases, so matches need a wildcard arm.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[non_exhaustive]
 pub enum QrError {
     /// The data is too long to encode into a QR code for the given version.
     DataTooLong,
 
-    /// The provided version / error correction level combination is invalid.
+    /// The text is too long to encode, returned instead of `DataTooLong` by
+    /// the constructors taking a `&str`. Both lengths are reported, since they
+    /// differ for multibyte text, see `estimate_chars_that_fit`.
+    TextTooLong {
+        /// The number of characters of the text.
+        chars: usize,
+        /// The number of bytes of the encoded text.
+        bytes: usize,
+    },
+
+    /// The provided version / error correction level combination is invalid,
+    /// e.g. the version does not exist. A valid version which does not
+    /// support the level gives `EcLevelUnavailable` instead when encoding.
     InvalidVersion,
 
-    /// Some characters in the data cannot be supported by the provided QR code
-    /// version.
+    /// The provided version does not support the error correction level, e.g.
+    /// M1 at any level but L, or a rMQR code at L or Q. The details tell the
+    /// levels the version supports, see `Version::check_ec_level`.
+    EcLevelUnavailable(EcLevelUnavailable),
+
+    /// Some characters in the data, or the character set selected by an ECI
+    /// designator, cannot be supported by the provided QR code version, e.g.
+    /// any ECI designator in a Micro QR code.
     UnsupportedCharacterSet,
 
+    /// The provided version does not support the header, e.g. a FNC1
+    /// indicator in a Micro QR code, or a structured append header in a Micro
+    /// QR or rMQR code.
+    UnsupportedHeader,
+
+    /// The mode needed by some characters of the data is not supported by the
+    /// provided Micro QR code version. See `Version::supported_modes()` for
+    /// the modes supported by each version.
+    ModeUnavailable {
+        /// The version asked for.
+        version: Version,
+        /// The mode asked for.
+        mode: Mode,
+        /// The smallest Micro QR code version supporting `mode`, see
+        /// `Mode::smallest_micro_version()`.
+        smallest: Version,
+    },
+
+    /// The name of a rMQR code version is well-formed, but no version has the
+    /// size, see `Version::rmqr_named`. The message lists the valid widths for
+    /// the height, or the valid heights if the height is invalid.
+    InvalidRmqrSize {
+        /// The height asked for.
+        height: u8,
+        /// The width asked for.
+        width: u8,
+    },
+
     /// The provided ECI designator is invalid. A valid designator should be
     /// between 0 and 999999.
     InvalidEciDesignator,
 
     /// A character not belonging to the character set is found.
     InvalidCharacter,
+
+    /// The encoded data does not fill the data capacity of the version, e.g.
+    /// because the bits were not terminated with `Bits::push_terminator` at
+    /// the same error correction level.
+    DataTooShort,
+
+    /// The encoding was cancelled by the cancellation flag or the deadline of
+    /// an `Encoder`.
+    Cancelled,
+
+    /// More smaller versions than allowed would hold the data, see
+    /// `SizePolicy::ErrorIfOversized`.
+    VersionOversized {
+        /// The version asked for.
+        requested: Version,
+        /// The smallest version of the same type holding the data.
+        minimal: Version,
+    },
+
+    /// The structured append part info cannot be encoded, i.e. the total is
+    /// not between 1 and 16 or the index is not less than the total.
+    InvalidStructuredAppend,
+
+    /// A segment lies outside the data, or the segments do not cover the data
+    /// in order, each one starting where the previous one ends.
+    InvalidSegment,
+
+    /// More bits are asked for than the given bytes hold, see
+    /// `Bits::push_bits`.
+    InvalidBitLength,
 }
 
 impl Display for QrError {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
         let msg = match *self {
+            QrError::ModeUnavailable {
+                version,
+                mode,
+                smallest,
+            } => {
+                return write!(
+                    fmt,
+                    "{version} does not support the {mode:?} mode; smallest version supporting it is {smallest}"
+                );
+            }
+            QrError::InvalidRmqrSize { height, width } => {
+                write!(fmt, "R{height}x{width} is not a rMQR version; ")?;
+                let widths = Version::rmqr_widths(height);
+                let sizes = if widths.is_empty() {
+                    fmt.write_str("the valid heights are ")?;
+                    Version::rmqr_all_height().to_vec()
+                } else {
+                    write!(fmt, "the valid widths for height {height} are ")?;
+                    widths
+                };
+                let (last, sizes) = sizes.split_last().ok_or(Error)?;
+                let sizes = sizes.iter().map(u8::to_string).collect::<Vec<_>>();
+                return write!(fmt, "{} and {last}", sizes.join(", "));
+            }
+            QrError::EcLevelUnavailable(error) => return write!(fmt, "{error}"),
+            QrError::VersionOversized { requested, minimal } => {
+                return write!(
+                    fmt,
+                    "version {requested} larger than needed; {minimal} holds the data"
+                );
+            }
+            QrError::TextTooLong { chars, bytes } => {
+                return write!(fmt, "text too long: {chars} characters in {bytes} bytes");
+            }
             QrError::DataTooLong => "data too long",
             QrError::InvalidVersion => "invalid version",
             QrError::UnsupportedCharacterSet => "unsupported character set",
+            QrError::UnsupportedHeader => "header not supported by the version",
             QrError::InvalidEciDesignator => "invalid ECI designator",
             QrError::InvalidCharacter => "invalid character",
+            QrError::DataTooShort => "data too short",
+            QrError::Cancelled => "encoding cancelled",
+            QrError::InvalidStructuredAppend => "invalid structured append part",
+            QrError::InvalidSegment => "invalid segment range",
+            QrError::InvalidBitLength => "bit length larger than the given bits",
         };
         fmt.write_str(msg)
     }
@@ -37,6 +156,20 @@ impl Display for QrError {
 
 impl ::std::error::Error for QrError {}
 
+impl QrError {
+    /// Turns `DataTooLong` into `TextTooLong` with the lengths of the text,
+    /// and keeps the other errors.
+    pub(crate) fn for_text(self, text: &str) -> Self {
+        match self {
+            QrError::DataTooLong => QrError::TextTooLong {
+                chars: text.chars().count(),
+                bytes: text.len(),
+            },
+            e => e,
+        }
+    }
+}
+
 /// `QrResult` is a convenient alias for a QR code generation result.
 pub type QrResult<T> = Result<T, QrError>;
 
@@ -78,7 +211,10 @@ impl Not for Color {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The error correction levels are ordered by robustness, i.e.
+/// `L < M < Q < H`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EcLevel {
     /// Low error correction. Allows up to 7% of wrong blocks.
     L = 0,
@@ -93,6 +229,42 @@ pub enum EcLevel {
     H = 3,
 }
 
+/// The versions are totally ordered, first by family in the order `Normal`,
+/// `Micro`, `Rmqr`, then by size within a family:
+///
+/// - QR and Micro QR code versions are ordered by number, which is also the
+///   order of their area,
+/// - rMQR code versions are ordered by area, then by height for the same area.
+///
+/// For example:
+///
+///     use qrqrpar::Version;
+///
+///     let mut versions = vec![
+///         Version::Rmqr(9, 43),
+///         Version::Micro(2),
+///         Version::Rmqr(7, 59),
+///         Version::Normal(3),
+///         Version::Normal(1),
+///     ];
+///     versions.sort();
+///     assert_eq!(
+///         versions,
+///         [
+///             Version::Normal(1),
+///             Version::Normal(3),
+///             Version::Micro(2),
+///             Version::Rmqr(9, 43),
+///             Version::Rmqr(7, 59),
+///         ]
+///     );
+///
+/// Sorting QR or Micro QR code versions gives a capacity progression, as a
+/// larger version holds more data at the same error correction level. This
+/// only holds roughly for rMQR codes, whose capacity also depends on their
+/// alignment patterns: R11x27 holds more data than the larger R7x43. Neither
+/// are the families ordered by capacity, use `bits::capacity_bits` to compare
+/// the capacities of arbitrary versions.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Version {
     /// A normal QR code version. The parameter should be between 1 and 40.
@@ -125,8 +297,32 @@ impl Version {
     }
 
     /// Get the area
+    ///
+    /// The area saturates at `i16::MAX` for invalid versions too large to be
+    /// represented.
     pub fn area(self) -> i16 {
-        self.width() * self.height()
+        self.width().saturating_mul(self.height())
+    }
+
+    /// Checks whether the version exists, i.e. is one of the QR code versions
+    /// 1 to 40, the Micro QR code versions 1 to 4, or one of the 32 rMQR code
+    /// versions.
+    ///
+    ///     use qrqrpar::Version;
+    ///
+    ///     assert!(Version::Micro(4).is_valid());
+    ///     assert!(!Version::Micro(0).is_valid());
+    ///     assert!(!Version::Rmqr(7, 27).is_valid());
+    ///
+    /// The encoding functions return `Err(QrError::InvalidVersion)` for
+    /// invalid versions, while the functions returning plain numbers give
+    /// meaningless values for them.
+    pub const fn is_valid(self) -> bool {
+        match self {
+            Version::Normal(v) => 1 <= v && v <= 40,
+            Version::Micro(v) => 1 <= v && v <= 4,
+            Version::Rmqr(_, _) => self.rmqr_index().is_ok(),
+        }
     }
 
     /// Obtains an object from a hard-coded table.
@@ -169,14 +365,146 @@ impl Version {
     }
 
     /// The number of bits needed to encode the mode indicator.
-    pub fn mode_bits_count(self) -> usize {
+    pub const fn mode_bits_count(self) -> usize {
         match self {
             Version::Normal(_) => 4,
-            Version::Micro(a) => (a - 1).into(),
+            Version::Micro(a) => a.saturating_sub(1) as usize,
             Version::Rmqr(_, _) => 3,
         }
     }
 
+    /// The encoding modes supported by this version.
+    ///
+    ///     use qrqrpar::types::{Mode, Version};
+    ///
+    ///     assert_eq!(Version::Micro(2).supported_modes(), &[Mode::Numeric, Mode::Alphanumeric]);
+    ///
+    /// Micro QR code version 1 only supports numeric data, and version 2 adds
+    /// alphanumeric data. All other versions support every mode.
+    pub fn supported_modes(self) -> &'static [Mode] {
+        match self {
+            Version::Micro(1) => &[Mode::Numeric],
+            Version::Micro(2) => &[Mode::Numeric, Mode::Alphanumeric],
+            _ => &[Mode::Numeric, Mode::Alphanumeric, Mode::Byte, Mode::Kanji],
+        }
+    }
+
+    /// The error correction levels this version can be encoded at, from the
+    /// weakest to the strongest.
+    ///
+    ///     use qrqrpar::{EcLevel, Version};
+    ///
+    ///     assert_eq!(Version::Micro(1).available_ec_levels(), &[EcLevel::L]);
+    ///     assert_eq!(Version::Rmqr(7, 43).available_ec_levels(), &[EcLevel::M, EcLevel::H]);
+    ///
+    /// QR codes support every level. Micro QR code version 1 only has error
+    /// detection, which is modelled as `EcLevel::L`, versions 2 and 3 add M,
+    /// and version 4 adds Q. rMQR codes only support M and H. Invalid versions
+    /// support no level.
+    pub fn available_ec_levels(self) -> &'static [EcLevel] {
+        if !self.is_valid() {
+            return &[];
+        }
+        match self {
+            Version::Normal(_) => &[EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H],
+            Version::Micro(1) => &[EcLevel::L],
+            Version::Micro(2 | 3) => &[EcLevel::L, EcLevel::M],
+            Version::Micro(_) => &[EcLevel::L, EcLevel::M, EcLevel::Q],
+            Version::Rmqr(_, _) => &[EcLevel::M, EcLevel::H],
+        }
+    }
+
+    /// The number of characters of the mode this version holds at the error
+    /// correction level, in a single segment.
+    ///
+    ///     use qrqrpar::types::{Mode, Version};
+    ///     use qrqrpar::EcLevel;
+    ///
+    ///     assert_eq!(Version::Micro(3).char_capacity(EcLevel::L, Mode::Numeric), Ok(23));
+    ///     assert_eq!(Version::Normal(1).char_capacity(EcLevel::M, Mode::Kanji), Ok(8));
+    ///
+    /// This is the data capacity less the mode indicator and the length
+    /// field, divided by the cost of a character: 10 bits per 3 digits, with
+    /// 4 or 7 bits for the 1 or 2 remaining ones, 11 bits per 2 alphanumeric
+    /// characters, with 6 bits for a remaining one, 8 bits per byte and 13
+    /// bits per Kanji character. It is capped by the largest count the length
+    /// field holds.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidVersion)` if the version does not support
+    /// the error correction level or the mode.
+    pub fn char_capacity(self, ec_level: EcLevel, mode: Mode) -> QrResult<usize> {
+        let capacity = self.fetch(ec_level, &crate::bits::DATA_LENGTHS)?;
+        if !self.supported_modes().contains(&mode) {
+            return Err(QrError::InvalidVersion);
+        }
+        let length_bits = mode.length_bits_count(self);
+        let Some(data_bits) = capacity.checked_sub(self.mode_bits_count() + length_bits) else {
+            return Ok(0);
+        };
+        let chars_count = match mode {
+            Mode::Numeric => data_bits / 10 * 3 + [0, 0, 0, 0, 1, 1, 1, 2, 2, 2][data_bits % 10],
+            Mode::Alphanumeric => data_bits / 11 * 2 + usize::from(data_bits % 11 >= 6),
+            Mode::Byte => data_bits / 8,
+            Mode::Kanji => data_bits / 13,
+        };
+        Ok(chars_count.min((1 << length_bits) - 1))
+    }
+
+    /// Checks that this version can be encoded at the error correction level,
+    /// explaining otherwise which version of the same family supports it.
+    ///
+    ///     use qrqrpar::{EcLevel, Version};
+    ///
+    ///     let error = Version::Micro(1).check_ec_level(EcLevel::M).unwrap_err();
+    ///     assert_eq!(
+    ///         error.to_string(),
+    ///         "M1 supports error detection only; smallest version supporting M is M2"
+    ///     );
+    ///
+    /// The encoding functions return `Err(QrError::InvalidVersion)` in this
+    /// case, so this can be called to report a more helpful message.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(EcLevelUnavailable)` if `ec_level` is not one of
+    /// `available_ec_levels`.
+    pub fn check_ec_level(self, ec_level: EcLevel) -> Result<(), EcLevelUnavailable> {
+        if self.available_ec_levels().contains(&ec_level) {
+            return Ok(());
+        }
+        let family = match self {
+            Version::Normal(_) => (1..=40).map(Version::Normal).collect(),
+            Version::Micro(_) => (1..=4).map(Version::Micro).collect(),
+            Version::Rmqr(_, _) => Version::rmqr_all().to_vec(),
+        };
+        let smallest = family
+            .into_iter()
+            .filter(|version: &Version| version.available_ec_levels().contains(&ec_level))
+            .min_by_key(|version| version.area());
+        Err(EcLevelUnavailable {
+            version: self,
+            ec_level,
+            smallest,
+        })
+    }
+
+    /// The width of the quiet zone required by the specification, in modules.
+    ///
+    ///     use qrqrpar::types::Version;
+    ///
+    ///     assert_eq!(Version::Normal(1).recommended_quiet_zone(), 4);
+    ///     assert_eq!(Version::Rmqr(7, 43).recommended_quiet_zone(), 2);
+    ///
+    /// QR codes need 4 modules, while Micro QR and rMQR codes only need 2.
+    pub fn recommended_quiet_zone(self) -> u8 {
+        match self {
+            Version::Normal(_) => 4,
+            Version::Micro(_) | Version::Rmqr(_, _) => 2,
+        }
+    }
+
     /// Checks whether is version refers to a Micro QR code.
     pub fn is_micro(self) -> bool {
         matches!(self, Version::Micro(_))
@@ -188,7 +516,7 @@ impl Version {
     }
 
     /// Get the index of the version of the rMQR code.
-    pub fn rmqr_index(self) -> QrResult<usize> {
+    pub const fn rmqr_index(self) -> QrResult<usize> {
         match self {
             Version::Rmqr(7, 43) => Ok(0),
             Version::Rmqr(7, 59) => Ok(1),
@@ -285,6 +613,166 @@ impl Version {
             Version::Rmqr(17, 139),
         ]
     }
+
+    /// Parses the standard name of a rMQR code version, `R{height}x{width}`.
+    /// The name is case-insensitive, and the leading `R` may be omitted.
+    ///
+    ///     use qrqrpar::Version;
+    ///
+    ///     assert_eq!(Version::rmqr_named("R13x77"), Ok(Version::Rmqr(13, 77)));
+    ///     assert_eq!(Version::rmqr_named("r13X77"), Ok(Version::Rmqr(13, 77)));
+    ///     assert_eq!(Version::rmqr_named("13x77"), Ok(Version::Rmqr(13, 77)));
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(QrError::InvalidVersion)` if the name is malformed, and
+    /// `Err(QrError::InvalidRmqrSize)` if it does not refer to a rMQR code
+    /// version, whose message suggests the valid sizes.
+    ///
+    ///     use qrqrpar::Version;
+    ///
+    ///     let error = Version::rmqr_named("R7x27").unwrap_err();
+    ///     assert_eq!(
+    ///         error.to_string(),
+    ///         "R7x27 is not a rMQR version; the valid widths for height 7 are 43, 59, 77, 99 and 139"
+    ///     );
+    pub fn rmqr_named(name: &str) -> QrResult<Version> {
+        let name = name.strip_prefix(['R', 'r']).unwrap_or(name);
+        let (height, width) = name.split_once(['x', 'X']).ok_or(QrError::InvalidVersion)?;
+        let parse = |n: &str| match n.bytes().all(|b| b.is_ascii_digit()) {
+            true => n.parse::<u8>().map_err(|_| QrError::InvalidVersion),
+            false => Err(QrError::InvalidVersion),
+        };
+        let (height, width) = (parse(height)?, parse(width)?);
+        let version = Version::Rmqr(height, width);
+        version
+            .rmqr_index()
+            .map_err(|_| QrError::InvalidRmqrSize { height, width })?;
+        Ok(version)
+    }
+
+    /// The widths of the rMQR code versions of the given height, in ascending
+    /// order. Empty if the height is invalid.
+    ///
+    ///     use qrqrpar::Version;
+    ///
+    ///     assert_eq!(Version::rmqr_widths(7), [43, 59, 77, 99, 139]);
+    ///     assert_eq!(Version::rmqr_widths(11), [27, 43, 59, 77, 99, 139]);
+    ///     assert!(Version::rmqr_widths(19).is_empty());
+    pub fn rmqr_widths(height: u8) -> Vec<u8> {
+        Self::rmqr_all()
+            .into_iter()
+            .filter_map(|version| match version {
+                Version::Rmqr(h, w) if h == height => Some(w),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The standard name of the version: the number for QR codes (`"2"`),
+    /// `M` followed by the number for Micro QR codes (`"M3"`), and
+    /// `R{height}x{width}` for rMQR codes (`"R13x77"`).
+    ///
+    /// The same name is used by `Display`.
+    pub fn name(&self) -> String {
+        match *self {
+            Version::Normal(v) => v.to_string(),
+            Version::Micro(v) => format!("M{v}"),
+            Version::Rmqr(h, w) => format!("R{h}x{w}"),
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        fmt.write_str(&self.name())
+    }
+}
+
+/// The error returned by `Version::check_ec_level` when a version cannot be
+/// encoded at an error correction level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EcLevelUnavailable {
+    /// The version asked for.
+    pub version: Version,
+    /// The error correction level asked for.
+    pub ec_level: EcLevel,
+    /// The smallest version of the same family supporting `ec_level`, if any.
+    pub smallest: Option<Version>,
+}
+
+impl Display for EcLevelUnavailable {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        let version = self.version;
+        match version.available_ec_levels() {
+            [] => return write!(fmt, "{version} is not a valid version"),
+            _ if version == Version::Micro(1) => {
+                write!(fmt, "{version} supports error detection only")?;
+            }
+            [levels @ .., last] => {
+                let levels = levels.iter().map(|level| format!("{level:?}"));
+                let levels = levels.collect::<Vec<_>>().join(", ");
+                write!(fmt, "{version} supports {levels} and {last:?} only")?;
+            }
+        }
+        let ec_level = self.ec_level;
+        match self.smallest {
+            Some(smallest) => write!(
+                fmt,
+                "; smallest version supporting {ec_level:?} is {smallest}"
+            ),
+            None => {
+                let family = if version.is_micro() {
+                    "Micro QR"
+                } else {
+                    "rMQR"
+                };
+                write!(fmt, "; no {family} version supports {ec_level:?}")
+            }
+        }
+    }
+}
+
+impl ::std::error::Error for EcLevelUnavailable {}
+
+/// The characters of the numeric mode, in value order.
+pub const NUMERIC_CHARSET: &[u8; 10] = b"0123456789";
+
+/// The characters of the alphanumeric mode, in value order, so that the
+/// index of a character is the base-45 digit encoding it.
+///
+/// The table is specified in ISO/IEC 18004:2006, §8.4.3, Table 5.
+///
+///     use qrqrpar::ALPHANUMERIC_CHARSET;
+///
+///     assert_eq!(ALPHANUMERIC_CHARSET[10], b'A');
+pub const ALPHANUMERIC_CHARSET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// The base-45 digit of each byte, or 0xff if the byte is not in
+/// `ALPHANUMERIC_CHARSET`.
+const ALPHANUMERIC_VALUES: [u8; 256] = {
+    let mut values = [0xff; 256];
+    let mut i = 0;
+    while i < ALPHANUMERIC_CHARSET.len() {
+        values[ALPHANUMERIC_CHARSET[i] as usize] = i as u8;
+        i += 1;
+    }
+    values
+};
+
+/// Gets the base-45 digit encoding a character in the alphanumeric mode, i.e.
+/// its index in `ALPHANUMERIC_CHARSET`, or `None` if the mode cannot encode
+/// it.
+///
+///     use qrqrpar::alphanumeric_value;
+///
+///     assert_eq!(alphanumeric_value(b'Z'), Some(35));
+///     assert_eq!(alphanumeric_value(b'z'), None);
+pub const fn alphanumeric_value(character: u8) -> Option<u16> {
+    match ALPHANUMERIC_VALUES[character as usize] {
+        0xff => None,
+        value => Some(value as u16),
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -296,18 +784,54 @@ pub enum Mode {
 }
 
 impl Mode {
+    /// Gets the characters the mode encodes one byte at a time, in value
+    /// order, or `None` for the byte and Kanji modes.
+    ///
+    ///     use qrqrpar::types::Mode;
+    ///
+    ///     assert_eq!(Mode::Numeric.charset(), Some(&b"0123456789"[..]));
+    ///     assert_eq!(Mode::Byte.charset(), None);
+    pub fn charset(self) -> Option<&'static [u8]> {
+        match self {
+            Mode::Numeric => Some(NUMERIC_CHARSET),
+            Mode::Alphanumeric => Some(ALPHANUMERIC_CHARSET),
+            Mode::Byte | Mode::Kanji => None,
+        }
+    }
+
+    /// Checks whether the mode can encode the byte, e.g. to validate user
+    /// input.
+    ///
+    ///     use qrqrpar::types::Mode;
+    ///
+    ///     assert!(Mode::Alphanumeric.is_encodable(b'$'));
+    ///     assert!(!Mode::Alphanumeric.is_encodable(b'a'));
+    ///
+    /// The Kanji mode encodes Shift JIS double-byte characters, so this only
+    /// tells whether the byte can be part of one, i.e. is between 0x40 and
+    /// 0xfc except 0x7f. The pairs are checked when encoding.
+    pub fn is_encodable(self, byte: u8) -> bool {
+        match self {
+            Mode::Numeric => byte.is_ascii_digit(),
+            Mode::Alphanumeric => alphanumeric_value(byte).is_some(),
+            Mode::Byte => true,
+            Mode::Kanji => matches!(byte, 0x40..=0x7e | 0x80..=0xfc),
+        }
+    }
+
     /// Computes the number of bits needed to encode the data length.
     ///
     ///     use qrqrpar::types::{Version, Mode};
     ///
     ///     assert_eq!(Mode::Numeric.length_bits_count(Version::Normal(1)), 10);
     ///
-    /// This method will return `Err(QrError::UnsupportedCharacterSet)` if the
-    /// mode is not supported in the given version.
-    pub fn length_bits_count(self, version: Version) -> usize {
+    /// The result is meaningless if the mode is not supported in the given
+    /// version, or if the version is invalid, see `Version::is_valid`. Invalid
+    /// rMQR versions get the counts of R17x139.
+    pub const fn length_bits_count(self, version: Version) -> usize {
         match version {
             Version::Micro(a) => {
-                let a = a.into();
+                let a = a as usize;
                 match self {
                     Mode::Numeric => 2 + a,
                     Mode::Alphanumeric | Mode::Byte => 1 + a,
@@ -333,7 +857,10 @@ impl Mode {
                 Mode::Kanji => 12,
             },
             Version::Rmqr(_, _) => {
-                let index = version.rmqr_index().unwrap_or(31);
+                let index = match version.rmqr_index() {
+                    Ok(index) => index,
+                    Err(_) => 31,
+                };
                 match self {
                     Mode::Numeric => RMQR_LENGTH_BITS_COUNT[index][0],
                     Mode::Alphanumeric => RMQR_LENGTH_BITS_COUNT[index][1],
@@ -352,15 +879,28 @@ impl Mode {
     ///
     /// Note that in Kanji mode, the `raw_data_len` is the number of Kanjis,
     /// i.e. half the total size of bytes.
-    pub fn data_bits_count(self, raw_data_len: usize) -> usize {
+    pub const fn data_bits_count(self, raw_data_len: usize) -> usize {
         match self {
-            Mode::Numeric => (raw_data_len * 10 + 2) / 3,
-            Mode::Alphanumeric => (raw_data_len * 11 + 1) / 2,
+            Mode::Numeric => (raw_data_len * 10).div_ceil(3),
+            Mode::Alphanumeric => (raw_data_len * 11).div_ceil(2),
             Mode::Byte => raw_data_len * 8,
             Mode::Kanji => raw_data_len * 13,
         }
     }
 
+    /// The smallest Micro QR code version supporting this mode.
+    ///
+    ///     use qrqrpar::types::{Mode, Version};
+    ///
+    ///     assert_eq!(Mode::Byte.smallest_micro_version(), Version::Micro(3));
+    ///
+    pub fn smallest_micro_version(self) -> Version {
+        (1..=4)
+            .map(Version::Micro)
+            .find(|v| v.supported_modes().contains(&self))
+            .unwrap_or(Version::Micro(4))
+    }
+
     /// Find the lowest common mode which both modes are compatible with.
     ///
     ///     use qrqrpar::types::Mode;
@@ -380,6 +920,32 @@ impl Mode {
     }
 }
 
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let family = |version: &Version| match version {
+            Version::Normal(_) => 0,
+            Version::Micro(_) => 1,
+            Version::Rmqr(_, _) => 2,
+        };
+        match (*self, *other) {
+            (Version::Normal(a), Version::Normal(b)) | (Version::Micro(a), Version::Micro(b)) => {
+                a.cmp(&b)
+            }
+            (Version::Rmqr(h1, w1), Version::Rmqr(h2, w2)) => {
+                let area = |h: u8, w: u8| u32::from(h) * u32::from(w);
+                (area(h1, w1), h1).cmp(&(area(h2, w2), h2))
+            }
+            _ => family(self).cmp(&family(other)),
+        }
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl PartialOrd for Mode {
     /// Defines a partial ordering between modes. If `a <= b`, then `b` contains
     /// a superset of all characters supported by `a`.
@@ -402,7 +968,7 @@ impl PartialOrd for Mode {
 /// The number of bits needed to encode the length of the data.
 ///
 /// \[ Numeric, Alphanumeric, Byte, Kanji \]
-static RMQR_LENGTH_BITS_COUNT: [[usize; 4]; 32] = [
+const RMQR_LENGTH_BITS_COUNT: [[usize; 4]; 32] = [
     [4, 3, 3, 2], //R7x43
     [5, 5, 4, 3], //R7x59
     [6, 5, 5, 4], //R7x77
@@ -436,3 +1002,484 @@ static RMQR_LENGTH_BITS_COUNT: [[usize; 4]; 32] = [
     [8, 8, 7, 6], //R17x99
     [9, 8, 8, 7], //R17x139
 ];
+
+#[cfg(test)]
+mod charset_tests {
+    use crate::types::{alphanumeric_value, Mode, ALPHANUMERIC_CHARSET, NUMERIC_CHARSET};
+
+    #[test]
+    fn test_iso_table_5() {
+        let table = [
+            (b'0', 0),
+            (b'9', 9),
+            (b'A', 10),
+            (b'Z', 35),
+            (b' ', 36),
+            (b'$', 37),
+            (b'%', 38),
+            (b'*', 39),
+            (b'+', 40),
+            (b'-', 41),
+            (b'.', 42),
+            (b'/', 43),
+            (b':', 44),
+        ];
+        for (character, value) in table {
+            assert_eq!(alphanumeric_value(character), Some(value));
+            assert_eq!(ALPHANUMERIC_CHARSET[usize::from(value)], character);
+        }
+        for (value, character) in (b'0'..=b'9').chain(b'A'..=b'Z').enumerate() {
+            assert_eq!(alphanumeric_value(character), Some(value as u16));
+        }
+        let count = (0..=255).filter_map(alphanumeric_value).count();
+        assert_eq!(count, 45);
+        assert_eq!(alphanumeric_value(b'a'), None);
+        assert_eq!(alphanumeric_value(b'#'), None);
+    }
+
+    #[test]
+    fn test_is_encodable() {
+        for byte in 0..=255 {
+            assert_eq!(
+                Mode::Numeric.is_encodable(byte),
+                NUMERIC_CHARSET.contains(&byte)
+            );
+            assert_eq!(
+                Mode::Alphanumeric.is_encodable(byte),
+                ALPHANUMERIC_CHARSET.contains(&byte)
+            );
+            assert!(Mode::Byte.is_encodable(byte));
+        }
+        assert!(Mode::Kanji.is_encodable(0x93));
+        assert!(!Mode::Kanji.is_encodable(0x7f));
+        assert!(!Mode::Kanji.is_encodable(b'!'));
+        assert_eq!(Mode::Kanji.charset(), None);
+    }
+}
+
+#[cfg(test)]
+mod supported_modes_tests {
+    use crate::bits::Bits;
+    use crate::types::{Mode, QrError, Version};
+
+    #[test]
+    fn test_micro_versions() {
+        use Mode::*;
+        // ISO/IEC 18004:2015 Table 2.
+        let table = [
+            (1, [true, false, false, false]),
+            (2, [true, true, false, false]),
+            (3, [true, true, true, true]),
+            (4, [true, true, true, true]),
+        ];
+        for (v, expected) in table {
+            for (mode, supported) in [Numeric, Alphanumeric, Byte, Kanji]
+                .into_iter()
+                .zip(expected)
+            {
+                assert_eq!(
+                    Version::Micro(v).supported_modes().contains(&mode),
+                    supported,
+                    "M{v} {mode:?}"
+                );
+            }
+        }
+        assert_eq!(Numeric.smallest_micro_version(), Version::Micro(1));
+        assert_eq!(Alphanumeric.smallest_micro_version(), Version::Micro(2));
+        assert_eq!(Byte.smallest_micro_version(), Version::Micro(3));
+        assert_eq!(Kanji.smallest_micro_version(), Version::Micro(3));
+    }
+
+    #[test]
+    fn test_recommended_quiet_zone() {
+        assert_eq!(Version::Normal(1).recommended_quiet_zone(), 4);
+        assert_eq!(Version::Normal(40).recommended_quiet_zone(), 4);
+        assert_eq!(Version::Micro(1).recommended_quiet_zone(), 2);
+        assert_eq!(Version::Micro(4).recommended_quiet_zone(), 2);
+        assert_eq!(Version::Rmqr(7, 43).recommended_quiet_zone(), 2);
+        assert_eq!(Version::Rmqr(17, 139).recommended_quiet_zone(), 2);
+    }
+
+    #[test]
+    fn test_other_versions() {
+        assert_eq!(Version::Normal(1).supported_modes().len(), 4);
+        assert_eq!(Version::Rmqr(7, 43).supported_modes().len(), 4);
+    }
+
+    #[test]
+    fn test_mode_unavailable() {
+        for v in 1..=4 {
+            let version = Version::Micro(v);
+            for mode in [Mode::Numeric, Mode::Alphanumeric, Mode::Byte, Mode::Kanji] {
+                let result = Bits::new(version).push_mode_indicator(mode);
+                if version.supported_modes().contains(&mode) {
+                    assert_eq!(result, Ok(()), "M{v} {mode:?}");
+                } else {
+                    let smallest = mode.smallest_micro_version();
+                    let expected = QrError::ModeUnavailable {
+                        version,
+                        mode,
+                        smallest,
+                    };
+                    assert_eq!(result, Err(expected), "M{v} {mode:?}");
+                }
+            }
+        }
+
+        let error = Bits::new(Version::Micro(2))
+            .push_byte_data(b"a")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "M2 does not support the Byte mode; smallest version supporting it is M3"
+        );
+    }
+}
+
+#[cfg(test)]
+mod ec_level_tests {
+    use crate::bits::Bits;
+    use crate::types::{EcLevel, Version};
+
+    #[test]
+    fn test_micro_versions() {
+        use EcLevel::*;
+        // ISO/IEC 18004:2015 Table 7.
+        let table = [
+            (
+                1,
+                [true, false, false, false],
+                [
+                    None,
+                    Some("M1 supports error detection only; smallest version supporting M is M2"),
+                    Some("M1 supports error detection only; smallest version supporting Q is M4"),
+                    Some("M1 supports error detection only; no Micro QR version supports H"),
+                ],
+            ),
+            (
+                2,
+                [true, true, false, false],
+                [
+                    None,
+                    None,
+                    Some("M2 supports L and M only; smallest version supporting Q is M4"),
+                    Some("M2 supports L and M only; no Micro QR version supports H"),
+                ],
+            ),
+            (
+                3,
+                [true, true, false, false],
+                [
+                    None,
+                    None,
+                    Some("M3 supports L and M only; smallest version supporting Q is M4"),
+                    Some("M3 supports L and M only; no Micro QR version supports H"),
+                ],
+            ),
+            (
+                4,
+                [true, true, true, false],
+                [
+                    None,
+                    None,
+                    None,
+                    Some("M4 supports L, M and Q only; no Micro QR version supports H"),
+                ],
+            ),
+        ];
+        for (v, available, messages) in table {
+            let version = Version::Micro(v);
+            for ((ec_level, available), message) in
+                [L, M, Q, H].into_iter().zip(available).zip(messages)
+            {
+                assert_eq!(
+                    version.available_ec_levels().contains(&ec_level),
+                    available,
+                    "M{v} {ec_level:?}"
+                );
+                assert_eq!(Bits::new(version).max_len(ec_level).is_ok(), available);
+                assert_eq!(
+                    version
+                        .check_ec_level(ec_level)
+                        .err()
+                        .map(|error| error.to_string())
+                        .as_deref(),
+                    message
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_other_versions() {
+        let all = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+        for version in (1..=40).map(Version::Normal).chain(Version::rmqr_all()) {
+            for ec_level in all {
+                assert_eq!(
+                    version.available_ec_levels().contains(&ec_level),
+                    Bits::new(version).max_len(ec_level).is_ok(),
+                    "{version:?} {ec_level:?}"
+                );
+            }
+        }
+        assert_eq!(Version::Normal(1).available_ec_levels(), &all);
+        assert_eq!(
+            Version::Rmqr(9, 77)
+                .check_ec_level(EcLevel::Q)
+                .unwrap_err()
+                .to_string(),
+            "R9x77 supports M and H only; no rMQR version supports Q"
+        );
+        let error = Version::Micro(5).check_ec_level(EcLevel::L).unwrap_err();
+        assert_eq!(error.smallest, Some(Version::Micro(1)));
+        assert_eq!(error.to_string(), "M5 is not a valid version");
+        assert!(Version::Normal(0).available_ec_levels().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod char_capacity_tests {
+    use crate::bits::Bits;
+    use crate::types::{EcLevel, Mode, QrError, QrResult, Version};
+
+    const MODES: [Mode; 4] = [Mode::Numeric, Mode::Alphanumeric, Mode::Byte, Mode::Kanji];
+
+    fn capacities(version: Version, ec_level: EcLevel) -> Vec<QrResult<usize>> {
+        MODES
+            .iter()
+            .map(|mode| version.char_capacity(ec_level, *mode))
+            .collect()
+    }
+
+    #[test]
+    fn test_iso_18004_table() {
+        // ISO/IEC 18004:2015 Table 7, numeric, alphanumeric, byte and Kanji.
+        let cases = [
+            (Version::Normal(1), EcLevel::L, [41, 25, 17, 10]),
+            (Version::Normal(1), EcLevel::H, [17, 10, 7, 4]),
+            (Version::Normal(10), EcLevel::M, [513, 311, 213, 131]),
+            (Version::Normal(40), EcLevel::L, [7089, 4296, 2953, 1817]),
+            (Version::Normal(40), EcLevel::H, [3057, 1852, 1273, 784]),
+            (Version::Micro(3), EcLevel::M, [18, 11, 7, 4]),
+            (Version::Micro(4), EcLevel::L, [35, 21, 15, 9]),
+            (Version::Micro(4), EcLevel::Q, [21, 13, 9, 5]),
+        ];
+        for (version, ec_level, expected) in cases {
+            assert_eq!(capacities(version, ec_level), expected.map(Ok), "{version}");
+        }
+        assert_eq!(
+            Version::Micro(1).char_capacity(EcLevel::L, Mode::Numeric),
+            Ok(5)
+        );
+        assert_eq!(
+            Version::Micro(2).char_capacity(EcLevel::M, Mode::Alphanumeric),
+            Ok(5)
+        );
+    }
+
+    #[test]
+    fn test_iso_23941_table() {
+        // ISO/IEC 23941:2022 Table 6.
+        let cases = [
+            (Version::Rmqr(7, 43), EcLevel::M, [12, 7, 5, 3]),
+            (Version::Rmqr(7, 43), EcLevel::H, [5, 3, 2, 1]),
+            (Version::Rmqr(17, 139), EcLevel::M, [361, 219, 150, 92]),
+        ];
+        for (version, ec_level, expected) in cases {
+            assert_eq!(capacities(version, ec_level), expected.map(Ok), "{version}");
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(
+            Version::Micro(1).char_capacity(EcLevel::L, Mode::Alphanumeric),
+            Err(QrError::InvalidVersion)
+        );
+        assert_eq!(
+            Version::Micro(2).char_capacity(EcLevel::L, Mode::Byte),
+            Err(QrError::InvalidVersion)
+        );
+        assert_eq!(
+            Version::Micro(1).char_capacity(EcLevel::M, Mode::Numeric),
+            Err(QrError::InvalidVersion)
+        );
+        assert_eq!(
+            Version::Rmqr(7, 43).char_capacity(EcLevel::L, Mode::Byte),
+            Err(QrError::InvalidVersion)
+        );
+        assert_eq!(
+            Version::Rmqr(7, 27).char_capacity(EcLevel::M, Mode::Byte),
+            Err(QrError::InvalidVersion)
+        );
+    }
+
+    #[test]
+    fn test_single_segment_fits() {
+        let versions = (1..=40)
+            .map(Version::Normal)
+            .chain((1..=4).map(Version::Micro))
+            .chain(Version::rmqr_all());
+        for version in versions {
+            for &ec_level in version.available_ec_levels() {
+                for &mode in version.supported_modes() {
+                    let capacity = version.char_capacity(ec_level, mode).unwrap();
+                    let fits = |count: usize| {
+                        let mut bits = Bits::new(version);
+                        let pushed = match mode {
+                            Mode::Numeric => bits.push_numeric_data(&vec![b'1'; count]),
+                            Mode::Alphanumeric => bits.push_alphanumeric_data(&vec![b'A'; count]),
+                            Mode::Byte => bits.push_byte_data(&vec![b'a'; count]),
+                            Mode::Kanji => bits.push_kanji_data(&b"\x93\x5f".repeat(count)),
+                        };
+                        pushed.is_ok() && bits.push_terminator(ec_level).is_ok()
+                    };
+                    assert!(fits(capacity), "{version} {ec_level:?} {mode:?}");
+                    assert!(!fits(capacity + 1), "{version} {ec_level:?} {mode:?}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod version_name_tests {
+    use crate::types::{QrError, Version};
+
+    #[test]
+    fn test_rmqr_named() {
+        for version in Version::rmqr_all() {
+            let name = version.name();
+            assert_eq!(Version::rmqr_named(&name), Ok(version));
+            assert_eq!(Version::rmqr_named(&name.to_lowercase()), Ok(version));
+            assert_eq!(Version::rmqr_named(&name[1..]), Ok(version));
+        }
+        for name in ["R13x", "x77", "R13*77", "RR13x77", "R+13x77", "R13x777", ""] {
+            assert_eq!(
+                Version::rmqr_named(name),
+                Err(QrError::InvalidVersion),
+                "{name}"
+            );
+        }
+        let error = Version::rmqr_named("R19x43").unwrap_err();
+        assert_eq!(
+            error,
+            QrError::InvalidRmqrSize {
+                height: 19,
+                width: 43
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "R19x43 is not a rMQR version; the valid heights are 7, 9, 11, 13, 15 and 17"
+        );
+        let error = Version::rmqr_named("R7x27").unwrap_err();
+        assert_eq!(
+            error,
+            QrError::InvalidRmqrSize {
+                height: 7,
+                width: 27
+            }
+        );
+    }
+
+    #[test]
+    fn test_name() {
+        assert_eq!(Version::Normal(2).name(), "2");
+        assert_eq!(Version::Micro(3).to_string(), "M3");
+        assert_eq!(format!("{}", Version::Rmqr(13, 77)), "R13x77");
+        assert_eq!(Version::rmqr_widths(17), [43, 59, 77, 99, 139]);
+    }
+}
+
+#[cfg(test)]
+mod ordering_tests {
+    use crate::bits::capacity_bits;
+    use crate::types::{EcLevel, Version};
+    use std::cmp::Ordering;
+
+    #[test]
+    fn test_ec_level_order() {
+        let mut levels = vec![EcLevel::H, EcLevel::L, EcLevel::Q, EcLevel::M];
+        levels.sort();
+        assert_eq!(levels, [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H]);
+    }
+
+    #[test]
+    fn test_mixed_version_order() {
+        let mut versions = vec![
+            Version::Rmqr(13, 27),
+            Version::Micro(4),
+            Version::Normal(40),
+            Version::Rmqr(7, 43),
+            Version::Micro(1),
+            Version::Rmqr(9, 139),
+            Version::Normal(2),
+            Version::Rmqr(11, 27),
+            Version::Rmqr(17, 77),
+        ];
+        versions.sort();
+        assert_eq!(
+            versions,
+            [
+                Version::Normal(2),
+                Version::Normal(40),
+                Version::Micro(1),
+                Version::Micro(4),
+                Version::Rmqr(11, 27),
+                Version::Rmqr(7, 43),
+                Version::Rmqr(13, 27),
+                Version::Rmqr(9, 139),
+                Version::Rmqr(17, 77),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_consistent_with_partial_ord_and_eq() {
+        let versions = (1..=40)
+            .map(Version::Normal)
+            .chain((1..=4).map(Version::Micro))
+            .chain(Version::rmqr_all())
+            .collect::<Vec<_>>();
+        for a in &versions {
+            for b in &versions {
+                assert_eq!(a.partial_cmp(b), Some(a.cmp(b)));
+                assert_eq!(a == b, a.cmp(b) == Ordering::Equal);
+                assert_eq!(a.cmp(b), b.cmp(a).reverse());
+            }
+        }
+    }
+
+    #[test]
+    fn test_capacity_progression() {
+        let mut rmqr = Version::rmqr_all().to_vec();
+        rmqr.sort();
+        let is_sorted = |indices: &mut dyn Iterator<Item = usize>, ec: usize| {
+            let capacities = indices.map(|i| capacity_bits(i, ec)).filter(|&c| c != 0);
+            capacities
+                .collect::<Vec<_>>()
+                .windows(2)
+                .all(|w| w[0] < w[1])
+        };
+        for ec in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+            let ec = ec as usize;
+            assert!(is_sorted(&mut (0..40), ec));
+            assert!(is_sorted(&mut (40..44), ec));
+            for height in Version::rmqr_all_height() {
+                let mut same_height = rmqr
+                    .iter()
+                    .filter(|v| v.height() == height as i16)
+                    .map(|v| v.rmqr_index().unwrap() + 44);
+                assert!(is_sorted(&mut same_height, ec));
+            }
+        }
+        let (small, large) = (Version::Rmqr(11, 27), Version::Rmqr(7, 43));
+        assert!(small < large);
+        let m = EcLevel::M as usize;
+        assert!(
+            capacity_bits(small.rmqr_index().unwrap() + 44, m)
+                > capacity_bits(large.rmqr_index().unwrap() + 44, m)
+        );
+    }
+}